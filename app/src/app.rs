@@ -7,6 +7,7 @@ use crate::utils::file_channels::FileChannels;
 use crate::utils::file_loader::FileLoader;
 use crate::utils::file_loader::PickedFile;
 use crate::utils::file_saver::SaveOutcome;
+use citrine_gb::gb::boot_rom::BootRom;
 use citrine_gb::rom::Rom;
 use eframe::{Frame, Storage};
 use egui::{CentralPanel, Color32, Context, FontDefinitions, TopBottomPanel};
@@ -107,6 +108,7 @@ impl eframe::App for Citrine {
         }
 
         self.handle_snapshot_hotkeys(ctx);
+        self.handle_rewind_hotkey(ctx);
         self.quick_load_overlay(ctx);
 
         self.drain_file_channels();
@@ -385,6 +387,9 @@ impl Citrine {
         while let Ok(file) = self.files.sav_rx.try_recv() {
             self.handle_import_save(file);
         }
+        while let Ok(file) = self.files.bess_rx.try_recv() {
+            self.handle_import_bess(file);
+        }
         #[cfg(not(target_arch = "wasm32"))]
         while let Ok(dir) = self.files.folder_rx.try_recv() {
             self.handle_export_e2e(&dir);
@@ -447,7 +452,7 @@ impl Citrine {
                 match self.emulator.load_snapshot(slot) {
                     Ok(true) => self.toasts.success(format!("Loaded slot {slot}")),
                     Ok(false) => self.toasts.info(format!("Slot {slot} is empty")),
-                    Err(err) => self.toasts.error(format!("Quick load failed: {err:?}")),
+                    Err(err) => self.toasts.error(format!("Quick load failed: {err}")),
                 };
                 self.ui.snapshots.invalidate();
             }
@@ -456,6 +461,13 @@ impl Citrine {
         }
     }
 
+    /// Holding R steps the game backwards
+    fn handle_rewind_hotkey(&mut self, ctx: &Context) {
+        let held = !ctx.wants_keyboard_input() && ctx.input(|i| i.key_down(egui::Key::R));
+        self.emulator.rewinding =
+            held && self.emulator.rewind_enabled && self.emulator.rom_key().is_some();
+    }
+
     fn quick_load_overlay(&self, ctx: &Context) {
         let Some(started) = self.quick_load_hold else {
             return;
@@ -536,14 +548,34 @@ impl Citrine {
                 self.toasts.error("Load a ROM before importing a save");
             }
             Err(err) => {
-                self.toasts.error(format!("Failed to import save: {err:?}"));
+                self.toasts.error(format!("Failed to import save: {err}"));
+            }
+        }
+    }
+
+    fn handle_import_bess(&mut self, file: PickedFile) {
+        match self.emulator.import_bess(&file.data) {
+            Ok(true) => {
+                self.toasts
+                    .success(format!("Loaded save state '{}'", file.name));
+            }
+            Ok(false) => {
+                self.toasts
+                    .error("Load a ROM before importing a save state");
+            }
+            Err(err) => {
+                self.toasts
+                    .error(format!("Failed to import save state: {err}"));
             }
         }
     }
 
     fn handle_load_boot_rom(&mut self, file: PickedFile) {
         self.try_start_audio();
-        self.emulator.gb.load_boot_rom(&file.data);
+        self.emulator.boot_rom = BootRom::Custom(file.data);
+        self.emulator
+            .gb
+            .set_boot_rom(self.emulator.boot_rom.clone());
         self.ui.settings.dirty = true;
         self.toasts.success("Boot ROM loaded");
     }
@@ -112,6 +112,30 @@ pub fn show(viewer: &mut TabViewer, ui: &mut egui::Ui) {
         if ui.button(format!("{} New Snapshot", icons::PLUS)).clicked() {
             action = Some(SlotAction::New);
         }
+        if ui
+            .button(format!("{} Import BESS", icons::UPLOAD_SIMPLE))
+            .on_hover_text("Load a save state from SameBoy or another emulator that writes BESS")
+            .clicked()
+        {
+            crate::utils::file_loader::FileLoader::new()
+                .title("Import save state")
+                .add_filter(
+                    "Save states",
+                    &["s0", "s1", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9"],
+                )
+                .dispatch(viewer.files.bess_tx.clone());
+        }
+        if ui
+            .button(format!("{} Export BESS", icons::DOWNLOAD_SIMPLE))
+            .on_hover_text("Save the current state in a format SameBoy and others can load")
+            .clicked()
+        {
+            let title = viewer.emulator.gb.cartridge.header.title.trim();
+            let name = if title.is_empty() { "state" } else { title };
+            crate::utils::file_saver::FileSaver::new(&format!("{name}.s0"))
+                .add_filter("Save states", &["s0"])
+                .dispatch(viewer.emulator.export_bess(), viewer.files.save_tx.clone());
+        }
         ui.separator();
         ui.small(format!(
             "F8 overwrites slot  {}{quick_slot}, Shift+F8 makes a new one, holding F9 loads slot \
@@ -221,7 +245,7 @@ fn apply(viewer: &mut TabViewer, rom_key: &str, action: SlotAction, ctx: &egui::
         Ok(message) => viewer.events.notify(message),
         Err(err) => viewer
             .events
-            .notify_error(format!("Snapshot failed: {err:?}")),
+            .notify_error(format!("Snapshot failed: {err}")),
     }
     // The set of snapshots changed, so drop the cached textures and read it back.
     viewer
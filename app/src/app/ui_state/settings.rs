@@ -20,9 +20,17 @@ pub struct Settings {
     pub current_tab: SettingsTab,
     pub quick_slot: usize,
     pub randomized_ram: bool,
+    #[serde(default)]
+    pub skip_boot_rom: bool,
     pub dev_mode: bool,
     pub focus_mode: bool,
     pub track_pc: bool,
+    #[serde(default = "default_rewind")]
+    pub rewind: bool,
+    #[serde(default = "default_rewind_memory_mib")]
+    pub rewind_memory_mib: usize,
+    #[serde(default)]
+    pub run_ahead_frames: u32,
     #[serde(skip, default = "default_dirty")]
     pub dirty: bool,
 }
@@ -42,9 +50,13 @@ impl Default for Settings {
             current_tab: SettingsTab::default(),
             quick_slot: 0,
             randomized_ram: false,
+            skip_boot_rom: false,
             dev_mode: false,
             focus_mode: false,
             track_pc: false,
+            rewind: default_rewind(),
+            rewind_memory_mib: default_rewind_memory_mib(),
+            run_ahead_frames: 0,
             dirty: default_dirty(),
         }
     }
@@ -54,6 +66,14 @@ fn default_dirty() -> bool {
     true
 }
 
+fn default_rewind() -> bool {
+    true
+}
+
+fn default_rewind_memory_mib() -> usize {
+    Settings::DEFAULT_REWIND_MEMORY_MIB
+}
+
 impl Settings {
     pub const DEFAULT_VOLUME: f32 = 0.25;
     #[cfg(target_arch = "wasm32")]
@@ -63,6 +83,7 @@ impl Settings {
     pub const DEFAULT_MATRIX_EDGE_DARKNESS: f32 = 0.15;
     pub const DEFAULT_MATRIX_CORNER_DARKNESS: f32 = 0.25;
     pub const DEFAULT_GHOSTING_STRENGTH: f32 = 0.3;
+    pub const DEFAULT_REWIND_MEMORY_MIB: usize = 32;
 
     pub fn apply(
         &mut self,
@@ -82,11 +103,18 @@ impl Settings {
         } else {
             citrine_gb::gb::ram_init::RamInit::Zeroed
         };
+        emulator.skip_boot_rom = self.skip_boot_rom;
         emulator.enable_matrix = self.matrix;
         emulator.enable_ghosting = self.ghosting;
         emulator.matrix_edge_brightness = 1.0 - self.matrix_edge_darkness;
         emulator.matrix_corner_brightness = 1.0 - self.matrix_corner_darkness;
         emulator.ghosting_blend = 1.0 - self.ghosting_strength;
+        emulator.run_ahead_frames = self.run_ahead_frames;
+        emulator.rewind_enabled = self.rewind;
+        emulator.rewind.config.memory_budget = self.rewind_memory_mib * 1024 * 1024;
+        if !self.rewind {
+            emulator.rewind.clear();
+        }
 
         if let Some(audio) = audio {
             audio.set_volume(self.volume);
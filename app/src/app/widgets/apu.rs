@@ -2,16 +2,27 @@ use crate::app::events::AppEventQueue;
 use crate::app::tabs::Tab;
 use crate::emulator::Emulator;
 use crate::icons;
+use crate::utils::file_channels::FileChannels;
+use crate::utils::file_saver::FileSaver;
 use egui::{Response, Ui, Widget};
 
 pub struct ApuWidget<'a> {
     emulator: &'a mut Emulator,
     events: &'a mut AppEventQueue,
+    files: &'a mut FileChannels,
 }
 
 impl<'a> ApuWidget<'a> {
-    pub fn new(emulator: &'a mut Emulator, events: &'a mut AppEventQueue) -> Self {
-        Self { emulator, events }
+    pub fn new(
+        emulator: &'a mut Emulator,
+        events: &'a mut AppEventQueue,
+        files: &'a mut FileChannels,
+    ) -> Self {
+        Self {
+            emulator,
+            events,
+            files,
+        }
     }
 }
 
@@ -40,6 +51,29 @@ impl Widget for ApuWidget<'_> {
                 if ui.checkbox(&mut ch4_enabled, "4").changed() {
                     self.emulator.gb.debugger.ch4_disabled = !ch4_enabled;
                 }
+
+                ui.separator();
+
+                if self.emulator.gb.vgm_logger.is_some() {
+                    if ui
+                        .button(format!("{} Stop VGM", icons::STOP))
+                        .on_hover_text("Stop logging and save the VGM file")
+                        .clicked()
+                        && let Some(vgm) = self.emulator.gb.stop_vgm_logging()
+                    {
+                        let title = self.emulator.gb.cartridge.header.title.trim();
+                        let name = if title.is_empty() { "citrine" } else { title };
+                        FileSaver::new(&format!("{name}.vgm"))
+                            .add_filter("VGM", &["vgm"])
+                            .dispatch(vgm, self.files.save_tx.clone());
+                    }
+                } else if ui
+                    .button(format!("{} Record VGM", icons::RECORD))
+                    .on_hover_text("Log sound register writes to a VGM file")
+                    .clicked()
+                {
+                    self.emulator.gb.start_vgm_logging();
+                }
             });
         })
         .response
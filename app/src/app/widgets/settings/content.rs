@@ -58,6 +58,31 @@ impl SettingsContent<'_> {
                     .changed();
                 ui.end_row();
 
+                ui.label("Skip Boot Animation");
+                s.dirty |= ui.checkbox(&mut s.skip_boot_rom, "").changed();
+                ui.end_row();
+
+                ui.label("Run-Ahead (frames)")
+                    .on_hover_text("Hides input lag, costs one emulated frame per frame ahead");
+                s.dirty |= ResetSlider::new(&mut s.run_ahead_frames, 0..=4)
+                    .default_value(0)
+                    .ui(ui)
+                    .changed();
+                ui.end_row();
+
+                ui.label("Rewind (hold R)");
+                s.dirty |= ui.checkbox(&mut s.rewind, "").changed();
+                ui.end_row();
+
+                ui.label("Rewind Memory (MiB)");
+                let response = ResetSlider::new(&mut s.rewind_memory_mib, 4..=256)
+                    .default_value(Settings::DEFAULT_REWIND_MEMORY_MIB)
+                    .ui(ui);
+                if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                    s.dirty = true;
+                }
+                ui.end_row();
+
                 ui.label("Developer Mode");
                 s.dirty |= ui.checkbox(&mut s.dev_mode, "").changed();
                 ui.end_row();
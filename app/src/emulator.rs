@@ -1,9 +1,12 @@
 use crate::recorder::InputRecorder;
-use crate::storage::SaveStore;
+use crate::storage::{SaveStore, now_unix};
 use crate::utils::avg_timer::AvgTimer;
 use citrine_gb::error::GbResult;
+use citrine_gb::gb::boot_rom::BootRom;
 use citrine_gb::gb::joypad::JoypadState;
 use citrine_gb::gb::{GameBoy, GbModel};
+use citrine_gb::persistence::rewind::RewindBuffer;
+use citrine_gb::persistence::sav::{RtcFooter, SavFile, SavLayout};
 use citrine_gb::persistence::sram_dump::SramDump;
 use citrine_gb::rom::Rom;
 use gilrs::Axis;
@@ -27,6 +30,9 @@ const GB_HEIGHT: usize = 144;
 
 pub struct Emulator {
     pub gb: GameBoy,
+    /// Runs before every loaded ROM unless `skip_boot_rom` is set
+    pub boot_rom: BootRom,
+    pub skip_boot_rom: bool,
     pub running: bool,
     pub audio_producer: Option<HeapProd<f32>>,
     pub audio_overrun_samples: u128,
@@ -55,12 +61,21 @@ pub struct Emulator {
     pub last_save: Option<web_time::Instant>,
     pub save_loaded: bool,
     pub recorder: InputRecorder,
+    pub rewind_enabled: bool,
+    pub rewind: RewindBuffer,
+    /// Set while the rewind hotkey is held, frames then step backwards with the audio muted
+    pub rewinding: bool,
+    /// Frames shown ahead of the emulated state to hide a game's input lag, see
+    /// [`GameBoy::run_frame_ahead`]
+    pub run_ahead_frames: u32,
 }
 
 impl Default for Emulator {
     fn default() -> Self {
         Self {
             gb: GameBoy::new_empty(GbModel::Dmg),
+            boot_rom: BootRom::Bundled,
+            skip_boot_rom: false,
             running: true,
             audio_producer: None,
             audio_overrun_samples: 0,
@@ -85,6 +100,10 @@ impl Default for Emulator {
             last_save: None,
             save_loaded: false,
             recorder: InputRecorder::default(),
+            rewind_enabled: true,
+            rewind: RewindBuffer::default(),
+            rewinding: false,
+            run_ahead_frames: 0,
         }
     }
 }
@@ -118,15 +137,29 @@ impl Emulator {
         }
 
         let mut ran_frame = false;
+        // Rewinding is muted
+        self.gb.apu.suppress_output = self.rewinding;
         while self.time_accumulator >= FRAME_TIME {
             self.frame_avg_timer.start();
-            self.gb.run_frame();
+            if self.rewinding {
+                self.rewind_frame()?;
+            } else {
+                // Only the frame that ends up on screen needs to be drawn and to look ahead
+                let shown = self.time_accumulator < FRAME_TIME * 2.0;
+                self.gb.ppu.skip_rendering = !shown;
+                self.gb
+                    .run_frame_ahead(if shown { self.run_ahead_frames } else { 0 });
+                if self.rewind_enabled {
+                    self.rewind.frame_finished(&self.gb)?;
+                }
+            }
             self.frame_avg_timer.stop();
             self.time_accumulator -= FRAME_TIME;
             ran_frame = true;
 
             if self.gb.debugger.hit_breakpoint {
                 self.gb.debugger.hit_breakpoint = false;
+                self.gb.ppu.skip_rendering = false;
                 self.running = false;
                 break;
             }
@@ -135,7 +168,9 @@ impl Emulator {
         if ran_frame {
             self.update_texture(ctx);
 
-            if let Some(producer) = &mut self.audio_producer {
+            if self.rewinding {
+                self.gb.apu.audio_buffer.clear();
+            } else if let Some(producer) = &mut self.audio_producer {
                 let samples = &self.gb.apu.audio_buffer;
 
                 let pushed = producer.push_slice(samples);
@@ -157,6 +192,14 @@ impl Emulator {
         Ok(())
     }
 
+    /// Steps back to the previous rewind state and runs a frame from it to show its picture
+    fn rewind_frame(&mut self) -> GbResult<()> {
+        if self.rewind.rewind(&mut self.gb)? {
+            self.gb.run_frame();
+        }
+        Ok(())
+    }
+
     pub fn start_recording(&mut self) {
         self.recorder.start(&self.gb);
     }
@@ -299,6 +342,11 @@ impl Emulator {
         self.running = false;
         self.save_loaded = false;
         self.imported_legacy_save = false;
+        if self.skip_boot_rom {
+            self.gb.set_boot_rom(BootRom::Skip);
+        } else {
+            self.gb.set_boot_rom(self.boot_rom.clone());
+        }
         self.gb.load_rom(rom)?;
 
         let key = self.gb.cartridge.header.sha256_hex_string();
@@ -306,7 +354,9 @@ impl Emulator {
         {
             self.rom_path = path.map(|p| p.to_owned());
             if let Some(path) = path {
-                self.imported_legacy_save = self.store.import_legacy_save_if_new(&key, path);
+                let layout = SavLayout::of(&self.gb.cartridge);
+                self.imported_legacy_save =
+                    self.store.import_legacy_save_if_new(&key, path, layout);
             }
         }
 
@@ -317,6 +367,7 @@ impl Emulator {
 
         self.rom_key = Some(key);
         self.last_save = None;
+        self.rewind.clear();
         self.running = true;
         Ok(())
     }
@@ -364,10 +415,26 @@ impl Emulator {
         let sample_rate = self.gb.apu.output_sample_rate;
         self.gb = restored;
         self.gb.apu.set_sample_rate(sample_rate);
+        self.rewind.clear();
         self.running = true;
         Ok(true)
     }
 
+    /// Loads a BESS save state from another emulator, `false` without a ROM
+    pub fn import_bess(&mut self, data: &[u8]) -> GbResult<bool> {
+        if self.rom_key.is_none() {
+            return Ok(false);
+        }
+        self.gb.load_bess(data)?;
+        self.rewind.clear();
+        self.running = true;
+        Ok(true)
+    }
+
+    pub fn export_bess(&self) -> Vec<u8> {
+        self.gb.dump_bess()
+    }
+
     pub fn delete_snapshot(&mut self, slot: usize) -> GbResult<()> {
         if let Some(key) = self.rom_key.clone() {
             self.store.delete_snapshot(&key, slot)?;
@@ -389,7 +456,8 @@ impl Emulator {
         let (Some(key), Some(path)) = (self.rom_key.clone(), self.rom_path.clone()) else {
             return false;
         };
-        if !self.store.import_legacy_save(&key, &path) {
+        let layout = SavLayout::of(&self.gb.cartridge);
+        if !self.store.import_legacy_save(&key, &path, layout) {
             return false;
         }
         if let Some(data) = self.store.load_battery(&key) {
@@ -404,17 +472,25 @@ impl Emulator {
         let Some(key) = self.rom_key.clone() else {
             return Ok(false);
         };
-        self.store.store_battery(&key, data)?;
-        self.gb.put_sram_dump(SramDump::from_slice(data));
+        let sav = SavFile::parse(data, SavLayout::of(&self.gb.cartridge))?;
+        self.store.store_sav(&key, &sav)?;
+        self.gb.put_sram_dump(sav.sram_dump());
         self.save_loaded = true;
         self.last_save = Some(web_time::Instant::now());
         Ok(true)
     }
 
+    /// The battery save as a `.sav`, with the RTC footer it was imported with for cartridges that
+    /// have a clock
     pub fn export_save_bytes(&mut self) -> Option<Vec<u8>> {
-        self.gb
-            .poll_sram_dump(true)
-            .map(|dump| dump.as_slice().to_vec())
+        let dump = self.gb.poll_sram_dump(true)?;
+        let rtc = self
+            .rom_key
+            .as_deref()
+            .and_then(|key| self.store.load_rtc_footer(key))
+            .unwrap_or_else(|| RtcFooter::new(now_unix()));
+        let layout = SavLayout::of(&self.gb.cartridge);
+        Some(SavFile::new(&dump, layout, Some(rtc)).to_bytes())
     }
 
     pub fn save_status(&self) -> SaveStatus {
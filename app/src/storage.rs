@@ -1,12 +1,15 @@
 mod backend;
 
 use backend::Backend;
+use citrine_gb::persistence::sav::{RtcFooter, SavFile, SavLayout};
 
 pub const THUMB_WIDTH: usize = 80;
 pub const THUMB_HEIGHT: usize = 72;
 
 const BATTERY: &str = "battery.sav";
 const BATTERY_META: &str = "battery.meta";
+/// The RTC footer of an imported `.sav`, written back out on export
+const BATTERY_RTC: &str = "battery.rtc";
 
 fn snapshot_blob(slot: usize) -> String {
     format!("slot{slot}.snap")
@@ -53,6 +56,19 @@ impl SaveStore {
             .write(rom_key, BATTERY_META, &now_unix().to_le_bytes())
     }
 
+    /// Stores the RAM of a `.sav` from another emulator, along with its RTC footer if it has one
+    pub fn store_sav(&self, rom_key: &str, sav: &SavFile) -> std::io::Result<()> {
+        self.store_battery(rom_key, &sav.ram)?;
+        match &sav.rtc {
+            Some(rtc) => self.backend.write(rom_key, BATTERY_RTC, &rtc.to_bytes()),
+            None => self.backend.delete(rom_key, BATTERY_RTC),
+        }
+    }
+
+    pub fn load_rtc_footer(&self, rom_key: &str) -> Option<RtcFooter> {
+        RtcFooter::from_bytes(&self.backend.read(rom_key, BATTERY_RTC)?)
+    }
+
     pub fn battery_saved_at(&self, rom_key: &str) -> Option<u64> {
         self.backend
             .read(rom_key, BATTERY_META)
@@ -75,19 +91,32 @@ impl SaveStore {
     }
 
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn import_legacy_save(&self, rom_key: &str, rom_path: &std::path::Path) -> bool {
+    pub fn import_legacy_save(
+        &self,
+        rom_key: &str,
+        rom_path: &std::path::Path,
+        layout: SavLayout,
+    ) -> bool {
         let Ok(data) = std::fs::read(Self::legacy_save_path(rom_path)) else {
             return false;
         };
-        self.store_battery(rom_key, &data).is_ok()
+        let Ok(sav) = SavFile::parse(&data, layout) else {
+            return false;
+        };
+        self.store_sav(rom_key, &sav).is_ok()
     }
 
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn import_legacy_save_if_new(&self, rom_key: &str, rom_path: &std::path::Path) -> bool {
+    pub fn import_legacy_save_if_new(
+        &self,
+        rom_key: &str,
+        rom_path: &std::path::Path,
+        layout: SavLayout,
+    ) -> bool {
         if self.has_battery(rom_key) {
             return false;
         }
-        self.import_legacy_save(rom_key, rom_path)
+        self.import_legacy_save(rom_key, rom_path, layout)
     }
 
     pub fn store_snapshot(
@@ -186,7 +215,7 @@ fn decompress(data: &[u8]) -> Option<Vec<u8>> {
     Some(out)
 }
 
-fn now_unix() -> u64 {
+pub(crate) fn now_unix() -> u64 {
     web_time::SystemTime::now()
         .duration_since(web_time::SystemTime::UNIX_EPOCH)
         .map(|d| d.as_secs())
@@ -218,6 +247,14 @@ mod tests {
     use super::*;
 
     const KEY: &str = "ABC123";
+    /// Cartridge RAM the size of `save`, so it is imported as it is
+    fn raw(save: &[u8]) -> SavLayout {
+        SavLayout {
+            ram_size: save.len(),
+            mbc2: false,
+            rtc: false,
+        }
+    }
 
     fn store(name: &str) -> (SaveStore, std::path::PathBuf) {
         let root =
@@ -245,6 +282,28 @@ mod tests {
         let _ = std::fs::remove_dir_all(root);
     }
 
+    #[test]
+    fn rtc_footer_is_kept_until_a_save_without_one() {
+        let (store, root) = store("rtc");
+        let rtc = RtcFooter::new(1_700_000_000);
+        let with_rtc = SavFile {
+            ram: vec![1, 2, 3],
+            rtc: Some(rtc),
+        };
+
+        store.store_sav(KEY, &with_rtc).expect("store");
+        assert_eq!(store.load_battery(KEY).as_deref(), Some(&[1, 2, 3][..]));
+        assert_eq!(store.load_rtc_footer(KEY), Some(rtc));
+
+        let without_rtc = SavFile {
+            rtc: None,
+            ..with_rtc
+        };
+        store.store_sav(KEY, &without_rtc).expect("overwrite");
+        assert_eq!(store.load_rtc_footer(KEY), None);
+        let _ = std::fs::remove_dir_all(root);
+    }
+
     #[test]
     fn writes_leave_no_temp_file_behind() {
         let (store, root) = store("atomic");
@@ -280,10 +339,10 @@ mod tests {
         assert!(SaveStore::has_legacy_save(&rom));
         store.store_battery(KEY, b"stored").expect("stored");
 
-        assert!(!store.import_legacy_save_if_new(KEY, &rom));
+        assert!(!store.import_legacy_save_if_new(KEY, &rom, raw(b"from-file")));
         assert_eq!(store.load_battery(KEY).as_deref(), Some(&b"stored"[..]));
 
-        assert!(store.import_legacy_save(KEY, &rom));
+        assert!(store.import_legacy_save(KEY, &rom, raw(b"from-file")));
         assert_eq!(store.load_battery(KEY).as_deref(), Some(&b"from-file"[..]));
         assert!(rom_dir.join("game.sav").exists(), "original is kept");
         let _ = std::fs::remove_dir_all(root);
@@ -298,7 +357,7 @@ mod tests {
         std::fs::write(&rom, b"rom").expect("rom");
 
         assert!(!SaveStore::has_legacy_save(&rom));
-        assert!(!store.import_legacy_save_if_new(KEY, &rom));
+        assert!(!store.import_legacy_save_if_new(KEY, &rom, raw(b"")));
         assert!(store.load_battery(KEY).is_none());
         let _ = std::fs::remove_dir_all(root);
     }
@@ -313,7 +372,7 @@ mod tests {
         std::fs::write(rom_dir.join("game.sav"), b"old-save").expect("sav");
 
         assert!(
-            store.import_legacy_save_if_new(KEY, &rom),
+            store.import_legacy_save_if_new(KEY, &rom, raw(b"old-save")),
             "first import runs"
         );
         assert_eq!(store.load_battery(KEY).as_deref(), Some(&b"old-save"[..]));
@@ -321,13 +380,36 @@ mod tests {
 
         store.store_battery(KEY, b"newer").expect("newer");
         assert!(
-            !store.import_legacy_save_if_new(KEY, &rom),
+            !store.import_legacy_save_if_new(KEY, &rom, raw(b"old-save")),
             "does not re-import"
         );
         assert_eq!(store.load_battery(KEY).as_deref(), Some(&b"newer"[..]));
         let _ = std::fs::remove_dir_all(root);
     }
 
+    #[test]
+    fn legacy_saves_are_fitted_to_the_cartridge_ram() {
+        let (store, root) = store("legacy-fit");
+        let rom_dir = root.join("roms");
+        std::fs::create_dir_all(&rom_dir).expect("rom dir");
+        let rom = rom_dir.join("game.gb");
+        std::fs::write(&rom, b"rom").expect("rom");
+        let layout = raw(&[0; 8]);
+
+        std::fs::write(rom_dir.join("game.sav"), b"short").expect("sav");
+        assert!(store.import_legacy_save(KEY, &rom, layout));
+        assert_eq!(
+            store.load_battery(KEY).as_deref(),
+            Some(&b"short\xFF\xFF\xFF"[..]),
+            "padded like untouched RAM"
+        );
+
+        std::fs::write(rom_dir.join("game.sav"), b"longer-than-ram").expect("sav");
+        assert!(store.import_legacy_save(KEY, &rom, layout));
+        assert_eq!(store.load_battery(KEY).as_deref(), Some(&b"longer-t"[..]));
+        let _ = std::fs::remove_dir_all(root);
+    }
+
     #[test]
     fn snapshot_round_trips_with_metadata() {
         let (store, root) = store("snapshot");
@@ -9,6 +9,8 @@ pub struct FileChannels {
     pub boot_rom_rx: Receiver<PickedFile>,
     pub sav_tx: Sender<PickedFile>,
     pub sav_rx: Receiver<PickedFile>,
+    pub bess_tx: Sender<PickedFile>,
+    pub bess_rx: Receiver<PickedFile>,
     #[cfg(not(target_arch = "wasm32"))]
     pub folder_tx: Sender<std::path::PathBuf>,
     #[cfg(not(target_arch = "wasm32"))]
@@ -22,6 +24,7 @@ impl Default for FileChannels {
         let (rom_tx, rom_rx) = channel();
         let (boot_rom_tx, boot_rom_rx) = channel();
         let (sav_tx, sav_rx) = channel();
+        let (bess_tx, bess_rx) = channel();
         #[cfg(not(target_arch = "wasm32"))]
         let (folder_tx, folder_rx) = channel();
         let (save_tx, save_rx) = channel();
@@ -32,6 +35,8 @@ impl Default for FileChannels {
             boot_rom_rx,
             sav_tx,
             sav_rx,
+            bess_tx,
+            bess_rx,
             #[cfg(not(target_arch = "wasm32"))]
             folder_tx,
             #[cfg(not(target_arch = "wasm32"))]
@@ -0,0 +1,268 @@
+/* C API of the Citrine Game Boy emulator */
+
+#ifndef CITRINE_H
+#define CITRINE_H
+
+/* Generated by cbindgen from capi/src/lib.rs, regenerate with `make capi-header` */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Width of the framebuffer in pixels
+ */
+#define CITRINE_SCREEN_WIDTH 160
+
+/**
+ * Height of the framebuffer in pixels
+ */
+#define CITRINE_SCREEN_HEIGHT 144
+
+#define CITRINE_BUTTON_A 1
+
+#define CITRINE_BUTTON_B 2
+
+#define CITRINE_BUTTON_SELECT 4
+
+#define CITRINE_BUTTON_START 8
+
+#define CITRINE_BUTTON_RIGHT 16
+
+#define CITRINE_BUTTON_LEFT 32
+
+#define CITRINE_BUTTON_UP 64
+
+#define CITRINE_BUTTON_DOWN 128
+
+/**
+ * Result of a fallible call, anything but `CITRINE_ERROR_OK` is a failure
+ */
+typedef enum CitrineError {
+  CITRINE_ERROR_OK = 0,
+  /**
+   * A required pointer was null
+   */
+  CITRINE_ERROR_NULL_POINTER = 1,
+  /**
+   * The call needs a ROM, but none is loaded
+   */
+  CITRINE_ERROR_NO_ROM = 2,
+  /**
+   * The output buffer can't hold the result, the required size was reported
+   */
+  CITRINE_ERROR_BUFFER_TOO_SMALL = 3,
+  CITRINE_ERROR_ROM_TOO_SMALL = 10,
+  CITRINE_ERROR_ROM_TOO_BIG = 11,
+  /**
+   * The ROM header names a cartridge type that isn't supported
+   */
+  CITRINE_ERROR_MISSING_CARTRIDGE_TYPE = 12,
+  /**
+   * The state was written by a newer version or taken with a different ROM
+   */
+  CITRINE_ERROR_INCOMPATIBLE_SNAPSHOT = 20,
+  /**
+   * The state is damaged or not a state at all
+   */
+  CITRINE_ERROR_INVALID_SNAPSHOT = 21,
+  CITRINE_ERROR_IO = 30,
+  /**
+   * Anything else, including a panic inside the emulator
+   */
+  CITRINE_ERROR_OTHER = 255,
+} CitrineError;
+
+/**
+ * The hardware to emulate, see [`GbModel`]. Passed to [`citrine_create`] as a `uint32_t`
+ */
+typedef enum CitrineModel {
+  CITRINE_MODEL_DMG = 0,
+  CITRINE_MODEL_CGB = 1,
+  CITRINE_MODEL_MGB = 2,
+  CITRINE_MODEL_SGB2 = 3,
+  CITRINE_MODEL_CGB_E = 4,
+  CITRINE_MODEL_AGB = 5,
+} CitrineModel;
+
+/**
+ * What the values of [`citrine_native_framebuffer`] are
+ */
+typedef enum CitrineNativeFormat {
+  /**
+   * DMG shades after the palette registers, 0 (lightest) to 3 (darkest)
+   */
+  CITRINE_NATIVE_FORMAT_SHADE = 0,
+  /**
+   * CGB colors from palette RAM: red in bits 0-4, green in 5-9, blue in 10-14
+   */
+  CITRINE_NATIVE_FORMAT_BGR555 = 1,
+} CitrineNativeFormat;
+
+/**
+ * An emulated Game Boy with the ROM it runs
+ */
+typedef struct CitrineGameBoy CitrineGameBoy;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * The version of the emulator as a static, nul-terminated string
+ */
+const char *citrine_version(void);
+
+/**
+ * Creates a Game Boy of the given [`CitrineModel`] without a ROM, free it with
+ * [`citrine_destroy`]. Null if `model` isn't one
+ */
+CitrineGameBoy *citrine_create(uint32_t model);
+
+/**
+ * # Safety
+ *
+ * `gb` must be null or come from [`citrine_create`] and not be used afterwards
+ */
+void citrine_destroy(CitrineGameBoy *gb);
+
+/**
+ * The message of the last failed call on `gb`, empty after a successful one. Valid until the
+ * next call on `gb`
+ *
+ * # Safety
+ *
+ * `gb` must be null or a live handle
+ */
+const char *citrine_last_error(const CitrineGameBoy *gb);
+
+/**
+ * Inserts a ROM and powers the Game Boy on, the data is copied. After a failure no ROM is loaded
+ *
+ * # Safety
+ *
+ * `gb` must be null or a live handle, `data` must point to `size` readable bytes
+ */
+CitrineError citrine_load_rom(CitrineGameBoy *gb, const uint8_t *data, size_t size);
+
+/**
+ * Powers the Game Boy off and on again, keeping the ROM and its battery RAM
+ *
+ * # Safety
+ *
+ * `gb` must be null or a live handle
+ */
+CitrineError citrine_reset(CitrineGameBoy *gb);
+
+/**
+ * Runs until the next frame is complete
+ *
+ * # Safety
+ *
+ * `gb` must be null or a live handle
+ */
+CitrineError citrine_run_frame(CitrineGameBoy *gb);
+
+/**
+ * The last complete frame: [`CITRINE_SCREEN_WIDTH`] x [`CITRINE_SCREEN_HEIGHT`] RGBA pixels, 4
+ * bytes each, row by row. Valid until the next call on `gb`, null if `gb` is null
+ *
+ * # Safety
+ *
+ * `gb` must be null or a live handle
+ */
+const uint8_t *citrine_framebuffer(const CitrineGameBoy *gb);
+
+/**
+ * The last complete frame before any theme or color conversion, one `uint16_t` per pixel, row
+ * by row. Writes what the values are to `format` unless it is null. Valid until the next call on
+ * `gb`, null if `gb` is null
+ *
+ * # Safety
+ *
+ * `gb` must be null or a live handle, `format` null or valid for writes
+ */
+const uint16_t *citrine_native_framebuffer(const CitrineGameBoy *gb, CitrineNativeFormat *format);
+
+/**
+ * Sets the rate of the audio [`citrine_read_audio`] hands out, 44100 Hz by default
+ *
+ * # Safety
+ *
+ * `gb` must be null or a live handle
+ */
+CitrineError citrine_set_sample_rate(CitrineGameBoy *gb, uint32_t sample_rate);
+
+/**
+ * How many stereo frames of audio are waiting to be read
+ *
+ * # Safety
+ *
+ * `gb` must be null or a live handle
+ */
+size_t citrine_audio_frames(const CitrineGameBoy *gb);
+
+/**
+ * Moves up to `frames` stereo frames of audio into `out` as interleaved left/right samples
+ * between -1.0 and 1.0 and returns how many were written. Audio piles up until it is read
+ *
+ * # Safety
+ *
+ * `gb` must be null or a live handle, `out` must point to `frames * 2` writable floats
+ */
+size_t citrine_read_audio(CitrineGameBoy *gb, float *out, size_t frames);
+
+/**
+ * Sets the buttons held from now on, a combination of the `CITRINE_BUTTON_*` bits
+ *
+ * # Safety
+ *
+ * `gb` must be null or a live handle
+ */
+CitrineError citrine_set_buttons(CitrineGameBoy *gb, uint8_t buttons);
+
+/**
+ * Writes a save state to `out` and its length to `size`. With `out` null or `capacity` too small
+ * only the length is reported and `CITRINE_ERROR_BUFFER_TOO_SMALL` returned
+ *
+ * # Safety
+ *
+ * `gb` must be null or a live handle, `out` must be null or point to `capacity` writable bytes,
+ * `size` must be null or writable
+ */
+CitrineError citrine_save_state(CitrineGameBoy *gb, uint8_t *out, size_t capacity, size_t *size);
+
+/**
+ * Restores a state from [`citrine_save_state`] taken with the loaded ROM, states of older
+ * versions are upgraded
+ *
+ * # Safety
+ *
+ * `gb` must be null or a live handle, `data` must point to `size` readable bytes
+ */
+CitrineError citrine_load_state(CitrineGameBoy *gb, const uint8_t *data, size_t size);
+
+/**
+ * Reads `addr` as the CPU sees it, without taking time. 0xFF if `gb` is null
+ *
+ * # Safety
+ *
+ * `gb` must be null or a live handle
+ */
+uint8_t citrine_read_memory(CitrineGameBoy *gb, uint16_t addr);
+
+/**
+ * Writes `addr` as the CPU would, without taking time. Writes to ROM reach the MBC
+ *
+ * # Safety
+ *
+ * `gb` must be null or a live handle
+ */
+CitrineError citrine_write_memory(CitrineGameBoy *gb, uint16_t addr, uint8_t value);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CITRINE_H */
@@ -0,0 +1,498 @@
+//! C API of [`citrine_gb`], for embedding the emulator into tools written in C, C# or anything else
+//! with a C FFI.
+//!
+//! A [`CitrineGameBoy`] is an opaque handle from [`citrine_create`], freed with
+//! [`citrine_destroy`]. Fallible functions return a [`CitrineError`], the full message of the last
+//! failure is kept in the handle for [`citrine_last_error`]. A panic is caught before it reaches
+//! the caller and fails the call. `include/citrine.h` is generated from this file by cbindgen.
+
+use citrine_gb::error::GbError;
+use citrine_gb::gb::joypad::JoypadState;
+use citrine_gb::gb::ppu::types::native_frame::NativeFormat;
+use citrine_gb::gb::{GameBoy, GbModel};
+use citrine_gb::persistence::snapshot::Incompatibility;
+use citrine_gb::rom::Rom;
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{self, AssertUnwindSafe};
+
+/// Width of the framebuffer in pixels
+pub const CITRINE_SCREEN_WIDTH: u32 = 160;
+/// Height of the framebuffer in pixels
+pub const CITRINE_SCREEN_HEIGHT: u32 = 144;
+
+pub const CITRINE_BUTTON_A: u8 = 0x01;
+pub const CITRINE_BUTTON_B: u8 = 0x02;
+pub const CITRINE_BUTTON_SELECT: u8 = 0x04;
+pub const CITRINE_BUTTON_START: u8 = 0x08;
+pub const CITRINE_BUTTON_RIGHT: u8 = 0x10;
+pub const CITRINE_BUTTON_LEFT: u8 = 0x20;
+pub const CITRINE_BUTTON_UP: u8 = 0x40;
+pub const CITRINE_BUTTON_DOWN: u8 = 0x80;
+
+const VERSION: &CStr =
+    match CStr::from_bytes_with_nul(concat!(env!("CARGO_PKG_VERSION"), "\0").as_bytes()) {
+        Ok(version) => version,
+        Err(_) => panic!("version contains a nul byte"),
+    };
+
+/// Result of a fallible call, anything but `CITRINE_ERROR_OK` is a failure
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CitrineError {
+    Ok = 0,
+    /// A required pointer was null
+    NullPointer = 1,
+    /// The call needs a ROM, but none is loaded
+    NoRom = 2,
+    /// The output buffer can't hold the result, the required size was reported
+    BufferTooSmall = 3,
+    RomTooSmall = 10,
+    RomTooBig = 11,
+    /// The ROM header names a cartridge type that isn't supported
+    MissingCartridgeType = 12,
+    /// The state was written by a newer version or taken with a different ROM
+    IncompatibleSnapshot = 20,
+    /// The state is damaged or not a state at all
+    InvalidSnapshot = 21,
+    Io = 30,
+    /// Anything else, including a panic inside the emulator
+    Other = 255,
+}
+
+impl From<&GbError> for CitrineError {
+    fn from(error: &GbError) -> Self {
+        match error {
+            GbError::RomTooSmall => Self::RomTooSmall,
+            GbError::RomTooBig => Self::RomTooBig,
+            GbError::MissingRomCartridgeType => Self::MissingCartridgeType,
+            GbError::IncompatibleSnapshot(Incompatibility::TruncatedHeader)
+            | GbError::Bincode(_)
+            | GbError::RmpDecode(_)
+            | GbError::InvalidBess(_) => Self::InvalidSnapshot,
+            GbError::IncompatibleSnapshot(_) => Self::IncompatibleSnapshot,
+            GbError::IO(_) => Self::Io,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// The hardware to emulate, see [`GbModel`]. Passed to [`citrine_create`] as a `uint32_t`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CitrineModel {
+    Dmg = 0,
+    Cgb = 1,
+    Mgb = 2,
+    Sgb2 = 3,
+    CgbE = 4,
+    Agb = 5,
+}
+
+impl CitrineModel {
+    /// The model a C caller passed, `None` for values outside the enum
+    fn from_raw(model: u32) -> Option<GbModel> {
+        Some(match model {
+            0 => GbModel::Dmg,
+            1 => GbModel::Cgb,
+            2 => GbModel::Mgb,
+            3 => GbModel::Sgb2,
+            4 => GbModel::CgbE,
+            5 => GbModel::Agb,
+            _ => return None,
+        })
+    }
+}
+
+/// What the values of [`citrine_native_framebuffer`] are
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CitrineNativeFormat {
+    /// DMG shades after the palette registers, 0 (lightest) to 3 (darkest)
+    Shade = 0,
+    /// CGB colors from palette RAM: red in bits 0-4, green in 5-9, blue in 10-14
+    Bgr555 = 1,
+}
+
+impl From<NativeFormat> for CitrineNativeFormat {
+    fn from(format: NativeFormat) -> Self {
+        match format {
+            NativeFormat::Shade => Self::Shade,
+            NativeFormat::Bgr555 => Self::Bgr555,
+        }
+    }
+}
+
+/// An emulated Game Boy with the ROM it runs
+pub struct CitrineGameBoy {
+    gb: GameBoy,
+    rom: Option<Rom>,
+    last_error: CString,
+}
+
+impl CitrineGameBoy {
+    /// Records the outcome of a call for [`citrine_last_error`]
+    fn finish(&mut self, result: Result<(), CitrineError>, message: impl ToString) -> CitrineError {
+        match result {
+            Ok(()) => {
+                self.last_error = CString::default();
+                CitrineError::Ok
+            }
+            Err(error) => {
+                let message = message.to_string().replace('\0', "");
+                self.last_error = CString::new(message).unwrap_or_default();
+                error
+            }
+        }
+    }
+
+    fn finish_gb(&mut self, result: Result<(), GbError>) -> CitrineError {
+        match result {
+            Ok(()) => self.finish(Ok(()), ""),
+            Err(error) => self.finish(Err((&error).into()), error),
+        }
+    }
+}
+
+/// Runs the body of an exported function on the handle. A panic must not unwind into the caller,
+/// it fails the call with `CITRINE_ERROR_OTHER` and its message instead. The Game Boy may be left
+/// in the middle of an instruction
+fn with_handle(
+    gb: Option<&mut CitrineGameBoy>,
+    body: impl FnOnce(&mut CitrineGameBoy) -> CitrineError,
+) -> CitrineError {
+    let Some(gb) = gb else {
+        return CitrineError::NullPointer;
+    };
+    match panic::catch_unwind(AssertUnwindSafe(|| body(&mut *gb))) {
+        Ok(error) => error,
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("unknown cause");
+            gb.finish(Err(CitrineError::Other), format!("panicked: {message}"))
+        }
+    }
+}
+
+/// Like [`with_handle`] for functions without an error code, a panic returns `fallback`
+fn catch_panic_or<T>(fallback: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(fallback)
+}
+
+/// The version of the emulator as a static, nul-terminated string
+#[unsafe(no_mangle)]
+pub extern "C" fn citrine_version() -> *const c_char {
+    catch_panic_or(std::ptr::null(), || VERSION.as_ptr())
+}
+
+/// Creates a Game Boy of the given [`CitrineModel`] without a ROM, free it with
+/// [`citrine_destroy`]. Null if `model` isn't one
+#[unsafe(no_mangle)]
+pub extern "C" fn citrine_create(model: u32) -> *mut CitrineGameBoy {
+    catch_panic_or(std::ptr::null_mut(), || {
+        let Some(model) = CitrineModel::from_raw(model) else {
+            return std::ptr::null_mut();
+        };
+        Box::into_raw(Box::new(CitrineGameBoy {
+            gb: GameBoy::new_empty(model),
+            rom: None,
+            last_error: CString::default(),
+        }))
+    })
+}
+
+/// # Safety
+///
+/// `gb` must be null or come from [`citrine_create`] and not be used afterwards
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn citrine_destroy(gb: *mut CitrineGameBoy) {
+    if !gb.is_null() {
+        let gb = unsafe { Box::from_raw(gb) };
+        catch_panic_or((), || drop(gb));
+    }
+}
+
+/// The message of the last failed call on `gb`, empty after a successful one. Valid until the
+/// next call on `gb`
+///
+/// # Safety
+///
+/// `gb` must be null or a live handle
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn citrine_last_error(gb: *const CitrineGameBoy) -> *const c_char {
+    let gb = unsafe { gb.as_ref() };
+    catch_panic_or(c"".as_ptr(), || match gb {
+        Some(gb) => gb.last_error.as_ptr(),
+        None => c"".as_ptr(),
+    })
+}
+
+/// Inserts a ROM and powers the Game Boy on, the data is copied. After a failure no ROM is loaded
+///
+/// # Safety
+///
+/// `gb` must be null or a live handle, `data` must point to `size` readable bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn citrine_load_rom(
+    gb: *mut CitrineGameBoy,
+    data: *const u8,
+    size: usize,
+) -> CitrineError {
+    with_handle(unsafe { gb.as_mut() }, |gb| {
+        if data.is_null() {
+            return gb.finish(Err(CitrineError::NullPointer), "data is null");
+        }
+
+        let rom = Rom::new(unsafe { std::slice::from_raw_parts(data, size) });
+        let result = gb.gb.load_rom(&rom);
+        gb.rom = result.is_ok().then_some(rom);
+        gb.finish_gb(result)
+    })
+}
+
+/// Powers the Game Boy off and on again, keeping the ROM and its battery RAM
+///
+/// # Safety
+///
+/// `gb` must be null or a live handle
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn citrine_reset(gb: *mut CitrineGameBoy) -> CitrineError {
+    with_handle(unsafe { gb.as_mut() }, |gb| {
+        gb.gb.soft_reset();
+        gb.finish(Ok(()), "")
+    })
+}
+
+/// Runs until the next frame is complete
+///
+/// # Safety
+///
+/// `gb` must be null or a live handle
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn citrine_run_frame(gb: *mut CitrineGameBoy) -> CitrineError {
+    with_handle(unsafe { gb.as_mut() }, |gb| {
+        if gb.rom.is_none() {
+            return gb.finish(Err(CitrineError::NoRom), "no ROM loaded");
+        }
+        gb.gb.run_frame();
+        gb.finish(Ok(()), "")
+    })
+}
+
+/// The last complete frame: [`CITRINE_SCREEN_WIDTH`] x [`CITRINE_SCREEN_HEIGHT`] RGBA pixels, 4
+/// bytes each, row by row. Valid until the next call on `gb`, null if `gb` is null
+///
+/// # Safety
+///
+/// `gb` must be null or a live handle
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn citrine_framebuffer(gb: *const CitrineGameBoy) -> *const u8 {
+    let gb = unsafe { gb.as_ref() };
+    catch_panic_or(std::ptr::null(), || match gb {
+        Some(gb) => gb.gb.frame().as_slice().as_ptr(),
+        None => std::ptr::null(),
+    })
+}
+
+/// The last complete frame before any theme or color conversion, one `uint16_t` per pixel, row
+/// by row. Writes what the values are to `format` unless it is null. Valid until the next call on
+/// `gb`, null if `gb` is null
+///
+/// # Safety
+///
+/// `gb` must be null or a live handle, `format` null or valid for writes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn citrine_native_framebuffer(
+    gb: *const CitrineGameBoy,
+    format: *mut CitrineNativeFormat,
+) -> *const u16 {
+    let Some(gb) = (unsafe { gb.as_ref() }) else {
+        return std::ptr::null();
+    };
+    let format = unsafe { format.as_mut() };
+    catch_panic_or(std::ptr::null(), || {
+        let frame = gb.gb.native_frame();
+        if let Some(format) = format {
+            *format = frame.format().into();
+        }
+        frame.as_slice().as_ptr()
+    })
+}
+
+/// Sets the rate of the audio [`citrine_read_audio`] hands out, 44100 Hz by default
+///
+/// # Safety
+///
+/// `gb` must be null or a live handle
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn citrine_set_sample_rate(
+    gb: *mut CitrineGameBoy,
+    sample_rate: u32,
+) -> CitrineError {
+    with_handle(unsafe { gb.as_mut() }, |gb| {
+        if sample_rate == 0 {
+            return gb.finish(Err(CitrineError::Other), "sample rate is 0");
+        }
+        gb.gb.apu.set_sample_rate(sample_rate);
+        gb.finish(Ok(()), "")
+    })
+}
+
+/// How many stereo frames of audio are waiting to be read
+///
+/// # Safety
+///
+/// `gb` must be null or a live handle
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn citrine_audio_frames(gb: *const CitrineGameBoy) -> usize {
+    let gb = unsafe { gb.as_ref() };
+    catch_panic_or(0, || match gb {
+        Some(gb) => gb.gb.apu.audio_buffer.len() / 2,
+        None => 0,
+    })
+}
+
+/// Moves up to `frames` stereo frames of audio into `out` as interleaved left/right samples
+/// between -1.0 and 1.0 and returns how many were written. Audio piles up until it is read
+///
+/// # Safety
+///
+/// `gb` must be null or a live handle, `out` must point to `frames * 2` writable floats
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn citrine_read_audio(
+    gb: *mut CitrineGameBoy,
+    out: *mut f32,
+    frames: usize,
+) -> usize {
+    let Some(gb) = (unsafe { gb.as_mut() }) else {
+        return 0;
+    };
+    if out.is_null() {
+        return 0;
+    }
+
+    let buffer = &mut gb.gb.apu.audio_buffer;
+    let samples = frames.saturating_mul(2).min(buffer.len() / 2 * 2);
+    let out = unsafe { std::slice::from_raw_parts_mut(out, samples) };
+    catch_panic_or(0, || {
+        out.copy_from_slice(&buffer[..samples]);
+        buffer.drain(..samples);
+        samples / 2
+    })
+}
+
+/// Sets the buttons held from now on, a combination of the `CITRINE_BUTTON_*` bits
+///
+/// # Safety
+///
+/// `gb` must be null or a live handle
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn citrine_set_buttons(gb: *mut CitrineGameBoy, buttons: u8) -> CitrineError {
+    with_handle(unsafe { gb.as_mut() }, |gb| {
+        let buttons = JoypadState::from_bits_truncate(buttons);
+        gb.gb.press_button(buttons);
+        gb.gb.release_button(buttons.complement());
+        gb.finish(Ok(()), "")
+    })
+}
+
+/// Writes a save state to `out` and its length to `size`. With `out` null or `capacity` too small
+/// only the length is reported and `CITRINE_ERROR_BUFFER_TOO_SMALL` returned
+///
+/// # Safety
+///
+/// `gb` must be null or a live handle, `out` must be null or point to `capacity` writable bytes,
+/// `size` must be null or writable
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn citrine_save_state(
+    gb: *mut CitrineGameBoy,
+    out: *mut u8,
+    capacity: usize,
+    size: *mut usize,
+) -> CitrineError {
+    with_handle(unsafe { gb.as_mut() }, |gb| {
+        if gb.rom.is_none() {
+            return gb.finish(Err(CitrineError::NoRom), "no ROM loaded");
+        }
+
+        let state = match gb.gb.dump_full() {
+            Ok(state) => state,
+            Err(error) => return gb.finish_gb(Err(error)),
+        };
+        if let Some(size) = unsafe { size.as_mut() } {
+            *size = state.len();
+        }
+        if out.is_null() || capacity < state.len() {
+            let message = format!("the state needs {} bytes", state.len());
+            return gb.finish(Err(CitrineError::BufferTooSmall), message);
+        }
+
+        unsafe { std::ptr::copy_nonoverlapping(state.as_ptr(), out, state.len()) };
+        gb.finish(Ok(()), "")
+    })
+}
+
+/// Restores a state from [`citrine_save_state`] taken with the loaded ROM, states of older
+/// versions are upgraded
+///
+/// # Safety
+///
+/// `gb` must be null or a live handle, `data` must point to `size` readable bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn citrine_load_state(
+    gb: *mut CitrineGameBoy,
+    data: *const u8,
+    size: usize,
+) -> CitrineError {
+    with_handle(unsafe { gb.as_mut() }, |gb| {
+        if data.is_null() {
+            return gb.finish(Err(CitrineError::NullPointer), "data is null");
+        }
+        let Some(rom) = &gb.rom else {
+            return gb.finish(Err(CitrineError::NoRom), "no ROM loaded");
+        };
+
+        let data = unsafe { std::slice::from_raw_parts(data, size) };
+        match GameBoy::from_dump(data, rom) {
+            Ok(mut state) => {
+                state.apu.set_sample_rate(gb.gb.apu.output_sample_rate);
+                gb.gb = state;
+                gb.finish(Ok(()), "")
+            }
+            Err(error) => gb.finish_gb(Err(error)),
+        }
+    })
+}
+
+/// Reads `addr` as the CPU sees it, without taking time. 0xFF if `gb` is null
+///
+/// # Safety
+///
+/// `gb` must be null or a live handle
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn citrine_read_memory(gb: *mut CitrineGameBoy, addr: u16) -> u8 {
+    let gb = unsafe { gb.as_mut() };
+    catch_panic_or(0xFF, || match gb {
+        Some(gb) => gb.gb.read_memory(addr),
+        None => 0xFF,
+    })
+}
+
+/// Writes `addr` as the CPU would, without taking time. Writes to ROM reach the MBC
+///
+/// # Safety
+///
+/// `gb` must be null or a live handle
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn citrine_write_memory(
+    gb: *mut CitrineGameBoy,
+    addr: u16,
+    value: u8,
+) -> CitrineError {
+    with_handle(unsafe { gb.as_mut() }, |gb| {
+        gb.gb.write_memory(addr, value);
+        gb.finish(Ok(()), "")
+    })
+}
@@ -0,0 +1,163 @@
+//! The C API driven the way a C caller would, through raw pointers
+
+use citrine::*;
+use citrine_gb::gb::joypad::JoypadState;
+use std::ffi::CStr;
+
+/// MBC1 with 8 KiB of battery RAM: enables the RAM, stores 0x42 at 0xA000, copies 0xA001 to
+/// 0xA002 and spins
+fn battery_rom() -> Vec<u8> {
+    let mut data = vec![0u8; 0x8000];
+    data[0x0147] = 0x03;
+    data[0x0149] = 0x02;
+    data[0x0100..0x0114].copy_from_slice(&[
+        0x3E, 0x0A, 0xEA, 0x00, 0x00, 0x3E, 0x42, 0xEA, 0x00, 0xA0, 0xFA, 0x01, 0xA0, 0xEA, 0x02,
+        0xA0, 0x00, 0x00, 0x18, 0xFE,
+    ]);
+    data
+}
+
+fn create_with(rom: &[u8]) -> *mut CitrineGameBoy {
+    let gb = citrine_create(CitrineModel::Dmg as u32);
+    assert!(!gb.is_null());
+    let result = unsafe { citrine_load_rom(gb, rom.as_ptr(), rom.len()) };
+    assert_eq!(result, CitrineError::Ok);
+    gb
+}
+
+fn last_error(gb: *const CitrineGameBoy) -> String {
+    unsafe { CStr::from_ptr(citrine_last_error(gb)) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[test]
+fn button_bits_match_the_joypad() {
+    assert_eq!(CITRINE_BUTTON_A, JoypadState::A.bits());
+    assert_eq!(CITRINE_BUTTON_B, JoypadState::B.bits());
+    assert_eq!(CITRINE_BUTTON_SELECT, JoypadState::SELECT.bits());
+    assert_eq!(CITRINE_BUTTON_START, JoypadState::START.bits());
+    assert_eq!(CITRINE_BUTTON_RIGHT, JoypadState::RIGHT.bits());
+    assert_eq!(CITRINE_BUTTON_LEFT, JoypadState::LEFT.bits());
+    assert_eq!(CITRINE_BUTTON_UP, JoypadState::UP.bits());
+    assert_eq!(CITRINE_BUTTON_DOWN, JoypadState::DOWN.bits());
+}
+
+#[test]
+fn unknown_models_are_rejected() {
+    assert!(citrine_create(6).is_null());
+    assert!(citrine_create(u32::MAX).is_null());
+
+    let gb = citrine_create(CitrineModel::Agb as u32);
+    assert!(!gb.is_null());
+    unsafe { citrine_destroy(gb) };
+}
+
+#[test]
+fn errors_are_reported_with_a_message() {
+    assert_eq!(
+        unsafe { citrine_run_frame(std::ptr::null_mut()) },
+        CitrineError::NullPointer
+    );
+
+    let gb = citrine_create(CitrineModel::Cgb as u32);
+    assert_eq!(unsafe { citrine_run_frame(gb) }, CitrineError::NoRom);
+    assert_eq!(last_error(gb), "no ROM loaded");
+
+    let rom = [0u8; 0x100];
+    assert_eq!(
+        unsafe { citrine_load_rom(gb, rom.as_ptr(), rom.len()) },
+        CitrineError::RomTooSmall
+    );
+    assert_eq!(last_error(gb), "ROM too small");
+
+    let rom = battery_rom();
+    assert_eq!(
+        unsafe { citrine_load_rom(gb, rom.as_ptr(), rom.len()) },
+        CitrineError::Ok
+    );
+    assert_eq!(last_error(gb), "");
+    unsafe { citrine_destroy(gb) };
+}
+
+#[test]
+fn runs_frames_with_video_and_audio() {
+    let gb = create_with(&battery_rom());
+    assert_eq!(
+        unsafe { citrine_set_sample_rate(gb, 48_000) },
+        CitrineError::Ok
+    );
+    for _ in 0..60 {
+        assert_eq!(unsafe { citrine_run_frame(gb) }, CitrineError::Ok);
+    }
+
+    let size = (CITRINE_SCREEN_WIDTH * CITRINE_SCREEN_HEIGHT * 4) as usize;
+    let frame = unsafe { std::slice::from_raw_parts(citrine_framebuffer(gb), size) };
+    assert!(frame.chunks_exact(4).all(|pixel| pixel[3] == 0xFF));
+    let mut format = CitrineNativeFormat::Bgr555;
+    let native = unsafe {
+        std::slice::from_raw_parts(citrine_native_framebuffer(gb, &mut format), size / 4)
+    };
+    assert_eq!(format, CitrineNativeFormat::Shade);
+    assert!(native.iter().all(|&shade| shade < 4));
+
+    // A second of audio, give or take what the resampler holds back
+    let frames = unsafe { citrine_audio_frames(gb) };
+    assert!(frames.abs_diff(48_000) < 1_000, "{frames} frames");
+    let mut audio = vec![0.0f32; 1_000 * 2];
+    assert_eq!(
+        unsafe { citrine_read_audio(gb, audio.as_mut_ptr(), 1_000) },
+        1_000
+    );
+    assert_eq!(unsafe { citrine_audio_frames(gb) }, frames - 1_000);
+    unsafe { citrine_destroy(gb) };
+}
+
+#[test]
+fn memory_is_read_and_written() {
+    let gb = create_with(&battery_rom());
+    unsafe { citrine_run_frame(gb) };
+    assert_eq!(unsafe { citrine_read_memory(gb, 0xA000) }, 0x42);
+
+    unsafe { citrine_write_memory(gb, 0xC000, 0x99) };
+    assert_eq!(unsafe { citrine_read_memory(gb, 0xC000) }, 0x99);
+    unsafe { citrine_destroy(gb) };
+}
+
+#[test]
+fn states_go_through_caller_buffers() {
+    let gb = create_with(&battery_rom());
+    unsafe { citrine_run_frame(gb) };
+
+    let mut size = 0;
+    let result = unsafe { citrine_save_state(gb, std::ptr::null_mut(), 0, &mut size) };
+    assert_eq!(result, CitrineError::BufferTooSmall);
+    assert!(size > 0);
+    let mut state = vec![0u8; size];
+    let result = unsafe { citrine_save_state(gb, state.as_mut_ptr(), size, &mut size) };
+    assert_eq!(result, CitrineError::Ok);
+
+    let result = unsafe { citrine_load_state(gb, state.as_ptr(), size) };
+    assert_eq!(result, CitrineError::Ok);
+
+    let result = unsafe { citrine_load_state(gb, state.as_ptr(), 10) };
+    assert_eq!(result, CitrineError::InvalidSnapshot);
+    assert!(!last_error(gb).is_empty());
+
+    let mut corrupted = state.clone();
+    let body = size / 2;
+    corrupted[body..].fill(0xC1);
+    let result = unsafe { citrine_load_state(gb, corrupted.as_ptr(), size) };
+    assert_eq!(result, CitrineError::InvalidSnapshot);
+    // A state that fails to load leaves the machine alone
+    assert_eq!(unsafe { citrine_read_memory(gb, 0xA000) }, 0x42);
+
+    // States only load with the ROM they were taken with
+    let mut other_rom = battery_rom();
+    other_rom[0x0134] = b'X';
+    let other = create_with(&other_rom);
+    let result = unsafe { citrine_load_state(other, state.as_ptr(), size) };
+    assert_eq!(result, CitrineError::IncompatibleSnapshot);
+    unsafe { citrine_destroy(other) };
+    unsafe { citrine_destroy(gb) };
+}
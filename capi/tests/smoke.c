@@ -0,0 +1,98 @@
+/*
+ * Exercises the C API from C: builds a tiny ROM, runs it and checks video, audio, input, memory
+ * and save states. Run it with `make capi-test`, it exits with 0 when every check passes.
+ */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "citrine.h"
+
+#define ROM_SIZE 0x8000
+
+static int failures = 0;
+
+#define CHECK(condition)                                                           \
+    do {                                                                           \
+        if (!(condition)) {                                                        \
+            fprintf(stderr, "%s:%d: %s failed\n", __FILE__, __LINE__, #condition); \
+            failures++;                                                            \
+        }                                                                          \
+    } while (0)
+
+/* MBC1 with 8 KiB of battery RAM: enables the RAM, stores 0x42 at 0xA000 and spins */
+static void build_rom(uint8_t *rom) {
+    static const uint8_t program[] = {
+        0x3E, 0x0A, 0xEA, 0x00, 0x00, 0x3E, 0x42, 0xEA, 0x00, 0xA0, 0x18, 0xFE,
+    };
+    memset(rom, 0, ROM_SIZE);
+    rom[0x0147] = 0x03;
+    rom[0x0149] = 0x02;
+    memcpy(rom + 0x0100, program, sizeof(program));
+}
+
+int main(void) {
+    static uint8_t rom[ROM_SIZE];
+    static float audio[4096 * 2];
+    build_rom(rom);
+
+    printf("Citrine %s\n", citrine_version());
+
+    CHECK(citrine_create(42) == NULL);
+    CitrineGameBoy *gb = citrine_create(CITRINE_MODEL_DMG);
+    CHECK(gb != NULL);
+
+    CHECK(citrine_run_frame(gb) == CITRINE_ERROR_NO_ROM);
+    CHECK(strcmp(citrine_last_error(gb), "no ROM loaded") == 0);
+    CHECK(citrine_load_rom(gb, rom, 0x100) == CITRINE_ERROR_ROM_TOO_SMALL);
+    CHECK(citrine_load_rom(gb, rom, ROM_SIZE) == CITRINE_ERROR_OK);
+    CHECK(citrine_set_sample_rate(gb, 48000) == CITRINE_ERROR_OK);
+
+    for (int frame = 0; frame < 60; frame++) {
+        CHECK(citrine_run_frame(gb) == CITRINE_ERROR_OK);
+    }
+
+    const uint8_t *pixels = citrine_framebuffer(gb);
+    CHECK(pixels != NULL);
+    for (size_t pixel = 0; pixel < CITRINE_SCREEN_WIDTH * CITRINE_SCREEN_HEIGHT; pixel++) {
+        if (pixels[pixel * 4 + 3] != 0xFF) {
+            CHECK(!"every pixel is opaque");
+            break;
+        }
+    }
+
+    size_t frames = citrine_audio_frames(gb);
+    CHECK(frames > 47000 && frames < 49000);
+    CHECK(citrine_read_audio(gb, audio, 4096) == 4096);
+    CHECK(citrine_audio_frames(gb) == frames - 4096);
+
+    CHECK(citrine_read_memory(gb, 0xA000) == 0x42);
+    CHECK(citrine_write_memory(gb, 0xFF00, 0x20) == CITRINE_ERROR_OK);
+    CHECK(citrine_set_buttons(gb, CITRINE_BUTTON_RIGHT | CITRINE_BUTTON_A) == CITRINE_ERROR_OK);
+    CHECK((citrine_read_memory(gb, 0xFF00) & 0x01) == 0x00);
+    CHECK(citrine_set_buttons(gb, 0) == CITRINE_ERROR_OK);
+    CHECK((citrine_read_memory(gb, 0xFF00) & 0x01) == 0x01);
+
+    size_t size = 0;
+    CHECK(citrine_save_state(gb, NULL, 0, &size) == CITRINE_ERROR_BUFFER_TOO_SMALL);
+    uint8_t *state = malloc(size);
+    CHECK(state != NULL);
+    CHECK(citrine_save_state(gb, state, size, &size) == CITRINE_ERROR_OK);
+
+    CHECK(citrine_write_memory(gb, 0xC000, 0x99) == CITRINE_ERROR_OK);
+    CHECK(citrine_load_state(gb, state, size) == CITRINE_ERROR_OK);
+    CHECK(citrine_read_memory(gb, 0xC000) != 0x99);
+    CHECK(citrine_load_state(gb, state, 10) == CITRINE_ERROR_INCOMPATIBLE_SNAPSHOT);
+    CHECK(strlen(citrine_last_error(gb)) > 0);
+
+    free(state);
+    citrine_destroy(gb);
+
+    if (failures > 0) {
+        fprintf(stderr, "%d checks failed\n", failures);
+        return 1;
+    }
+    printf("All checks passed\n");
+    return 0;
+}
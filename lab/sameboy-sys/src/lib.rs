@@ -1,5 +1,5 @@
 //! Hand-written FFI for the subset of the SameBoy core the lab needs, mirrored from
-//! `lab/SameBoy/Core/{gb,display,joypad,model}.h`. Keep in sync when the submodule moves.
+//! `lab/SameBoy/Core/{gb,apu,display,joypad,model}.h`. Keep in sync when the submodule moves.
 #![allow(non_camel_case_types, non_snake_case, non_upper_case_globals)]
 
 use std::ffi::{c_int, c_uint, c_void};
@@ -11,7 +11,11 @@ pub struct GB_gameboy_t {
 
 pub type GB_model_t = c_int;
 pub const GB_MODEL_DMG_B: GB_model_t = 0x002;
+pub const GB_MODEL_MGB: GB_model_t = 0x100;
+/// SGB2 hardware without the SNES side, so the screen stays 160x144 without a border.
+pub const GB_MODEL_SGB2_NO_SFC: GB_model_t = 0x181;
 pub const GB_MODEL_CGB_E: GB_model_t = 0x205;
+pub const GB_MODEL_AGB_A: GB_model_t = 0x207;
 
 pub type GB_key_t = c_int;
 pub const GB_KEY_RIGHT: GB_key_t = 0;
@@ -25,6 +29,15 @@ pub const GB_KEY_START: GB_key_t = 7;
 
 pub type GB_vblank_type_t = c_int;
 
+pub type GB_channel_t = c_int;
+pub const GB_SQUARE_1: GB_channel_t = 0;
+pub const GB_SQUARE_2: GB_channel_t = 1;
+pub const GB_WAVE: GB_channel_t = 2;
+pub const GB_NOISE: GB_channel_t = 3;
+
+pub type GB_highpass_mode_t = c_int;
+pub const GB_HIGHPASS_ACCURATE: GB_highpass_mode_t = 1;
+
 pub type GB_color_correction_mode_t = c_int;
 pub const GB_COLOR_CORRECTION_DISABLED: GB_color_correction_mode_t = 0;
 
@@ -42,6 +55,13 @@ pub struct GB_palette_t {
     pub colors: [GB_color_s; 5],
 }
 
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct GB_sample_t {
+    pub left: i16,
+    pub right: i16,
+}
+
 /// The C type is a union; its 16-bit view is laid out exactly as these fields.
 #[repr(C)]
 pub struct GB_registers_t {
@@ -56,6 +76,7 @@ pub struct GB_registers_t {
 pub type GB_vblank_callback_t = extern "C" fn(gb: *mut GB_gameboy_t, ty: GB_vblank_type_t);
 pub type GB_rgb_encode_callback_t =
     extern "C" fn(gb: *mut GB_gameboy_t, r: u8, g: u8, b: u8) -> u32;
+pub type GB_sample_callback_t = extern "C" fn(gb: *mut GB_gameboy_t, sample: *mut GB_sample_t);
 
 unsafe extern "C" {
     pub fn GB_alloc() -> *mut GB_gameboy_t;
@@ -68,6 +89,13 @@ unsafe extern "C" {
 
     pub fn GB_run(gb: *mut GB_gameboy_t) -> c_uint;
 
+    /// Reads SameBoy's own save states as well as BESS ones; returns 0 on success.
+    pub fn GB_load_state_from_buffer(
+        gb: *mut GB_gameboy_t,
+        buffer: *const u8,
+        length: usize,
+    ) -> c_int;
+
     /// Disables the real-time sync that otherwise `nanosleep`s the core to ~60 fps — without it a
     /// 10k-frame run spends ~167 s asleep.
     pub fn GB_set_turbo_mode(gb: *mut GB_gameboy_t, on: bool, no_frame_skip: bool);
@@ -78,6 +106,13 @@ unsafe extern "C" {
     pub fn GB_get_screen_width(gb: *mut GB_gameboy_t) -> c_uint;
     pub fn GB_get_screen_height(gb: *mut GB_gameboy_t) -> c_uint;
 
+    pub fn GB_set_sample_rate(gb: *mut GB_gameboy_t, sample_rate: c_uint);
+    pub fn GB_set_highpass_filter_mode(gb: *mut GB_gameboy_t, mode: GB_highpass_mode_t);
+    /// Called once per output sample while a sample rate is set.
+    pub fn GB_apu_set_sample_callback(gb: *mut GB_gameboy_t, callback: GB_sample_callback_t);
+    /// The current envelope volume of a channel, 0 while it is inactive.
+    pub fn GB_get_channel_volume(gb: *mut GB_gameboy_t, channel: GB_channel_t) -> u8;
+
     pub fn GB_set_key_state(gb: *mut GB_gameboy_t, index: GB_key_t, pressed: bool);
 
     pub fn GB_set_palette(gb: *mut GB_gameboy_t, palette: *const GB_palette_t);
@@ -0,0 +1,267 @@
+//! Audio comparison between two [`AudioChunk`] streams: sample-aligned RMS error, log-spectral
+//! distance and the timing of every channel's note onsets.
+//!
+//! Both streams start at power-on and run at [`crate::emulator::AUDIO_SAMPLE_RATE`], so sample N of
+//! each covers the same emulated instant and no further alignment is needed.
+
+use crate::emulator::AudioChunk;
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+/// Mono samples per spectrum, ~21 ms at 48 kHz.
+pub const SPECTRUM_SIZE: usize = 1024;
+/// A reference onset without a candidate onset this close counts as unmatched.
+pub const ONSET_WINDOW_MS: f64 = 50.0;
+/// Power floor of the spectra, so silence on both sides compares as equal rather than -inf dB.
+const POWER_FLOOR: f64 = 1e-10;
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AudioReport {
+    pub sample_rate: u32,
+    /// Stereo samples compared, the shorter of the two streams.
+    pub compared_samples: usize,
+    /// Root mean square of the per-sample difference over both outputs; full scale is 1.0.
+    pub rms_error: f64,
+    /// Mean log-spectral distance over [`SPECTRUM_SIZE`]-sample blocks of the mono downmix.
+    pub spectral_distance_db: f64,
+    pub channels: Vec<ChannelOnsets>,
+}
+
+/// An onset is a channel becoming audible: its volume rising from 0.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ChannelOnsets {
+    /// 1-based, like the APU's channel names.
+    pub channel: usize,
+    pub reference_onsets: usize,
+    pub candidate_onsets: usize,
+    /// Reference onsets with a candidate onset within [`ONSET_WINDOW_MS`].
+    pub matched: usize,
+    /// Mean absolute offset of the matched onsets; `None` if none matched.
+    pub mean_offset_ms: Option<f64>,
+    pub max_offset_ms: Option<f64>,
+}
+
+#[derive(Default)]
+struct Stream {
+    /// Interleaved stereo samples not yet paired with the other stream.
+    pending: VecDeque<f32>,
+    previous_volumes: [u8; 4],
+    onsets: [Vec<usize>; 4],
+    received: usize,
+}
+
+impl Stream {
+    fn push(&mut self, chunk: &AudioChunk) {
+        self.pending.extend(&chunk.samples);
+        for (channel, volumes) in chunk.channel_volumes.iter().enumerate() {
+            let mut previous = self.previous_volumes[channel];
+            for (i, &volume) in volumes.iter().enumerate() {
+                if previous == 0 && volume != 0 {
+                    self.onsets[channel].push(self.received + i);
+                }
+                previous = volume;
+            }
+            self.previous_volumes[channel] = previous;
+        }
+        self.received += chunk.channel_volumes[0].len();
+    }
+}
+
+/// Compares audio as it streams in, holding only the samples one side is ahead by.
+pub struct AudioComparator {
+    sample_rate: u32,
+    reference: Stream,
+    candidate: Stream,
+    compared: usize,
+    squared_error: f64,
+    reference_block: Vec<f64>,
+    candidate_block: Vec<f64>,
+    spectral_distance_sum: f64,
+    blocks: usize,
+}
+
+impl AudioComparator {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            reference: Stream::default(),
+            candidate: Stream::default(),
+            compared: 0,
+            squared_error: 0.0,
+            reference_block: Vec::with_capacity(SPECTRUM_SIZE),
+            candidate_block: Vec::with_capacity(SPECTRUM_SIZE),
+            spectral_distance_sum: 0.0,
+            blocks: 0,
+        }
+    }
+
+    pub fn push_reference(&mut self, chunk: &AudioChunk) {
+        self.reference.push(chunk);
+        self.compare_pending();
+    }
+
+    pub fn push_candidate(&mut self, chunk: &AudioChunk) {
+        self.candidate.push(chunk);
+        self.compare_pending();
+    }
+
+    fn compare_pending(&mut self) {
+        let pairs = self
+            .reference
+            .pending
+            .len()
+            .min(self.candidate.pending.len())
+            / 2;
+        for _ in 0..pairs {
+            let next = |stream: &mut Stream| {
+                let left = stream.pending.pop_front().unwrap_or_default() as f64;
+                let right = stream.pending.pop_front().unwrap_or_default() as f64;
+                (left, right)
+            };
+            let (ref_l, ref_r) = next(&mut self.reference);
+            let (cand_l, cand_r) = next(&mut self.candidate);
+
+            self.squared_error += (ref_l - cand_l).powi(2) + (ref_r - cand_r).powi(2);
+            self.compared += 1;
+
+            self.reference_block.push((ref_l + ref_r) / 2.0);
+            self.candidate_block.push((cand_l + cand_r) / 2.0);
+            if self.reference_block.len() == SPECTRUM_SIZE {
+                self.spectral_distance_sum +=
+                    log_spectral_distance(&self.reference_block, &self.candidate_block);
+                self.blocks += 1;
+                self.reference_block.clear();
+                self.candidate_block.clear();
+            }
+        }
+    }
+
+    pub fn finish(self) -> AudioReport {
+        let window = (ONSET_WINDOW_MS / 1000.0 * self.sample_rate as f64) as usize;
+        let to_ms = |samples: usize| samples as f64 * 1000.0 / self.sample_rate as f64;
+
+        let channels = (0..4)
+            .map(|channel| {
+                // Onsets past the compared span have nothing to be matched against yet.
+                let in_span = |onsets: &[usize]| -> Vec<usize> {
+                    onsets
+                        .iter()
+                        .copied()
+                        .filter(|&onset| onset < self.compared)
+                        .collect()
+                };
+                let reference = in_span(&self.reference.onsets[channel]);
+                let candidate = in_span(&self.candidate.onsets[channel]);
+                let offsets = match_onsets(&reference, &candidate, window);
+
+                ChannelOnsets {
+                    channel: channel + 1,
+                    reference_onsets: reference.len(),
+                    candidate_onsets: candidate.len(),
+                    matched: offsets.len(),
+                    mean_offset_ms: (!offsets.is_empty())
+                        .then(|| to_ms(offsets.iter().sum()) / offsets.len() as f64),
+                    max_offset_ms: offsets.iter().max().map(|&offset| to_ms(offset)),
+                }
+            })
+            .collect();
+
+        AudioReport {
+            sample_rate: self.sample_rate,
+            compared_samples: self.compared,
+            rms_error: if self.compared == 0 {
+                0.0
+            } else {
+                (self.squared_error / (self.compared * 2) as f64).sqrt()
+            },
+            spectral_distance_db: if self.blocks == 0 {
+                0.0
+            } else {
+                self.spectral_distance_sum / self.blocks as f64
+            },
+            channels,
+        }
+    }
+}
+
+/// Pairs every reference onset with the first unused candidate onset within `window` samples and
+/// returns the absolute offsets of the pairs. Both lists are sorted.
+fn match_onsets(reference: &[usize], candidate: &[usize], window: usize) -> Vec<usize> {
+    let mut offsets = Vec::new();
+    let mut next = 0;
+    for &onset in reference {
+        while next < candidate.len() && candidate[next] + window < onset {
+            next += 1;
+        }
+        if next < candidate.len() && candidate[next].abs_diff(onset) <= window {
+            offsets.push(candidate[next].abs_diff(onset));
+            next += 1;
+        }
+    }
+    offsets
+}
+
+/// RMS over the frequency bins of the difference of the two Hann-windowed power spectra, in dB.
+pub fn log_spectral_distance(reference: &[f64], candidate: &[f64]) -> f64 {
+    let reference = power_spectrum(reference);
+    let candidate = power_spectrum(candidate);
+    let sum: f64 = reference
+        .iter()
+        .zip(&candidate)
+        .map(|(r, c)| (10.0 * ((r + POWER_FLOOR) / (c + POWER_FLOOR)).log10()).powi(2))
+        .sum();
+    (sum / reference.len() as f64).sqrt()
+}
+
+/// Bins 0..=N/2 of a block whose length is a power of two.
+fn power_spectrum(block: &[f64]) -> Vec<f64> {
+    let n = block.len();
+    let mut re: Vec<f64> = block
+        .iter()
+        .enumerate()
+        .map(|(i, x)| x * 0.5 * (1.0 - (2.0 * PI * i as f64 / n as f64).cos()))
+        .collect();
+    let mut im = vec![0.0; n];
+    fft(&mut re, &mut im);
+    (0..=n / 2)
+        .map(|k| (re[k] * re[k] + im[k] * im[k]) / n as f64)
+        .collect()
+}
+
+/// In-place iterative radix-2 FFT.
+fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    debug_assert!(n.is_power_of_two());
+
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f64).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
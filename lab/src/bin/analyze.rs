@@ -1,5 +1,6 @@
-//! Significance analysis over every committed run in `experiments/runs/`, written to
-//! `experiments/analysis/`. The statistical model is documented in `experiments/README.md`.
+//! Significance analysis over every committed run in `experiments/runs/`, plus the emulation speed
+//! between them, written to `experiments/analysis/`. The statistical model is documented in
+//! `experiments/README.md`.
 //!
 //! Usage: `make significance`, or `cargo run --release -p citrine-gb-lab --bin analyze -- [options]`.
 
@@ -17,6 +18,8 @@ struct Results {
     mooneye: Option<MooneyeResults>,
     #[serde(default)]
     diff: Vec<DiffRun>,
+    #[serde(default)]
+    speed: Vec<SpeedRun>,
 }
 
 #[derive(serde::Deserialize)]
@@ -27,6 +30,10 @@ struct Meta {
     git_commit: String,
     #[serde(default)]
     tolerances: Vec<usize>,
+    #[serde(default)]
+    host: String,
+    #[serde(default)]
+    build: String,
 }
 
 #[derive(serde::Deserialize)]
@@ -77,6 +84,12 @@ struct MetricAgg {
     mean: f64,
 }
 
+#[derive(serde::Deserialize)]
+struct SpeedRun {
+    rom: String,
+    fps: f64,
+}
+
 struct Run {
     label: String,
     results: Results,
@@ -376,6 +389,24 @@ fn paired_tolerances(
     (low, high)
 }
 
+/// Frames per second of the ROMs both runs timed, paired by ROM.
+fn paired_speeds(earlier: &Results, later: &Results) -> Vec<(String, f64, f64)> {
+    let index: BTreeMap<&str, f64> = earlier
+        .speed
+        .iter()
+        .map(|s| (s.rom.as_str(), s.fps))
+        .collect();
+    later
+        .speed
+        .iter()
+        .filter_map(|s| {
+            index
+                .get(s.rom.as_str())
+                .map(|&fps| (s.rom.clone(), fps, s.fps))
+        })
+        .collect()
+}
+
 /// Returns (improved fail→pass, regressed pass→fail), paired by test name.
 fn paired_mooneye(earlier: &MooneyeResults, later: &MooneyeResults) -> (usize, usize) {
     let index = |m: &MooneyeResults| -> BTreeMap<String, bool> {
@@ -467,6 +498,10 @@ struct Args {
     /// Include `<rom> +recording` replays as separate observations.
     #[arg(long, default_value_t = false)]
     include_recordings: bool,
+
+    /// Slowdown between consecutive runs, as a fraction, reported as a performance regression.
+    #[arg(long, default_value_t = 0.05)]
+    speed_threshold: f64,
 }
 
 fn fmt(x: f64) -> String {
@@ -766,9 +801,141 @@ fn main() -> Result<()> {
             }
         }
     }
-    write_csv(&out_dir.join("pairwise.csv"), &pairwise_rows)?;
 
-    md.push_str("See `per_run.csv`, `mooneye_per_run.csv` and `pairwise.csv` in this directory.\n");
+    // --- speed --------------------------------------------------------------------------------
+    // Timings are noisy and machine-bound rather than deterministic, so a change is flagged by
+    // the threshold on the geometric mean; the Wilcoxon test on log ratios only backs it up.
+    let mut speed_rows = vec![vec![
+        "earlier".into(),
+        "later".into(),
+        "rom".into(),
+        "fps_earlier".into(),
+        "fps_later".into(),
+        "change".into(),
+        "regressed".into(),
+    ]];
+    let mut regressions = Vec::new();
+    let mut compared = 0;
+    let timed: Vec<&Run> = runs
+        .iter()
+        .filter(|r| !r.results.speed.is_empty())
+        .collect();
+    if !timed.is_empty() {
+        println!("\n=== speed (fastest pass per ROM, consecutive timed runs) ===");
+    }
+    for run in &timed {
+        let fps: Vec<f64> = run.results.speed.iter().map(|s| s.fps).collect();
+        println!(
+            "\n{}  ({} build, {})  median {:.1} fps over {} ROMs",
+            run.label,
+            run.results.meta.build,
+            run.results.meta.host,
+            median(&fps),
+            fps.len(),
+        );
+    }
+    for pair in timed.windows(2) {
+        let (earlier, later) = (pair[0], pair[1]);
+        let (me, ml) = (&earlier.results.meta, &later.results.meta);
+        if me.host != ml.host || me.build != ml.build {
+            println!(
+                "\n{}  ->  {}: different host or build, not compared",
+                earlier.label, later.label
+            );
+            continue;
+        }
+        let paired = paired_speeds(&earlier.results, &later.results);
+        if paired.is_empty() {
+            continue;
+        }
+        println!("\n{}  ->  {}", earlier.label, later.label);
+        compared += 1;
+        let mut log_ratios = Vec::new();
+        for (rom, fps_earlier, fps_later) in &paired {
+            let change = fps_later / fps_earlier - 1.0;
+            let regressed = change < -args.speed_threshold;
+            println!(
+                "  {rom:<24} {fps_earlier:>8.1} -> {fps_later:>8.1} fps  {:+6.1}%{}",
+                100.0 * change,
+                if regressed { "  REGRESSION" } else { "" },
+            );
+            log_ratios.push((fps_later / fps_earlier).ln());
+            speed_rows.push(vec![
+                earlier.label.clone(),
+                later.label.clone(),
+                rom.clone(),
+                format!("{fps_earlier:.1}"),
+                format!("{fps_later:.1}"),
+                format!("{change:+.4}"),
+                regressed.to_string(),
+            ]);
+        }
+        let overall = mean(&log_ratios).exp() - 1.0;
+        let overall_regressed = overall < -args.speed_threshold;
+        println!(
+            "  geometric mean {:+.1}%{}",
+            100.0 * overall,
+            if overall_regressed {
+                "  REGRESSION"
+            } else {
+                ""
+            },
+        );
+        if overall_regressed {
+            regressions.push(format!(
+                "- `{}` -> `{}`: {:+.1}% frames per second (geometric mean over {} ROMs)\n",
+                earlier.label,
+                later.label,
+                100.0 * overall,
+                paired.len(),
+            ));
+        }
+        if let Some(w) = wilcoxon_signed_rank(&log_ratios) {
+            println!(
+                "  Wilcoxon log fps ratio: n={} z={:+.3} p={:.4} {}",
+                w.n,
+                w.z,
+                w.p,
+                stars(w.p, args.alpha),
+            );
+            pairwise_rows.push(vec![
+                earlier.label.clone(),
+                later.label.clone(),
+                "wilcoxon_speed".into(),
+                "-".into(),
+                w.n.to_string(),
+                format!("W+={:.1}", w.w_plus),
+                format!("geomeanΔ={overall:+.4};z={:+.3}", w.z),
+                fmt(w.p),
+                stars(w.p, args.alpha).into(),
+            ]);
+        }
+    }
+    write_csv(&out_dir.join("pairwise.csv"), &pairwise_rows)?;
+    write_csv(&out_dir.join("speed.csv"), &speed_rows)?;
+
+    md.push_str("## Performance\n\n");
+    if compared == 0 {
+        md.push_str("No two consecutive runs on the same host and build have speed records.\n\n");
+    } else if regressions.is_empty() {
+        md.push_str(&format!(
+            "No run is more than {:.0}% slower than the one before it.\n\n",
+            100.0 * args.speed_threshold,
+        ));
+    } else {
+        md.push_str(&format!(
+            "Runs more than {:.0}% slower than the one before it:\n\n",
+            100.0 * args.speed_threshold,
+        ));
+        for line in &regressions {
+            md.push_str(line);
+        }
+        md.push('\n');
+    }
+    md.push_str(
+        "See `per_run.csv`, `mooneye_per_run.csv`, `pairwise.csv` and `speed.csv` in this \
+         directory.\n",
+    );
     std::fs::write(out_dir.join("significance.md"), md)?;
 
     println!("\nwritten to {}", out_dir.display());
@@ -1,9 +1,12 @@
 //! Captures the emulator's accuracy in one run — the mooneye suite plus a SameBoy frame diff over
-//! every local ROM — into `experiments/runs/<date>_<git-short-hash>/`. See `experiments/README.md`.
+//! every local ROM — and its speed over the bundled homebrew games into
+//! `experiments/runs/<date>_<git-short-hash>/`. See `experiments/README.md`.
 //!
 //! Usage: `make results`, or `cargo run --release -p citrine-gb-lab --bin collect -- [options]`.
 
 use anyhow::Context;
+use citrine_gb::gb::apu::APU_CLOCK_RATE;
+use citrine_gb::gb::boot_rom::BootRom;
 use citrine_gb::gb::{GameBoy, GbModel};
 use citrine_gb::rom::Rom;
 use citrine_gb::rom::header::RomHeader;
@@ -51,6 +54,17 @@ struct Args {
     #[arg(long, default_value_t = false)]
     skip_diff: bool,
 
+    #[arg(long, default_value_t = false)]
+    skip_speed: bool,
+
+    /// Frames to time per homebrew ROM.
+    #[arg(long, default_value_t = 3600)]
+    speed_frames: u32,
+
+    /// Timed passes per homebrew ROM, the fastest one counts.
+    #[arg(long, default_value_t = 3)]
+    speed_repeats: usize,
+
     /// Also write the full per-frame reports to `raw/` (large; gitignored).
     #[arg(long, default_value_t = false)]
     per_frame: bool,
@@ -82,6 +96,8 @@ struct Results {
     mooneye: Option<MooneyeResults>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     diff: Vec<DiffRun>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    speed: Vec<SpeedRun>,
 }
 
 #[derive(serde::Serialize)]
@@ -96,6 +112,8 @@ struct Meta {
     align: String,
     normalization: String,
     host: String,
+    /// `release` or `debug`, speed figures only compare within one.
+    build: String,
     total_wall_time_s: f64,
 }
 
@@ -149,6 +167,9 @@ struct DiffRun {
     last_divergent_frame: Option<usize>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     metrics: Vec<MetricAgg>,
+    /// Independent of the tolerance, audio is paired by sample rather than by frame.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    audio: Option<AudioAgg>,
     wall_time_s: f64,
 }
 
@@ -160,6 +181,26 @@ struct MetricAgg {
     worst: f64,
 }
 
+#[derive(serde::Serialize)]
+struct AudioAgg {
+    rms_error: f64,
+    spectral_distance_db: f64,
+    /// Per channel 1-4: mean onset offset in ms, `None` if no onset matched.
+    onset_offset_ms: Vec<Option<f64>>,
+    /// Per channel 1-4: matched / reference onsets, `None` if the reference has none.
+    onset_match_rate: Vec<Option<f64>>,
+}
+
+#[derive(serde::Serialize)]
+struct SpeedRun {
+    rom: String,
+    frames: u32,
+    fps: f64,
+    /// Multiple of a DMG's own frame rate.
+    realtime: f64,
+    wall_time_s: f64,
+}
+
 fn round6(x: f64) -> f64 {
     (x * 1e6).round() / 1e6
 }
@@ -336,7 +377,11 @@ impl RomConfig {
 fn model_name(model: GbModel) -> &'static str {
     match model {
         GbModel::Dmg => "dmg",
+        GbModel::Mgb => "mgb",
+        GbModel::Sgb2 => "sgb2",
         GbModel::Cgb => "cgb",
+        GbModel::CgbE => "cgb-e",
+        GbModel::Agb => "agb",
     }
 }
 
@@ -435,7 +480,6 @@ fn run_diff(
     config: &RomConfig,
     tolerance: usize,
     frames: usize,
-    boot_roms: &BTreeMap<&'static str, Vec<u8>>,
     per_frame_dir: Option<&Path>,
 ) -> DiffRun {
     let mut run = DiffRun {
@@ -457,6 +501,7 @@ fn run_diff(
         first_divergent_frame: None,
         last_divergent_frame: None,
         metrics: Vec::new(),
+        audio: None,
         wall_time_s: 0.0,
     };
 
@@ -464,7 +509,6 @@ fn run_diff(
         Some((_, recording)) => recording.clone(),
         None => Recording::new("", config.model),
     };
-    let boot_rom = &boot_roms[model_name(config.model)];
     let metrics = build_metrics();
 
     let start = Instant::now();
@@ -473,13 +517,14 @@ fn run_diff(
             citrine_lab::emulators::SameBoyEmulator::new(),
             citrine_lab::emulators::CitrineEmulator::new(),
             &config.rom,
-            Some(boot_rom),
+            &BootRom::Bundled,
             &recording,
             &metrics,
             frames,
             true,
             Alignment::Cycle,
             tolerance,
+            true,
             |_, _, _, _| Ok(()),
         )
     }));
@@ -527,6 +572,23 @@ fn run_diff(
             worst: round6(s.worst),
         })
         .collect();
+    run.audio = report.audio.map(|audio| AudioAgg {
+        rms_error: round6(audio.rms_error),
+        spectral_distance_db: round6(audio.spectral_distance_db),
+        onset_offset_ms: audio
+            .channels
+            .iter()
+            .map(|c| c.mean_offset_ms.map(round6))
+            .collect(),
+        onset_match_rate: audio
+            .channels
+            .iter()
+            .map(|c| {
+                (c.reference_onsets > 0)
+                    .then(|| round6(c.matched as f64 / c.reference_onsets as f64))
+            })
+            .collect(),
+    });
     run
 }
 
@@ -537,17 +599,6 @@ fn run_all_diffs(
     jobs: usize,
     per_frame_dir: Option<&Path>,
 ) -> anyhow::Result<Vec<DiffRun>> {
-    let root = repo_root();
-    let mut boot_roms = BTreeMap::new();
-    for (model, file) in [("dmg", "dmg_boot.bin"), ("cgb", "cgb_boot.bin")] {
-        let path = root.join("roms/boot").join(file);
-        boot_roms.insert(
-            model,
-            std::fs::read(&path)
-                .with_context(|| format!("failed to read boot ROM {}", path.display()))?,
-        );
-    }
-
     let tasks: Vec<(&RomConfig, usize)> = configs
         .iter()
         .flat_map(|c| tolerances.iter().map(move |&t| (c, t)))
@@ -570,7 +621,7 @@ fn run_all_diffs(
                     let Some(&(config, tolerance)) = tasks.get(i) else {
                         break;
                     };
-                    let run = run_diff(config, tolerance, frames, &boot_roms, per_frame_dir);
+                    let run = run_diff(config, tolerance, frames, per_frame_dir);
                     let done = completed.fetch_add(1, Ordering::Relaxed) + 1;
                     let note = match run.match_rate {
                         Some(rate) if run.status == "ok" => {
@@ -597,6 +648,63 @@ fn run_all_diffs(
         .collect())
 }
 
+// ------------------------------------------------------------------------------------- speed
+
+/// Played by the app, so they're in every checkout, unlike `roms/`.
+const HOMEBREW: [&str; 5] = [
+    "adjustris",
+    "porklike",
+    "shock_lobster",
+    "sushi_nights",
+    "tobu",
+];
+/// A DMG's frame rate, 70224 T-cycles per frame.
+const DMG_FPS: f64 = APU_CLOCK_RATE as f64 / 70224.0;
+
+/// Times `frames` frames from power-on with audio and rendering on, as the app runs them. One
+/// ROM at a time, so the diff runs' threads don't skew the figures.
+fn time_rom(name: &str, rom: &Rom, frames: u32, repeats: usize) -> anyhow::Result<SpeedRun> {
+    let mut fastest = f64::INFINITY;
+    for _ in 0..repeats.max(1) {
+        let mut gb = GameBoy::new_empty(GbModel::Dmg);
+        gb.load_rom(rom)
+            .map_err(|e| anyhow::anyhow!("failed to load {name}: {e:?}"))?;
+        let start = Instant::now();
+        for _ in 0..frames {
+            gb.run_frame();
+            gb.apu.audio_buffer.clear();
+        }
+        fastest = fastest.min(start.elapsed().as_secs_f64());
+    }
+    let fps = frames as f64 / fastest;
+    Ok(SpeedRun {
+        rom: name.to_string(),
+        frames,
+        fps: (fps * 10.0).round() / 10.0,
+        realtime: (fps / DMG_FPS * 100.0).round() / 100.0,
+        wall_time_s: (fastest * 1000.0).round() / 1000.0,
+    })
+}
+
+fn run_speed(root: &Path, frames: u32, repeats: usize) -> anyhow::Result<Vec<SpeedRun>> {
+    println!(
+        "\n=== speed: {} homebrew ROMs, {frames} frames, fastest of {repeats} ===",
+        HOMEBREW.len()
+    );
+    if cfg!(debug_assertions) {
+        println!("WARNING: debug build - speed figures only compare with other debug builds");
+    }
+    let mut runs = Vec::new();
+    for name in HOMEBREW {
+        let path = root.join(format!("app/homebrew/{name}.gb"));
+        let data = std::fs::read(&path).with_context(|| format!("failed to read {name}.gb"))?;
+        let run = time_rom(name, &Rom::new(&data), frames, repeats)?;
+        println!("{name:<14} {:>8.1} fps  {:>6.2}x", run.fps, run.realtime);
+        runs.push(run);
+    }
+    Ok(runs)
+}
+
 // ------------------------------------------------------------------------------------- output
 
 fn csv_field(s: &str) -> String {
@@ -702,6 +810,11 @@ fn write_outputs(out_dir: &Path, results: &Results) -> anyhow::Result<()> {
                 header.push(format!("{metric}_{stat}"));
             }
         }
+        header.extend(["audio_rms_error", "audio_spectral_distance_db"].map(String::from));
+        for channel in 1..=4 {
+            header.push(format!("onset_offset_ms_ch{channel}"));
+            header.push(format!("onset_match_rate_ch{channel}"));
+        }
         let mut rows = vec![header];
         for run in &results.diff {
             let mut row = vec![
@@ -723,6 +836,20 @@ fn write_outputs(out_dir: &Path, results: &Results) -> anyhow::Result<()> {
                     None => row.extend([String::new(), String::new(), String::new()]),
                 }
             }
+            match &run.audio {
+                Some(audio) => {
+                    row.extend([
+                        fmt_f64(audio.rms_error),
+                        fmt_f64(audio.spectral_distance_db),
+                    ]);
+                    for (offset, rate) in audio.onset_offset_ms.iter().zip(&audio.onset_match_rate)
+                    {
+                        row.push(offset.map(fmt_f64).unwrap_or_default());
+                        row.push(rate.map(fmt_f64).unwrap_or_default());
+                    }
+                }
+                None => row.extend(std::iter::repeat_n(String::new(), 10)),
+            }
             rows.push(row);
         }
         write_csv(&out_dir.join("diff_results.csv"), &rows)?;
@@ -771,6 +898,26 @@ fn write_outputs(out_dir: &Path, results: &Results) -> anyhow::Result<()> {
             write_csv(&out_dir.join(file), &rows)?;
         }
     }
+
+    if !results.speed.is_empty() {
+        let mut rows = vec![vec![
+            "rom".into(),
+            "frames".into(),
+            "fps".into(),
+            "realtime".into(),
+            "wall_time_s".into(),
+        ]];
+        rows.extend(results.speed.iter().map(|run| {
+            vec![
+                run.rom.clone(),
+                run.frames.to_string(),
+                format!("{:.1}", run.fps),
+                format!("{:.2}", run.realtime),
+                format!("{:.3}", run.wall_time_s),
+            ]
+        }));
+        write_csv(&out_dir.join("speed.csv"), &rows)?;
+    }
     Ok(())
 }
 
@@ -821,6 +968,11 @@ fn main() -> anyhow::Result<()> {
             per_frame_dir.as_deref(),
         )?
     };
+    let speed = if args.skip_speed {
+        Vec::new()
+    } else {
+        run_speed(&root, args.speed_frames, args.speed_repeats)?
+    };
 
     let results = Results {
         meta: Meta {
@@ -838,10 +990,17 @@ fn main() -> anyhow::Result<()> {
             align: "cycle".to_string(),
             normalization: "greyscale".to_string(),
             host: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+            build: if cfg!(debug_assertions) {
+                "debug"
+            } else {
+                "release"
+            }
+            .to_string(),
             total_wall_time_s: (start.elapsed().as_secs_f64() * 10.0).round() / 10.0,
         },
         mooneye,
         diff,
+        speed,
     };
 
     write_outputs(&out_dir, &results)?;
@@ -863,6 +1022,14 @@ fn main() -> anyhow::Result<()> {
             }
         );
     }
+    if !results.speed.is_empty() {
+        let fps: Vec<f64> = results.speed.iter().map(|r| r.fps).collect();
+        println!(
+            "speed: {:.1}..{:.1} fps",
+            fps.iter().copied().fold(f64::INFINITY, f64::min),
+            fps.iter().copied().fold(0.0, f64::max),
+        );
+    }
     println!("results: {}", out_dir.display());
     Ok(())
 }
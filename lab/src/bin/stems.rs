@@ -0,0 +1,102 @@
+//! Replays a recording headlessly and writes its audio as WAV files: the mix and, with `--stems`,
+//! one file per channel (`ch1.wav`..`ch4.wav`).
+//!
+//! Usage: `cargo run --release -p citrine-gb-lab --bin stems -- --rom <rom> [options]`.
+
+use anyhow::Context;
+use citrine_gb::audio_capture::{AudioCapture, capture_recording};
+use citrine_gb::gb::boot_rom::BootRom;
+use citrine_gb::gb::speed::Speed;
+use citrine_gb::rom::Rom;
+use citrine_lab::recording::Recording;
+use clap::Parser;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "stems",
+    about = "Export the audio of a recording replay as WAV files"
+)]
+struct Args {
+    /// Path to the ROM to run.
+    #[arg(long)]
+    rom: PathBuf,
+
+    /// Path to an input recording JSON. If omitted, the ROM runs with no input.
+    #[arg(long)]
+    recording: Option<PathBuf>,
+
+    /// Model to use when no recording is provided.
+    #[arg(long, default_value = "dmg")]
+    model: String,
+
+    /// Path to a boot ROM. Defaults to Citrine's bundled boot ROM.
+    #[arg(long, conflicts_with = "skip_boot")]
+    boot_rom: Option<PathBuf>,
+
+    /// Start in the post-boot state instead of running a boot ROM.
+    #[arg(long, default_value_t = false)]
+    skip_boot: bool,
+
+    /// Number of frames to capture.
+    #[arg(long, default_value_t = 3600)]
+    frames: u64,
+
+    #[arg(long, default_value_t = 48000)]
+    sample_rate: u32,
+
+    /// Also write one WAV file per channel.
+    #[arg(long, default_value_t = false)]
+    stems: bool,
+
+    /// Output directory.
+    #[arg(long, default_value = "stems")]
+    out: PathBuf,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    let rom = std::fs::read(&args.rom)
+        .with_context(|| format!("failed to read ROM {}", args.rom.display()))?;
+
+    let boot_rom = match &args.boot_rom {
+        Some(path) => BootRom::Custom(
+            std::fs::read(path)
+                .with_context(|| format!("failed to read boot ROM {}", path.display()))?,
+        ),
+        None if args.skip_boot => BootRom::Skip,
+        None => BootRom::Bundled,
+    };
+
+    let recording = match &args.recording {
+        Some(path) => citrine_lab::recording::load(path)
+            .with_context(|| format!("failed to load recording {}", path.display()))?,
+        None => Recording::new("", citrine_lab::recording::parse_model(&args.model)?),
+    };
+    // Frames counted at normal speed, like recordings count their cycles
+    let t_cycles_per_frame = Speed::new(recording.model).frame_cycles() as u64 * 4;
+
+    let capture = capture_recording(
+        &Rom::new(&rom),
+        boot_rom,
+        &recording,
+        args.frames * t_cycles_per_frame,
+        AudioCapture::new(args.sample_rate, args.stems),
+    )?;
+
+    std::fs::create_dir_all(&args.out)?;
+    std::fs::write(args.out.join("mix.wav"), capture.mix_wav())?;
+    for channel in 0..4 {
+        if let Some(wav) = capture.stem_wav(channel) {
+            std::fs::write(args.out.join(format!("ch{}.wav", channel + 1)), wav)?;
+        }
+    }
+
+    println!(
+        "wrote {:.1}s of audio to {}",
+        capture.duration_secs(),
+        args.out.display()
+    );
+    Ok(())
+}
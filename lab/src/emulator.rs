@@ -1,10 +1,16 @@
 use citrine_gb::gb::GbModel;
+use citrine_gb::gb::boot_rom::BootRom;
 
 pub use citrine_gb::recording::Button;
 
 pub const SCREEN_WIDTH: usize = 160;
 pub const SCREEN_HEIGHT: usize = 144;
 pub const FRAME_BYTES: usize = SCREEN_WIDTH * SCREEN_HEIGHT * 4;
+/// Every adapter resamples its audio to this rate, so sample N of each covers the same instant.
+pub const AUDIO_SAMPLE_RATE: u32 = 48_000;
+
+/// The greys [`Frame::to_canonical_greyscale`] snaps to, darkest first.
+pub const CANONICAL_LEVELS: [u8; 4] = [0x00, 0x55, 0xAA, 0xFF];
 
 /// A completed frame as RGBA8888, row-major, top-left origin.
 #[derive(Clone, PartialEq, Eq)]
@@ -33,14 +39,13 @@ impl Frame {
 
     pub fn normalize_into(&self, out: &mut Frame) {
         // Evenly spaced ~85 apart, so the nearest level is the rounded quotient — no palette search.
-        const LEVELS: [u8; 4] = [0x00, 0x55, 0xAA, 0xFF];
         out.width = self.width;
         out.height = self.height;
         out.rgba.resize(self.rgba.len(), 0);
         for (px, o) in self.rgba.chunks_exact(4).zip(out.rgba.chunks_exact_mut(4)) {
             let luma = 0.299 * px[0] as f32 + 0.587 * px[1] as f32 + 0.114 * px[2] as f32;
             let idx = ((luma / 85.0).round() as usize).min(3);
-            let level = LEVELS[idx];
+            let level = CANONICAL_LEVELS[idx];
             o[0] = level;
             o[1] = level;
             o[2] = level;
@@ -49,12 +54,44 @@ impl Frame {
     }
 }
 
+/// Audio produced over some stretch of emulated time.
+#[derive(Clone, Default, PartialEq)]
+pub struct AudioChunk {
+    /// Interleaved stereo in -1.0..=1.0, with the hardware's polarity (digital 0 is the high end
+    /// of the DAC range).
+    pub samples: Vec<f32>,
+    /// Per APU channel, its volume (0..=15, 0 while off) at every stereo sample.
+    pub channel_volumes: [Vec<u8>; 4],
+}
+
+impl AudioChunk {
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.channel_volumes.iter_mut().for_each(Vec::clear);
+    }
+
+    pub fn append(&mut self, other: &mut AudioChunk) {
+        self.samples.append(&mut other.samples);
+        for (volumes, other) in self
+            .channel_volumes
+            .iter_mut()
+            .zip(&mut other.channel_volumes)
+        {
+            volumes.append(other);
+        }
+    }
+}
+
 /// The harness-wide cycle unit is the T-cycle; adapters convert their native counter to it.
 pub trait FrameEmulator {
     fn name(&self) -> &str;
 
-    /// `None` jumps straight to the post-boot state — SameBoy cannot run without a boot ROM.
-    fn load(&mut self, rom: &[u8], boot_rom: Option<&[u8]>, model: GbModel) -> anyhow::Result<()>;
+    /// [`BootRom::Skip`] jumps straight to the post-boot state; feed both emulators the same boot
+    /// ROM (the bundled one by default) to start from an identical power-on state.
+    fn load(&mut self, rom: &[u8], boot_rom: &BootRom, model: GbModel) -> anyhow::Result<()>;
+
+    /// Replaces the freshly loaded machine with a BESS save state of the same ROM and model.
+    fn load_state(&mut self, bess: &[u8]) -> anyhow::Result<()>;
 
     fn set_button(&mut self, button: Button, pressed: bool);
 
@@ -66,9 +103,76 @@ pub trait FrameEmulator {
     /// `out` is a recycled buffer with stale contents; implementors must overwrite it completely.
     fn render_into(&self, out: &mut Vec<u8>);
 
+    /// Like [`FrameEmulator::render_into`], but straight in the four greys of
+    /// [`Frame::to_canonical_greyscale`], from the shades before any palette. `false` (leaving
+    /// `out` alone) when the emulator can't, then the caller quantizes the RGBA frame instead.
+    fn render_canonical_into(&self, _out: &mut Vec<u8>) -> bool {
+        false
+    }
+
+    /// Starts or stops buffering audio at [`AUDIO_SAMPLE_RATE`] for [`FrameEmulator::drain_audio`].
+    /// Off by default so video-only runs don't pile up samples; survives [`FrameEmulator::load`].
+    fn set_audio_capture(&mut self, enabled: bool);
+
+    /// Appends the audio buffered since the last call to `out`.
+    fn drain_audio(&mut self, out: &mut AudioChunk);
+
     fn frame(&self) -> Frame {
         let mut rgba = Vec::with_capacity(FRAME_BYTES);
         self.render_into(&mut rgba);
         Frame::new(SCREEN_WIDTH, SCREEN_HEIGHT, rgba)
     }
 }
+
+/// Starts `inner` from a BESS save state instead of power-on when there is one, so both emulators
+/// pick up the same mid-game moment.
+pub struct WithStartState<E> {
+    pub inner: E,
+    pub state: Option<Vec<u8>>,
+}
+
+impl<E: FrameEmulator> FrameEmulator for WithStartState<E> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn load(&mut self, rom: &[u8], boot_rom: &BootRom, model: GbModel) -> anyhow::Result<()> {
+        self.inner.load(rom, boot_rom, model)?;
+        match &self.state {
+            Some(state) => self.inner.load_state(state),
+            None => Ok(()),
+        }
+    }
+
+    fn load_state(&mut self, bess: &[u8]) -> anyhow::Result<()> {
+        self.inner.load_state(bess)
+    }
+
+    fn set_button(&mut self, button: Button, pressed: bool) {
+        self.inner.set_button(button, pressed);
+    }
+
+    fn total_cycles(&self) -> u64 {
+        self.inner.total_cycles()
+    }
+
+    fn step(&mut self) -> bool {
+        self.inner.step()
+    }
+
+    fn render_into(&self, out: &mut Vec<u8>) {
+        self.inner.render_into(out);
+    }
+
+    fn render_canonical_into(&self, out: &mut Vec<u8>) -> bool {
+        self.inner.render_canonical_into(out)
+    }
+
+    fn set_audio_capture(&mut self, enabled: bool) {
+        self.inner.set_audio_capture(enabled);
+    }
+
+    fn drain_audio(&mut self, out: &mut AudioChunk) {
+        self.inner.drain_audio(out);
+    }
+}
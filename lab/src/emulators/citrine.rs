@@ -1,4 +1,7 @@
-use crate::emulator::{Button, FrameEmulator};
+use crate::emulator::{AUDIO_SAMPLE_RATE, AudioChunk, Button, CANONICAL_LEVELS, FrameEmulator};
+use citrine_gb::gb::apu::APU_CLOCK_RATE;
+use citrine_gb::gb::boot_rom::BootRom;
+use citrine_gb::gb::ppu::types::native_frame::NativeFormat;
 use citrine_gb::gb::ppu::types::theme::DmgTheme;
 use citrine_gb::gb::ram_init::RamInit;
 use citrine_gb::gb::{GameBoy, GbModel};
@@ -6,14 +9,21 @@ use citrine_gb::rom::Rom;
 
 pub struct CitrineEmulator {
     gb: GameBoy,
-    model: GbModel,
+    capture_audio: bool,
+    audio: AudioChunk,
+    /// APU clock ticks since load, the channel volumes are sampled on the resampler's clock.
+    apu_ticks: u64,
+    volume_samples: u64,
 }
 
 impl CitrineEmulator {
     pub fn new() -> Self {
         Self {
             gb: GameBoy::new_empty_with_ram_init(GbModel::Dmg, RamInit::random()),
-            model: GbModel::Dmg,
+            capture_audio: false,
+            audio: AudioChunk::default(),
+            apu_ticks: 0,
+            volume_samples: 0,
         }
     }
 }
@@ -25,11 +35,26 @@ impl Default for CitrineEmulator {
 }
 
 impl CitrineEmulator {
-    /// Mirrors `GameBoy::run_frame`. Without the flush `blip_buf`'s clock overflows after a few
-    /// hundred frames, and the unread samples grow unbounded.
-    fn finish_frame(&mut self) {
-        self.gb.apu.flush_audio();
-        self.gb.apu.audio_buffer.clear();
+    /// Takes the audio `GameBoy::step_frame` flushed at the end of a frame, unread samples would
+    /// grow unbounded.
+    fn collect_audio(&mut self) {
+        if self.capture_audio {
+            self.audio.samples.append(&mut self.gb.apu.audio_buffer);
+        } else {
+            self.gb.apu.audio_buffer.clear();
+        }
+    }
+
+    fn sample_channel_volumes(&mut self, m_cycles: u64) {
+        self.apu_ticks += m_cycles * if self.gb.speed.double_speed { 2 } else { 4 };
+        let due = self.apu_ticks * AUDIO_SAMPLE_RATE as u64 / APU_CLOCK_RATE as u64;
+        let volumes = self.gb.apu.channel_volumes();
+        while self.volume_samples < due {
+            for (channel, volume) in self.audio.channel_volumes.iter_mut().zip(volumes) {
+                channel.push(volume);
+            }
+            self.volume_samples += 1;
+        }
     }
 }
 
@@ -38,18 +63,30 @@ impl FrameEmulator for CitrineEmulator {
         "citrine"
     }
 
-    fn load(&mut self, rom: &[u8], boot_rom: Option<&[u8]>, model: GbModel) -> anyhow::Result<()> {
-        self.model = model;
+    fn load(&mut self, rom: &[u8], boot_rom: &BootRom, model: GbModel) -> anyhow::Result<()> {
         self.gb = GameBoy::new_empty_with_ram_init(model, RamInit::random());
-        if let Some(boot) = boot_rom {
-            self.gb.load_boot_rom(boot);
-        }
+        self.gb.set_boot_rom(boot_rom.clone());
         let rom = Rom::new(rom);
         self.gb
             .load_rom(&rom)
             .map_err(|e| anyhow::anyhow!("citrine failed to load rom: {e:?}"))?;
         // Evenly-spaced greys, matching SameBoy's forced grey palette after normalization.
         self.gb.ppu.dmg_theme = DmgTheme::GreyScale;
+        self.gb.apu.set_sample_rate(AUDIO_SAMPLE_RATE);
+        self.gb.apu.suppress_output = !self.capture_audio;
+        self.audio.clear();
+        self.apu_ticks = 0;
+        self.volume_samples = 0;
+        Ok(())
+    }
+
+    fn load_state(&mut self, bess: &[u8]) -> anyhow::Result<()> {
+        self.gb
+            .load_bess(bess)
+            .map_err(|e| anyhow::anyhow!("citrine failed to load state: {e}"))?;
+        self.gb.ppu.dmg_theme = DmgTheme::GreyScale;
+        self.gb.apu.set_sample_rate(AUDIO_SAMPLE_RATE);
+        self.gb.apu.suppress_output = !self.capture_audio;
         Ok(())
     }
 
@@ -68,26 +105,17 @@ impl FrameEmulator for CitrineEmulator {
     }
 
     fn step(&mut self) -> bool {
-        self.gb.step();
-        let frame_cycles = self.model.frame_cycles();
-
-        if self.gb.ppu.frame_ready {
-            self.gb.ppu.frame_ready = false;
-            if self.gb.cycle_counter >= frame_cycles {
-                self.gb.cycle_counter -= frame_cycles;
-            }
-            self.finish_frame();
-            return true;
+        let before = self.gb.debugger.total_cycles;
+        // Frames end like in `GameBoy::run_frame`, with an artificial one while the LCD is off or
+        // stopped, matching SameBoy's LCD-off vblanks.
+        let frame_ended = self.gb.step_frame();
+        if self.capture_audio {
+            self.sample_channel_volumes((self.gb.debugger.total_cycles - before) as u64);
         }
-
-        // Emit an artificial frame while the LCD is off, matching SameBoy's LCD-off vblanks.
-        if !self.gb.ppu.lcdc.lcd_enabled && self.gb.cycle_counter >= frame_cycles {
-            self.gb.cycle_counter -= frame_cycles;
-            self.finish_frame();
-            return true;
+        if frame_ended {
+            self.collect_audio();
         }
-
-        false
+        frame_ended
     }
 
     fn render_into(&self, out: &mut Vec<u8>) {
@@ -95,4 +123,29 @@ impl FrameEmulator for CitrineEmulator {
         out.clear();
         out.extend_from_slice(src);
     }
+
+    fn render_canonical_into(&self, out: &mut Vec<u8>) -> bool {
+        let native = self.gb.native_frame();
+        if native.format() != NativeFormat::Shade {
+            return false;
+        }
+        out.clear();
+        out.extend(native.as_slice().iter().flat_map(|&shade| {
+            // Shade 0 is the lightest
+            let level = CANONICAL_LEVELS[3 - (shade & 0b11) as usize];
+            [level, level, level, 0xFF]
+        }));
+        true
+    }
+
+    fn set_audio_capture(&mut self, enabled: bool) {
+        self.capture_audio = enabled;
+        // Video-only runs skip the synthesis, the channel volumes don't need it
+        self.gb.apu.suppress_output = !enabled;
+        self.audio.clear();
+    }
+
+    fn drain_audio(&mut self, out: &mut AudioChunk) {
+        out.append(&mut self.audio);
+    }
 }
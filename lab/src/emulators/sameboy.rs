@@ -1,11 +1,17 @@
-use crate::emulator::{Button, FrameEmulator, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::emulator::{
+    AUDIO_SAMPLE_RATE, AudioChunk, Button, FrameEmulator, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
 use citrine_gb::gb::GbModel;
+use citrine_gb::gb::boot_rom::BootRom;
+use citrine_gb::gb::cpu::Cpu;
 use sameboy_sys as sys;
 use std::ffi::c_void;
 use std::sync::Mutex;
 
 struct Ctx {
     frame_ready: bool,
+    capture_audio: bool,
+    audio: AudioChunk,
 }
 
 pub struct SameBoyEmulator {
@@ -33,13 +39,41 @@ extern "C" fn vblank(gb: *mut sys::GB_gameboy_t, _ty: sys::GB_vblank_type_t) {
     }
 }
 
+const CHANNELS: [sys::GB_channel_t; 4] = [
+    sys::GB_SQUARE_1,
+    sys::GB_SQUARE_2,
+    sys::GB_WAVE,
+    sys::GB_NOISE,
+];
+
+extern "C" fn audio_sample(gb: *mut sys::GB_gameboy_t, sample: *mut sys::GB_sample_t) {
+    // SAFETY: as in `vblank`; `sample` is valid for the duration of the call.
+    unsafe {
+        let ctx = sys::GB_get_user_data(gb) as *mut Ctx;
+        if ctx.is_null() || !(*ctx).capture_audio {
+            return;
+        }
+        let audio = &mut (*ctx).audio;
+        let sample = *sample;
+        audio.samples.push(sample.left as f32 / 32768.0);
+        audio.samples.push(sample.right as f32 / 32768.0);
+        for (volumes, channel) in audio.channel_volumes.iter_mut().zip(CHANNELS) {
+            volumes.push(sys::GB_get_channel_volume(gb, channel));
+        }
+    }
+}
+
 const RAM_SEED: u64 = 0x0C17_A17E;
 static RESET_LOCK: Mutex<()> = Mutex::new(());
 
+/// `Cgb` stays on CGB E, the revision the lab has always compared against.
 fn sys_model(model: GbModel) -> sys::GB_model_t {
     match model {
         GbModel::Dmg => sys::GB_MODEL_DMG_B,
-        GbModel::Cgb => sys::GB_MODEL_CGB_E,
+        GbModel::Mgb => sys::GB_MODEL_MGB,
+        GbModel::Sgb2 => sys::GB_MODEL_SGB2_NO_SFC,
+        GbModel::Cgb | GbModel::CgbE => sys::GB_MODEL_CGB_E,
+        GbModel::Agb => sys::GB_MODEL_AGB_A,
     }
 }
 
@@ -63,7 +97,11 @@ impl SameBoyEmulator {
             let gb = sys::GB_init(sys::GB_alloc(), sys_model(model));
             SameBoyEmulator {
                 gb,
-                ctx: Box::new(Ctx { frame_ready: false }),
+                ctx: Box::new(Ctx {
+                    frame_ready: false,
+                    capture_audio: false,
+                    audio: AudioChunk::default(),
+                }),
                 pixels: Vec::new(),
                 width: 0,
                 height: 0,
@@ -85,6 +123,10 @@ impl SameBoyEmulator {
             sys::GB_set_palette(self.gb, &raw const sys::GB_PALETTE_GREY);
             // Without turbo the core `nanosleep`s to real-time 60 fps.
             sys::GB_set_turbo_mode(self.gb, true, true);
+            // Citrine runs its output through the same DMG capacitor model.
+            sys::GB_set_sample_rate(self.gb, AUDIO_SAMPLE_RATE);
+            sys::GB_set_highpass_filter_mode(self.gb, sys::GB_HIGHPASS_ACCURATE);
+            sys::GB_apu_set_sample_callback(self.gb, audio_sample);
             self.resize_pixel_buffer();
         }
     }
@@ -99,27 +141,17 @@ impl SameBoyEmulator {
         }
     }
 
-    /// Mirrors Citrine's `Cpu::new_dmg`/`new_cgb` so both start byte-identical without a boot ROM.
+    /// Copies Citrine's `Cpu::new_post_boot` so both start byte-identical without a boot ROM.
     fn set_post_boot_registers(&mut self, rom: &[u8]) {
         let header_checksum = rom.get(0x014D).copied().unwrap_or(0);
+        let cpu = Cpu::new_post_boot(self.model, header_checksum);
         let regs = unsafe { &mut *sys::GB_get_registers(self.gb) };
-        match self.model {
-            GbModel::Dmg => {
-                let f: u16 = if header_checksum == 0 { 0xB0 } else { 0x80 };
-                regs.af = 0x0100 | f;
-                regs.bc = 0x0013;
-                regs.de = 0x00D8;
-                regs.hl = 0x014D;
-            }
-            GbModel::Cgb => {
-                regs.af = 0x1180;
-                regs.bc = 0x0000;
-                regs.de = 0xFF56;
-                regs.hl = 0x000D;
-            }
-        }
-        regs.sp = 0xFFFE;
-        regs.pc = 0x0100;
+        regs.af = u16::from_be_bytes([cpu.a, cpu.f.into()]);
+        regs.bc = u16::from_be_bytes([cpu.b, cpu.c]);
+        regs.de = u16::from_be_bytes([cpu.d, cpu.e]);
+        regs.hl = u16::from_be_bytes([cpu.h, cpu.l]);
+        regs.sp = cpu.sp;
+        regs.pc = cpu.pc;
     }
 }
 
@@ -134,7 +166,8 @@ impl FrameEmulator for SameBoyEmulator {
         "sameboy"
     }
 
-    fn load(&mut self, rom: &[u8], boot_rom: Option<&[u8]>, model: GbModel) -> anyhow::Result<()> {
+    fn load(&mut self, rom: &[u8], boot_rom: &BootRom, model: GbModel) -> anyhow::Result<()> {
+        let boot_image = boot_rom.image(model);
         if model != self.model {
             self.model = model;
             unsafe {
@@ -149,7 +182,7 @@ impl FrameEmulator for SameBoyEmulator {
                 .lock()
                 .unwrap_or_else(|poisoned| poisoned.into_inner());
             unsafe {
-                if let Some(boot) = boot_rom {
+                if let Some(boot) = boot_image {
                     sys::GB_load_boot_rom_from_buffer(self.gb, boot.as_ptr(), boot.len());
                 }
                 sys::GB_load_rom_from_buffer(self.gb, rom.as_ptr(), rom.len());
@@ -158,15 +191,23 @@ impl FrameEmulator for SameBoyEmulator {
                 self.resize_pixel_buffer();
             }
         }
-        if boot_rom.is_none() {
+        if boot_image.is_none() {
             self.set_post_boot_registers(rom);
         }
 
         self.ctx.frame_ready = false;
+        self.ctx.audio.clear();
         self.total_ticks = 0;
         Ok(())
     }
 
+    fn load_state(&mut self, bess: &[u8]) -> anyhow::Result<()> {
+        let result = unsafe { sys::GB_load_state_from_buffer(self.gb, bess.as_ptr(), bess.len()) };
+        anyhow::ensure!(result == 0, "sameboy failed to load state (error {result})");
+        self.ctx.frame_ready = false;
+        Ok(())
+    }
+
     fn set_button(&mut self, button: Button, pressed: bool) {
         unsafe {
             sys::GB_set_key_state(self.gb, map_button(button), pressed);
@@ -204,6 +245,15 @@ impl FrameEmulator for SameBoyEmulator {
             }
         }
     }
+
+    fn set_audio_capture(&mut self, enabled: bool) {
+        self.ctx.capture_audio = enabled;
+        self.ctx.audio.clear();
+    }
+
+    fn drain_audio(&mut self, out: &mut AudioChunk) {
+        out.append(&mut self.ctx.audio);
+    }
 }
 
 fn bytemuck_cast(pixels: &[u32]) -> &[u8] {
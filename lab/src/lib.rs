@@ -1,6 +1,7 @@
 //! A harness for comparing the Citrine Game Boy emulator against a reference emulator, frame by
-//! frame. See `lab/README.md`.
+//! frame and sample by sample. See `lab/README.md`.
 
+pub mod audio;
 pub mod emulator;
 pub mod emulators;
 pub mod metric;
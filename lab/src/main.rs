@@ -1,5 +1,6 @@
 use anyhow::Context;
-use citrine_gb::gb::GbModel;
+use citrine_gb::gb::boot_rom::BootRom;
+use citrine_lab::emulator::WithStartState;
 use citrine_lab::emulators::{CitrineEmulator, SameBoyEmulator};
 use citrine_lab::metric::FrameMetric;
 use citrine_lab::metrics;
@@ -25,10 +26,19 @@ struct Args {
     #[arg(long)]
     recording: Option<PathBuf>,
 
-    /// Path to a boot ROM fed to BOTH emulators (recommended; SameBoy needs one to run).
-    #[arg(long)]
+    /// Path to a boot ROM fed to BOTH emulators. Defaults to Citrine's bundled boot ROM.
+    #[arg(long, conflicts_with = "skip_boot")]
     boot_rom: Option<PathBuf>,
 
+    /// Start both emulators in the post-boot state instead of running a boot ROM.
+    #[arg(long, default_value_t = false)]
+    skip_boot: bool,
+
+    /// Path to a BESS save state both emulators start from instead of power-on. Its model
+    /// replaces --model.
+    #[arg(long)]
+    state: Option<PathBuf>,
+
     /// Number of frames to compare.
     #[arg(long, default_value_t = 600)]
     frames: usize,
@@ -71,6 +81,10 @@ struct Args {
     /// two independent emulators; `0` is strict. Ignored when `--align emission`.
     #[arg(long, default_value_t = 0)]
     tolerance: usize,
+
+    /// Also compare the audio output: RMS error, spectral distance and per-channel onset timing.
+    #[arg(long, default_value_t = false)]
+    audio: bool,
 }
 
 #[derive(Copy, Clone, Debug, clap::ValueEnum)]
@@ -88,14 +102,6 @@ impl From<AlignArg> for citrine_lab::runner::Alignment {
     }
 }
 
-fn parse_model(s: &str) -> anyhow::Result<GbModel> {
-    match s.to_ascii_lowercase().as_str() {
-        "dmg" => Ok(GbModel::Dmg),
-        "cgb" => Ok(GbModel::Cgb),
-        other => anyhow::bail!("unknown model '{other}' (expected dmg or cgb)"),
-    }
-}
-
 fn build_metrics(spec: &str) -> anyhow::Result<Vec<Box<dyn FrameMetric>>> {
     spec.split(',')
         .map(str::trim)
@@ -111,20 +117,42 @@ fn main() -> anyhow::Result<()> {
         .with_context(|| format!("failed to read ROM {}", args.rom.display()))?;
 
     let boot_rom = match &args.boot_rom {
-        Some(path) => Some(
+        Some(path) => BootRom::Custom(
             std::fs::read(path)
                 .with_context(|| format!("failed to read boot ROM {}", path.display()))?,
         ),
+        None if args.skip_boot => BootRom::Skip,
+        None => BootRom::Bundled,
+    };
+
+    let state = match &args.state {
+        Some(path) => {
+            let state = std::fs::read(path)
+                .with_context(|| format!("failed to read state {}", path.display()))?;
+            let model = citrine_gb::persistence::bess::model(&state)
+                .with_context(|| format!("{} is not a BESS save state", path.display()))?;
+            Some((state, model))
+        }
         None => None,
     };
-    let boot_rom_ref = boot_rom.as_deref();
 
     let recording = match &args.recording {
         Some(path) => citrine_lab::recording::load(path)
             .with_context(|| format!("failed to load recording {}", path.display()))?,
-        None => Recording::new("", parse_model(&args.model)?),
+        None => match &state {
+            Some((_, model)) => Recording::new("", *model),
+            None => Recording::new("", citrine_lab::recording::parse_model(&args.model)?),
+        },
     };
+    if let Some((_, model)) = &state {
+        anyhow::ensure!(
+            *model == recording.model,
+            "the state was taken on {model:?}, the recording on {:?}",
+            recording.model
+        );
+    }
 
+    let start_state = state.map(|(state, _)| state);
     let metrics = build_metrics(&args.metrics)?;
 
     println!(
@@ -157,16 +185,23 @@ fn main() -> anyhow::Result<()> {
     // SameBoy is the reference; Citrine is the candidate under test.
     let mut divergent = 0usize;
     let report = run_streaming(
-        SameBoyEmulator::new(),
-        CitrineEmulator::new(),
+        WithStartState {
+            inner: SameBoyEmulator::new(),
+            state: start_state.clone(),
+        },
+        WithStartState {
+            inner: CitrineEmulator::new(),
+            state: start_state,
+        },
         &rom,
-        boot_rom_ref,
+        &boot_rom,
         &recording,
         &metrics,
         args.frames,
         !args.raw,
         args.align.into(),
         args.tolerance,
+        args.audio,
         |index, reference, candidate, diverged| {
             if diverged {
                 divergent += 1;
@@ -2,6 +2,8 @@
 
 pub use citrine_gb::recording::{Button, InputEvent, Recording};
 
+use citrine_gb::gb::GbModel;
+
 use std::path::Path;
 
 /// Loads and sorts by cycle.
@@ -16,3 +18,18 @@ pub fn save(recording: &Recording, path: &Path) -> anyhow::Result<()> {
     std::fs::write(path, recording.to_json()?)?;
     Ok(())
 }
+
+/// The model for a `--model` argument, used when no recording brings its own.
+pub fn parse_model(s: &str) -> anyhow::Result<GbModel> {
+    match s.to_ascii_lowercase().as_str() {
+        "dmg" => Ok(GbModel::Dmg),
+        "mgb" => Ok(GbModel::Mgb),
+        "sgb2" => Ok(GbModel::Sgb2),
+        "cgb" => Ok(GbModel::Cgb),
+        "cgb-e" | "cgbe" => Ok(GbModel::CgbE),
+        "agb" => Ok(GbModel::Agb),
+        other => {
+            anyhow::bail!("unknown model '{other}' (expected dmg, mgb, sgb2, cgb, cgb-e or agb)")
+        }
+    }
+}
@@ -81,6 +81,28 @@ impl Reporter for ConsoleReporter {
                 s.name, s.mean, s.best, s.worst
             );
         }
+
+        if let Some(audio) = &report.audio {
+            println!(
+                "\naudio: {} samples compared at {} Hz",
+                audio.compared_samples, audio.sample_rate
+            );
+            println!("  {:<10} ↓  {:.5}", "rms_error", audio.rms_error);
+            println!(
+                "  {:<10} ↓  {:.5} dB",
+                "spectral", audio.spectral_distance_db
+            );
+            for c in &audio.channels {
+                let offsets = match (c.mean_offset_ms, c.max_offset_ms) {
+                    (Some(mean), Some(max)) => format!("  mean={mean:.3} ms  max={max:.3} ms"),
+                    _ => String::new(),
+                };
+                println!(
+                    "  onsets ch{}  {}/{} matched ({} candidate){offsets}",
+                    c.channel, c.matched, c.reference_onsets, c.candidate_onsets
+                );
+            }
+        }
         Ok(())
     }
 }
@@ -1,6 +1,10 @@
-use crate::emulator::{FRAME_BYTES, Frame, FrameEmulator, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::audio::{AudioComparator, AudioReport};
+use crate::emulator::{
+    AUDIO_SAMPLE_RATE, AudioChunk, FRAME_BYTES, Frame, FrameEmulator, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
 use crate::metric::{FrameMetric, Polarity};
 use crate::recording::{InputEvent, Recording};
+use citrine_gb::gb::boot_rom::BootRom;
 use std::sync::mpsc::{Receiver, SyncSender, channel, sync_channel};
 use std::thread;
 
@@ -52,7 +56,7 @@ impl<'a> FrameDriver<'a> {
 pub fn replay<E: FrameEmulator>(
     emu: &mut E,
     rom: &[u8],
-    boot_rom: Option<&[u8]>,
+    boot_rom: &BootRom,
     recording: &Recording,
     max_frames: usize,
 ) -> anyhow::Result<Vec<Frame>> {
@@ -96,6 +100,9 @@ pub struct ComparisonReport {
     pub reference_frame_count: usize,
     pub candidate_frame_count: usize,
     pub compared_frame_count: usize,
+    /// Only present when the run compared audio.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio: Option<AudioReport>,
 }
 
 struct Aggregator {
@@ -188,6 +195,7 @@ impl Aggregator {
             reference_frame_count: n,
             candidate_frame_count: n,
             compared_frame_count: n,
+            audio: None,
         }
     }
 }
@@ -208,17 +216,23 @@ const FRAME_CYCLES: u64 = 70224;
 struct CycleFrame {
     cycle: u64,
     rgba: Vec<u8>,
+    /// The audio since the previous frame, empty unless the run compares audio.
+    audio: AudioChunk,
 }
 
+#[allow(clippy::too_many_arguments)]
 fn produce<E: FrameEmulator>(
     mut emu: E,
     rom: &[u8],
-    boot_rom: Option<&[u8]>,
+    boot_rom: &BootRom,
     recording: &Recording,
     count: usize,
+    normalize: bool,
+    audio: bool,
     tx: SyncSender<CycleFrame>,
     recycle: Receiver<Vec<u8>>,
 ) -> anyhow::Result<()> {
+    emu.set_audio_capture(audio);
     emu.load(rom, boot_rom, recording.model)?;
     let mut driver = FrameDriver::new(recording);
     for _ in 0..count {
@@ -226,12 +240,18 @@ fn produce<E: FrameEmulator>(
         let mut rgba = recycle
             .try_recv()
             .unwrap_or_else(|_| Vec::with_capacity(FRAME_BYTES));
-        emu.render_into(&mut rgba);
+        // Frames already in the canonical greys come through normalization unchanged.
+        if !(normalize && emu.render_canonical_into(&mut rgba)) {
+            emu.render_into(&mut rgba);
+        }
+        let mut chunk = AudioChunk::default();
+        emu.drain_audio(&mut chunk);
         // The consumer went away (early stop / error).
         if tx
             .send(CycleFrame {
                 cycle: emu.total_cycles(),
                 rgba,
+                audio: chunk,
             })
             .is_err()
         {
@@ -257,19 +277,23 @@ fn frame_distance(a: &[u8], b: &[u8]) -> u64 {
 /// In [`Alignment::Cycle`], `tolerance` widens the match to a ±`tolerance`-frame window: each metric
 /// keeps its best score over the window and a frame diverges only if no candidate in it is
 /// byte-identical, absorbing the sub-frame sampling skew between two independent emulators.
+///
+/// With `audio`, both emulators also capture their sound output and the report carries an
+/// [`AudioReport`]. Audio is paired by sample index from power-on, independent of `alignment`.
 // A parameter struct would read better; deferred so the call sites stay stable for now.
 #[allow(clippy::too_many_arguments)]
 pub fn run_streaming<R, C>(
     reference: R,
     candidate: C,
     rom: &[u8],
-    boot_rom: Option<&[u8]>,
+    boot_rom: &BootRom,
     recording: &Recording,
     metrics: &[Box<dyn FrameMetric>],
     max_frames: usize,
     normalize: bool,
     alignment: Alignment,
     tolerance: usize,
+    audio: bool,
     mut on_frame: impl FnMut(usize, &Frame, &Frame, bool) -> anyhow::Result<()>,
 ) -> anyhow::Result<ComparisonReport>
 where
@@ -279,6 +303,7 @@ where
     let ref_name = reference.name().to_string();
     let cand_name = candidate.name().to_string();
     let mut agg = Aggregator::new(metrics, max_frames);
+    let mut audio_comparator = audio.then(|| AudioComparator::new(AUDIO_SAMPLE_RATE));
 
     let cand_count = match alignment {
         Alignment::Cycle => max_frames + ALIGN_SLACK + tolerance,
@@ -298,6 +323,8 @@ where
                 boot_rom,
                 recording,
                 max_frames,
+                normalize,
+                audio,
                 ref_tx,
                 ref_recycle_rx,
             )
@@ -309,6 +336,8 @@ where
                 boot_rom,
                 recording,
                 cand_count,
+                normalize,
+                audio,
                 cand_tx,
                 cand_recycle_rx,
             )
@@ -341,6 +370,10 @@ where
                     let (Ok(r), Ok(c)) = (ref_rx.recv(), cand_rx.recv()) else {
                         break;
                     };
+                    if let Some(comparator) = audio_comparator.as_mut() {
+                        comparator.push_reference(&r.audio);
+                        comparator.push_candidate(&c.audio);
+                    }
                     ref_raw.rgba = r.rgba;
                     cand_raw.rgba = c.rgba;
                     let (rf, cf) = if normalize {
@@ -370,6 +403,9 @@ where
 
                 for i in 0..max_frames {
                     let Ok(r) = ref_rx.recv() else { break };
+                    if let Some(comparator) = audio_comparator.as_mut() {
+                        comparator.push_reference(&r.audio);
+                    }
 
                     while !stream_done
                         && window
@@ -377,7 +413,12 @@ where
                             .is_none_or(|b| b.cycle <= r.cycle + tol_cycles)
                     {
                         match pending.take().or_else(|| cand_rx.recv().ok()) {
-                            Some(nf) => window.push_back(nf),
+                            Some(nf) => {
+                                if let Some(comparator) = audio_comparator.as_mut() {
+                                    comparator.push_candidate(&nf.audio);
+                                }
+                                window.push_back(nf);
+                            }
                             None => stream_done = true,
                         }
                     }
@@ -481,5 +522,7 @@ where
         Ok(())
     })?;
 
-    Ok(agg.finish(metrics, &ref_name, &cand_name))
+    let mut report = agg.finish(metrics, &ref_name, &cand_name);
+    report.audio = audio_comparator.map(AudioComparator::finish);
+    Ok(report)
 }
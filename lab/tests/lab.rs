@@ -1,5 +1,7 @@
 use citrine_gb::gb::GbModel;
-use citrine_lab::emulator::{Frame, SCREEN_HEIGHT, SCREEN_WIDTH};
+use citrine_gb::gb::boot_rom::BootRom;
+use citrine_lab::audio::AudioComparator;
+use citrine_lab::emulator::{AUDIO_SAMPLE_RATE, AudioChunk, Frame, SCREEN_HEIGHT, SCREEN_WIDTH};
 use citrine_lab::emulators::{CitrineEmulator, SameBoyEmulator};
 use citrine_lab::metric::FrameMetric;
 use citrine_lab::metrics::{ExactFrame, ExactPixelRatio, Mse, Nmse, Psnr, Ssim};
@@ -58,6 +60,82 @@ fn canonical_greyscale_collapses_palette() {
     assert_eq!(na.rgba, nb.rgba);
 }
 
+/// A square wave on channel 1 that starts at each of `onsets` and plays for 2400 samples.
+fn square_tone(len: usize, period: usize, onsets: &[usize]) -> AudioChunk {
+    let mut chunk = AudioChunk::default();
+    for i in 0..len {
+        let playing = onsets.iter().any(|&o| (o..o + 2400).contains(&i));
+        let value = match (playing, (i / (period / 2)) % 2) {
+            (false, _) => 0.0,
+            (true, 0) => 0.25,
+            (true, _) => -0.25,
+        };
+        chunk.samples.extend([value, value]);
+        chunk.channel_volumes[0].push(if playing { 15 } else { 0 });
+        for channel in 1..4 {
+            chunk.channel_volumes[channel].push(0);
+        }
+    }
+    chunk
+}
+
+#[test]
+fn audio_identical_streams() {
+    let tone = square_tone(48_000, 100, &[1_000, 10_000, 30_000]);
+    let mut comparator = AudioComparator::new(AUDIO_SAMPLE_RATE);
+    // Uneven chunks on each side, pairing is by sample index.
+    comparator.push_reference(&tone);
+    let (mut first, mut second) = (tone.clone(), tone.clone());
+    first.samples.truncate(2 * 20_000);
+    first
+        .channel_volumes
+        .iter_mut()
+        .for_each(|v| v.truncate(20_000));
+    second.samples.drain(..2 * 20_000);
+    second.channel_volumes.iter_mut().for_each(|v| {
+        v.drain(..20_000);
+    });
+    comparator.push_candidate(&first);
+    comparator.push_candidate(&second);
+
+    let report = comparator.finish();
+    assert_eq!(report.compared_samples, 48_000);
+    assert_eq!(report.rms_error, 0.0);
+    assert!(report.spectral_distance_db.abs() < 1e-9);
+    let ch1 = &report.channels[0];
+    assert_eq!(
+        (ch1.reference_onsets, ch1.candidate_onsets, ch1.matched),
+        (3, 3, 3)
+    );
+    assert_eq!(ch1.max_offset_ms, Some(0.0));
+    assert_eq!(report.channels[1].matched, 0);
+    assert_eq!(report.channels[1].mean_offset_ms, None);
+}
+
+#[test]
+fn audio_onset_offsets_and_pitch() {
+    let reference = square_tone(48_000, 100, &[1_000, 10_000, 30_000]);
+    // Second note 96 samples (2 ms) late, third one too late to match.
+    let late = square_tone(48_000, 100, &[1_000, 10_096, 35_000]);
+    let mut comparator = AudioComparator::new(AUDIO_SAMPLE_RATE);
+    comparator.push_reference(&reference);
+    comparator.push_candidate(&late);
+    let report = comparator.finish();
+    let ch1 = &report.channels[0];
+    assert_eq!(ch1.matched, 2);
+    assert_eq!(ch1.max_offset_ms, Some(2.0));
+    assert_eq!(ch1.mean_offset_ms, Some(1.0));
+    assert!(report.rms_error > 0.0);
+
+    let detuned = square_tone(48_000, 160, &[1_000, 10_000, 30_000]);
+    let mut comparator = AudioComparator::new(AUDIO_SAMPLE_RATE);
+    comparator.push_reference(&reference);
+    comparator.push_candidate(&detuned);
+    let report = comparator.finish();
+    assert_eq!(report.channels[0].matched, 3);
+    assert!(report.spectral_distance_db > 1.0);
+}
+
 fn roms_dir() -> PathBuf {
     PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../roms")
 }
@@ -65,18 +143,17 @@ fn roms_dir() -> PathBuf {
 #[test]
 fn replay_produces_requested_frame_count_on_both_emulators() {
     let rom = std::fs::read(roms_dir().join("test/dmg-acid2.gb")).expect("test ROM present");
-    let boot = std::fs::read(roms_dir().join("boot/dmg_boot.bin")).expect("boot ROM present");
     let recording = Recording::new("", GbModel::Dmg);
     let frames = 30;
 
     let mut citrine = CitrineEmulator::new();
-    let cf = replay(&mut citrine, &rom, Some(&boot), &recording, frames).unwrap();
+    let cf = replay(&mut citrine, &rom, &BootRom::Bundled, &recording, frames).unwrap();
     assert_eq!(cf.len(), frames);
     assert_eq!(cf[0].width, SCREEN_WIDTH);
     assert_eq!(cf[0].height, SCREEN_HEIGHT);
 
     let mut sameboy = SameBoyEmulator::new();
-    let sf = replay(&mut sameboy, &rom, Some(&boot), &recording, frames).unwrap();
+    let sf = replay(&mut sameboy, &rom, &BootRom::Bundled, &recording, frames).unwrap();
     assert_eq!(sf.len(), frames);
     assert_eq!(sf[0].rgba.len(), SCREEN_WIDTH * SCREEN_HEIGHT * 4);
 
@@ -0,0 +1,33 @@
+//! What the benchmarks share: the homebrew games of the app and machines booted into them.
+
+#![allow(dead_code)]
+
+use citrine_gb::gb::{GameBoy, GbModel};
+use citrine_gb::rom::Rom;
+use std::path::Path;
+
+pub const HOMEBREW: [&str; 5] = [
+    "adjustris",
+    "porklike",
+    "shock_lobster",
+    "sushi_nights",
+    "tobu",
+];
+/// Past the boot ROM and the title screens' fade-ins
+pub const WARMUP_FRAMES: u32 = 300;
+
+pub fn homebrew(name: &str) -> Rom {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("../app/homebrew/{name}.gb"));
+    Rom::new(&std::fs::read(&path).expect("homebrew ROM"))
+}
+
+/// A DMG running `rom` for [`WARMUP_FRAMES`], with the audio of the warmup dropped
+pub fn booted(rom: &Rom) -> GameBoy {
+    let mut gb = GameBoy::new_empty(GbModel::Dmg);
+    gb.load_rom(rom).unwrap();
+    for _ in 0..WARMUP_FRAMES {
+        gb.run_frame();
+    }
+    gb.apu.audio_buffer.clear();
+    gb
+}
@@ -0,0 +1,35 @@
+//! Static analysis of whole homebrew ROMs, from their entry point and every restart and interrupt
+//! vector. Run with `make bench`.
+
+mod common;
+
+use citrine_gb::disassembly::{Confidence, Disassembly};
+use citrine_gb::gb::{GameBoy, GbModel};
+use common::{HOMEBREW, homebrew};
+use criterion::{Criterion, criterion_group, criterion_main};
+
+const ENTRY_POINTS: [u16; 14] = [
+    0x0100, 0x0000, 0x0008, 0x0010, 0x0018, 0x0020, 0x0028, 0x0030, 0x0038, 0x0040, 0x0048, 0x0050,
+    0x0058, 0x0060,
+];
+
+fn analyze(c: &mut Criterion) {
+    let mut group = c.benchmark_group("disassembly");
+    for name in HOMEBREW {
+        let mut gb = GameBoy::new_empty(GbModel::Dmg);
+        gb.load_rom(&homebrew(name)).unwrap();
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut disassembly = Disassembly::new();
+                for start in ENTRY_POINTS {
+                    disassembly.analyze(&gb.cartridge, start, Confidence::Unconditional);
+                }
+                disassembly
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, analyze);
+criterion_main!(benches);
@@ -0,0 +1,132 @@
+//! Frames per second, run with `make bench`. The homebrew games of the app cover the usual mix,
+//! `fast_forward` measures what fast-forwarding and the lab pay: no audio and only the last frame
+//! drawn. `cpu_heavy` and `ppu_heavy` are made up to stress one side each. `lock_step` runs both
+//! fast-forwarded with the components scheduled and ticked every M-cycle, the way the bus used to.
+
+mod common;
+
+use citrine_gb::gb::{GameBoy, GbModel};
+use citrine_gb::rom::Rom;
+use common::{HOMEBREW, booted, homebrew};
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+
+/// Turns the LCD and the APU off, then loops over loads, stores, ALU and stack operations and
+/// calls on work RAM
+const CPU_HEAVY: [u8; 37] = [
+    // LD SP,0xFFFE; XOR A; LDH [LCDC],A; LDH [NR52],A
+    0x31, 0xFE, 0xFF, 0xAF, 0xE0, 0x40, 0xE0, 0x26, // LD HL,0xC000; LD DE,0xD000; LD B,0
+    0x21, 0x00, 0xC0, 0x11, 0x00, 0xD0, 0x06, 0x00,
+    // LD A,[HL+]; ADD A,C; SWAP A; XOR B; LD C,A; LD [DE],A; INC DE; PUSH BC; CALL 0x0172
+    0x2A, 0x81, 0xCB, 0x37, 0xA8, 0x4F, 0x12, 0x13, 0xC5, 0xCD, 0x72, 0x01,
+    // POP BC; DEC B; JR NZ,-16; JR -26
+    0xC1, 0x05, 0x20, 0xF0, 0x18, 0xE6, // ADC A,L; RLA; RET
+    0x8D, 0x17, 0xC9,
+];
+
+/// Fills VRAM with a pattern and OAM with 8x16 objects spread over the screen, about five on
+/// each line, turns the window on halfway down, then halts for good
+const PPU_HEAVY: [u8; 63] = [
+    // LD SP,0xFFFE; XOR A; LDH [LCDC],A; LDH [NR52],A
+    0x31, 0xFE, 0xFF, 0xAF, 0xE0, 0x40, 0xE0, 0x26,
+    // LD HL,0x8000; LD A,L; XOR H; LD [HL+],A; LD A,H; CP 0xA0; JR NZ,-8
+    0x21, 0x00, 0x80, 0x7D, 0xAC, 0x22, 0x7C, 0xFE, 0xA0, 0x20, 0xF8,
+    // LD HL,0xFE00; LD A,L; AND 0x7F; ADD A,16; LD [HL+],A; LD A,L; LD [HL+],A; LD [HL+],A
+    0x21, 0x00, 0xFE, 0x7D, 0xE6, 0x7F, 0xC6, 0x10, 0x22, 0x7D, 0x22, 0x22,
+    // LD A,L; AND 0x60; LD [HL+],A; LD A,L; CP 0xA0; JR NZ,-18
+    0x7D, 0xE6, 0x60, 0x22, 0x7D, 0xFE, 0xA0, 0x20, 0xEE, // WX, WY, BGP, OBP0, OBP1
+    0x3E, 0x07, 0xE0, 0x4B, 0x3E, 0x48, 0xE0, 0x4A, 0x3E, 0xE4, 0xE0, 0x47, 0xE0, 0x48, 0xE0, 0x49,
+    // LD A,0xF7; LDH [LCDC],A (window on 0x9C00, 8x16 objects); HALT; JR -3
+    0x3E, 0xF7, 0xE0, 0x40, 0x76, 0x18, 0xFD,
+];
+
+/// A ROM starting `program` at 0x0150, behind the header
+fn synthetic(program: &[u8]) -> Rom {
+    let mut data = vec![0x00; 0x8000];
+    // NOP; JP 0x0150
+    data[0x100..0x104].copy_from_slice(&[0x00, 0xC3, 0x50, 0x01]);
+    data[0x150..0x150 + program.len()].copy_from_slice(program);
+    Rom::new(&data)
+}
+
+fn run_frame(c: &mut Criterion) {
+    let mut group = c.benchmark_group("run_frame");
+    group.throughput(Throughput::Elements(1));
+    for name in HOMEBREW {
+        let mut gb = booted(&homebrew(name));
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                gb.run_frame();
+                gb.apu.audio_buffer.clear();
+            })
+        });
+    }
+    group.finish();
+}
+
+fn fast_forward(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fast_forward");
+    group.throughput(Throughput::Elements(1));
+    for name in HOMEBREW {
+        let mut gb = booted(&homebrew(name));
+        gb.apu.suppress_output = true;
+        gb.ppu.skip_rendering = true;
+        group.bench_function(name, |b| b.iter(|| gb.run_frame()));
+    }
+    group.finish();
+}
+
+/// A DMG past the setup of `program`
+fn synthetic_booted(program: &[u8]) -> GameBoy {
+    let mut gb = GameBoy::new_empty(GbModel::Dmg);
+    gb.load_rom(&synthetic(program)).unwrap();
+    for _ in 0..10 {
+        gb.run_frame();
+    }
+    gb
+}
+
+fn synthetic_workloads(c: &mut Criterion) {
+    let mut group = c.benchmark_group("synthetic");
+    group.throughput(Throughput::Elements(1));
+    for (name, program) in [("cpu_heavy", &CPU_HEAVY[..]), ("ppu_heavy", &PPU_HEAVY[..])] {
+        let mut gb = synthetic_booted(program);
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                gb.run_frame();
+                gb.apu.audio_buffer.clear();
+            })
+        });
+    }
+    group.finish();
+}
+
+fn lock_step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lock_step");
+    group.throughput(Throughput::Elements(1));
+    let machines = HOMEBREW
+        .iter()
+        .map(|name| (*name, booted(&homebrew(name))))
+        .chain([
+            ("cpu_heavy", synthetic_booted(&CPU_HEAVY)),
+            ("ppu_heavy", synthetic_booted(&PPU_HEAVY)),
+        ]);
+    for (name, gb) in machines {
+        for (mode, lock_step) in [("lock_step", true), ("scheduled", false)] {
+            let mut gb = gb.clone();
+            gb.set_lock_step(lock_step);
+            gb.apu.suppress_output = true;
+            gb.ppu.skip_rendering = true;
+            group.bench_function(format!("{name}/{mode}"), |b| b.iter(|| gb.run_frame()));
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    run_frame,
+    fast_forward,
+    synthetic_workloads,
+    lock_step
+);
+criterion_main!(benches);
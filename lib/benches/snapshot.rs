@@ -0,0 +1,32 @@
+//! Taking and restoring whole-machine snapshots, the cost of a save state or a rewind step. Run
+//! with `make bench`.
+
+mod common;
+
+use citrine_gb::gb::GameBoy;
+use common::{HOMEBREW, booted, homebrew};
+use criterion::{Criterion, criterion_group, criterion_main};
+
+fn dump_full(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dump_full");
+    for name in HOMEBREW {
+        let mut gb = booted(&homebrew(name));
+        group.bench_function(name, |b| b.iter(|| gb.dump_full().unwrap()));
+    }
+    group.finish();
+}
+
+fn from_dump(c: &mut Criterion) {
+    let mut group = c.benchmark_group("from_dump");
+    for name in HOMEBREW {
+        let rom = homebrew(name);
+        let dump = booted(&rom).dump_full().unwrap();
+        group.bench_function(name, |b| {
+            b.iter(|| GameBoy::from_dump(&dump, &rom).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, dump_full, from_dump);
+criterion_main!(benches);
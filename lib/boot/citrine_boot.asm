@@ -0,0 +1,264 @@
+; Citrine boot ROM
+;
+; A small, freely licensed replacement for the Game Boy boot ROM (MIT, like the rest of Citrine).
+; It draws the cartridge logo, scrolls it into view, plays a short chime and hands over to the
+; cartridge with the register state of the selected model. Unlike the original it never locks
+; up on a logo or header checksum mismatch. The CGB and AGB images switch to DMG compatibility
+; mode for cartridges without CGB support, with one fixed palette instead of a per-game one.
+;
+; Build one image per model with RGBDS:
+;   rgbasm -D MODEL_DMG -o dmg.o citrine_boot.asm && rgblink -x -o dmg_boot.bin dmg.o
+; Available models: MODEL_DMG, MODEL_MGB, MODEL_SGB2, MODEL_CGB, MODEL_AGB
+; `make boot-roms` builds all of them and checks they match lib/boot/SHA256SUMS.
+
+DEF rLCDC EQU $40
+DEF rSCY  EQU $42
+DEF rLY   EQU $44
+DEF rBGP  EQU $47
+DEF rKEY0 EQU $4C
+DEF rBOOT EQU $50
+DEF rBCPS EQU $68
+DEF rBCPD EQU $69
+DEF rOCPS EQU $6A
+DEF rOCPD EQU $6B
+DEF rNR11 EQU $11
+DEF rNR13 EQU $13
+DEF rNR14 EQU $14
+DEF rNR52 EQU $FF26
+
+DEF LOGO_START EQU $0104
+DEF LOGO_END   EQU $0134
+DEF CGB_FLAG EQU $0143
+DEF HEADER_CHECKSUM EQU $014D
+
+; Register state right after the boot ROM unmaps itself.
+; F depends on the header checksum on DMG and MGB (Z and C are left over from the checksum loop).
+IF DEF(MODEL_DMG)
+    DEF BOOT_A EQU $01
+    DEF BOOT_F_ZERO EQU $80
+    DEF BOOT_F_NONZERO EQU $B0
+    DEF BOOT_BC EQU $0013
+    DEF BOOT_DE EQU $00D8
+    DEF BOOT_HL EQU $014D
+ELIF DEF(MODEL_MGB)
+    DEF BOOT_A EQU $FF
+    DEF BOOT_F_ZERO EQU $80
+    DEF BOOT_F_NONZERO EQU $B0
+    DEF BOOT_BC EQU $0013
+    DEF BOOT_DE EQU $00D8
+    DEF BOOT_HL EQU $014D
+ELIF DEF(MODEL_SGB2)
+    DEF BOOT_A EQU $FF
+    DEF BOOT_F_ZERO EQU $00
+    DEF BOOT_F_NONZERO EQU $00
+    DEF BOOT_BC EQU $0014
+    DEF BOOT_DE EQU $0000
+    DEF BOOT_HL EQU $C060
+ELIF DEF(MODEL_CGB)
+    DEF BOOT_A EQU $11
+    DEF BOOT_F_ZERO EQU $80
+    DEF BOOT_F_NONZERO EQU $80
+    DEF BOOT_BC EQU $0000
+    DEF BOOT_DE EQU $FF56
+    DEF BOOT_HL EQU $000D
+ELIF DEF(MODEL_AGB)
+    DEF BOOT_A EQU $11
+    DEF BOOT_F_ZERO EQU $00
+    DEF BOOT_F_NONZERO EQU $00
+    DEF BOOT_BC EQU $0100
+    DEF BOOT_DE EQU $FF56
+    DEF BOOT_HL EQU $000D
+ELSE
+    FAIL "Select a model, e.g. -D MODEL_DMG"
+ENDC
+
+SECTION "Boot", ROM0[$0000]
+Boot:
+    ld sp, $FFFE
+
+    ; Clear VRAM
+    xor a
+    ld hl, $9FFF
+.clearVram:
+    ld [hl-], a
+    bit 7, h
+    jr nz, .clearVram
+
+    ; Audio on, pulse 1 at full volume with a fading envelope, both speakers
+    ld hl, rNR52
+    ld c, rNR11
+    ld a, $80
+    ld [hl-], a
+    ldh [c], a
+    inc c
+    ld a, $F3
+    ldh [c], a
+    ld [hl-], a
+    ld a, $77
+    ld [hl], a
+
+    ld a, $FC
+    ldh [rBGP], a
+
+    ; Expand the 1bpp cartridge logo into tiles 1-24, doubling every pixel
+    ld hl, LOGO_START
+    ld de, $8010
+.logo:
+    ld c, [hl]
+    inc hl
+    call ExpandNibble
+    call ExpandNibble
+    ld a, l
+    cp LOW(LOGO_END)
+    jr nz, .logo
+
+    ; Tile 25 holds the citrine gem shown next to the logo
+    ld hl, GemTile
+    ld b, 8
+.gem:
+    ld a, [hl+]
+    ld [de], a
+    inc de
+    inc de
+    dec b
+    jr nz, .gem
+
+    ; Logo tile map, two rows of twelve tiles
+    ld a, 1
+    ld hl, $9904
+    call MapRow
+    ld hl, $9924
+    call MapRow
+    ld a, $19
+    ld [$9910], a
+
+    ; Scroll the logo down into the center of the screen
+    ld a, $64
+    ldh [rSCY], a
+    ld a, $91
+    ldh [rLCDC], a
+.scroll:
+    call WaitFrame
+    ldh a, [rSCY]
+    dec a
+    ldh [rSCY], a
+    jr nz, .scroll
+
+    ; Chime
+    ld a, $C1
+    ldh [rNR13], a
+    ld a, $87
+    ldh [rNR14], a
+
+    ld b, 60
+.hold:
+    call WaitFrame
+    dec b
+    jr nz, .hold
+
+IF DEF(MODEL_CGB) || DEF(MODEL_AGB)
+    ; KEY0 takes the CGB flag of CGB cartridges, bit 2 selects DMG compatibility mode for the
+    ; others. The palettes have to be in place before, KEY0 locks them.
+    ld a, [CGB_FLAG]
+    bit 7, a
+    jr nz, .key0
+    ld a, $80
+    ldh [rBCPS], a
+    ldh [rOCPS], a
+    ld hl, CompatPalettes
+    ld c, LOW(rBCPD)
+    call CopyPalette
+    ld c, LOW(rOCPD)
+    call CopyPalette
+    ld l, LOW(CompatPalettes + 8)
+    call CopyPalette
+    ld a, $04
+.key0:
+    ldh [rKEY0], a
+ENDC
+
+    ; Hand over with the post-boot registers of the selected model
+    ld a, [HEADER_CHECKSUM]
+    and a
+    ld c, BOOT_F_NONZERO
+    jr nz, .flags
+    ld c, BOOT_F_ZERO
+.flags:
+    ld b, BOOT_A
+    push bc
+    pop af
+    ld bc, BOOT_BC
+    ld de, BOOT_DE
+    ld hl, BOOT_HL
+    jr Unmap
+
+; Writes the high nibble of C with every bit doubled to the tile rows at DE and DE + 2,
+; then shifts the low nibble of C into place for the next call.
+ExpandNibble:
+    push hl
+    ld b, 4
+.bit:
+    sla c
+    push af
+    rl l
+    pop af
+    rl l
+    dec b
+    jr nz, .bit
+    ld a, l
+    ld [de], a
+    inc de
+    inc de
+    ld [de], a
+    inc de
+    inc de
+    pop hl
+    ret
+
+; Writes twelve consecutive tile IDs starting at A to HL
+MapRow:
+    ld b, 12
+.tile:
+    ld [hl+], a
+    inc a
+    dec b
+    jr nz, .tile
+    ret
+
+; Waits for the start of the next vertical blank
+WaitFrame:
+.leave:
+    ldh a, [rLY]
+    cp $90
+    jr z, .leave
+.enter:
+    ldh a, [rLY]
+    cp $90
+    jr nz, .enter
+    ret
+
+GemTile:
+    db $18, $3C, $7E, $FF, $7E, $3C, $18, $00
+
+IF DEF(MODEL_CGB) || DEF(MODEL_AGB)
+; Writes the 8 bytes of one palette at HL to the palette data port C
+CopyPalette:
+    ld b, 8
+.byte:
+    ld a, [hl+]
+    ldh [c], a
+    dec b
+    jr nz, .byte
+    ret
+
+; BGR555, the BG palette and then the one both OBJ palettes share
+CompatPalettes:
+    dw $7FFF, $1BEF, $6180, $0000
+    dw $7FFF, $421F, $1CF2, $0000
+ENDC
+
+    ds $FE - @, $00
+
+; Unmapping the boot ROM has to be the very last instruction, execution continues at $0100
+Unmap:
+    ldh [rBOOT], a
@@ -0,0 +1,133 @@
+//! Audio capture: the mixed output and optionally one stem per channel, written as WAV files.
+//!
+//! [`AudioCapture`] collects from a running [`GameBoy`], with the `recording` feature
+//! [`capture_recording`] replays an input [`crate::recording::Recording`] headlessly to produce one.
+
+use crate::gb::GameBoy;
+
+/// Interleaved stereo samples of the mix and, if requested, of every channel
+#[derive(Debug, Clone, Default)]
+pub struct AudioCapture {
+    pub sample_rate: u32,
+    pub mix: Vec<f32>,
+    pub stems: Option<[Vec<f32>; 4]>,
+}
+
+impl AudioCapture {
+    pub fn new(sample_rate: u32, stems: bool) -> Self {
+        Self {
+            sample_rate,
+            mix: vec![],
+            stems: stems.then(Default::default),
+        }
+    }
+
+    /// Sets the APU up to produce what this capture collects
+    pub fn attach(&self, gb: &mut GameBoy) {
+        gb.apu.set_sample_rate(self.sample_rate);
+        gb.apu.set_stems_enabled(self.stems.is_some());
+    }
+
+    /// Moves the samples the APU produced since the last flush into the capture
+    pub fn collect(&mut self, gb: &mut GameBoy) {
+        self.mix.append(&mut gb.apu.audio_buffer);
+
+        if let (Some(stems), Some(apu_stems)) = (&mut self.stems, &mut gb.apu.stems) {
+            for (stem, mut samples) in stems.iter_mut().zip(apu_stems.take()) {
+                stem.append(&mut samples);
+            }
+        }
+    }
+
+    pub fn duration_secs(&self) -> f64 {
+        (self.mix.len() / 2) as f64 / self.sample_rate as f64
+    }
+
+    pub fn mix_wav(&self) -> Vec<u8> {
+        encode_wav(&self.mix, self.sample_rate)
+    }
+
+    /// The WAV file of a single channel, `channel` counts from 0
+    pub fn stem_wav(&self, channel: usize) -> Option<Vec<u8>> {
+        let stems = self.stems.as_ref()?;
+        Some(encode_wav(stems.get(channel)?, self.sample_rate))
+    }
+}
+
+/// A 16-bit PCM stereo WAV file from interleaved samples in -1.0..=1.0
+pub fn encode_wav(samples: &[f32], sample_rate: u32) -> Vec<u8> {
+    const CHANNELS: u16 = 2;
+    const BYTES_PER_SAMPLE: u16 = 2;
+
+    let data_len = (samples.len() * BYTES_PER_SAMPLE as usize) as u32;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend(b"RIFF");
+    wav.extend((36 + data_len).to_le_bytes());
+    wav.extend(b"WAVE");
+
+    wav.extend(b"fmt ");
+    wav.extend(16u32.to_le_bytes());
+    wav.extend(1u16.to_le_bytes()); // PCM
+    wav.extend(CHANNELS.to_le_bytes());
+    wav.extend(sample_rate.to_le_bytes());
+    wav.extend((sample_rate * (CHANNELS * BYTES_PER_SAMPLE) as u32).to_le_bytes());
+    wav.extend((CHANNELS * BYTES_PER_SAMPLE).to_le_bytes());
+    wav.extend((BYTES_PER_SAMPLE * 8).to_le_bytes());
+
+    wav.extend(b"data");
+    wav.extend(data_len.to_le_bytes());
+    for sample in samples {
+        wav.extend(((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes());
+    }
+
+    wav
+}
+
+/// Replays the recording from power-on for `t_cycles` T-cycles and captures the audio
+#[cfg(feature = "recording")]
+pub fn capture_recording(
+    rom: &crate::rom::Rom,
+    boot_rom: crate::gb::boot_rom::BootRom,
+    recording: &crate::recording::Recording,
+    t_cycles: u64,
+    mut capture: AudioCapture,
+) -> crate::error::GbResult<AudioCapture> {
+    let mut gb = GameBoy::new_empty(recording.model);
+    gb.set_boot_rom(boot_rom);
+    gb.load_rom(rom)?;
+    capture.attach(&mut gb);
+
+    let mut events = recording.events.clone();
+    events.sort_by_key(|event| event.cycle);
+    let mut events = events.into_iter().peekable();
+    let mut m_cycles: u64 = 0;
+
+    while m_cycles * 4 < t_cycles {
+        while let Some(event) = events.next_if(|event| event.cycle <= m_cycles * 4) {
+            let button = event.button.to_joypad_state();
+            if event.pressed {
+                gb.press_button(button);
+            } else {
+                gb.release_button(button);
+            }
+        }
+
+        // Frames end like in `GameBoy::run_frame`, that's where the audio is flushed
+        let before = gb.cycle_counter;
+        let frame_ended = gb.step_frame();
+        let mut after = gb.cycle_counter;
+        if after < before {
+            // The end of the frame started counting the next one
+            after += gb.frame_cycles();
+        }
+        m_cycles += (after - before) as u64;
+
+        if frame_ended {
+            capture.collect(&mut gb);
+        }
+    }
+
+    gb.apu.flush_audio();
+    capture.collect(&mut gb);
+    Ok(capture)
+}
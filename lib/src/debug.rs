@@ -7,7 +7,7 @@ pub mod e2e;
 
 const MAX_PLOT_SAMPLES: usize = 2048;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Debugger {
     pub disassembly: Disassembly,
     pub static_analysis_enabled: bool,
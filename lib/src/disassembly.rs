@@ -43,7 +43,7 @@ impl Display for DecodedInstruction {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Disassembly {
     entries: BTreeMap<RomLocation, DecodedInstruction>,
 }
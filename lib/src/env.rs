@@ -0,0 +1,360 @@
+//! A gym-style environment for reinforcement learning: [`Env::reset`] starts an episode and
+//! [`Env::step`] holds a set of buttons for a number of frames, returning what the agent observes
+//! and what it earned.
+//!
+//! Episodes start either from power-on with RAM filled from the seed ([`RamInit::Random`]), so the
+//! same seed replays the same episode, or from a clone of a start state set with
+//! [`Env::set_start`], like a savegame past the title screen. Rewards come from [`Reward`] hooks
+//! reading the game's RAM, [`RamDelta`] covers scores and counters.
+
+use crate::error::GbResult;
+use crate::gb::joypad::JoypadState;
+use crate::gb::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::gb::ram_init::RamInit;
+use crate::gb::{GameBoy, GbModel};
+use crate::rom::Rom;
+
+/// Width of [`TileObservation::background`] in tiles
+pub const SCREEN_TILES_X: usize = SCREEN_WIDTH / 8;
+/// Height of [`TileObservation::background`] in tiles
+pub const SCREEN_TILES_Y: usize = SCREEN_HEIGHT / 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObservationKind {
+    /// The frame as RGBA, 4 bytes per pixel
+    Rgba,
+    /// The frame as one luminance byte per pixel
+    Greyscale,
+    /// Greyscale averaged over `factor` x `factor` pixel blocks
+    Downsampled { factor: usize },
+    /// The tile ids on screen and OAM, the frame isn't drawn at all
+    Tiles,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Observation {
+    Rgba(Vec<u8>),
+    Greyscale(Vec<u8>),
+    Downsampled {
+        width: usize,
+        height: usize,
+        pixels: Vec<u8>,
+    },
+    Tiles(TileObservation),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TileObservation {
+    /// The tile id at the top left pixel of every 8x8 block on screen, row by row, from the window
+    /// where it covers the background and with the background scroll applied
+    pub background: Vec<u8>,
+    /// The 40 sprite entries of OAM: Y, X, tile id and attributes
+    pub oam: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnvConfig {
+    pub model: GbModel,
+    pub observation: ObservationKind,
+    /// Episodes are truncated after this many frames, 0 for never
+    pub max_frames: u64,
+    /// Idle frames after a reset, between 0 and this many as picked by the seed, so episodes from
+    /// the same start state don't play out the same
+    pub noop_max: u32,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            model: GbModel::Dmg,
+            observation: ObservationKind::Rgba,
+            max_frames: 0,
+            noop_max: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepInfo {
+    /// The sum of all reward hooks for this step
+    pub reward: f64,
+    /// The done hook ended the episode
+    pub terminated: bool,
+    /// The episode ran into [`EnvConfig::max_frames`]
+    pub truncated: bool,
+    /// Frames since the reset
+    pub frame: u64,
+}
+
+/// Scores a step by looking at the machine after it, usually at RAM through
+/// [`GameBoy::read_memory`]
+pub trait Reward: Send {
+    /// Called after every reset, before the first step
+    fn reset(&mut self, _gb: &mut GameBoy) {}
+
+    fn reward(&mut self, gb: &mut GameBoy) -> f64;
+}
+
+impl<F: FnMut(&mut GameBoy) -> f64 + Send> Reward for F {
+    fn reward(&mut self, gb: &mut GameBoy) -> f64 {
+        self(gb)
+    }
+}
+
+/// How a number is stored in RAM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RamNumber {
+    U8,
+    U16Le,
+    U16Be,
+    /// Binary coded decimal over this many bytes, least significant byte first. Only the
+    /// [`MAX_BCD_BYTES`] least significant bytes are read
+    BcdLe(u8),
+    /// Binary coded decimal over this many bytes, most significant byte first. Only the
+    /// [`MAX_BCD_BYTES`] least significant bytes are read
+    BcdBe(u8),
+}
+
+/// Longest BCD number [`RamNumber`] reads, 18 digits fit a `u64`
+pub const MAX_BCD_BYTES: u8 = 9;
+
+impl RamNumber {
+    pub fn read(self, gb: &mut GameBoy, addr: u16) -> u64 {
+        let mut byte = |offset: u8| gb.read_memory(addr.wrapping_add(offset as u16)) as u64;
+        let bcd = |byte: u64| (byte >> 4) * 10 + (byte & 0x0F);
+        match self {
+            Self::U8 => byte(0),
+            Self::U16Le => byte(0) | byte(1) << 8,
+            Self::U16Be => byte(0) << 8 | byte(1),
+            Self::BcdLe(bytes) => (0..bytes.min(MAX_BCD_BYTES))
+                .rev()
+                .fold(0, |n, i| n * 100 + bcd(byte(i))),
+            Self::BcdBe(bytes) => {
+                (bytes.saturating_sub(MAX_BCD_BYTES)..bytes).fold(0, |n, i| n * 100 + bcd(byte(i)))
+            }
+        }
+    }
+}
+
+/// Rewards the change of a number in RAM since the last step, like a score
+#[derive(Debug, Clone, PartialEq)]
+pub struct RamDelta {
+    pub addr: u16,
+    pub number: RamNumber,
+    /// Multiplies the change, negative to punish growth
+    pub scale: f64,
+    last: u64,
+}
+
+impl RamDelta {
+    pub fn new(addr: u16, number: RamNumber) -> Self {
+        Self {
+            addr,
+            number,
+            scale: 1.0,
+            last: 0,
+        }
+    }
+
+    pub fn scaled(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+}
+
+impl Reward for RamDelta {
+    fn reset(&mut self, gb: &mut GameBoy) {
+        self.last = self.number.read(gb, self.addr);
+    }
+
+    fn reward(&mut self, gb: &mut GameBoy) -> f64 {
+        let value = self.number.read(gb, self.addr);
+        let delta = value as f64 - self.last as f64;
+        self.last = value;
+        delta * self.scale
+    }
+}
+
+type DoneHook = Box<dyn FnMut(&mut GameBoy) -> bool + Send>;
+
+pub struct Env {
+    pub config: EnvConfig,
+    /// The machine of the running episode
+    pub gb: GameBoy,
+    /// The powered-on machine every seeded episode is cloned from
+    power_on: GameBoy,
+    start: Option<GameBoy>,
+    rewards: Vec<Box<dyn Reward>>,
+    done: Option<DoneHook>,
+    frame: u64,
+}
+
+impl Env {
+    /// An environment running `rom`, call [`Env::reset`] to start the first episode
+    pub fn new(rom: &Rom, config: EnvConfig) -> GbResult<Self> {
+        let mut power_on = GameBoy::new_empty(config.model);
+        power_on.load_rom(rom)?;
+        Ok(Self {
+            config,
+            gb: power_on.clone(),
+            power_on,
+            start: None,
+            rewards: Vec::new(),
+            done: None,
+            frame: 0,
+        })
+    }
+
+    pub fn add_reward(&mut self, reward: impl Reward + 'static) {
+        self.rewards.push(Box::new(reward));
+    }
+
+    /// Ends episodes once `done` returns true, like when the game over screen shows up
+    pub fn set_done(&mut self, done: impl FnMut(&mut GameBoy) -> bool + Send + 'static) {
+        self.done = Some(Box::new(done));
+    }
+
+    /// Starts the following episodes from a clone of `state` instead of power-on, `None` goes
+    /// back to power-on
+    pub fn set_start(&mut self, state: Option<GameBoy>) {
+        self.start = state;
+    }
+
+    /// Starts a new episode. From power-on, `seed` fills the RAM and the same seed gives the same
+    /// episode for the same actions. It also picks the number of idle frames up to
+    /// [`EnvConfig::noop_max`], which is all it does with a start state from [`Env::set_start`]:
+    /// that is cloned as it is
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.gb = match &self.start {
+            Some(start) => start.clone(),
+            None => {
+                let mut gb = self.power_on.clone();
+                gb.ram_init = RamInit::Random { seed };
+                gb.soft_reset();
+                gb
+            }
+        };
+        self.frame = 0;
+
+        let noops = splitmix64(seed) % (self.config.noop_max as u64 + 1);
+        self.gb.release_button(JoypadState::all());
+        self.run_frames(noops as u32);
+        for reward in &mut self.rewards {
+            reward.reset(&mut self.gb);
+        }
+        self.observe()
+    }
+
+    /// Holds `buttons` for `frame_skip` frames (at least one) and observes the last one
+    pub fn step(&mut self, buttons: JoypadState, frame_skip: u32) -> (Observation, StepInfo) {
+        self.gb.press_button(buttons);
+        self.gb.release_button(buttons.complement());
+        self.run_frames(frame_skip.max(1));
+
+        let reward = self
+            .rewards
+            .iter_mut()
+            .map(|reward| reward.reward(&mut self.gb))
+            .sum();
+        let terminated = self.done.as_mut().is_some_and(|done| done(&mut self.gb));
+        let truncated = self.config.max_frames > 0 && self.frame >= self.config.max_frames;
+
+        let info = StepInfo {
+            reward,
+            terminated,
+            truncated,
+            frame: self.frame,
+        };
+        (self.observe(), info)
+    }
+
+    /// Runs frames without audio, drawing only the last one and only if the observation needs it
+    fn run_frames(&mut self, frames: u32) {
+        let draws = self.config.observation != ObservationKind::Tiles;
+        self.gb.apu.suppress_output = true;
+        for frame in 0..frames {
+            self.gb.ppu.skip_rendering = !draws || frame + 1 < frames;
+            self.gb.run_frame();
+            self.frame += 1;
+        }
+        self.gb.ppu.skip_rendering = false;
+    }
+
+    pub fn observe(&self) -> Observation {
+        let rgba = self.gb.frame().as_slice();
+        match self.config.observation {
+            ObservationKind::Rgba => Observation::Rgba(rgba.to_vec()),
+            ObservationKind::Greyscale => {
+                Observation::Greyscale(rgba.chunks_exact(4).map(luminance).collect())
+            }
+            ObservationKind::Downsampled { factor } => {
+                let factor = factor.max(1);
+                let (width, height) = (SCREEN_WIDTH / factor, SCREEN_HEIGHT / factor);
+                let mut pixels = Vec::with_capacity(width * height);
+                for y in 0..height {
+                    for x in 0..width {
+                        let sum: usize = (0..factor * factor)
+                            .map(|i| {
+                                let px = x * factor + i % factor;
+                                let py = y * factor + i / factor;
+                                let index = (py * SCREEN_WIDTH + px) * 4;
+                                luminance(&rgba[index..index + 4]) as usize
+                            })
+                            .sum();
+                        pixels.push((sum / (factor * factor)) as u8);
+                    }
+                }
+                Observation::Downsampled {
+                    width,
+                    height,
+                    pixels,
+                }
+            }
+            ObservationKind::Tiles => Observation::Tiles(self.tiles()),
+        }
+    }
+
+    fn tiles(&self) -> TileObservation {
+        let ppu = &self.gb.ppu;
+        let vram = &ppu.vram[0];
+        let window = ppu.lcdc.do_render_window();
+        let mut background = Vec::with_capacity(SCREEN_TILES_X * SCREEN_TILES_Y);
+
+        for tile_y in 0..SCREEN_TILES_Y as u8 {
+            for tile_x in 0..SCREEN_TILES_X as u8 {
+                let (x, y) = (tile_x * 8, tile_y * 8);
+                let addr = if window && y >= ppu.wy && x + 7 >= ppu.wx {
+                    let (window_x, window_y) = (x + 7 - ppu.wx, y - ppu.wy);
+                    ppu.lcdc.window_tile_id_address(window_x / 8, window_y / 8)
+                } else {
+                    let (map_x, map_y) = (x.wrapping_add(ppu.scx), y.wrapping_add(ppu.scy));
+                    ppu.lcdc.bg_tile_id_address(map_x / 8, map_y / 8)
+                };
+                background.push(vram[(addr - 0x8000) as usize]);
+            }
+        }
+
+        TileObservation {
+            background,
+            oam: ppu.oam.to_vec(),
+        }
+    }
+
+    /// Frames since the reset
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+}
+
+/// ITU-R BT.601 luma
+fn luminance(rgba: &[u8]) -> u8 {
+    let (r, g, b) = (rgba[0] as u32, rgba[1] as u32, rgba[2] as u32);
+    ((r * 299 + g * 587 + b * 114) / 1000) as u8
+}
+
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
@@ -5,6 +5,23 @@ pub enum GbError {
     #[cfg(feature = "base64")]
     #[error("Base64 decode error: {0}")]
     Base64Decode(#[from] base64::DecodeError),
+    #[error("Not a GBS file")]
+    InvalidGbsHeader,
+    #[error("GBS load address {0:#06X} is outside of 0x0400-0x7FFF")]
+    UnsupportedGbsLoadAddress(u16),
+    #[error("GBS track {0} does not exist")]
+    GbsTrackOutOfRange(u8),
+    #[cfg(feature = "bincode")]
+    #[error("Bincode error: {0}")]
+    Bincode(#[from] bincode::Error),
+    #[cfg(feature = "persistence")]
+    #[error("Incompatible snapshot: {0}")]
+    IncompatibleSnapshot(crate::persistence::snapshot::Incompatibility),
+    #[cfg(feature = "persistence")]
+    #[error("Invalid BESS save state: {0}")]
+    InvalidBess(&'static str),
+    #[error("Empty save file")]
+    EmptySaveFile,
     #[error("IO error: {0}")]
     IO(#[from] std::io::Error),
     #[error("Missing ROM cartridge type")]
@@ -15,6 +32,8 @@ pub enum GbError {
     #[cfg(feature = "rmp-serde")]
     #[error("RMP encode error: {0}")]
     RmpEncode(#[from] rmp_serde::encode::Error),
+    #[error("Corrupted rewind state")]
+    RewindStateCorrupted,
     #[error("ROM too small")]
     RomTooSmall,
     #[error("ROM size exceeded expected rom bank count")]
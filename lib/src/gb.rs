@@ -1,25 +1,35 @@
 use crate::error::GbResult;
 use crate::rom::Rom;
+use crate::rom::header::RomCgbMode;
+use crate::{ReadMemory, WriteMemory};
 use ppu::types::framebuffer::Framebuffer;
+use ppu::types::native_frame::NativeFrame;
+use std::fmt::Display;
 
 pub mod apu;
-mod boot_rom;
+pub mod boot_rom;
 pub mod bus;
 pub mod cartridge;
 pub mod cpu;
 mod dma;
 pub mod ic;
 pub mod joypad;
-mod memory;
+pub mod machine_state;
+pub(crate) mod memory;
 pub mod ppu;
 pub mod ram_init;
+pub mod scheduler;
+pub mod speed;
 pub mod timer;
 
-// ToDo: CGB specific registers like speed mode
+// ToDo: Remaining CGB specific registers
+/// Cloning forks the machine: the ROM is shared, everything else is copied. The clone's
+/// resamplers start empty, so audio that hasn't been flushed yet stays with the original
+#[derive(Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GameBoy {
     #[cfg_attr(feature = "serde", serde(skip, default))]
-    pub boot_rom: boot_rom::BootRom,
+    pub boot_rom: boot_rom::MappedBootRom,
     pub cpu: cpu::Cpu,
     pub cartridge: cartridge::Cartridge,
     #[cfg(feature = "debug")]
@@ -32,13 +42,22 @@ pub struct GameBoy {
     pub ppu: ppu::Ppu,
     pub apu: apu::Apu,
     pub joypad: joypad::Joypad,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub speed: speed::Speed,
     pub model: GbModel,
     pub cycle_counter: u32,
     pub ram_init: ram_init::RamInit,
+    #[cfg_attr(feature = "serde", serde(skip, default))]
+    scheduler: scheduler::Scheduler,
+    #[cfg_attr(feature = "serde", serde(skip, default))]
+    pub vgm_logger: Option<crate::vgm::VgmLogger>,
+    /// Bytes sent over the link port since [`GameBoy::start_serial_logging`]
+    #[cfg_attr(feature = "serde", serde(skip, default))]
+    pub serial_log: Option<Vec<u8>>,
 }
 
 impl GameBoy {
-    pub fn new(model: GbModel, boot_rom: Option<Vec<u8>>, rom_header_checksum: u8) -> Self {
+    pub fn new(model: GbModel, boot_rom: boot_rom::BootRom, rom_header_checksum: u8) -> Self {
         Self::new_with_ram_init(
             model,
             boot_rom,
@@ -49,26 +68,21 @@ impl GameBoy {
 
     pub fn new_with_ram_init(
         model: GbModel,
-        boot_rom: Option<Vec<u8>>,
+        boot_rom: boot_rom::BootRom,
         rom_header_checksum: u8,
         ram_init: ram_init::RamInit,
     ) -> Self {
-        let cpu = if boot_rom.is_some() {
-            cpu::Cpu::new_with_boot_rom(model)
-        } else {
-            match model {
-                GbModel::Dmg => cpu::Cpu::new_dmg(rom_header_checksum),
-                GbModel::Cgb => cpu::Cpu::new_cgb(),
-            }
-        };
-
-        let boot_rom = if let Some(boot_rom) = boot_rom {
-            boot_rom::BootRom {
-                rom: boot_rom,
-                mounted: true,
-            }
+        let boot_rom = boot_rom::MappedBootRom::new(boot_rom, model);
+        let (cpu, ppu) = if boot_rom.is_present() {
+            (
+                cpu::Cpu::new_with_boot_rom(model),
+                ppu::Ppu::new_with_boot_rom(model),
+            )
         } else {
-            boot_rom::BootRom::default()
+            (
+                cpu::Cpu::new_post_boot(model, rom_header_checksum),
+                ppu::Ppu::new(model),
+            )
         };
 
         Self {
@@ -81,50 +95,90 @@ impl GameBoy {
             ic: ic::InterruptController::new(),
             memory: memory::Memory::new(model, ram_init),
             timer: timer::Timer::new(),
-            ppu: ppu::Ppu::new(model),
-            apu: apu::Apu::new(),
+            ppu,
+            apu: apu::Apu::new(model),
             joypad: joypad::Joypad::new(),
+            speed: speed::Speed::new(model),
             model,
             cycle_counter: 0,
             ram_init,
+            scheduler: scheduler::Scheduler::default(),
+            vgm_logger: None,
+            serial_log: None,
         }
     }
 
+    /// A Game Boy without cartridge which skips the boot sequence
     pub fn new_empty(model: GbModel) -> Self {
-        Self::new(model, None, 0x00)
+        Self::new(model, boot_rom::BootRom::Skip, 0x00)
     }
 
     pub fn new_empty_with_ram_init(model: GbModel, ram_init: ram_init::RamInit) -> Self {
-        Self::new_with_ram_init(model, None, 0x00, ram_init)
+        Self::new_with_ram_init(model, boot_rom::BootRom::Skip, 0x00, ram_init)
     }
 
     pub fn load_rom(&mut self, rom: &Rom) -> GbResult<()> {
         let sample_rate = self.apu.output_sample_rate;
-        let boot_rom = if !self.boot_rom.rom.is_empty() {
-            Some(self.boot_rom.rom.clone())
-        } else {
-            None
-        };
-
+        let lock_step = self.scheduler.lock_step();
         *self = Self::new_with_ram_init(
             self.model,
-            boot_rom,
+            self.boot_rom.source.clone(),
             rom.provided_header_checksum()?,
             self.ram_init,
         );
+        self.scheduler = scheduler::Scheduler::new(lock_step);
         self.cartridge.load_rom(rom)?;
+        // Without a boot ROM nothing switches a CGB to DMG compatibility mode for a DMG cartridge
+        if self.model.is_cgb()
+            && !self.boot_rom.is_present()
+            && self.cartridge.header.cgb_mode == RomCgbMode::None
+        {
+            self.ppu.enter_dmg_compat();
+        }
         self.apu.set_sample_rate(sample_rate);
         Ok(())
     }
 
     pub fn load_boot_rom(&mut self, rom: &[u8]) {
+        self.set_boot_rom(boot_rom::BootRom::Custom(rom.to_vec()));
+    }
+
+    /// Powers the Game Boy back on with the given boot ROM, it is kept across [`GameBoy::load_rom`]
+    pub fn set_boot_rom(&mut self, boot_rom: boot_rom::BootRom) {
         let sample_rate = self.apu.output_sample_rate;
-        *self = Self::new_with_ram_init(self.model, Some(rom.to_vec()), 0x00, self.ram_init);
+        let lock_step = self.scheduler.lock_step();
+        *self = Self::new_with_ram_init(self.model, boot_rom, 0x00, self.ram_init);
+        self.scheduler = scheduler::Scheduler::new(lock_step);
         self.apu.set_sample_rate(sample_rate);
     }
 
+    /// Runs one instruction, or one M-cycle while halted or stopped
     pub fn step(&mut self) {
-        self.cpu.step(&mut bus::CpuBus {
+        self.step_lazily();
+        self.sync();
+    }
+
+    /// Runs one instruction and leaves the components behind that can be, see
+    /// [`scheduler::Scheduler`]
+    fn step_lazily(&mut self) {
+        let (cpu, mut bus) = self.split_bus();
+        cpu.step(&mut bus);
+    }
+
+    fn sync(&mut self) {
+        self.split_bus().1.sync();
+    }
+
+    /// Runs every component every M-cycle instead of scheduling them, for comparing against.
+    /// Emulation is the same either way
+    pub fn set_lock_step(&mut self, lock_step: bool) {
+        self.sync();
+        self.scheduler = scheduler::Scheduler::new(lock_step);
+    }
+
+    /// The CPU and the bus connecting it to everything else
+    pub(crate) fn split_bus(&mut self) -> (&mut cpu::Cpu, bus::CpuBus<'_>) {
+        let bus = bus::CpuBus {
             boot_rom: &mut self.boot_rom,
             cartridge: &mut self.cartridge,
             #[cfg(feature = "debug")]
@@ -135,16 +189,33 @@ impl GameBoy {
             memory: &mut self.memory,
             ppu: &mut self.ppu,
             apu: &mut self.apu,
+            scheduler: &mut self.scheduler,
+            speed: &mut self.speed,
             timer: &mut self.timer,
             cycles: &mut self.cycle_counter,
-        });
+            vgm_logger: &mut self.vgm_logger,
+            serial_log: &mut self.serial_log,
+        };
+        (&mut self.cpu, bus)
+    }
+
+    /// Runs one instruction like [`GameBoy::step`] and tells whether that ended a frame, at the
+    /// point where [`GameBoy::run_frame`] returns. The audio of a frame is flushed at its end
+    pub fn step_frame(&mut self) -> bool {
+        self.step_lazily();
+        self.sync();
+        let ended = self.frame_ended();
+        if ended {
+            self.end_frame();
+        }
+        ended
     }
 
     pub fn run_frame(&mut self) {
         self.ppu.frame_ready = false;
 
-        while !self.ppu.frame_ready {
-            self.step();
+        loop {
+            self.step_lazily();
 
             #[cfg(feature = "debug")]
             if !self.debugger.breakpoints.is_empty() {
@@ -154,22 +225,133 @@ impl GameBoy {
                     .probe_rom_location(self.cpu.pc.wrapping_sub(1));
                 if self.debugger.breakpoints.contains(&loc) {
                     self.debugger.hit_breakpoint = true;
+                    self.sync();
                     return;
                 }
             }
 
-            if !self.ppu.lcdc.lcd_enabled && self.cycle_counter >= self.model.frame_cycles() {
+            if self.frame_ended() {
                 break;
             }
         }
 
-        if self.cycle_counter >= self.model.frame_cycles() {
-            self.cycle_counter -= self.model.frame_cycles();
-        }
+        self.sync();
+        self.end_frame();
+    }
+
+    /// Whether the PPU finished a frame or, while the LCD is off or the CPU stopped, a frame's
+    /// worth of cycles went by
+    fn frame_ended(&self) -> bool {
+        self.ppu.frame_ready
+            || ((!self.ppu.lcdc.lcd_enabled || self.cpu.stopped)
+                && self.cycle_counter >= self.frame_cycles())
+    }
 
+    /// Starts counting the next frame and flushes the audio of this one, the APU has to be caught
+    /// up
+    fn end_frame(&mut self) {
+        self.ppu.frame_ready = false;
+        if self.cycle_counter >= self.frame_cycles() {
+            self.cycle_counter -= self.frame_cycles();
+        }
         self.apu.flush_audio();
     }
 
+    /// A copy of everything that changes while the machine runs, see [`GameBoy::load_state`]
+    pub fn save_state(&self) -> machine_state::MachineState {
+        machine_state::MachineState {
+            boot_rom: self.boot_rom.clone(),
+            cpu: self.cpu.clone(),
+            cartridge: self.cartridge.clone(),
+            dma: self.dma.clone(),
+            ic: self.ic.clone(),
+            memory: self.memory.clone(),
+            timer: self.timer.clone(),
+            ppu: self.ppu.clone(),
+            apu: self.apu.clone(),
+            joypad: self.joypad.clone(),
+            speed: self.speed,
+            cycle_counter: self.cycle_counter,
+            #[cfg(feature = "debug")]
+            total_cycles: self.debugger.total_cycles,
+        }
+    }
+
+    /// Puts a state from [`GameBoy::save_state`] back. The framebuffer and the audio output are
+    /// kept, they show what ran last
+    pub fn load_state(&mut self, state: &machine_state::MachineState) {
+        self.boot_rom = state.boot_rom.clone();
+        self.cpu = state.cpu.clone();
+        self.cartridge = state.cartridge.clone();
+        self.dma = state.dma.clone();
+        self.ic = state.ic.clone();
+        self.memory = state.memory.clone();
+        self.timer = state.timer.clone();
+
+        let mut ppu = state.ppu.clone();
+        ppu.take_frame(&mut self.ppu);
+        ppu.skip_rendering = self.ppu.skip_rendering;
+        self.ppu = ppu;
+
+        let mut apu = state.apu.clone();
+        apu.take_output(&mut self.apu);
+        apu.suppress_output = self.apu.suppress_output;
+        self.apu = apu;
+
+        self.joypad = state.joypad.clone();
+        self.speed = state.speed;
+        self.cycle_counter = state.cycle_counter;
+        self.scheduler = scheduler::Scheduler::new(self.scheduler.lock_step());
+        #[cfg(feature = "debug")]
+        {
+            self.debugger.total_cycles = state.total_cycles;
+        }
+    }
+
+    /// Run-ahead: runs a frame, then `frames` more with the current input and rolls those back.
+    /// The framebuffer shows the last of them while the machine and its audio stay at the first,
+    /// which hides up to `frames` frames of input lag a game has on its own
+    pub fn run_frame_ahead(&mut self, frames: u32) {
+        if frames == 0 {
+            self.run_frame();
+            return;
+        }
+
+        // The frame kept is never shown, the last one run ahead takes its place
+        let skip_rendering = self.ppu.skip_rendering;
+        self.ppu.skip_rendering = true;
+        self.run_frame();
+        self.ppu.skip_rendering = skip_rendering;
+        #[cfg(feature = "debug")]
+        if self.debugger.hit_breakpoint {
+            return;
+        }
+
+        let state = self.save_state();
+        let audio = std::mem::take(&mut self.apu.audio_buffer);
+        let vgm_logger = self.vgm_logger.take();
+        let serial_log = self.serial_log.take();
+        let suppress_output = self.apu.suppress_output;
+
+        self.apu.suppress_output = true;
+        for frame in 1..=frames {
+            // Only the last frame is shown
+            self.ppu.skip_rendering = skip_rendering || frame < frames;
+            self.run_frame();
+        }
+        self.apu.suppress_output = suppress_output;
+        self.ppu.skip_rendering = skip_rendering;
+        #[cfg(feature = "debug")]
+        {
+            self.debugger.hit_breakpoint = false;
+        }
+
+        self.load_state(&state);
+        self.apu.audio_buffer = audio;
+        self.vgm_logger = vgm_logger;
+        self.serial_log = serial_log;
+    }
+
     pub fn run_cycles(&mut self, cycles: u32) {
         self.cycle_counter = 0;
         while self.cycle_counter < cycles {
@@ -177,21 +359,69 @@ impl GameBoy {
         }
     }
 
+    /// M-cycles per frame in the current speed mode
+    pub fn frame_cycles(&self) -> u32 {
+        self.speed.frame_cycles()
+    }
+
+    /// Starts logging sound register writes, see [`crate::vgm`]
+    pub fn start_vgm_logging(&mut self) {
+        self.vgm_logger = Some(crate::vgm::VgmLogger::new(&self.apu));
+    }
+
+    /// Stops logging and returns the VGM file, `None` if nothing was being logged
+    pub fn stop_vgm_logging(&mut self) -> Option<Vec<u8>> {
+        self.vgm_logger.take().map(crate::vgm::VgmLogger::finish)
+    }
+
+    /// Starts collecting the bytes games send over the link port, like test ROMs printing their
+    /// results. Nothing is connected, transfers never complete
+    pub fn start_serial_logging(&mut self) {
+        self.serial_log = Some(Vec::new());
+    }
+
     pub fn frame(&self) -> &Framebuffer {
         self.ppu.frame()
     }
 
+    /// The last frame as DMG shades or CGB colors, for comparing pictures regardless of the theme
+    /// or converting them straight to what the display wants
+    pub fn native_frame(&self) -> &NativeFrame {
+        self.ppu.native_frame()
+    }
+
+    /// Reads `addr` as the CPU sees it, without taking time and regardless of what the PPU or OAM
+    /// DMA currently lock out
+    pub fn read_memory(&mut self, addr: u16) -> u8 {
+        self.split_bus().1.read_naive(addr)
+    }
+
+    /// Writes `addr` as the CPU would, without taking time. Writes to ROM reach the MBC
+    pub fn write_memory(&mut self, addr: u16, value: u8) {
+        self.split_bus().1.write_naive(addr, value);
+    }
+
     pub fn soft_reset(&mut self) {
         self.boot_rom.soft_reset();
-        self.cpu
-            .soft_reset(self.cartridge.header.provided_header_checksum);
+        if self.boot_rom.is_present() {
+            self.cpu = cpu::Cpu::new_with_boot_rom(self.model);
+        } else {
+            self.cpu
+                .soft_reset(self.cartridge.header.provided_header_checksum);
+        }
         self.cartridge.soft_reset();
         self.dma.soft_reset();
         self.ic.soft_reset();
         self.memory.soft_reset(self.model, self.ram_init);
         self.timer.soft_reset();
-        self.ppu.soft_reset();
+        let dmg_compat = self.ppu.dmg_compat;
+        self.ppu.soft_reset(self.boot_rom.is_present());
+        if dmg_compat && !self.boot_rom.is_present() {
+            self.ppu.enter_dmg_compat();
+        }
+        self.speed.soft_reset();
         self.cycle_counter = 0;
+        self.scheduler = scheduler::Scheduler::new(self.scheduler.lock_step());
         #[cfg(feature = "debug")]
         {
             self.debugger.soft_reset();
@@ -219,18 +449,12 @@ impl GameBoy {
         self.cartridge.put_sram_dump(dump);
     }
 
+    /// A snapshot of the whole machine, see [`super::persistence::snapshot`]
     #[cfg(feature = "persistence")]
     pub fn dump_full(&mut self) -> GbResult<Vec<u8>> {
         let sram = self.poll_sram_dump(true);
         let dump_ref = super::persistence::full_dump::FullDumpRef { gb: self, sram };
-        #[cfg(feature = "brotli")]
-        {
-            dump_ref.to_brotli()
-        }
-        #[cfg(not(feature = "brotli"))]
-        {
-            dump_ref.to_rmp()
-        }
+        super::persistence::snapshot::encode(&dump_ref)
     }
 
     #[cfg(all(feature = "persistence", feature = "serde_json"))]
@@ -240,19 +464,11 @@ impl GameBoy {
         dump_ref.to_json_pretty()
     }
 
+    /// Restores a snapshot from [`GameBoy::dump_full`] taken with `rom`, upgrading it from older
+    /// versions
     #[cfg(feature = "persistence")]
     pub fn from_dump(data: &[u8], rom: &Rom) -> GbResult<Self> {
-        let dump = {
-            #[cfg(feature = "brotli")]
-            {
-                super::persistence::full_dump::FullDump::from_brotli(data)?
-            }
-            #[cfg(not(feature = "brotli"))]
-            {
-                super::persistence::full_dump::FullDump::from_rmp(data)?
-            }
-        };
-
+        let dump = super::persistence::snapshot::decode(data, rom)?;
         let mut gb = dump.gb;
         gb.cartridge.restore_rom(rom)?;
         if let Some(sram) = dump.sram {
@@ -262,6 +478,62 @@ impl GameBoy {
         Ok(gb)
     }
 
+    /// The state as BESS, the save state format shared with other emulators like SameBoy, see
+    /// [`super::persistence::bess`]
+    #[cfg(feature = "persistence")]
+    pub fn dump_bess(&self) -> Vec<u8> {
+        super::persistence::bess::export(self)
+    }
+
+    /// Loads a BESS state taken with the loaded ROM. A state of another model powers on a Game Boy
+    /// of that model first, keeping the cartridge
+    #[cfg(feature = "persistence")]
+    pub fn load_bess(&mut self, data: &[u8]) -> GbResult<()> {
+        let model = super::persistence::bess::model(data)?;
+        if model != self.model {
+            let mut gb =
+                Self::new_with_ram_init(model, boot_rom::BootRom::Skip, 0x00, self.ram_init);
+            gb.cartridge = std::mem::take(&mut self.cartridge);
+            #[cfg(feature = "debug")]
+            {
+                gb.debugger = std::mem::take(&mut self.debugger);
+            }
+            gb.vgm_logger = self.vgm_logger.take();
+            gb.serial_log = self.serial_log.take();
+            gb.apu.set_sample_rate(self.apu.output_sample_rate);
+            *self = gb;
+        }
+        super::persistence::bess::import(self, data)
+    }
+
+    /// A Game Boy of the model the BESS state was taken with, running `rom` from that state
+    #[cfg(feature = "persistence")]
+    pub fn from_bess(data: &[u8], rom: &Rom) -> GbResult<Self> {
+        let mut gb = Self::new_empty(super::persistence::bess::model(data)?);
+        gb.load_rom(rom)?;
+        super::persistence::bess::import(&mut gb, data)?;
+        Ok(gb)
+    }
+
+    /// Replaces the emulated state with a machine restored from a save state. What save states leave
+    /// out is kept: the ROM, cartridge RAM, boot ROM, framebuffer, audio output, debugger, VGM and
+    /// serial log
+    #[cfg(feature = "persistence")]
+    pub fn restore_state(&mut self, mut state: GameBoy) {
+        state.boot_rom = std::mem::take(&mut self.boot_rom);
+        #[cfg(feature = "debug")]
+        {
+            state.debugger = std::mem::take(&mut self.debugger);
+        }
+        state.vgm_logger = self.vgm_logger.take();
+        state.serial_log = self.serial_log.take();
+        state.cartridge.take_memory(&mut self.cartridge);
+        state.ppu.take_frame(&mut self.ppu);
+        state.apu.take_output(&mut self.apu);
+        state.scheduler = scheduler::Scheduler::new(self.scheduler.lock_step());
+        *self = state;
+    }
+
     #[cfg(feature = "debug")]
     pub fn create_e2e_test(
         &self,
@@ -272,28 +544,66 @@ impl GameBoy {
     }
 }
 
+/// The hardware revision to emulate. Models differ in their post-boot state and in hardware quirks.
+/// How the CGB and AGB screens render colors (their color response) is not emulated, colors come
+/// out as stored in palette RAM
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum GbModel {
+    /// Original Game Boy (CPU DMG A/B/C)
     #[default]
     Dmg = 0,
+    /// Game Boy Color (CPU CGB D and earlier)
     Cgb = 1,
+    /// Game Boy Pocket and Game Boy Light
+    Mgb = 2,
+    /// Super Game Boy 2, without the SNES side
+    Sgb2 = 3,
+    /// Game Boy Color (CPU CGB E)
+    CgbE = 4,
+    /// Game Boy Advance (and SP) running Game Boy software
+    Agb = 5,
 }
 
 impl GbModel {
-    pub fn frame_cycles(&self) -> u32 {
-        match self {
-            GbModel::Dmg => 17556,
-            GbModel::Cgb => 35112,
-        }
-    }
-
+    pub const ALL: &'static [Self] = &[
+        Self::Dmg,
+        Self::Mgb,
+        Self::Sgb2,
+        Self::Cgb,
+        Self::CgbE,
+        Self::Agb,
+    ];
+
+    /// Models built around the monochrome DMG hardware
     pub fn is_dmg(&self) -> bool {
-        matches!(self, GbModel::Dmg)
+        matches!(self, GbModel::Dmg | GbModel::Mgb | GbModel::Sgb2)
     }
 
+    /// Models capable of running Game Boy Color software
     pub fn is_cgb(&self) -> bool {
-        matches!(self, GbModel::Cgb)
+        matches!(self, GbModel::Cgb | GbModel::CgbE | GbModel::Agb)
+    }
+
+    pub fn is_sgb(&self) -> bool {
+        matches!(self, GbModel::Sgb2)
+    }
+
+    pub fn is_agb(&self) -> bool {
+        matches!(self, GbModel::Agb)
+    }
+}
+
+impl Display for GbModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GbModel::Dmg => write!(f, "DMG"),
+            GbModel::Mgb => write!(f, "MGB"),
+            GbModel::Sgb2 => write!(f, "SGB2"),
+            GbModel::Cgb => write!(f, "CGB"),
+            GbModel::CgbE => write!(f, "CGB-E"),
+            GbModel::Agb => write!(f, "AGB"),
+        }
     }
 }
@@ -1,3 +1,4 @@
+use crate::gb::GbModel;
 use crate::gb::apu::channels::channel_1::Channel1;
 use crate::gb::apu::channels::channel_2::Channel2;
 use crate::gb::apu::channels::channel_3::Channel3;
@@ -6,12 +7,16 @@ use crate::gb::timer::Timer;
 use crate::{ReadMemory, WriteMemory};
 use blip_buf::BlipBuf;
 use registers::audio_master_control::AudioMasterControl;
+use registers::ch12_timer::Channel12Timer;
+use registers::ch123_control::Channel123Control;
 use registers::master_volume_vin::MasterVolumeVin;
 use registers::sound_panning::SoundPanning;
+use stems::ApuStems;
 
 mod channels;
 mod components;
 mod registers;
+pub mod stems;
 
 pub const APU_CLOCK_RATE: u32 = 4_194_304;
 pub const MAX_AUDIO_BUFFER_SIZE: u32 = 8192;
@@ -19,6 +24,7 @@ const DEFAULT_SAMPLE_RATE: u32 = 44_100;
 
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Apu {
+    pub model: GbModel,
     /// Increments at a frequency of 512 Hz
     div_apu: u8,
     prev_div: u16,
@@ -38,9 +44,36 @@ pub struct Apu {
     time: u32,
     prev_l: i32,
     prev_r: i32,
+    /// T-cycles the APU is behind the rest of the machine, see [`Apu::sync`]
+    #[cfg_attr(feature = "serde", serde(skip, default))]
+    pending: u32,
+    /// The resamplers are at the current output, ticks that change no channel's output can skip
+    /// mixing
+    #[cfg_attr(feature = "serde", serde(skip, default))]
+    mixed: bool,
+    /// Ticks and mixes one T-cycle at a time like before the catch-up, what it is tested against
+    #[cfg(test)]
+    #[cfg_attr(feature = "serde", serde(skip, default))]
+    pub(crate) mix_every_tick: bool,
+    /// The channel outputs of the last mix, repeated to the debugger for skipped ticks
+    #[cfg(feature = "debug")]
+    #[cfg_attr(feature = "serde", serde(skip, default))]
+    channel_outputs: [f32; 4],
+    /// The channels the debugger let through in the last catch-up
+    #[cfg(feature = "debug")]
+    #[cfg_attr(feature = "serde", serde(skip, default))]
+    audible_channels: [bool; 4],
     pub output_sample_rate: u32,
     pub charge_factor: f32,
+    #[cfg_attr(feature = "serde", serde(skip, default))]
     pub audio_buffer: Vec<f32>,
+    /// Per-channel output, only collected while enabled, see [`Apu::set_stems_enabled`]
+    #[cfg_attr(feature = "serde", serde(skip, default))]
+    pub stems: Option<ApuStems>,
+    /// Audio-off mode for when nobody listens: the channels, the frame sequencer and the registers
+    /// keep running, but nothing is mixed or synthesized and `audio_buffer` stays empty
+    #[cfg_attr(feature = "serde", serde(skip, default))]
+    pub suppress_output: bool,
 }
 
 #[cfg(feature = "serde")]
@@ -56,6 +89,7 @@ impl Default for Apu {
         blip_r.set_rates(APU_CLOCK_RATE as f64, DEFAULT_SAMPLE_RATE as f64);
 
         Self {
+            model: GbModel::default(),
             div_apu: 0,
             prev_div: 0,
             nr50: Default::default(),
@@ -72,109 +106,290 @@ impl Default for Apu {
             time: 0,
             prev_l: 0,
             prev_r: 0,
+            pending: 0,
+            mixed: false,
+            #[cfg(test)]
+            mix_every_tick: false,
+            #[cfg(feature = "debug")]
+            channel_outputs: [0.0; 4],
+            #[cfg(feature = "debug")]
+            audible_channels: [true; 4],
             output_sample_rate: DEFAULT_SAMPLE_RATE,
-            charge_factor: charge_factor(DEFAULT_SAMPLE_RATE),
+            charge_factor: charge_factor(DEFAULT_SAMPLE_RATE, GbModel::default()),
             audio_buffer: vec![],
+            stems: None,
+            suppress_output: false,
+        }
+    }
+}
+
+/// The resamplers can't be cloned, a clone starts with empty ones at the same sample rate
+impl Clone for Apu {
+    fn clone(&self) -> Self {
+        let mut blip_l = BlipBuf::new(MAX_AUDIO_BUFFER_SIZE);
+        let mut blip_r = BlipBuf::new(MAX_AUDIO_BUFFER_SIZE);
+        blip_l.set_rates(APU_CLOCK_RATE as f64, self.output_sample_rate as f64);
+        blip_r.set_rates(APU_CLOCK_RATE as f64, self.output_sample_rate as f64);
+
+        Self {
+            model: self.model,
+            div_apu: self.div_apu,
+            prev_div: self.prev_div,
+            nr50: self.nr50,
+            nr51: self.nr51,
+            nr52: self.nr52,
+            ch1: self.ch1.clone(),
+            ch2: self.ch2.clone(),
+            ch3: self.ch3.clone(),
+            ch4: self.ch4.clone(),
+            hpf_capacitor_l: self.hpf_capacitor_l,
+            hpf_capacitor_r: self.hpf_capacitor_r,
+            blip_l,
+            blip_r,
+            time: self.time,
+            // The new resamplers start at level 0
+            prev_l: 0,
+            prev_r: 0,
+            pending: self.pending,
+            mixed: false,
+            #[cfg(test)]
+            mix_every_tick: self.mix_every_tick,
+            #[cfg(feature = "debug")]
+            channel_outputs: self.channel_outputs,
+            #[cfg(feature = "debug")]
+            audible_channels: self.audible_channels,
+            output_sample_rate: self.output_sample_rate,
+            charge_factor: self.charge_factor,
+            audio_buffer: self.audio_buffer.clone(),
+            stems: self
+                .stems
+                .as_ref()
+                .map(|_| ApuStems::new(self.output_sample_rate)),
+            suppress_output: self.suppress_output,
         }
     }
 }
 
 impl Apu {
-    pub fn new() -> Self {
-        Self::default()
+    pub fn new(model: GbModel) -> Self {
+        Self {
+            model,
+            charge_factor: charge_factor(DEFAULT_SAMPLE_RATE, model),
+            ..Self::default()
+        }
     }
 
+    /// Advances the APU by one M-cycle. Only the frame sequencer runs right away, the channels
+    /// fall behind until [`Apu::sync`] catches them up
     pub fn cycle(
         &mut self,
         timer: &Timer,
         double_speed: bool,
         #[cfg(feature = "debug")] debugger: &mut impl crate::debug::DebuggerInterface,
     ) {
-        self.update_frame_sequencer(timer, double_speed);
-        let ticks = if double_speed { 2 } else { 4 };
-
-        for _ in 0..ticks {
-            self.tick();
-
-            let (out_l_f, out_r_f) = self.sample(
+        if self.frame_sequencer_ticks(timer, double_speed) {
+            self.sync(
                 #[cfg(feature = "debug")]
                 debugger,
             );
-            let current_l = (out_l_f * 1000.0) as i32;
-            let current_r = (out_r_f * 1000.0) as i32;
+            self.step_frame_sequencer();
+        }
+        self.pending += if double_speed { 2 } else { 4 };
+    }
+
+    /// M-cycles that can go by before the frame sequencer steps, see [`Apu::skip_cycles`]
+    pub(crate) fn quiet_cycles(&self, timer: &Timer, double_speed: bool) -> u32 {
+        // DIV was reset since the APU last looked at it
+        if self.prev_div != timer.div {
+            return 0;
+        }
+        timer.cycles_until_falling_edge(Self::frame_sequencer_bit(double_speed)) - 1
+    }
+
+    /// Advances the APU by M-cycles in which the frame sequencer doesn't step, like as many calls
+    /// of [`Apu::cycle`]. The timer has to be past them already
+    pub(crate) fn skip_cycles(&mut self, cycles: u32, timer: &Timer, double_speed: bool) {
+        self.pending += cycles * if double_speed { 2 } else { 4 };
+        self.prev_div = timer.div;
+    }
 
-            let delta_l = current_l - self.prev_l;
-            let delta_r = current_r - self.prev_r;
+    /// Catches the channels up with the rest of the machine. Stretches of ticks in which no
+    /// channel's timer runs out leave the output as it is, they are skipped in one go without
+    /// mixing. Has to run before the sound registers are accessed and before anything looks at
+    /// the channels
+    pub fn sync(
+        &mut self,
+        #[cfg(feature = "debug")] debugger: &mut impl crate::debug::DebuggerInterface,
+    ) {
+        if self.pending == 0 {
+            return;
+        }
 
-            if delta_l != 0 {
-                self.blip_l.add_delta(self.time, delta_l);
-                self.prev_l = current_l;
+        // Channels can be muted in the debugger at any time
+        #[cfg(feature = "debug")]
+        {
+            let audible = [
+                debugger.channel_1_enabled(),
+                debugger.channel_2_enabled(),
+                debugger.channel_3_enabled(),
+                debugger.channel_4_enabled(),
+            ];
+            if audible != self.audible_channels {
+                self.audible_channels = audible;
+                self.mixed = false;
+            }
+        }
+
+        while self.pending > 0 {
+            #[cfg(test)]
+            if self.mix_every_tick {
+                self.mixed = false;
             }
 
-            if delta_r != 0 {
-                self.blip_r.add_delta(self.time, delta_r);
-                self.prev_r = current_r;
+            let quiet = if self.mixed || self.suppress_output {
+                self.ticks_until_event().saturating_sub(1).min(self.pending)
+            } else {
+                0
+            };
+
+            if quiet > 0 {
+                self.skip(quiet);
+                #[cfg(feature = "debug")]
+                if !self.suppress_output {
+                    let [ch1, ch2, ch3, ch4] = self.channel_outputs;
+                    for _ in 0..quiet {
+                        debugger.record_apu_channels(self.output_sample_rate, ch1, ch2, ch3, ch4);
+                    }
+                }
+                self.time += quiet;
+                self.pending -= quiet;
+                continue;
             }
 
+            self.tick();
+            if self.suppress_output {
+                self.mixed = false;
+            } else {
+                self.output_sample(
+                    #[cfg(feature = "debug")]
+                    debugger,
+                );
+            }
             self.time += 1;
+            self.pending -= 1;
         }
     }
 
-    pub fn flush_audio(&mut self) {
-        self.blip_l.end_frame(self.time);
-        self.blip_r.end_frame(self.time);
-        self.time = 0;
+    /// Mixes the current channel outputs into the resamplers
+    fn output_sample(
+        &mut self,
+        #[cfg(feature = "debug")] debugger: &mut impl crate::debug::DebuggerInterface,
+    ) {
+        let channels = self.channel_samples(
+            #[cfg(feature = "debug")]
+            debugger,
+        );
+        let (out_l_f, out_r_f) = self.mix(channels);
+        self.mixed = true;
+        #[cfg(feature = "debug")]
+        {
+            self.channel_outputs = channels;
+        }
+
+        if self.stems.is_some() {
+            let outputs = [0, 1, 2, 3].map(|channel| {
+                let mut solo = [0.0; 4];
+                solo[channel] = channels[channel];
+                self.mix(solo)
+            });
+            if let Some(stems) = &mut self.stems {
+                stems.add(self.time, outputs);
+            }
+        }
 
-        let available_samples = self.blip_l.samples_avail() as usize;
-        let mut out_l = vec![0i16; available_samples];
-        let mut out_r = vec![0i16; available_samples];
+        let current_l = (out_l_f * 1000.0) as i32;
+        let current_r = (out_r_f * 1000.0) as i32;
 
-        self.blip_l.read_samples(&mut out_l, false);
-        self.blip_r.read_samples(&mut out_r, false);
+        let delta_l = current_l - self.prev_l;
+        let delta_r = current_r - self.prev_r;
 
-        for i in 0..available_samples {
-            let raw_l = out_l[i] as f32 / 1000.0;
-            let raw_r = out_r[i] as f32 / 1000.0;
+        if delta_l != 0 {
+            self.blip_l.add_delta(self.time, delta_l);
+            self.prev_l = current_l;
+        }
 
-            let filtered_l = raw_l - self.hpf_capacitor_l;
-            let filtered_r = raw_r - self.hpf_capacitor_r;
+        if delta_r != 0 {
+            self.blip_r.add_delta(self.time, delta_r);
+            self.prev_r = current_r;
+        }
+    }
 
-            self.hpf_capacitor_l = raw_l - filtered_l * self.charge_factor;
-            self.hpf_capacitor_r = raw_r - filtered_r * self.charge_factor;
+    /// Ends the audio frame, the APU has to be caught up with [`Apu::sync`]
+    pub fn flush_audio(&mut self) {
+        debug_assert_eq!(self.pending, 0, "flushing an APU that is behind");
+        if self.suppress_output {
+            self.time = 0;
+            return;
+        }
 
-            self.audio_buffer.push(filtered_l);
-            self.audio_buffer.push(filtered_r);
+        self.blip_l.end_frame(self.time);
+        self.blip_r.end_frame(self.time);
+        if let Some(stems) = &mut self.stems {
+            stems.flush(self.time, self.charge_factor);
         }
+        self.time = 0;
+
+        drain_filtered(
+            &mut self.blip_l,
+            &mut self.blip_r,
+            (&mut self.hpf_capacitor_l, &mut self.hpf_capacitor_r),
+            self.charge_factor,
+            &mut self.audio_buffer,
+        );
+    }
+
+    /// Collects every channel's output separately next to the mixed output, at the cost of
+    /// running four more resamplers
+    pub fn set_stems_enabled(&mut self, enabled: bool) {
+        self.stems = enabled.then(|| ApuStems::new(self.output_sample_rate));
+        self.mixed = false;
     }
 
-    fn update_frame_sequencer(&mut self, timer: &Timer, double_speed: bool) {
-        let bit = if double_speed { 13 } else { 12 };
+    /// Whether DIV just ticked the frame sequencer, on the falling edge of its bit 4 (bit 5 in
+    /// double speed)
+    fn frame_sequencer_ticks(&mut self, timer: &Timer, double_speed: bool) -> bool {
+        let bit = Self::frame_sequencer_bit(double_speed);
         let set_prev = ((self.prev_div >> bit) & 1) == 1;
         let set_now = ((timer.div >> bit) & 1) == 1;
+        self.prev_div = timer.div;
+        set_prev && !set_now
+    }
 
-        if set_prev && !set_now {
-            self.div_apu = (self.div_apu + 1) & 0b111;
+    fn frame_sequencer_bit(double_speed: bool) -> u32 {
+        if double_speed { 13 } else { 12 }
+    }
 
-            match self.div_apu {
-                0 => self.clock_length_counters(),
-                1 => {}
-                2 => {
-                    self.clock_length_counters();
-                    self.clock_sweep();
-                }
-                3 => {}
-                4 => self.clock_length_counters(),
-                5 => {}
-                6 => {
-                    self.clock_length_counters();
-                    self.clock_sweep();
-                }
-                7 => self.clock_volume_envelopes(),
-                _ => unreachable!(),
+    fn step_frame_sequencer(&mut self) {
+        self.div_apu = (self.div_apu + 1) & 0b111;
+        self.mixed = false;
+
+        match self.div_apu {
+            0 => self.clock_length_counters(),
+            1 => {}
+            2 => {
+                self.clock_length_counters();
+                self.clock_sweep();
             }
+            3 => {}
+            4 => self.clock_length_counters(),
+            5 => {}
+            6 => {
+                self.clock_length_counters();
+                self.clock_sweep();
+            }
+            7 => self.clock_volume_envelopes(),
+            _ => unreachable!(),
         }
-
-        self.prev_div = timer.div;
     }
 
     fn tick(&mut self) {
@@ -184,6 +399,21 @@ impl Apu {
         self.ch4.tick();
     }
 
+    fn ticks_until_event(&self) -> u32 {
+        self.ch1
+            .ticks_until_event()
+            .min(self.ch2.ticks_until_event())
+            .min(self.ch3.ticks_until_event())
+            .min(self.ch4.ticks_until_event())
+    }
+
+    fn skip(&mut self, ticks: u32) {
+        self.ch1.skip(ticks);
+        self.ch2.skip(ticks);
+        self.ch3.skip(ticks);
+        self.ch4.skip(ticks);
+    }
+
     fn clock_length_counters(&mut self) {
         self.ch1.clock_length();
         self.ch2.clock_length();
@@ -208,8 +438,20 @@ impl Apu {
         &mut self,
         #[cfg(feature = "debug")] debugger: &mut impl crate::debug::DebuggerInterface,
     ) -> (f32, f32) {
+        let channels = self.channel_samples(
+            #[cfg(feature = "debug")]
+            debugger,
+        );
+        self.mix(channels)
+    }
+
+    /// The DAC output of every channel, before panning and master volume
+    fn channel_samples(
+        &mut self,
+        #[cfg(feature = "debug")] debugger: &mut impl crate::debug::DebuggerInterface,
+    ) -> [f32; 4] {
         if !self.nr52.audio_enabled {
-            return (0.0, 0.0);
+            return [0.0; 4];
         };
 
         let ch1_sample = 'ch1: {
@@ -275,6 +517,43 @@ impl Apu {
             );
         }
 
+        [ch1_sample, ch2_sample, ch3_sample, ch4_sample]
+    }
+
+    /// The current volume of every channel, 0 while a channel or its DAC is off. Channel 3 has no
+    /// envelope, its output level is reported as 15, 8 or 4 instead
+    pub fn channel_volumes(&self) -> [u8; 4] {
+        if !self.nr52.audio_enabled {
+            return [0; 4];
+        }
+
+        let envelope = |enabled: bool, dac_enabled: bool, volume: u8| {
+            if enabled && dac_enabled { volume } else { 0 }
+        };
+        let wave_volume = [0, 15, 8, 4][(self.ch3.output_level & 0b11) as usize];
+
+        [
+            envelope(
+                self.ch1.enabled,
+                self.ch1.dac_enabled(),
+                self.ch1.volume_envelope.current_volume,
+            ),
+            envelope(
+                self.ch2.enabled,
+                self.ch2.dac_enabled(),
+                self.ch2.volume_envelope.current_volume,
+            ),
+            envelope(self.ch3.enabled, self.ch3.dac_enabled(), wave_volume),
+            envelope(
+                self.ch4.enabled,
+                self.ch4.dac_enabled(),
+                self.ch4.volume_envelope.current_volume,
+            ),
+        ]
+    }
+
+    /// Pans the channels to the two outputs and applies the master volume
+    fn mix(&self, [ch1_sample, ch2_sample, ch3_sample, ch4_sample]: [f32; 4]) -> (f32, f32) {
         let mut left = 0.0;
         let mut right = 0.0;
 
@@ -333,20 +612,127 @@ impl Apu {
             .set_rates(APU_CLOCK_RATE as f64, sample_rate as f64);
         self.blip_r
             .set_rates(APU_CLOCK_RATE as f64, sample_rate as f64);
-        self.charge_factor = charge_factor(sample_rate);
+        self.charge_factor = charge_factor(sample_rate, self.model);
+        if let Some(stems) = &mut self.stems {
+            stems.set_sample_rate(sample_rate);
+        }
+    }
+
+    /// Takes the resamplers, pending samples and stems over from the APU this one replaces, so a
+    /// restored state keeps playing through the host's audio setup
+    pub(crate) fn take_output(&mut self, previous: &mut Apu) {
+        std::mem::swap(&mut self.blip_l, &mut previous.blip_l);
+        std::mem::swap(&mut self.blip_r, &mut previous.blip_r);
+        // The resamplers integrate the deltas, their level has to stay where they left off
+        self.prev_l = previous.prev_l;
+        self.prev_r = previous.prev_r;
+        self.audio_buffer = std::mem::take(&mut previous.audio_buffer);
+        self.stems = previous.stems.take();
+        self.mixed = false;
+        self.set_sample_rate(previous.output_sample_rate);
+    }
+}
+
+// Power control
+impl Apu {
+    fn write_nr52(&mut self, value: u8) {
+        let enabled = value & 0x80 != 0;
+        if self.nr52.audio_enabled && !enabled {
+            self.power_off();
+        } else if !self.nr52.audio_enabled && enabled {
+            // The frame sequencer restarts, its next step is 0
+            self.div_apu = 7;
+        }
+        self.nr52.audio_enabled = enabled;
+    }
+
+    /// Clears every register except wave RAM, the DMG also keeps the length timers
+    fn power_off(&mut self) {
+        let lengths = [
+            self.ch1.length_counter.counter,
+            self.ch2.length_counter.counter,
+            self.ch3.length_counter.counter,
+            self.ch4.length_counter.counter,
+        ];
+        let wave_ram = self.ch3.wave_ram;
+
+        self.ch1 = Default::default();
+        self.ch2 = Default::default();
+        self.ch3 = Default::default();
+        self.ch4 = Default::default();
+        self.nr50 = Default::default();
+        self.nr51 = Default::default();
+        self.ch3.wave_ram = wave_ram;
+
+        if !self.model.is_cgb() {
+            self.ch1.length_counter.counter = lengths[0];
+            self.ch2.length_counter.counter = lengths[1];
+            self.ch3.length_counter.counter = lengths[2];
+            self.ch4.length_counter.counter = lengths[3];
+        }
+    }
+
+    /// A sound register with its write-only bits as they were last written instead of the 1s they
+    /// read back as, which is what save state formats store
+    pub fn written_register(&self, addr: u16) -> u8 {
+        let control =
+            |control: Channel123Control| control.period_high | ((control.length_enable as u8) << 6);
+        let timer = |timer: Channel12Timer| timer.initial_length_timer | (timer.wave_duty << 6);
+
+        match addr {
+            0xFF11 => timer(self.ch1.timer),
+            0xFF13 => self.ch1.period_low,
+            0xFF14 => control(self.ch1.control),
+            0xFF16 => timer(self.ch2.timer),
+            0xFF18 => self.ch2.period_low,
+            0xFF19 => control(self.ch2.control),
+            0xFF1B => self.ch3.initial_length_timer,
+            0xFF1D => self.ch3.period_low,
+            0xFF1E => control(self.ch3.control),
+            0xFF20 => self.ch4.initial_length_timer,
+            0xFF23 => (self.ch4.length_counter.enabled as u8) << 6,
+            0xFF30..=0xFF3F => self.ch3.wave_ram[(addr - 0xFF30) as usize],
+            _ => self.read_naive(addr),
+        }
+    }
+
+    /// While powered off only NR52 and wave RAM take writes, the DMG also lets the length timers
+    /// of NR11, NR21, NR31 and NR41 through. Returns the value to write, if any
+    fn powered_off_write(&self, addr: u16, value: u8) -> Option<u8> {
+        if self.model.is_cgb() {
+            return None;
+        }
+
+        match addr {
+            0xFF11 | 0xFF16 => Some(value & 0b0011_1111),
+            0xFF1B | 0xFF20 => Some(value),
+            _ => None,
+        }
     }
 }
 
 impl ReadMemory for Apu {
     fn read_naive(&self, addr: u16) -> u8 {
+        let cgb = self.model.is_cgb();
         match addr {
             0xFF10..=0xFF14 => self.ch1.read_naive(addr),
             0xFF16..=0xFF19 => self.ch2.read_naive(addr),
-            0xFF1A..=0xFF1E | 0xFF30..=0xFF3F => self.ch3.read_naive(addr),
+            0xFF1A..=0xFF1E => self.ch3.read_naive(addr),
+            0xFF30..=0xFF3F => self.ch3.read_wave_ram(addr, cgb),
             0xFF20..=0xFF23 => self.ch4.read_naive(addr),
             0xFF24 => self.nr50.into(),
             0xFF25 => self.nr51.into(),
-            0xFF26 => self.nr52.into(),
+            0xFF26 => AudioMasterControl {
+                channel_1_enabled: self.ch1.enabled,
+                channel_2_enabled: self.ch2.enabled,
+                channel_3_enabled: self.ch3.enabled,
+                channel_4_enabled: self.ch4.enabled,
+                audio_enabled: self.nr52.audio_enabled,
+            }
+            .into(),
+            // PCM12 and PCM34, the digital outputs of the channels
+            0xFF76 if cgb => self.ch1.sample() | (self.ch2.sample() << 4),
+            0xFF77 if cgb => self.ch3.sample() | (self.ch4.sample() << 4),
             _ => 0xFF,
         }
     }
@@ -354,19 +740,74 @@ impl ReadMemory for Apu {
 
 impl WriteMemory for Apu {
     fn write_naive(&mut self, addr: u16, value: u8) {
+        let cgb = self.model.is_cgb();
+        self.mixed = false;
+        match addr {
+            0xFF26 => return self.write_nr52(value),
+            0xFF30..=0xFF3F => return self.ch3.write_wave_ram(addr, value, cgb),
+            _ => {}
+        }
+
+        let value = if self.nr52.audio_enabled {
+            value
+        } else {
+            match self.powered_off_write(addr, value) {
+                Some(value) => value,
+                None => return,
+            }
+        };
+
         match addr {
             0xFF10..=0xFF14 => self.ch1.write_naive(addr, value),
             0xFF16..=0xFF19 => self.ch2.write_naive(addr, value),
-            0xFF1A..=0xFF1E | 0xFF30..=0xFF3F => self.ch3.write_naive(addr, value),
+            0xFF1A..=0xFF1E => {
+                if addr == 0xFF1E && value & 0x80 != 0 && !cgb {
+                    self.ch3.corrupt_wave_ram_on_retrigger();
+                }
+                self.ch3.write_naive(addr, value)
+            }
             0xFF20..=0xFF23 => self.ch4.write_naive(addr, value),
             0xFF24 => self.nr50 = value.into(),
             0xFF25 => self.nr51 = value.into(),
-            0xFF26 => self.nr52 = value.into(),
             _ => {}
         }
     }
 }
 
-pub fn charge_factor(sample_rate: u32) -> f32 {
-    0.999958_f64.powf(APU_CLOCK_RATE as f64 / sample_rate as f64) as f32
+/// Reads the available samples of a stereo pair of resamplers through the high-pass filter and
+/// appends them interleaved to `out`
+fn drain_filtered(
+    blip_l: &mut BlipBuf,
+    blip_r: &mut BlipBuf,
+    (capacitor_l, capacitor_r): (&mut f32, &mut f32),
+    charge_factor: f32,
+    out: &mut Vec<f32>,
+) {
+    let available_samples = blip_l.samples_avail() as usize;
+    let mut out_l = vec![0i16; available_samples];
+    let mut out_r = vec![0i16; available_samples];
+
+    blip_l.read_samples(&mut out_l, false);
+    blip_r.read_samples(&mut out_r, false);
+
+    for i in 0..available_samples {
+        let raw_l = out_l[i] as f32 / 1000.0;
+        let raw_r = out_r[i] as f32 / 1000.0;
+
+        let filtered_l = raw_l - *capacitor_l;
+        let filtered_r = raw_r - *capacitor_r;
+
+        *capacitor_l = raw_l - filtered_l * charge_factor;
+        *capacitor_r = raw_r - filtered_r * charge_factor;
+
+        out.push(filtered_l);
+        out.push(filtered_r);
+    }
+}
+
+/// How much of its charge the high-pass filter capacitor keeps per output sample. The CGB's
+/// capacitor discharges faster than the DMG's
+pub fn charge_factor(sample_rate: u32, model: GbModel) -> f32 {
+    let per_tick = if model.is_cgb() { 0.998943 } else { 0.999958 };
+    f64::powf(per_tick, APU_CLOCK_RATE as f64 / sample_rate as f64) as f32
 }
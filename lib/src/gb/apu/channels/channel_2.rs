@@ -6,7 +6,7 @@ use crate::gb::apu::registers::ch123_control::Channel123Control;
 use crate::gb::apu::registers::ch124_volume::Channel124Volume;
 use crate::{ReadMemory, WriteMemory};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Channel2 {
     pub enabled: bool,
@@ -39,6 +39,22 @@ impl Channel2 {
         }
     }
 
+    /// APU ticks until the output can change on its own, `u32::MAX` while the channel is off
+    pub fn ticks_until_event(&self) -> u32 {
+        if self.enabled {
+            self.square_wave.ticks_until_step()
+        } else {
+            u32::MAX
+        }
+    }
+
+    /// Runs ticks that end before [`Self::ticks_until_event`]
+    pub fn skip(&mut self, ticks: u32) {
+        if self.enabled {
+            self.square_wave.skip(ticks);
+        }
+    }
+
     pub fn trigger(&mut self) {
         self.enabled = true;
 
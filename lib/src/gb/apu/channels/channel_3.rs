@@ -2,7 +2,7 @@ use crate::gb::apu::components::length_counter::LengthCounter;
 use crate::gb::apu::registers::ch123_control::Channel123Control;
 use crate::{ReadMemory, WriteMemory};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Channel3 {
     pub enabled: bool,
@@ -21,6 +21,8 @@ pub struct Channel3 {
     pub control: Channel123Control,
     /// (FF30-FF3F)
     pub wave_ram: [u8; 16],
+    /// APU ticks since the channel last fetched a sample from wave RAM
+    pub ticks_since_fetch: u8,
 }
 
 impl Channel3 {
@@ -51,6 +53,28 @@ impl Channel3 {
         if self.frequency_timer == 0 {
             self.reset_frequency_timer();
             self.wave_step = (self.wave_step + 1) & 0b11111;
+            self.ticks_since_fetch = 0;
+        } else {
+            self.ticks_since_fetch = self.ticks_since_fetch.saturating_add(1);
+        }
+    }
+
+    /// APU ticks until the channel fetches its next sample, `u32::MAX` while it's off
+    pub fn ticks_until_event(&self) -> u32 {
+        if self.enabled {
+            self.frequency_timer.max(1) as u32
+        } else {
+            u32::MAX
+        }
+    }
+
+    /// Runs ticks that end before [`Self::ticks_until_event`]
+    pub fn skip(&mut self, ticks: u32) {
+        if self.enabled {
+            self.frequency_timer -= ticks as u16;
+            self.ticks_since_fetch = self
+                .ticks_since_fetch
+                .saturating_add(ticks.min(u8::MAX as u32) as u8);
         }
     }
 
@@ -90,6 +114,49 @@ impl Channel3 {
     pub fn dac_enabled(&self) -> bool {
         self.dac_enabled
     }
+
+    /// While the channel plays, the CPU reaches the byte the channel is reading instead of the
+    /// addressed one. The DMG only lets it through on the cycle the channel fetches a sample,
+    /// otherwise reads return 0xFF and writes are dropped.
+    ///
+    /// Source: https://gbdev.io/pandocs/Audio_Registers.html#ff30ff3f--wave-pattern-ram
+    fn wave_ram_index(&self, addr: u16, cgb: bool) -> Option<usize> {
+        if !self.enabled {
+            Some(addr as usize - 0xFF30)
+        } else if cgb || self.ticks_since_fetch < 2 {
+            Some(self.wave_step as usize / 2)
+        } else {
+            None
+        }
+    }
+
+    pub fn read_wave_ram(&self, addr: u16, cgb: bool) -> u8 {
+        self.wave_ram_index(addr, cgb)
+            .map_or(0xFF, |index| self.wave_ram[index])
+    }
+
+    pub fn write_wave_ram(&mut self, addr: u16, value: u8, cgb: bool) {
+        if let Some(index) = self.wave_ram_index(addr, cgb) {
+            self.wave_ram[index] = value;
+        }
+    }
+
+    /// Retriggering the channel on the DMG right as it fetches a sample overwrites the start of
+    /// wave RAM: the first byte with the one being fetched if that lies in the first four bytes,
+    /// else the first four bytes with the aligned block it lies in
+    pub fn corrupt_wave_ram_on_retrigger(&mut self) {
+        if !self.enabled || self.frequency_timer > 2 {
+            return;
+        }
+
+        let index = ((self.wave_step + 1) & 0b11111) as usize / 2;
+        if index < 4 {
+            self.wave_ram[0] = self.wave_ram[index];
+        } else {
+            let block = index & !0b11;
+            self.wave_ram.copy_within(block..block + 4, 0);
+        }
+    }
 }
 
 impl ReadMemory for Channel3 {
@@ -98,8 +165,7 @@ impl ReadMemory for Channel3 {
             0xFF1A => 0x7F | ((self.dac_enabled as u8) << 7),
             0xFF1C => 0x9F | ((self.output_level & 0b11) << 5),
             0xFF1E => self.control.into(),
-            // ToDo: Wave RAM read quirks => https://gbdev.io/pandocs/Audio_Registers.html#ff30ff3f--wave-pattern-ram
-            0xFF30..=0xFF3F => self.wave_ram[addr as usize - 0xFF30],
+            0xFF30..=0xFF3F => self.read_wave_ram(addr, false),
             _ => 0xFF,
         }
     }
@@ -128,8 +194,7 @@ impl WriteMemory for Channel3 {
                     self.trigger();
                 }
             }
-            // ToDo: Wave RAM write quirks => https://gbdev.io/pandocs/Audio_Registers.html#ff30ff3f--wave-pattern-ram
-            0xFF30..=0xFF3F => self.wave_ram[addr as usize - 0xFF30] = value,
+            0xFF30..=0xFF3F => self.write_wave_ram(addr, value, false),
             _ => {}
         }
     }
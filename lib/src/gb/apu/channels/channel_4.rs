@@ -4,7 +4,7 @@ use crate::gb::apu::registers::ch4_frequency::Channel4Frequency;
 use crate::gb::apu::registers::ch124_volume::Channel124Volume;
 use crate::{ReadMemory, WriteMemory};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Channel4 {
     pub enabled: bool,
@@ -61,6 +61,22 @@ impl Channel4 {
         }
     }
 
+    /// APU ticks until the LFSR shifts next, `u32::MAX` while the channel is off
+    pub fn ticks_until_event(&self) -> u32 {
+        if self.enabled {
+            self.frequency_timer.max(1)
+        } else {
+            u32::MAX
+        }
+    }
+
+    /// Runs ticks that end before [`Self::ticks_until_event`]
+    pub fn skip(&mut self, ticks: u32) {
+        if self.enabled {
+            self.frequency_timer -= ticks;
+        }
+    }
+
     pub fn trigger(&mut self) {
         self.enabled = true;
 
@@ -1,4 +1,4 @@
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SquareWave {
     pub duty_pattern: u8,
@@ -25,6 +25,16 @@ impl SquareWave {
         }
     }
 
+    /// Ticks until the wave steps through its duty cycle
+    pub fn ticks_until_step(&self) -> u32 {
+        self.frequency_timer.max(1) as u32
+    }
+
+    /// Runs ticks that end before the next step
+    pub fn skip(&mut self, ticks: u32) {
+        self.frequency_timer -= ticks as u16;
+    }
+
     pub fn set_duty(&mut self, wave_duty: u8) {
         self.duty_pattern = wave_duty & 0b11;
     }
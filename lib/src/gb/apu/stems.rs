@@ -0,0 +1,77 @@
+use crate::gb::apu::{APU_CLOCK_RATE, MAX_AUDIO_BUFFER_SIZE, drain_filtered};
+use blip_buf::BlipBuf;
+
+/// The stereo output of each channel on its own, with the channel's panning and the master volume
+/// applied. The four stems add up to the mixed output.
+pub struct ApuStems {
+    blips: [(BlipBuf, BlipBuf); 4],
+    previous: [(i32, i32); 4],
+    capacitors: [(f32, f32); 4],
+    /// Interleaved stereo samples per channel, filled on every flush of the APU
+    pub buffers: [Vec<f32>; 4],
+}
+
+impl ApuStems {
+    pub fn new(sample_rate: u32) -> Self {
+        let mut stems = Self {
+            blips: std::array::from_fn(|_| {
+                (
+                    BlipBuf::new(MAX_AUDIO_BUFFER_SIZE),
+                    BlipBuf::new(MAX_AUDIO_BUFFER_SIZE),
+                )
+            }),
+            previous: [(0, 0); 4],
+            capacitors: [(0.0, 0.0); 4],
+            buffers: Default::default(),
+        };
+        stems.set_sample_rate(sample_rate);
+        stems
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        for (blip_l, blip_r) in &mut self.blips {
+            blip_l.set_rates(APU_CLOCK_RATE as f64, sample_rate as f64);
+            blip_r.set_rates(APU_CLOCK_RATE as f64, sample_rate as f64);
+        }
+    }
+
+    pub(super) fn add(&mut self, time: u32, outputs: [(f32, f32); 4]) {
+        for (channel, (out_l, out_r)) in outputs.into_iter().enumerate() {
+            let (blip_l, blip_r) = &mut self.blips[channel];
+            let (prev_l, prev_r) = &mut self.previous[channel];
+            let current_l = (out_l * 1000.0) as i32;
+            let current_r = (out_r * 1000.0) as i32;
+
+            if current_l != *prev_l {
+                blip_l.add_delta(time, current_l - *prev_l);
+                *prev_l = current_l;
+            }
+            if current_r != *prev_r {
+                blip_r.add_delta(time, current_r - *prev_r);
+                *prev_r = current_r;
+            }
+        }
+    }
+
+    pub(super) fn flush(&mut self, time: u32, charge_factor: f32) {
+        for channel in 0..4 {
+            let (blip_l, blip_r) = &mut self.blips[channel];
+            blip_l.end_frame(time);
+            blip_r.end_frame(time);
+
+            let (capacitor_l, capacitor_r) = &mut self.capacitors[channel];
+            drain_filtered(
+                blip_l,
+                blip_r,
+                (capacitor_l, capacitor_r),
+                charge_factor,
+                &mut self.buffers[channel],
+            );
+        }
+    }
+
+    /// Takes the samples collected so far
+    pub fn take(&mut self) -> [Vec<f32>; 4] {
+        std::mem::take(&mut self.buffers)
+    }
+}
@@ -1,22 +1,83 @@
+use crate::gb::GbModel;
+
+/// Citrine's own boot ROMs, assembled from `boot/citrine_boot.asm`.
+/// They are MIT licensed like the rest of the emulator and can be redistributed freely.
+pub const DMG_BOOT_ROM: &[u8; 256] = include_bytes!("../../boot/dmg_boot.bin");
+pub const MGB_BOOT_ROM: &[u8; 256] = include_bytes!("../../boot/mgb_boot.bin");
+pub const SGB2_BOOT_ROM: &[u8; 256] = include_bytes!("../../boot/sgb2_boot.bin");
+pub const CGB_BOOT_ROM: &[u8; 256] = include_bytes!("../../boot/cgb_boot.bin");
+pub const AGB_BOOT_ROM: &[u8; 256] = include_bytes!("../../boot/agb_boot.bin");
+
+/// Selects what runs before the cartridge takes over.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BootRom {
+    /// Start right at the cartridge entry point with the post-boot register state of the model
+    Skip,
+    /// Citrine's bundled boot ROM, it scrolls in the cartridge logo and hands over with the
+    /// post-boot register state of the model
+    #[default]
+    Bundled,
+    /// A user-supplied image, e.g. a dump of the original boot ROM
+    Custom(Vec<u8>),
+}
+
+impl BootRom {
+    /// The bundled boot ROM matching the given model
+    pub fn bundled_image(model: GbModel) -> &'static [u8] {
+        match model {
+            GbModel::Dmg => DMG_BOOT_ROM,
+            GbModel::Mgb => MGB_BOOT_ROM,
+            GbModel::Sgb2 => SGB2_BOOT_ROM,
+            GbModel::Cgb | GbModel::CgbE => CGB_BOOT_ROM,
+            GbModel::Agb => AGB_BOOT_ROM,
+        }
+    }
+
+    /// The image mapped at power-on, `None` if the boot sequence is skipped
+    pub fn image(&self, model: GbModel) -> Option<&[u8]> {
+        match self {
+            Self::Skip => None,
+            Self::Bundled => Some(Self::bundled_image(model)),
+            Self::Custom(rom) => Some(rom),
+        }
+    }
+}
+
+/// The boot ROM as it is mapped over the cartridge until a write to 0xFF50 disables it.
 #[derive(Debug, Clone)]
-pub struct BootRom {
+pub struct MappedBootRom {
+    pub source: BootRom,
     pub rom: Vec<u8>,
     pub mounted: bool,
 }
 
-impl Default for BootRom {
+impl Default for MappedBootRom {
     fn default() -> Self {
+        Self::new(BootRom::Skip, GbModel::Dmg)
+    }
+}
+
+impl MappedBootRom {
+    pub fn new(source: BootRom, model: GbModel) -> Self {
+        let rom = source.image(model).map(<[u8]>::to_vec).unwrap_or_default();
         Self {
-            rom: vec![],
+            source,
+            rom,
             mounted: true,
         }
     }
-}
 
-impl BootRom {
-    /// The address right outside the boot ROM
-    pub fn boundary_address(&self) -> u16 {
-        self.rom.len() as u16
+    /// Whether the boot sequence runs at all
+    pub fn is_present(&self) -> bool {
+        !self.rom.is_empty()
+    }
+
+    /// Whether the given address currently reads from the boot ROM.
+    /// CGB boot ROMs are larger than 256 bytes and leave the cartridge header at 0x100-0x1FF visible.
+    pub fn maps(&self, addr: u16) -> bool {
+        let addr = addr as usize;
+        self.mounted && addr < self.rom.len() && !(0x100..0x200).contains(&addr)
     }
 
     pub fn soft_reset(&mut self) {
@@ -1,18 +1,21 @@
 use crate::gb::apu::Apu;
-use crate::gb::boot_rom::BootRom;
+use crate::gb::boot_rom::MappedBootRom;
 use crate::gb::cartridge::Cartridge;
 use crate::gb::dma::DmaController;
 use crate::gb::ic::{ICInterface, InterruptController};
 use crate::gb::joypad::Joypad;
 use crate::gb::memory::Memory;
-use crate::gb::ppu::Ppu;
+use crate::gb::ppu::{OamCorruption, Ppu};
+use crate::gb::scheduler::Scheduler;
+use crate::gb::speed::Speed;
 use crate::gb::timer::Timer;
 use crate::utils::bit::{hi, lo};
+use crate::vgm::VgmLogger;
 use crate::{ReadMemory, WriteMemory};
 
 /// Connecting the CPU to the other components of the Game Boy
 pub struct CpuBus<'a> {
-    pub boot_rom: &'a mut BootRom,
+    pub boot_rom: &'a mut MappedBootRom,
     pub cartridge: &'a mut Cartridge,
     #[cfg(feature = "debug")]
     pub debugger: &'a mut crate::debug::Debugger,
@@ -22,14 +25,18 @@ pub struct CpuBus<'a> {
     pub memory: &'a mut Memory,
     pub ppu: &'a mut Ppu,
     pub apu: &'a mut Apu,
+    pub scheduler: &'a mut Scheduler,
+    pub speed: &'a mut Speed,
     pub timer: &'a mut Timer,
     pub cycles: &'a mut u32,
+    pub vgm_logger: &'a mut Option<VgmLogger>,
+    pub serial_log: &'a mut Option<Vec<u8>>,
 }
 
 impl ReadMemory for CpuBus<'_> {
     #[allow(clippy::match_overlapping_arm)]
     fn read_naive(&self, addr: u16) -> u8 {
-        if self.boot_rom.mounted && addr < self.boot_rom.boundary_address() {
+        if self.boot_rom.maps(addr) {
             return self.boot_rom.rom[addr as usize];
         }
 
@@ -43,10 +50,14 @@ impl ReadMemory for CpuBus<'_> {
             0xFF02 => (self.memory.read_naive(addr) & 0x81) | 0x7E,
             0xFF04..=0xFF07 => self.timer.read_naive(addr),
             0xFF0F => self.ic.flag.into(),
-            0xFF10..=0xFF14 | 0xFF16..=0xFF1E | 0xFF20..=0xFF26 | 0xFF30..=0xFF3F => {
-                self.apu.read_naive(addr)
-            }
+            0xFF10..=0xFF14
+            | 0xFF16..=0xFF1E
+            | 0xFF20..=0xFF26
+            | 0xFF30..=0xFF3F
+            | 0xFF76
+            | 0xFF77 => self.apu.read_naive(addr),
             0xFF46 => self.dma.source,
+            0xFF4D if !self.ppu.dmg_compat => self.speed.read_naive(addr),
             0xFF40..=0xFF45 | 0xFF47..=0xFF4B | 0xFF4F | 0xFF51..=0xFF55 | 0xFF68..=0xFF6C => {
                 self.ppu.read_naive(addr)
             }
@@ -64,18 +75,38 @@ impl WriteMemory for CpuBus<'_> {
             return;
         }
 
+        if let Some(logger) = self.vgm_logger {
+            logger.log_write(addr, value);
+        }
+        self.sync_apu(addr);
+        self.sync_for_write(addr);
+
         match addr {
             0x0000..=0x7FFF => self.cartridge.write_naive(addr, value),
             0x8000..=0x9FFF => self.ppu.write_naive(addr, value),
             0xA000..=0xBFFF => self.cartridge.write_naive(addr, value),
             0xFE00..=0xFE9F => self.ppu.write_naive(addr, value),
             0xFF00 => self.joypad.write_naive(addr, value),
+            0xFF02 => {
+                // A transfer on the internal clock sends SB
+                if value & 0x81 == 0x81
+                    && let Some(log) = self.serial_log
+                {
+                    log.push(self.memory.read_naive(0xFF01));
+                }
+                self.memory.write_naive(addr, value)
+            }
             0xFF04..=0xFF07 => self.timer.write_naive(addr, value),
             0xFF0F => self.ic.flag = value.into(),
             0xFF10..=0xFF14 | 0xFF16..=0xFF1E | 0xFF20..=0xFF26 | 0xFF30..=0xFF3F => {
                 self.apu.write_naive(addr, value)
             }
             0xFF46 => self.dma.start(value),
+            // KEY0 is only writable while the boot ROM is mapped
+            0xFF4C if self.boot_rom.is_present() && self.boot_rom.mounted => {
+                self.ppu.write_naive(addr, value)
+            }
+            0xFF4D if !self.ppu.dmg_compat => self.speed.write_naive(addr, value),
             0xFF40..=0xFF45 | 0xFF47..=0xFF4B | 0xFF4F | 0xFF51..=0xFF55 | 0xFF68..=0xFF6C => {
                 self.ppu.write_naive(addr, value)
             }
@@ -92,40 +123,71 @@ impl WriteMemory for CpuBus<'_> {
 
 impl CpuBusInterface for CpuBus<'_> {
     fn cycle(&mut self) {
-        self.timer.cycle(self.ic);
-        self.ppu.cycle(self.ic, self.dma.active);
-        // ToDo: adjust for double speed mode
-        self.apu.cycle(
-            self.timer,
-            false,
-            #[cfg(feature = "debug")]
-            self.debugger,
-        );
+        if self.scheduler.lock_step {
+            self.cycle_lock_step();
+            return;
+        }
 
-        if let Some((src, dst)) = self.dma.cycle() {
+        if self.scheduler.timer.tick() {
+            self.sync_timer();
+        }
+        if self.scheduler.ppu.tick() {
+            self.sync_ppu();
+        }
+        if let Some(logger) = self.vgm_logger {
+            logger.tick(if self.speed.double_speed { 2 } else { 4 });
+        }
+
+        if self.dma.active
+            && let Some((src, dst)) = self.dma.cycle()
+        {
+            self.sync_for_read(src);
             self.write_naive(dst, self.read_naive(src));
         }
 
-        *self.cycles = self.cycles.wrapping_add(1);
+        self.count_cycle();
+    }
 
-        #[cfg(feature = "debug")]
-        {
-            self.debugger.total_cycles = self.debugger.total_cycles.wrapping_add(1);
+    fn stopped_cycle(&mut self) {
+        self.count_cycle();
+    }
+
+    fn joypad_low(&self) -> bool {
+        self.joypad.read_naive(0xFF00) & 0x0F != 0x0F
+    }
+
+    fn speed_switch_armed(&self) -> bool {
+        self.speed.switch_armed
+    }
+
+    fn stop(&mut self) {
+        self.sync();
+        self.timer.write_naive(0xFF04, 0);
+        if self.speed.switch_armed {
+            self.speed.switch();
+        } else {
+            self.ppu.stop();
         }
+        self.scheduler.timer.wake();
+        self.scheduler.ppu.wake();
     }
 
-    fn read(&mut self, addr: u16) -> u8 {
+    fn idu_cycle(&mut self, addr: u16) {
         self.cycle();
+        self.ppu.corrupt_oam(addr, OamCorruption::Write);
+    }
 
-        if self.dma.cpu_conflicts(addr) || self.ppu.cpu_conflicts(addr) {
-            return 0xFF;
-        }
+    fn read(&mut self, addr: u16) -> u8 {
+        self.read_corrupting(addr, OamCorruption::Read)
+    }
 
-        self.read_naive(addr)
+    fn read_inc(&mut self, addr: u16) -> u8 {
+        self.read_corrupting(addr, OamCorruption::ReadIncrease)
     }
 
     fn write(&mut self, addr: u16, value: u8) {
         self.cycle();
+        self.ppu.corrupt_oam(addr, OamCorruption::Write);
 
         if self.dma.cpu_conflicts(addr) || self.ppu.cpu_conflicts(addr) {
             return;
@@ -148,11 +210,191 @@ impl CpuBusInterface for CpuBus<'_> {
     }
 }
 
+impl CpuBus<'_> {
+    fn read_corrupting(&mut self, addr: u16, corruption: OamCorruption) -> u8 {
+        self.cycle();
+        self.ppu.corrupt_oam(addr, corruption);
+
+        if self.dma.cpu_conflicts(addr) || self.ppu.cpu_conflicts(addr) {
+            return 0xFF;
+        }
+
+        self.sync_for_read(addr);
+        self.read_naive(addr)
+    }
+
+    /// Catches every component up, see [`Scheduler`]
+    pub(crate) fn sync(&mut self) {
+        self.sync_timer();
+        self.sync_ppu();
+        self.apu.sync(
+            #[cfg(feature = "debug")]
+            self.debugger,
+        );
+    }
+
+    /// Catches up what `addr` reads from. Of the PPU only internal counters fall behind, its
+    /// registers read the same
+    fn sync_for_read(&mut self, addr: u16) {
+        match addr {
+            0xFF04..=0xFF07 => self.sync_timer(),
+            _ => self.sync_apu(addr),
+        }
+    }
+
+    /// Catches up what a write to `addr` can reschedule and has it run the next M-cycle in full
+    fn sync_for_write(&mut self, addr: u16) {
+        match addr {
+            0xFF04..=0xFF07 => {
+                self.sync_timer();
+                self.scheduler.timer.wake();
+            }
+            0xFF40..=0xFF4F | 0xFF51..=0xFF55 | 0xFF68..=0xFF6C => {
+                self.sync_ppu();
+                self.scheduler.ppu.wake();
+            }
+            _ => {}
+        }
+    }
+
+    /// Catches the APU up before `addr` is accessed if it belongs to the APU
+    fn sync_apu(&mut self, addr: u16) {
+        if matches!(addr, 0xFF10..=0xFF3F | 0xFF76 | 0xFF77) {
+            self.sync_timer();
+            self.apu.sync(
+                #[cfg(feature = "debug")]
+                self.debugger,
+            );
+        }
+    }
+
+    /// Runs the timer and the APU's frame sequencer through the M-cycles they are behind
+    fn sync_timer(&mut self) {
+        let double_speed = self.speed.double_speed;
+        let mut behind = std::mem::take(&mut self.scheduler.timer.behind);
+        while behind > 0 {
+            let quiet = self.timer_quiet_cycles().min(behind);
+            if quiet > 0 {
+                self.timer.skip(quiet);
+                self.apu.skip_cycles(quiet, self.timer, double_speed);
+                behind -= quiet;
+                continue;
+            }
+
+            self.timer.cycle(self.ic);
+            self.apu.cycle(
+                self.timer,
+                double_speed,
+                #[cfg(feature = "debug")]
+                self.debugger,
+            );
+            behind -= 1;
+        }
+        self.scheduler.timer.slack = self.timer_quiet_cycles();
+    }
+
+    fn timer_quiet_cycles(&self) -> u32 {
+        self.timer
+            .quiet_cycles()
+            .min(self.apu.quiet_cycles(self.timer, self.speed.double_speed))
+    }
+
+    /// Runs the PPU through the M-cycles it is behind
+    fn sync_ppu(&mut self) {
+        let double_speed = self.speed.double_speed;
+        let mut behind = std::mem::take(&mut self.scheduler.ppu.behind);
+        while behind > 0 {
+            let quiet = self.ppu_quiet_cycles().min(behind);
+            if quiet > 0 {
+                self.ppu.skip(quiet, double_speed);
+                behind -= quiet;
+                continue;
+            }
+
+            self.ppu
+                .cycle(self.ic, self.dma.current_source(), double_speed);
+            behind -= 1;
+        }
+        self.scheduler.ppu.slack = self.ppu_quiet_cycles();
+    }
+
+    /// OAM DMA takes the PPU's buses, it runs every M-cycle of a transfer
+    fn ppu_quiet_cycles(&self) -> u32 {
+        if self.dma.active {
+            0
+        } else {
+            self.ppu.quiet_cycles(self.speed.double_speed)
+        }
+    }
+
+    /// Ticks every component every M-cycle, see [`Scheduler`]
+    fn cycle_lock_step(&mut self) {
+        self.timer.cycle(self.ic);
+        self.ppu
+            .cycle(self.ic, self.dma.current_source(), self.speed.double_speed);
+        self.apu.cycle(
+            self.timer,
+            self.speed.double_speed,
+            #[cfg(feature = "debug")]
+            self.debugger,
+        );
+        if let Some(logger) = self.vgm_logger {
+            logger.tick(if self.speed.double_speed { 2 } else { 4 });
+        }
+
+        if let Some((src, dst)) = self.dma.cycle() {
+            self.sync_apu(src);
+            self.write_naive(dst, self.read_naive(src));
+        }
+
+        self.count_cycle();
+    }
+
+    fn count_cycle(&mut self) {
+        *self.cycles = self.cycles.wrapping_add(1);
+
+        #[cfg(feature = "debug")]
+        {
+            self.debugger.total_cycles = self.debugger.total_cycles.wrapping_add(1);
+        }
+    }
+}
+
 pub trait CpuBusInterface {
     fn cycle(&mut self);
     fn read(&mut self, addr: u16) -> u8;
     fn write(&mut self, addr: u16, value: u8);
 
+    /// Advances one M-cycle while the system clock is stopped (STOP mode or a speed switch)
+    fn stopped_cycle(&mut self) {
+        self.cycle();
+    }
+
+    /// Whether a selected joypad line is held low, this changes how STOP behaves and ends STOP mode
+    fn joypad_low(&self) -> bool {
+        false
+    }
+
+    /// Whether KEY1 prepared a CGB speed switch for the next STOP
+    fn speed_switch_armed(&self) -> bool {
+        false
+    }
+
+    /// An internal M-cycle in which the 16-bit increment/decrement unit puts `addr` on the address
+    /// bus, on DMG models this corrupts OAM like a write
+    fn idu_cycle(&mut self, _addr: u16) {
+        self.cycle();
+    }
+
+    /// A read whose address register is incremented or decremented in the same M-cycle
+    fn read_inc(&mut self, addr: u16) -> u8 {
+        self.read(addr)
+    }
+
+    /// The side effects of entering STOP: DIV resets and either the prepared speed switch happens
+    /// or the LCD controller stops
+    fn stop(&mut self) {}
+
     fn read_word(&mut self, addr: u16) -> u16 {
         u16::from_le_bytes([self.read(addr), self.read(addr + 1)])
     }
@@ -5,12 +5,14 @@ use crate::rom::Rom;
 use crate::rom::header::RomHeader;
 use crate::{ReadMemory, WriteMemory};
 use std::fmt::{Display, Formatter};
+use std::sync::Arc;
 
 mod mbc;
 
 pub const ROM_BANK_SIZE: usize = 0x4000; // 16KiB
 pub const RAM_BANK_SIZE: usize = 0x2000; // 8KiB
 
+#[derive(Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cartridge {
     pub header: RomHeader,
@@ -18,8 +20,9 @@ pub struct Cartridge {
     has_battery: bool,
     sram_dirty: bool,
     mbc: mbc::Mbc,
+    /// Shared between clones, nothing writes to it once loaded
     #[cfg_attr(feature = "serde", serde(skip, default))]
-    rom: Vec<[u8; ROM_BANK_SIZE]>,
+    rom: Arc<[[u8; ROM_BANK_SIZE]]>,
     #[cfg_attr(feature = "serde", serde(skip, default))]
     ram: Vec<[u8; RAM_BANK_SIZE]>,
 }
@@ -32,7 +35,7 @@ impl Default for Cartridge {
             has_battery: false,
             sram_dirty: false,
             mbc: mbc::Mbc::None,
-            rom: vec![[0; ROM_BANK_SIZE]; 2],
+            rom: vec![[0; ROM_BANK_SIZE]; 2].into(),
             ram: vec![[0; RAM_BANK_SIZE]; 1],
         }
     }
@@ -51,48 +54,24 @@ impl Cartridge {
         self.rom.concat()
     }
 
+    /// The fixed first ROM bank, which holds the cartridge header
+    pub fn rom_bank0(&self) -> &[u8; ROM_BANK_SIZE] {
+        &self.rom[0]
+    }
+
     #[cfg(feature = "persistence")]
     pub fn restore_rom(&mut self, rom: &Rom) -> GbResult<()> {
         let header = rom.header()?;
-        let rom_banks = header.rom_banks.max(2);
-
-        self.rom = rom
-            .data
-            .chunks(ROM_BANK_SIZE)
-            .map(|chunk| {
-                let mut bank = [0u8; ROM_BANK_SIZE];
-                bank[..chunk.len()].copy_from_slice(chunk);
-                bank
-            })
-            .collect();
-
-        if self.rom.len() > rom_banks {
-            return Err(GbError::RomTooBig);
-        }
-        self.rom.resize(rom_banks, [0; ROM_BANK_SIZE]);
+        self.rom = split_rom_banks(rom, header.rom_banks.max(2))?;
         self.ram = vec![[0; RAM_BANK_SIZE]; header.ram_banks.max(1)];
         Ok(())
     }
 
     pub fn load_rom(&mut self, rom: &Rom) -> GbResult<()> {
         let header = rom.header()?;
-        let rom_banks = header.rom_banks.max(2);
         let ram_banks = header.ram_banks.max(1);
         self.mbc = mbc::Mbc::try_from(&header)?;
-
-        self.rom = rom
-            .data
-            .chunks(ROM_BANK_SIZE)
-            .map(|chunk| {
-                let mut bank = [0u8; ROM_BANK_SIZE];
-                bank[..chunk.len()].copy_from_slice(chunk);
-                bank
-            })
-            .collect();
-
-        if self.rom.len() > rom_banks {
-            return Err(GbError::RomTooBig);
-        }
+        self.rom = split_rom_banks(rom, header.rom_banks.max(2))?;
 
         self.has_battery = header
             .cartridge_type
@@ -100,7 +79,6 @@ impl Cartridge {
             .unwrap_or(false);
         self.header = header;
         self.has_rom_loaded = true;
-        self.rom.resize(rom_banks, [0; ROM_BANK_SIZE]);
         self.ram = vec![[0; RAM_BANK_SIZE]; ram_banks];
 
         Ok(())
@@ -120,17 +98,28 @@ impl Cartridge {
             return None;
         };
 
-        let dump = if let Some(data) = self.mbc.get_internal_data() {
-            Some(crate::persistence::sram_dump::SramDump::from_slice(data))
-        } else {
-            Some(crate::persistence::sram_dump::SramDump::from_banks(
-                self.ram.as_slice(),
-            ))
-        };
-
+        let dump = self.ram_dump();
         self.sram_dirty = false;
 
-        dump
+        Some(dump)
+    }
+
+    /// Takes the ROM and RAM banks over from the cartridge this one replaces, save states leave them
+    /// out
+    #[cfg(feature = "persistence")]
+    pub(crate) fn take_memory(&mut self, previous: &mut Cartridge) {
+        self.rom = std::mem::take(&mut previous.rom);
+        self.ram = std::mem::take(&mut previous.ram);
+    }
+
+    /// The cartridge RAM whether it is battery backed or not
+    #[cfg(feature = "persistence")]
+    pub fn ram_dump(&self) -> crate::persistence::sram_dump::SramDump {
+        if let Some(data) = self.mbc.get_internal_data() {
+            crate::persistence::sram_dump::SramDump::from_slice(data)
+        } else {
+            crate::persistence::sram_dump::SramDump::from_banks(self.ram.as_slice())
+        }
     }
 
     #[cfg(feature = "persistence")]
@@ -139,8 +128,19 @@ impl Cartridge {
             return;
         };
 
-        let data = dump.as_slice();
+        self.write_ram_data(dump.as_slice());
+    }
+
+    /// Puts RAM from a save state back whether it is battery backed or not, battery RAM counts as
+    /// changed since it may differ from the save file
+    #[cfg(feature = "persistence")]
+    pub fn put_ram_dump(&mut self, dump: &crate::persistence::sram_dump::SramDump) {
+        self.write_ram_data(dump.as_slice());
+        self.sram_dirty |= self.has_battery;
+    }
 
+    #[cfg(feature = "persistence")]
+    fn write_ram_data(&mut self, data: &[u8]) {
         let internal = self.mbc.put_internal_data(data);
         if internal {
             return;
@@ -151,11 +151,43 @@ impl Cartridge {
         }
     }
 
+    /// Size of the RAM on the cartridge or inside the MBC, 0 without any
+    pub fn ram_size(&self) -> usize {
+        match self.mbc.get_internal_data() {
+            Some(data) => data.len(),
+            None => self.header.ram_size_bytes(),
+        }
+    }
+
+    /// The MBC register writes that restore its banking state on a freshly loaded cartridge
+    pub fn mbc_register_writes(&self) -> Vec<(u16, u8)> {
+        self.mbc.register_writes()
+    }
+
     pub fn supports_sram_saves(&self) -> bool {
         self.has_battery
     }
 }
 
+/// The ROM image in banks, padded with zeros to the bank count of the header
+fn split_rom_banks(rom: &Rom, rom_banks: usize) -> GbResult<Arc<[[u8; ROM_BANK_SIZE]]>> {
+    let mut banks: Vec<_> = rom
+        .data
+        .chunks(ROM_BANK_SIZE)
+        .map(|chunk| {
+            let mut bank = [0u8; ROM_BANK_SIZE];
+            bank[..chunk.len()].copy_from_slice(chunk);
+            bank
+        })
+        .collect();
+
+    if banks.len() > rom_banks {
+        return Err(GbError::RomTooBig);
+    }
+    banks.resize(rom_banks, [0; ROM_BANK_SIZE]);
+    Ok(banks.into())
+}
+
 impl ReadMemory for Cartridge {
     fn read_naive(&self, addr: u16) -> u8 {
         if let Some(value) = self.mbc.on_read(addr) {
@@ -16,7 +16,7 @@ pub trait MbcInterface {
     fn soft_reset(&mut self);
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Mbc {
     None,
@@ -36,6 +36,35 @@ impl Mbc {
         }
     }
 
+    /// The register writes that bring a freshly loaded MBC into the same banking state
+    pub fn register_writes(&self) -> Vec<(u16, u8)> {
+        let enable = |enabled: bool| if enabled { 0x0A } else { 0x00 };
+        match self {
+            Self::None => Vec::new(),
+            Self::Mbc1(mbc) => vec![
+                (0x0000, enable(mbc.ram_enabled)),
+                (0x2000, mbc.rom_bank_register),
+                (0x4000, mbc.secondary_register),
+                (0x6000, mbc.advanced_banking_mode as u8),
+            ],
+            Self::Mbc2(mbc) => vec![
+                (0x0000, enable(mbc.ram_enabled)),
+                (0x0100, mbc.rom_bank_register),
+            ],
+            Self::Mbc3(mbc) => vec![
+                (0x0000, enable(mbc.ram_rtc_enabled)),
+                (0x2000, mbc.rom_bank_register),
+                (0x4000, mbc.ram_rtc_select),
+            ],
+            Self::Mbc5(mbc) => vec![
+                (0x0000, enable(mbc.ram_enabled)),
+                (0x2000, mbc.rom_bank_register_low),
+                (0x3000, mbc.rom_bank_register_high),
+                (0x4000, mbc.ram_bank_register),
+            ],
+        }
+    }
+
     pub fn put_internal_data(&mut self, data: &[u8]) -> bool {
         if let Self::Mbc2(mbc) = self {
             mbc.ram.copy_from_slice(data);
@@ -1,6 +1,6 @@
 use crate::gb::cartridge::mbc::{MbcInterface, mask_bank_number};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mbc2 {
     pub ram_enabled: bool,
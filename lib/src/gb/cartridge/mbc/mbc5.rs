@@ -1,6 +1,6 @@
 use crate::gb::cartridge::mbc::{MbcInterface, mask_bank_number};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mbc5 {
     pub rom_bank_count: usize,
@@ -1,6 +1,7 @@
 use crate::gb::GbModel;
 use crate::gb::bus::CpuBusInterface;
 use crate::gb::ic::ICInterface;
+use crate::gb::speed::SWITCH_STALL_CYCLES;
 use crate::instructions::{Cond, Instruction, R8, R16, R16Mem, R16Stk};
 use crate::utils::bit::{
     add_bytes, add_bytes_carry, add_word_signed_byte, add_words, get_bit, hi, lo,
@@ -17,7 +18,7 @@ pub trait Bus: CpuBusInterface + ICInterface + crate::debug::DebuggerInterface {
 #[cfg(feature = "debug")]
 impl<T: CpuBusInterface + ICInterface + crate::debug::DebuggerInterface> Bus for T {}
 
-#[derive(Debug, Default, Eq, PartialEq)]
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cpu {
     pub a: u8,
@@ -35,6 +36,12 @@ pub struct Cpu {
     pub ime_next: bool,
     pub halted: bool,
     pub halt_bug: bool,
+    /// In STOP mode, only a held joypad button wakes the CPU up
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub stopped: bool,
+    /// Remaining M-cycles of a CGB speed switch
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub speed_switch_stall: u16,
     pub model: GbModel,
     pub invalid_opcode: bool,
 }
@@ -44,6 +51,21 @@ impl Cpu {
         Self::default()
     }
 
+    /// The register state the boot ROM of the given model leaves behind when handing over to the cartridge
+    pub fn new_post_boot(model: GbModel, header_checksum: u8) -> Self {
+        match model {
+            GbModel::Dmg => Self::new_dmg(header_checksum),
+            GbModel::Mgb => Self::new_mgb(header_checksum),
+            GbModel::Sgb2 => Self::new_sgb2(),
+            GbModel::Cgb => Self::new_cgb(),
+            GbModel::CgbE => Self {
+                model: GbModel::CgbE,
+                ..Self::new_cgb()
+            },
+            GbModel::Agb => Self::new_agb(),
+        }
+    }
+
     pub fn new_dmg(header_checksum: u8) -> Self {
         let flags = if header_checksum == 0x00 {
             Flags {
@@ -75,6 +97,8 @@ impl Cpu {
             ime_next: false,
             halted: false,
             halt_bug: false,
+            stopped: false,
+            speed_switch_stall: 0,
             model: GbModel::Dmg,
             invalid_opcode: false,
         }
@@ -102,11 +126,57 @@ impl Cpu {
             ime_next: false,
             halted: false,
             halt_bug: false,
+            stopped: false,
+            speed_switch_stall: 0,
             model: GbModel::Cgb,
             invalid_opcode: false,
         }
     }
 
+    /// Same as DMG, except for A which identifies the Pocket/Light hardware
+    pub fn new_mgb(header_checksum: u8) -> Self {
+        Self {
+            a: 0xFF,
+            model: GbModel::Mgb,
+            ..Self::new_dmg(header_checksum)
+        }
+    }
+
+    pub fn new_sgb2() -> Self {
+        Self {
+            a: 0xFF,
+            b: 0x00,
+            c: 0x14,
+            d: 0x00,
+            e: 0x00,
+            f: Flags::default(),
+            h: 0xC0,
+            l: 0x60,
+            sp: 0xFFFE,
+            pc: 0x0100,
+            ir: 0x00,
+            ime: false,
+            ime_next: false,
+            halted: false,
+            halt_bug: false,
+            stopped: false,
+            speed_switch_stall: 0,
+            model: GbModel::Sgb2,
+            invalid_opcode: false,
+        }
+    }
+
+    /// The AGB boot ROM increments B right before handing over, which also clears the zero flag.
+    /// Software uses bit 0 of B to detect that it is running on a Game Boy Advance.
+    pub fn new_agb() -> Self {
+        Self {
+            b: 0x01,
+            f: Flags::default(),
+            model: GbModel::Agb,
+            ..Self::new_cgb()
+        }
+    }
+
     pub fn new_with_boot_rom(model: GbModel) -> Self {
         Self {
             model,
@@ -120,10 +190,23 @@ impl Cpu {
             return;
         }
 
+        if self.speed_switch_stall > 0 {
+            bus.stopped_cycle();
+            self.speed_switch_stall -= 1;
+            return;
+        }
+
+        if self.stopped {
+            bus.stopped_cycle();
+            if bus.joypad_low() {
+                self.stopped = false;
+            }
+            return;
+        }
+
         if self.halted {
             bus.cycle();
             if bus.has_pending_interrupt() {
-                // ToDo: Halt bug => https://gbdev.io/pandocs/halt.html#halt-bug
                 self.halted = false;
             }
             return;
@@ -153,14 +236,8 @@ impl Cpu {
             Instruction::CCF => self.ccf(),
             Instruction::JR_n => self.jr_n(bus),
             Instruction::JR_c_n(cond) => self.jr_c_n(bus, cond),
-            Instruction::STOP => {}
-            Instruction::HALT | Instruction::LD_r_r(R8::HL, R8::HL) => {
-                if !self.ime && bus.has_pending_interrupt() {
-                    self.halt_bug = true;
-                } else {
-                    self.halted = true;
-                }
-            }
+            Instruction::STOP => self.stop(bus),
+            Instruction::HALT | Instruction::LD_r_r(R8::HL, R8::HL) => self.halt(bus),
             Instruction::LD_r_r(dest, src) => self.ld_r_r(bus, dest, src),
             Instruction::ADD_r(r8) => self.add_r(bus, r8),
             Instruction::ADC_r(r8) => self.adc_r(bus, r8),
@@ -247,7 +324,8 @@ impl Cpu {
             self.ime_next = false;
 
             bus.cycle();
-            bus.cycle();
+            // Internal cycle: SP is decremented before the first write
+            bus.idu_cycle(self.sp);
 
             let return_address = self.pc.wrapping_sub(1);
             self.push(bus, hi(return_address));
@@ -266,12 +344,49 @@ impl Cpu {
         self.ime = self.ime_next;
     }
 
-    pub fn soft_reset(&mut self, header_checksum: u8) {
-        match self.model {
-            GbModel::Dmg => *self = Self::new_dmg(header_checksum),
-            GbModel::Cgb => *self = Self::new_cgb(),
+    /// HALT with an interrupt already pending does not halt, instead the halt bug keeps PC from
+    /// advancing past the next opcode. With IME off that opcode runs twice, after EI the interrupt
+    /// is serviced right away and returns to the HALT, which then halts.
+    /// See https://gbdev.io/pandocs/halt.html#halt-bug
+    pub fn halt(&mut self, bus: &mut impl Bus) {
+        if bus.has_pending_interrupt() {
+            self.halt_bug = true;
+        } else {
+            self.halted = true;
+        }
+    }
+
+    /// STOP depends on the joypad, pending interrupts and a prepared CGB speed switch, whether it
+    /// skips its operand byte follows from the pending interrupts alone.
+    /// See https://gbdev.io/pandocs/Reducing_Power_Consumption.html#using-the-stop-instruction
+    pub fn stop(&mut self, bus: &mut impl Bus) {
+        let pending = bus.has_pending_interrupt();
+
+        // A held button keeps STOP from stopping the clock, it halts instead
+        if bus.joypad_low() {
+            if !pending {
+                self.pc = self.pc.wrapping_add(1);
+                self.halted = true;
+            }
+            return;
+        }
+
+        let speed_switch = bus.speed_switch_armed();
+        bus.stop();
+        if !pending {
+            self.pc = self.pc.wrapping_add(1);
+        }
+
+        if !speed_switch {
+            self.stopped = true;
+        } else if !pending {
+            self.speed_switch_stall = SWITCH_STALL_CYCLES;
         }
     }
+
+    pub fn soft_reset(&mut self, header_checksum: u8) {
+        *self = Self::new_post_boot(self.model, header_checksum);
+    }
 }
 
 // Program helpers
@@ -300,7 +415,9 @@ impl Cpu {
     }
 
     pub fn pop_word(&mut self, bus: &mut impl Bus) -> u16 {
-        word(self.pop(bus), self.pop(bus))
+        let low = bus.read_inc(self.sp);
+        self.sp = self.sp.wrapping_add(1);
+        word(low, self.pop(bus))
     }
 
     pub fn push(&mut self, bus: &mut impl Bus, value: u8) {
@@ -328,7 +445,10 @@ impl Cpu {
 
     pub fn ld_a_rr(&mut self, bus: &mut impl Bus, r16_mem: R16Mem) {
         let address = self.get_r16mem(r16_mem);
-        self.a = bus.read(address);
+        self.a = match r16_mem {
+            R16Mem::HLinc | R16Mem::HLdec => bus.read_inc(address),
+            _ => bus.read(address),
+        };
     }
 
     pub fn ld_nn_sp(&mut self, bus: &mut impl Bus) {
@@ -337,13 +457,13 @@ impl Cpu {
     }
 
     pub fn inc_rr(&mut self, bus: &mut impl Bus, r16: R16) {
-        bus.cycle();
+        bus.idu_cycle(self.get_r16_nn(r16));
 
         self.set_r16_nn(r16, self.get_r16_nn(r16).wrapping_add(1));
     }
 
     pub fn dec_rr(&mut self, bus: &mut impl Bus, r16: R16) {
-        bus.cycle();
+        bus.idu_cycle(self.get_r16_nn(r16));
 
         self.set_r16_nn(r16, self.get_r16_nn(r16).wrapping_sub(1));
     }
@@ -573,7 +693,7 @@ impl Cpu {
 
     pub fn push_rr(&mut self, bus: &mut impl Bus, r16stk: R16Stk) {
         // Internal cycle: CPU computes decremented SP before first write
-        bus.cycle();
+        bus.idu_cycle(self.sp);
 
         let value = self.get_r16stk(r16stk);
         self.push_word(bus, value);
@@ -623,7 +743,7 @@ impl Cpu {
     pub fn call_nn(&mut self, bus: &mut impl Bus) {
         let address = self.read_program_nn(bus);
 
-        bus.cycle();
+        bus.idu_cycle(self.sp);
         self.push_word(bus, self.pc);
 
         self.pc = address;
@@ -633,14 +753,14 @@ impl Cpu {
         let address = self.read_program_nn(bus);
 
         if self.f.cond_true(cond) {
-            bus.cycle();
+            bus.idu_cycle(self.sp);
             self.push_word(bus, self.pc);
             self.pc = address;
         }
     }
 
     pub fn rst(&mut self, bus: &mut impl Bus, address_lsb: u8) {
-        bus.cycle();
+        bus.idu_cycle(self.sp);
         self.push_word(bus, self.pc);
         self.pc = word(address_lsb, 0x00);
     }
@@ -1,6 +1,6 @@
 use crate::gb::GbModel;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DmaController {
     pub active: bool,
@@ -13,7 +13,7 @@ impl DmaController {
     pub fn new(model: GbModel) -> Self {
         Self {
             active: false,
-            source: if model == GbModel::Cgb { 0x00 } else { 0xFF },
+            source: if model.is_cgb() { 0x00 } else { 0xFF },
             progress: 0,
             model,
         }
@@ -43,6 +43,12 @@ impl DmaController {
         Some((source, target))
     }
 
+    /// The address the transfer reads next, if one is ongoing
+    pub fn current_source(&self) -> Option<u16> {
+        (self.active && self.progress < 160)
+            .then_some((self.source as u16) << 8 | self.progress as u16)
+    }
+
     pub fn cpu_conflicts(&self, addr: u16) -> bool {
         if !self.active {
             return false;
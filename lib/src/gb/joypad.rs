@@ -2,6 +2,7 @@ use crate::gb::ic::{ICInterface, Interrupt};
 use crate::{ReadMemory, WriteMemory};
 use bitflags::bitflags;
 
+#[derive(Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Joypad {
     register: u8,
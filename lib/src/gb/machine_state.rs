@@ -0,0 +1,24 @@
+//! In-memory copies of the machine state, taken without any serialization. Cheap enough to take
+//! and restore every frame, which is what run-ahead does.
+
+use crate::gb::{apu, boot_rom, cartridge, cpu, dma, ic, joypad, memory, ppu, speed, timer};
+
+/// Everything that changes while a [`crate::gb::GameBoy`] runs. The ROM is shared with the
+/// machine the state was taken from instead of copied
+#[derive(Clone)]
+pub struct MachineState {
+    pub(super) boot_rom: boot_rom::MappedBootRom,
+    pub(super) cpu: cpu::Cpu,
+    pub(super) cartridge: cartridge::Cartridge,
+    pub(super) dma: dma::DmaController,
+    pub(super) ic: ic::InterruptController,
+    pub(super) memory: memory::Memory,
+    pub(super) timer: timer::Timer,
+    pub(super) ppu: ppu::Ppu,
+    pub(super) apu: apu::Apu,
+    pub(super) joypad: joypad::Joypad,
+    pub(super) speed: speed::Speed,
+    pub(super) cycle_counter: u32,
+    #[cfg(feature = "debug")]
+    pub(super) total_cycles: u128,
+}
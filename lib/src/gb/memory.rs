@@ -2,16 +2,17 @@ use crate::gb::GbModel;
 use crate::gb::ram_init::RamInit;
 use crate::{ReadMemory, WriteMemory};
 
-const WRAM_BANK_SIZE: usize = 0x1000; // 4KiB
+pub(crate) const WRAM_BANK_SIZE: usize = 0x1000; // 4KiB
 const HRAM_SIZE: usize = 127; // Bytes
 const IO_SIZE: usize = 128; // Bytes
 
+#[derive(Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Memory {
     #[cfg_attr(feature = "serde", serde(with = "serde_wram"))]
-    wram: Vec<[u8; WRAM_BANK_SIZE]>,
+    pub(crate) wram: Vec<[u8; WRAM_BANK_SIZE]>,
     #[cfg_attr(feature = "serde", serde(with = "serde_hram"))]
-    hram: [u8; HRAM_SIZE],
+    pub(crate) hram: [u8; HRAM_SIZE],
     #[cfg_attr(feature = "serde", serde(with = "serde_io"))]
     // ToDo: Put in IO components (e.g. Timer, Serial, Joypad)
     io: [u8; IO_SIZE],
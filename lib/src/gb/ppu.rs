@@ -5,9 +5,11 @@ use crate::gb::ppu::fifo::PixelFifo;
 use crate::gb::ppu::scanner::OamScanner;
 use crate::gb::ppu::types::theme::DmgTheme;
 use crate::{ReadMemory, WriteMemory};
+use types::color::RGBA;
 use types::framebuffer::Framebuffer;
 use types::lcdc::LCDC;
 use types::mode::PpuMode;
+use types::native_frame::{NativeFormat, NativeFrame};
 use types::stat::STAT;
 
 mod fetcher;
@@ -15,21 +17,41 @@ mod fifo;
 mod scanner;
 pub mod types;
 
+pub use scanner::OamCorruption;
+
 pub const SCREEN_WIDTH: usize = 160;
 pub const SCREEN_HEIGHT: usize = 144;
 const VRAM_BANK_SIZE: usize = 0x2000; // 8KiB
 const OAM_SIZE: usize = 160; // Bytes
 
+/// The BG palette a CGB boot ROM gives DMG cartridges it has no palette of its own for, BGR555
+pub(crate) const COMPAT_BG_PALETTE: [u8; 8] = [0xFF, 0x7F, 0xEF, 0x1B, 0x80, 0x61, 0x00, 0x00];
+/// The palette both OBJ palettes get alongside [`COMPAT_BG_PALETTE`]
+pub(crate) const COMPAT_OBJ_PALETTE: [u8; 8] = [0xFF, 0x7F, 0x1F, 0x42, 0xF2, 0x1C, 0x00, 0x00];
+
+#[derive(Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Ppu {
     #[cfg_attr(feature = "serde", serde(skip, default))]
     frame: Framebuffer,
+    /// The same picture as shades or BGR555 colors, before the theme
+    #[cfg_attr(feature = "serde", serde(skip, default))]
+    native_frame: NativeFrame,
     model: GbModel,
     pub frame_ready: bool,
+    /// Frame-skip mode for frames nobody looks at: mode, STAT and LY timing, the FIFO and memory
+    /// access stay exact, but no pixel is converted to a color and the framebuffer keeps the last
+    /// frame drawn
+    #[cfg_attr(feature = "serde", serde(skip, default))]
+    pub skip_rendering: bool,
     pub dmg_theme: DmgTheme,
     pub fetcher: PixelFetcher,
     pub fifo: PixelFifo,
     pub scanner: OamScanner,
+    /// The address OAM DMA reads during the current M-cycle, the PPU loses OAM to it and shares
+    /// the VRAM bus with it
+    #[cfg_attr(feature = "serde", serde(skip, default))]
+    oam_dma: Option<u16>,
     stat_line_previous: bool,
     /// How many dots are left in the current H or V blank period
     pub blank_timeout: u16,
@@ -38,10 +60,10 @@ pub struct Ppu {
     // Memory
     /// Video RAM (2 banks on CGB)
     #[cfg_attr(feature = "serde", serde(with = "serde_vram"))]
-    vram: [[u8; VRAM_BANK_SIZE]; 2],
+    pub(crate) vram: [[u8; VRAM_BANK_SIZE]; 2],
     /// Sprite attribute table
     #[cfg_attr(feature = "serde", serde(with = "serde_oam"))]
-    oam: [u8; OAM_SIZE],
+    pub(crate) oam: [u8; OAM_SIZE],
     /// LCD control
     pub lcdc: LCDC,
     /// LCD status
@@ -70,14 +92,17 @@ pub struct Ppu {
     bcps: u8,
     /// Internal BG palette RAM, accessed via BCPS
     #[cfg_attr(feature = "serde", serde(with = "serde_64"))]
-    bg_palette_ram: [u8; 64],
+    pub(crate) bg_palette_ram: [u8; 64],
     /// OBJ palette index (CGB)
     ocps: u8,
     /// Internal OBJ palette RAM, accessed via OCPS
     #[cfg_attr(feature = "serde", serde(with = "serde_64"))]
-    obj_palette_ram: [u8; 64],
+    pub(crate) obj_palette_ram: [u8; 64],
     /// OBJ priority mode (CGB)
     opri: u8,
+    /// Set through KEY0 by the boot ROM of a CGB running a DMG cartridge. The CGB registers are
+    /// locked then and the DMG palettes pick colors from palette RAM
+    pub(crate) dmg_compat: bool,
     /// VRAM DMA source high (CGB)
     hdma1: u8,
     /// VRAM DMA source low (CGB)
@@ -94,12 +119,15 @@ impl Ppu {
     pub fn new(model: GbModel) -> Self {
         Self {
             frame: Framebuffer::new(),
+            native_frame: NativeFrame::new(NativeFormat::of(model)),
             model,
             frame_ready: false,
+            skip_rendering: false,
             dmg_theme: DmgTheme::default(),
             fetcher: PixelFetcher::default(),
             fifo: PixelFifo::default(),
             scanner: OamScanner::default(),
+            oam_dma: None,
             stat_line_previous: false,
             blank_timeout: 456,
             line_dot_counter: 0,
@@ -122,6 +150,7 @@ impl Ppu {
             ocps: 0x00,
             obj_palette_ram: [0x00; 64],
             opri: 0x00,
+            dmg_compat: false,
             hdma1: 0xFF,
             hdma2: 0xFF,
             hdma3: 0xFF,
@@ -130,24 +159,55 @@ impl Ppu {
         }
     }
 
-    pub fn cycle(&mut self, ic: &mut impl ICInterface, oam_dma: bool) {
+    /// One M-cycle, the PPU runs at the same pace in both speed modes
+    pub fn cycle(&mut self, ic: &mut impl ICInterface, oam_dma: Option<u16>, double_speed: bool) {
+        self.oam_dma = oam_dma;
+
         if !self.lcdc.lcd_enabled {
             return;
         }
 
-        if self.model.is_cgb() {
-            self.dot(ic, oam_dma);
-            self.dot(ic, oam_dma);
-        } else {
-            self.dot(ic, oam_dma);
-            self.dot(ic, oam_dma);
-            self.dot(ic, oam_dma);
-            self.dot(ic, oam_dma);
+        let dots = if double_speed { 2 } else { 4 };
+
+        // Nothing happens in the blanking periods until their timeout runs out, their dots can be
+        // counted off at once
+        if matches!(self.stat.ppu_mode, PpuMode::HBlank | PpuMode::VBlank)
+            && self.blank_timeout > dots
+        {
+            self.blank_timeout -= dots;
+            self.check_window_condition();
+            self.evaluate_stat_interrupts(ic);
+            return;
+        }
+
+        for _ in 0..dots {
+            self.dot(ic);
         }
     }
 
-    // ToDo: Conflicts on oam dma? Check relevance and implementation
-    pub fn dot(&mut self, ic: &mut impl ICInterface, _oam_dma: bool) {
+    /// M-cycles that can go by before the next one that does more than count off blanking dots:
+    /// none while the PPU draws, the rest of the period in H and V blank, all while the LCD is off
+    pub(crate) fn quiet_cycles(&self, double_speed: bool) -> u32 {
+        if !self.lcdc.lcd_enabled {
+            return u32::MAX;
+        }
+        match self.stat.ppu_mode {
+            PpuMode::HBlank | PpuMode::VBlank => {
+                (self.blank_timeout as u32).saturating_sub(1) / if double_speed { 2 } else { 4 }
+            }
+            PpuMode::OamScan | PpuMode::Drawing => 0,
+        }
+    }
+
+    /// Counts off quiet M-cycles at once, see [`Ppu::quiet_cycles`]. The STAT line and the window
+    /// condition can't change in them, they were evaluated in the last M-cycle that ran
+    pub(crate) fn skip(&mut self, cycles: u32, double_speed: bool) {
+        if self.lcdc.lcd_enabled {
+            self.blank_timeout -= (cycles * if double_speed { 2 } else { 4 }) as u16;
+        }
+    }
+
+    pub fn dot(&mut self, ic: &mut impl ICInterface) {
         self.check_window_condition();
 
         match self.stat.ppu_mode {
@@ -238,49 +298,126 @@ impl Ppu {
         }
     }
 
-    pub fn cpu_conflicts(&self, _addr: u16) -> bool {
-        false
-
-        // ToDo: Improve accuracy, test viability?? => currently causing conflicts where it shouldn't
-        // => run cpu_instrs test => part of the text is missing
-        //if !self.lcdc.lcd_enabled
-        //    || self.stat.ppu_mode == PpuMode::HBlank
-        //    || self.stat.ppu_mode == PpuMode::VBlank
-        //{
-        //    return false;
-        //}
-
-        //match addr {
-        //    // VRAM blocked during mode 3
-        //    0x8000..=0x9FFF => self.stat.ppu_mode == PpuMode::Drawing,
-        //    // OAM blocked during mode 2 and 3
-        //    0xFE00..=0xFE9F => matches!(self.stat.ppu_mode, PpuMode::OamScan | PpuMode::Drawing),
-        //    // CGB palettes blocked during mode 3
-        //    0xFF69 | 0xFF6B => self.model.is_cgb() && self.stat.ppu_mode == PpuMode::Drawing,
-        //    _ => false,
-        //}
+    /// Whether the PPU currently locks the CPU out of the given address. OAM belongs to the PPU
+    /// during mode 2 and 3, VRAM and the CGB palette RAM during mode 3. Access reopens in the dot
+    /// mode 0 begins, CPU accesses land at the end of their M-cycle.
+    /// See https://gbdev.io/pandocs/Rendering.html#ppu-modes
+    pub fn cpu_conflicts(&self, addr: u16) -> bool {
+        if !self.lcdc.lcd_enabled {
+            return false;
+        }
+
+        match addr {
+            0x8000..=0x9FFF => self.stat.ppu_mode == PpuMode::Drawing,
+            0xFE00..=0xFE9F => {
+                matches!(self.stat.ppu_mode, PpuMode::OamScan | PpuMode::Drawing)
+            }
+            0xFF69 | 0xFF6B => self.cgb_mode() && self.stat.ppu_mode == PpuMode::Drawing,
+            _ => false,
+        }
+    }
+
+    /// A CGB running CGB software, not in DMG compatibility mode
+    pub fn cgb_mode(&self) -> bool {
+        self.model.is_cgb() && !self.dmg_compat
+    }
+
+    /// Switches a CGB without boot ROM to DMG compatibility mode, with the palettes its boot ROM
+    /// loads for cartridges it has no palette of its own for
+    pub(crate) fn enter_dmg_compat(&mut self) {
+        self.dmg_compat = true;
+        self.bg_palette_ram[..8].copy_from_slice(&COMPAT_BG_PALETTE);
+        self.obj_palette_ram[..8].copy_from_slice(&COMPAT_OBJ_PALETTE);
+        self.obj_palette_ram[8..16].copy_from_slice(&COMPAT_OBJ_PALETTE);
     }
 
     pub fn frame(&self) -> &Framebuffer {
         &self.frame
     }
 
-    pub fn soft_reset(&mut self) {
+    pub fn native_frame(&self) -> &NativeFrame {
+        &self.native_frame
+    }
+
+    /// Takes the framebuffers over from the PPU this one replaces, save states leave them out
+    pub(crate) fn take_frame(&mut self, previous: &mut Ppu) {
+        std::mem::swap(&mut self.frame, &mut previous.frame);
+        std::mem::swap(&mut self.native_frame, &mut previous.native_frame);
+        self.native_frame.set_format(NativeFormat::of(self.model));
+    }
+
+    /// The power-on state, with the LCD still off until the boot ROM turns it on
+    pub fn new_with_boot_rom(model: GbModel) -> Self {
+        Self {
+            lcdc: 0x00.into(),
+            stat: 0x80.into(),
+            ly: 0x00,
+            bgp: 0x00,
+            ..Self::new(model)
+        }
+    }
+
+    pub fn soft_reset(&mut self, boot_rom: bool) {
         let theme = self.dmg_theme;
-        *self = Self::new(self.model);
+        *self = if boot_rom {
+            Self::new_with_boot_rom(self.model)
+        } else {
+            Self::new(self.model)
+        };
         self.dmg_theme = theme;
     }
 
+    /// Starts the current scanline over from the registers alone, for save states that don't know
+    /// how far into the line the PPU was
+    #[cfg(feature = "persistence")]
+    pub(crate) fn restart_line(&mut self) {
+        self.line_dot_counter = 0;
+        self.blank_timeout = 456;
+        self.scanner.reset();
+        self.fetcher.reset_scanline();
+
+        if !self.lcdc.lcd_enabled {
+            self.ly = 0;
+            self.stat.ppu_mode = PpuMode::HBlank;
+        } else if self.ly >= 144 {
+            self.stat.ppu_mode = PpuMode::VBlank;
+        } else {
+            if self.ly == 0 {
+                self.fetcher.reset_frame();
+            }
+            self.stat.ppu_mode = PpuMode::OamScan;
+        }
+
+        self.check_lyc();
+        self.stat_line_previous = self.current_stat_line();
+    }
+
     pub fn clear_frame(&mut self) {
         self.frame.clear_with_test_pattern();
     }
+
+    /// STOP halts the LCD controller, the screen shows a blank frame until the CPU wakes up
+    pub fn stop(&mut self) {
+        if !self.lcdc.lcd_enabled {
+            return;
+        }
+
+        let (blank, native_blank) = match self.native_frame.format() {
+            NativeFormat::Shade => (self.dmg_theme.color_from_shade(0), 0),
+            NativeFormat::Bgr555 => (RGBA::from_bgr555(0x7FFF), 0x7FFF),
+        };
+        for index in 0..SCREEN_WIDTH * SCREEN_HEIGHT {
+            self.frame.set(index, blank);
+            self.native_frame.set(index, native_blank);
+        }
+    }
 }
 
 impl ReadMemory for Ppu {
     fn read_naive(&self, addr: u16) -> u8 {
         match addr {
             0x8000..=0x9FFF => {
-                if self.model.is_cgb() {
+                if self.cgb_mode() {
                     self.vram[(self.vbk & 1) as usize][(addr - 0x8000) as usize]
                 } else {
                     self.vram[0][(addr - 0x8000) as usize]
@@ -298,77 +435,17 @@ impl ReadMemory for Ppu {
             0xFF49 => self.obp1,
             0xFF4A => self.wy,
             0xFF4B => self.wx,
-            0xFF4F => {
-                if self.model.is_cgb() {
-                    self.vbk | 0xFE // only bit 0 readable
-                } else {
-                    0xFF
-                }
-            }
-            0xFF51 => {
-                if self.model.is_cgb() {
-                    self.hdma1
-                } else {
-                    0xFF
-                }
-            }
-            0xFF52 => {
-                if self.model.is_cgb() {
-                    self.hdma2
-                } else {
-                    0xFF
-                }
-            }
-            0xFF53 => {
-                if self.model.is_cgb() {
-                    self.hdma3
-                } else {
-                    0xFF
-                }
-            }
-            0xFF54 => {
-                if self.model.is_cgb() {
-                    self.hdma4
-                } else {
-                    0xFF
-                }
-            }
-            0xFF55 => {
-                if self.model.is_cgb() {
-                    self.hdma5
-                } else {
-                    0xFF
-                }
-            }
-            0xFF68 => {
-                if self.model.is_cgb() {
-                    self.bcps
-                } else {
-                    0xFF
-                }
-            }
-            0xFF69 => {
-                if self.model.is_cgb() {
-                    self.bg_palette_ram[(self.bcps & 0x3F) as usize]
-                } else {
-                    0xFF
-                }
-            }
-            0xFF6A => {
-                if self.model.is_cgb() {
-                    self.ocps
-                } else {
-                    0xFF
-                }
-            }
-            0xFF6B => {
-                if self.model.is_cgb() {
-                    self.obj_palette_ram[(self.ocps & 0x3F) as usize]
-                } else {
-                    0xFF
-                }
-            }
-            0xFF6C if self.model.is_cgb() => self.opri,
+            0xFF4F if self.cgb_mode() => self.vbk | 0xFE, // only bit 0 readable
+            0xFF51 if self.cgb_mode() => self.hdma1,
+            0xFF52 if self.cgb_mode() => self.hdma2,
+            0xFF53 if self.cgb_mode() => self.hdma3,
+            0xFF54 if self.cgb_mode() => self.hdma4,
+            0xFF55 if self.cgb_mode() => self.hdma5,
+            0xFF68 if self.cgb_mode() => self.bcps,
+            0xFF69 if self.cgb_mode() => self.bg_palette_ram[(self.bcps & 0x3F) as usize],
+            0xFF6A if self.cgb_mode() => self.ocps,
+            0xFF6B if self.cgb_mode() => self.obj_palette_ram[(self.ocps & 0x3F) as usize],
+            0xFF6C if self.cgb_mode() => self.opri,
             _ => 0xFF,
         }
     }
@@ -378,7 +455,7 @@ impl WriteMemory for Ppu {
     fn write_naive(&mut self, addr: u16, value: u8) {
         match addr {
             0x8000..=0x9FFF => {
-                if self.model.is_cgb() {
+                if self.cgb_mode() {
                     self.vram[(self.vbk & 1) as usize][(addr - 0x8000) as usize] = value
                 } else {
                     self.vram[0][(addr - 0x8000) as usize] = value;
@@ -416,8 +493,10 @@ impl WriteMemory for Ppu {
             0xFF49 => self.obp1 = value,
             0xFF4A => self.wy = value,
             0xFF4B => self.wx = value,
+            0xFF4F | 0xFF51..=0xFF55 | 0xFF68..=0xFF6C if self.dmg_compat => {}
+            0xFF4C if self.model.is_cgb() => self.dmg_compat = value & 0x04 != 0,
             0xFF4F => {
-                if self.model.is_cgb() {
+                if self.cgb_mode() {
                     self.vbk = value & 0x01
                 } else {
                     self.vbk = value
@@ -2,7 +2,7 @@ use crate::ReadMemory;
 use crate::gb::ppu::Ppu;
 use crate::gb::ppu::fifo::FifoPixel;
 use crate::gb::ppu::types::sprite::Sprite;
-use crate::gb::ppu::types::tile::TileLine;
+use crate::gb::ppu::types::tile::{BgAttributes, TileLine};
 
 /// Responsible for loading data into the pixel FIFO
 /// Continuously active throughout mode 3 (Drawing)
@@ -12,6 +12,8 @@ pub struct PixelFetcher {
     pub state: PixelFetcherState,
     pub x: u8,
     pub tile_id: u8,
+    /// Attributes of the BG or window tile, only read on CGB
+    pub attributes: BgAttributes,
     pub tile_line: TileLine,
     /// If in sprite mode this will be the sprite to fetch pixels for
     pub sprite_mode: Option<Sprite>,
@@ -27,6 +29,7 @@ impl PixelFetcher {
         self.state = PixelFetcherState::GetTile1;
         self.x = 0;
         self.tile_id = 0;
+        self.attributes = BgAttributes::default();
         self.tile_line = TileLine::default();
         self.window_mode = false;
     }
@@ -54,8 +57,14 @@ pub enum PixelFetcherState {
 
 impl Ppu {
     pub fn dot_fetcher(&mut self) {
+        // LCDC bit 0 doesn't hide the window on CGB
+        let window_enabled = if self.cgb_mode() {
+            self.lcdc.window_enable
+        } else {
+            self.lcdc.do_render_window()
+        };
         if !self.fetcher.window_mode
-            && self.lcdc.do_render_window()
+            && window_enabled
             && self.fetcher.wy_triggered
             && self.fifo.lcd_x >= self.wx.saturating_sub(7)
         {
@@ -114,7 +123,10 @@ impl Ppu {
 
                     let index = tile_x as u16 + (tile_y as u16 * 32);
                     let addr = tilemap_addr + index;
-                    self.fetcher.tile_id = self.blocked_read(addr);
+                    self.fetcher.tile_id = self.blocked_read(0, addr);
+                    if self.cgb_mode() {
+                        self.fetcher.attributes = self.blocked_read(1, addr).into();
+                    }
                 }
 
                 self.fetcher.state = PixelFetcherState::GetTileDataLow1;
@@ -130,7 +142,7 @@ impl Ppu {
                     self.lcdc
                         .bg_win_tile_line_address(self.fetcher.tile_id, self.fetcher_y())
                 };
-                self.fetcher.tile_line.low = self.blocked_read(addr);
+                self.fetcher.tile_line.low = self.blocked_read(self.tile_bank(), addr);
                 self.fetcher.state = PixelFetcherState::GetTileDataHigh1;
             }
             PixelFetcherState::GetTileDataHigh1 => {
@@ -144,7 +156,7 @@ impl Ppu {
                     self.lcdc
                         .bg_win_tile_line_address(self.fetcher.tile_id, self.fetcher_y())
                 };
-                self.fetcher.tile_line.high = self.blocked_read(addr + 1);
+                self.fetcher.tile_line.high = self.blocked_read(self.tile_bank(), addr + 1);
 
                 // ToDo: Check where exactly the push happens
                 //self.try_push_to_fifo();
@@ -173,9 +185,25 @@ impl Ppu {
         }
     }
 
-    // ToDo: Blocked PPU read => https://gbdev.io/pandocs/pixel_fifo.html#vram-access
-    fn blocked_read(&self, addr: u16) -> u8 {
-        self.read_naive(addr)
+    /// Reads VRAM `bank` whatever VBK selects for the CPU. OAM DMA from VRAM occupies the VRAM
+    /// bus, the fetcher then sees the byte being transferred
+    fn blocked_read(&self, bank: usize, addr: u16) -> u8 {
+        match self.oam_dma {
+            Some(source @ 0x8000..=0x9FFF) => self.read_naive(source),
+            _ => self.vram[bank][(addr - 0x8000) as usize],
+        }
+    }
+
+    /// The VRAM bank of the tile being fetched, always 0 outside of CGB mode
+    fn tile_bank(&self) -> usize {
+        if !self.cgb_mode() {
+            return 0;
+        }
+        let bank = match &self.fetcher.sprite_mode {
+            Some(sprite) => sprite.flags.bank,
+            None => self.fetcher.attributes.bank,
+        };
+        bank as usize
     }
 
     fn fetcher_y(&self) -> u8 {
@@ -186,10 +214,18 @@ impl Ppu {
                 sprite_line = height.saturating_sub(1).wrapping_sub(sprite_line);
             }
             sprite_line
-        } else if self.fetcher.window_mode {
-            self.fetcher.wl
         } else {
-            self.ly.wrapping_add(self.scy)
+            let line = if self.fetcher.window_mode {
+                self.fetcher.wl
+            } else {
+                self.ly.wrapping_add(self.scy)
+            };
+            // Flips the line within the tile
+            if self.fetcher.attributes.y_flip {
+                line ^ 0x07
+            } else {
+                line
+            }
         }
     }
 
@@ -199,10 +235,10 @@ impl Ppu {
                 let i = if sprite.flags.x_flip { 7 - i } else { i };
                 FifoPixel {
                     color_index: self.fetcher.tile_line.color_index(i as u8),
-                    palette: if self.model.is_dmg() {
-                        sprite.flags.dmg_palette as u8
-                    } else {
+                    palette: if self.cgb_mode() {
                         sprite.flags.cgb_palette
+                    } else {
+                        sprite.flags.dmg_palette as u8
                     },
                     sprite_priority: sprite.oam_index,
                     obj_bg_priority: sprite.flags.obj_bg_priority,
@@ -217,12 +253,15 @@ impl Ppu {
                 return false;
             };
 
-            // ToDo: CGB palette from attribute byte in VRAM bank 1
-            let pixels = std::array::from_fn(|i| FifoPixel {
-                color_index: self.fetcher.tile_line.color_index(i as u8),
-                palette: 0,
-                sprite_priority: 0,
-                obj_bg_priority: false,
+            let attributes = self.fetcher.attributes;
+            let pixels = std::array::from_fn(|i| {
+                let i = if attributes.x_flip { 7 - i } else { i };
+                FifoPixel {
+                    color_index: self.fetcher.tile_line.color_index(i as u8),
+                    palette: attributes.palette,
+                    sprite_priority: 0,
+                    obj_bg_priority: attributes.priority,
+                }
             });
             self.fifo.push_bg(pixels);
         }
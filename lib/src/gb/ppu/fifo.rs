@@ -1,7 +1,7 @@
 //! Source: https://gbdev.io/pandocs/pixel_fifo.html and https://ashiepaws.github.io/GBEDG/ppu/
 
-use crate::gb::ppu::Ppu;
 use crate::gb::ppu::types::color::RGBA;
+use crate::gb::ppu::{Ppu, SCREEN_WIDTH};
 use std::collections::VecDeque;
 
 #[derive(Debug, Default, Copy, Clone)]
@@ -17,7 +17,7 @@ pub struct FifoPixel {
     pub obj_bg_priority: bool,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PixelFifo {
     // Separated queues, but mixed when popping items
@@ -96,27 +96,9 @@ impl Ppu {
                 self.fifo.scx_discard -= 1;
             } else {
                 let sprite = self.fifo.pop_sprite();
-
-                let bg_color_index = if self.lcdc.do_render_bg() {
-                    bg.color_index
-                } else {
-                    0
-                };
-
-                let color = if let Some(sprite) = sprite {
-                    if sprite.color_index == 0
-                        || ((bg.obj_bg_priority || sprite.obj_bg_priority) && bg_color_index != 0)
-                    {
-                        self.apply_bg_palette(bg.palette, bg_color_index)
-                    } else {
-                        self.apply_sprite_palette(sprite.palette, sprite.color_index)
-                    }
-                } else {
-                    self.apply_bg_palette(bg.palette, bg_color_index)
-                };
-
-                self.frame
-                    .set_xy(self.fifo.lcd_x as usize, self.ly as usize, color);
+                if !self.skip_rendering {
+                    self.draw_pixel(bg, sprite);
+                }
                 self.fifo.lcd_x += 1;
             }
         };
@@ -124,20 +106,72 @@ impl Ppu {
         self.fifo.lcd_x == 160
     }
 
-    // ToDo: CGB color palette
-    fn apply_bg_palette(&self, _palette: u8, color_index: u8) -> RGBA {
-        let shade = (self.bgp >> (color_index * 2)) & 0x03;
-        self.dmg_theme.color_from_shade(shade)
+    fn draw_pixel(&mut self, bg: FifoPixel, sprite: Option<FifoPixel>) {
+        // On CGB, LCDC bit 0 takes the priority from BG and window instead of hiding them
+        let cgb_mode = self.cgb_mode();
+        let bg_color_index = if cgb_mode || self.lcdc.do_render_bg() {
+            bg.color_index
+        } else {
+            0
+        };
+        let bg_priority = (bg.obj_bg_priority || sprite.is_some_and(|s| s.obj_bg_priority))
+            && bg_color_index != 0
+            && (!cgb_mode || self.lcdc.bg_window_enable);
+
+        let (shade, native) = match sprite {
+            Some(sprite) if sprite.color_index != 0 && !bg_priority => {
+                let shade = self.sprite_shade(sprite.palette, sprite.color_index);
+                let index = self.cgb_color_index(sprite.color_index, shade);
+                (
+                    shade,
+                    self.cgb_color(&self.obj_palette_ram, sprite.palette, index),
+                )
+            }
+            _ => {
+                let shade = self.bg_shade(bg_color_index);
+                let index = self.cgb_color_index(bg_color_index, shade);
+                (
+                    shade,
+                    self.cgb_color(&self.bg_palette_ram, bg.palette, index),
+                )
+            }
+        };
+
+        let (x, y) = (self.fifo.lcd_x as usize, self.ly as usize);
+        let color = match native {
+            Some(native) => RGBA::from_bgr555(native),
+            None => self.dmg_theme.color_from_shade(shade),
+        };
+        self.frame.set_xy(x, y, color);
+        let native = native.unwrap_or(shade as u16);
+        self.native_frame.set(y * SCREEN_WIDTH + x, native);
     }
 
-    // ToDo: CGB color palette
-    fn apply_sprite_palette(&self, palette: u8, color_index: u8) -> RGBA {
+    fn bg_shade(&self, color_index: u8) -> u8 {
+        (self.bgp >> (color_index * 2)) & 0x03
+    }
+
+    fn sprite_shade(&self, palette: u8, color_index: u8) -> u8 {
         let p = if palette & 1 == 1 {
             self.obp1
         } else {
             self.obp0
         };
-        let shade = (p >> (color_index * 2)) & 0x03;
-        self.dmg_theme.color_from_shade(shade)
+        (p >> (color_index * 2)) & 0x03
+    }
+
+    /// Which color of its palette a pixel takes on CGB, in DMG compatibility mode the DMG palettes
+    /// pick it
+    fn cgb_color_index(&self, color_index: u8, shade: u8) -> u8 {
+        if self.dmg_compat { shade } else { color_index }
+    }
+
+    /// The BGR555 color from palette RAM, `None` on DMG
+    fn cgb_color(&self, palette_ram: &[u8; 64], palette: u8, color_index: u8) -> Option<u16> {
+        if !self.model.is_cgb() {
+            return None;
+        }
+        let index = (palette & 0x07) as usize * 8 + color_index as usize * 2;
+        Some(u16::from_le_bytes([palette_ram[index], palette_ram[index + 1]]) & 0x7FFF)
     }
 }
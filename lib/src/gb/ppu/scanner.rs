@@ -1,7 +1,21 @@
 use crate::gb::ppu::Ppu;
+use crate::gb::ppu::types::mode::PpuMode;
 use crate::gb::ppu::types::sprite::Sprite;
 
-#[derive(Debug, Default)]
+/// OAM is made of 20 rows of 8 bytes, the scanner reads one row per M-cycle
+const OAM_ROWS: usize = 20;
+
+/// CPU accesses that corrupt OAM on DMG models while the PPU scans it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OamCorruption {
+    /// A write, or a 16-bit increment/decrement of an address in 0xFE00-0xFEFF
+    Write,
+    Read,
+    /// A read whose address register is incremented or decremented in the same M-cycle
+    ReadIncrease,
+}
+
+#[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OamScanner {
     // Takes 2 dots to scan 1 entry in oam => 80 dots total
@@ -16,6 +30,11 @@ impl OamScanner {
         self.buffer.clear();
     }
 
+    /// The OAM row the scanner reads during the current M-cycle
+    pub fn accessed_row(&self) -> usize {
+        (self.dot_progress.saturating_sub(1) / 4) as usize
+    }
+
     pub fn pop_sprite_for_x(&mut self, x: u8) -> Option<Sprite> {
         let index = self
             .buffer
@@ -60,11 +79,81 @@ impl Ppu {
         self.scanner.dot_progress == 80
     }
 
+    /// OAM reads 0xFF while OAM DMA owns it, which puts every sprite off-screen
     fn fetch_sprite(&self, index: u8) -> Sprite {
         let i = (index % 40) as usize * 4;
-        let bytes: [u8; 4] = self.oam[i..i + 4].try_into().unwrap();
+        let bytes: [u8; 4] = if self.oam_dma.is_some() {
+            [0xFF; 4]
+        } else {
+            self.oam[i..i + 4].try_into().unwrap()
+        };
         let mut sprite: Sprite = bytes.into();
         sprite.oam_index = index;
         sprite
     }
 }
+
+// OAM corruption bug => https://gbdev.io/pandocs/OAM_Corruption_Bug.html
+impl Ppu {
+    /// Applies the DMG OAM corruption caused by a CPU access to the given address.
+    /// Only accesses to 0xFE00-0xFEFF during mode 2 corrupt the row the scanner is reading.
+    pub fn corrupt_oam(&mut self, addr: u16, corruption: OamCorruption) {
+        if !self.model.is_dmg()
+            || !self.lcdc.lcd_enabled
+            || self.stat.ppu_mode != PpuMode::OamScan
+            || !(0xFE00..=0xFEFF).contains(&addr)
+        {
+            return;
+        }
+
+        let row = self.scanner.accessed_row();
+        match corruption {
+            OamCorruption::Write => self.corrupt_oam_row(row, |a, b, c| ((a ^ c) & (b ^ c)) ^ c),
+            OamCorruption::Read => self.corrupt_oam_row(row, |a, b, c| b | (a & c)),
+            OamCorruption::ReadIncrease => {
+                // Spares the first four rows and the last one, a regular read corruption follows
+                if (4..OAM_ROWS - 1).contains(&row) {
+                    let a = self.oam_word(row - 2, 0);
+                    let b = self.oam_word(row - 1, 0);
+                    let c = self.oam_word(row, 0);
+                    let d = self.oam_word(row - 2, 2);
+                    self.set_oam_word(row - 1, 0, (b & (a | c | d)) | (a & c & d));
+
+                    let preceding = (row - 1) * 8;
+                    self.oam.copy_within(preceding..preceding + 8, row * 8);
+                    self.oam
+                        .copy_within(preceding..preceding + 8, (row - 2) * 8);
+                }
+                self.corrupt_oam_row(row, |a, b, c| b | (a & c));
+            }
+        }
+    }
+
+    /// Replaces the first word of the row with `glitch(a, b, c)` and copies the other three words
+    /// from the preceding row. `a` is the first word of the row, `b` and `c` are the first and
+    /// third word of the preceding row. The first row is never corrupted.
+    fn corrupt_oam_row(&mut self, row: usize, glitch: impl Fn(u16, u16, u16) -> u16) {
+        if row == 0 || row >= OAM_ROWS {
+            return;
+        }
+
+        let a = self.oam_word(row, 0);
+        let b = self.oam_word(row - 1, 0);
+        let c = self.oam_word(row - 1, 2);
+        self.set_oam_word(row, 0, glitch(a, b, c));
+
+        let preceding = (row - 1) * 8;
+        self.oam
+            .copy_within(preceding + 2..preceding + 8, row * 8 + 2);
+    }
+
+    fn oam_word(&self, row: usize, word: usize) -> u16 {
+        let i = row * 8 + word * 2;
+        u16::from_le_bytes([self.oam[i], self.oam[i + 1]])
+    }
+
+    fn set_oam_word(&mut self, row: usize, word: usize, value: u16) {
+        let i = row * 8 + word * 2;
+        self.oam[i..i + 2].copy_from_slice(&value.to_le_bytes());
+    }
+}
@@ -2,6 +2,7 @@ pub mod color;
 pub mod framebuffer;
 pub mod lcdc;
 pub mod mode;
+pub mod native_frame;
 pub mod sprite;
 pub mod stat;
 pub mod theme;
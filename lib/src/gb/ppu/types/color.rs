@@ -24,6 +24,16 @@ impl RGBA {
         Self::new(value, value, value, 0xFF)
     }
 
+    /// A CGB color, red in bits 0-4, green in 5-9 and blue in 10-14. The channels are scaled up to
+    /// 8 bits without any color correction
+    pub fn from_bgr555(value: u16) -> Self {
+        let channel = |shift: u16| {
+            let five = ((value >> shift) & 0x1F) as u8;
+            (five << 3) | (five >> 2)
+        };
+        Self::rgb(channel(0), channel(5), channel(10))
+    }
+
     pub fn r(&self) -> u8 {
         self.0[0]
     }
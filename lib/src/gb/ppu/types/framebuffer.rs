@@ -4,7 +4,7 @@ use crate::gb::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
 const FB_PIXELS: usize = SCREEN_WIDTH * SCREEN_HEIGHT;
 const FB_SIZE: usize = FB_PIXELS * 4;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Framebuffer(Box<[u8; FB_SIZE]>);
 
 impl Default for Framebuffer {
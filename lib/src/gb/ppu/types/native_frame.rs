@@ -0,0 +1,109 @@
+use crate::gb::GbModel;
+use crate::gb::ppu::types::color::RGBA;
+use crate::gb::ppu::types::theme::DmgTheme;
+use crate::gb::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+const FB_PIXELS: usize = SCREEN_WIDTH * SCREEN_HEIGHT;
+
+/// What the values of a [`NativeFrame`] are
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum NativeFormat {
+    /// DMG shades after BGP, OBP0 and OBP1, from 0 (lightest) to 3 (darkest)
+    #[default]
+    Shade,
+    /// CGB colors from palette RAM: red in bits 0-4, green in bits 5-9, blue in bits 10-14
+    Bgr555,
+}
+
+impl NativeFormat {
+    pub fn of(model: GbModel) -> Self {
+        if model.is_cgb() {
+            Self::Bgr555
+        } else {
+            Self::Shade
+        }
+    }
+}
+
+/// The picture as the PPU hands it to the LCD, before any theme or color conversion. One `u16` per
+/// pixel, row by row
+#[derive(Debug, Clone)]
+pub struct NativeFrame {
+    pixels: Box<[u16; FB_PIXELS]>,
+    format: NativeFormat,
+}
+
+impl Default for NativeFrame {
+    fn default() -> Self {
+        Self::new(NativeFormat::default())
+    }
+}
+
+impl NativeFrame {
+    pub fn new(format: NativeFormat) -> Self {
+        Self {
+            pixels: vec![0; FB_PIXELS].into_boxed_slice().try_into().unwrap(),
+            format,
+        }
+    }
+
+    pub fn format(&self) -> NativeFormat {
+        self.format
+    }
+
+    pub(crate) fn set_format(&mut self, format: NativeFormat) {
+        self.format = format;
+    }
+
+    pub fn as_slice(&self) -> &[u16] {
+        self.pixels.as_slice()
+    }
+
+    pub(crate) fn set(&mut self, index: usize, value: u16) {
+        if let Some(pixel) = self.pixels.get_mut(index) {
+            *pixel = value;
+        }
+    }
+
+    /// The pixel at `index` as a color, DMG shades take theirs from `theme`. CGB colors are
+    /// converted with [`RGBA::from_bgr555`], as the PPU draws them into the RGBA frame
+    pub fn color(&self, index: usize, theme: DmgTheme) -> RGBA {
+        let value = self.pixels[index];
+        match self.format {
+            NativeFormat::Shade => theme.color_from_shade(value as u8),
+            NativeFormat::Bgr555 => RGBA::from_bgr555(value),
+        }
+    }
+
+    fn colors(&self, theme: DmgTheme) -> impl Iterator<Item = RGBA> + '_ {
+        (0..FB_PIXELS).map(move |index| self.color(index, theme))
+    }
+
+    /// Replaces `out` with the frame as RGBA, 4 bytes per pixel like [`super::framebuffer::Framebuffer`]
+    pub fn rgba_into(&self, theme: DmgTheme, out: &mut Vec<u8>) {
+        out.clear();
+        out.extend(
+            self.colors(theme)
+                .flat_map(|color| [color.r(), color.g(), color.b(), color.a()]),
+        );
+    }
+
+    /// Replaces `out` with the frame as RGB565, red in the top bits
+    pub fn rgb565_into(&self, theme: DmgTheme, out: &mut Vec<u16>) {
+        out.clear();
+        out.extend(self.colors(theme).map(|color| {
+            ((color.r() as u16 >> 3) << 11)
+                | ((color.g() as u16 >> 2) << 5)
+                | (color.b() as u16 >> 3)
+        }));
+    }
+
+    /// Replaces `out` with the frame as XRGB8888, the top byte unused
+    pub fn xrgb8888_into(&self, theme: DmgTheme, out: &mut Vec<u32>) {
+        out.clear();
+        out.extend(
+            self.colors(theme)
+                .map(|color| u32::from_be_bytes([0, color.r(), color.g(), color.b()])),
+        );
+    }
+}
@@ -17,3 +17,32 @@ impl TileLine {
         (hi << 1) | lo
     }
 }
+
+/// The CGB attributes of a BG or window tile, in VRAM bank 1 at the tile's tilemap address
+#[derive(Debug, Default, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BgAttributes {
+    /// 1 = Colors 1-3 are drawn above objects, unless LCDC bit 0 is clear
+    pub priority: bool,
+    /// 1 = Tile flipped vertically
+    pub y_flip: bool,
+    /// 1 = Tile flipped horizontally
+    pub x_flip: bool,
+    /// 0 = Fetch tile from VRAM bank 0
+    /// 1 = Fetch tile from VRAM bank 1
+    pub bank: bool,
+    /// 3 bits => CGB palette number (0-7)
+    pub palette: u8,
+}
+
+impl From<u8> for BgAttributes {
+    fn from(value: u8) -> Self {
+        Self {
+            priority: (value & 0b1000_0000) != 0,
+            y_flip: (value & 0b0100_0000) != 0,
+            x_flip: (value & 0b0010_0000) != 0,
+            bank: (value & 0b0000_1000) != 0,
+            palette: (value & 0b0000_0111),
+        }
+    }
+}
@@ -40,28 +40,26 @@ impl RamRng {
 
     pub(crate) fn wram_byte(&mut self, index: usize, model: GbModel) -> u8 {
         let byte = self.next_byte();
-        match model {
-            GbModel::Dmg => {
-                if index & 0x100 != 0 {
-                    byte & self.next_byte()
-                } else {
-                    byte | self.next_byte()
-                }
-            }
-            GbModel::Cgb => byte,
+        if !model.is_dmg() {
+            return byte;
+        }
+
+        if index & 0x100 != 0 {
+            byte & self.next_byte()
+        } else {
+            byte | self.next_byte()
         }
     }
 
     pub(crate) fn hram_byte(&mut self, index: usize, model: GbModel) -> u8 {
-        match model {
-            GbModel::Dmg => {
-                if index & 1 != 0 {
-                    self.next_byte() | self.next_byte() | self.next_byte()
-                } else {
-                    self.next_byte() & self.next_byte() & self.next_byte()
-                }
-            }
-            GbModel::Cgb => self.next_byte(),
+        if !model.is_dmg() {
+            return self.next_byte();
+        }
+
+        if index & 1 != 0 {
+            self.next_byte() | self.next_byte() | self.next_byte()
+        } else {
+            self.next_byte() & self.next_byte() & self.next_byte()
         }
     }
 }
@@ -0,0 +1,51 @@
+/// Lets the components that don't have to run every M-cycle fall behind the CPU. Each one declares
+/// how many M-cycles can go by before its next event: the timer its next TIMA increment or reload
+/// and the next step of the APU's frame sequencer, the PPU the end of a blanking period. The bus
+/// only catches a component up once that slack is used up or when the CPU accesses its registers,
+/// and counts off the M-cycles in between at once. OAM DMA runs only while a transfer is ongoing
+/// and keeps the PPU running every M-cycle meanwhile.
+///
+/// Outside of [`crate::gb::GameBoy`]'s run methods every component is caught up.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Scheduler {
+    /// The timer and the APU's frame sequencer, clocked by DIV
+    pub(crate) timer: Lag,
+    pub(crate) ppu: Lag,
+    /// Runs every component every M-cycle instead, as a baseline for comparisons
+    pub(crate) lock_step: bool,
+}
+
+impl Scheduler {
+    pub fn new(lock_step: bool) -> Self {
+        Self {
+            lock_step,
+            ..Self::default()
+        }
+    }
+
+    pub fn lock_step(&self) -> bool {
+        self.lock_step
+    }
+}
+
+/// How far a component is behind and how far it may fall behind before its next event
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Lag {
+    /// M-cycles the component still has to run
+    pub behind: u32,
+    /// M-cycles it can be left behind
+    pub slack: u32,
+}
+
+impl Lag {
+    /// Counts an M-cycle, true once the component has to catch up
+    pub fn tick(&mut self) -> bool {
+        self.behind += 1;
+        self.behind > self.slack
+    }
+
+    /// Makes the component run the next M-cycle in full, after something changed its schedule
+    pub fn wake(&mut self) {
+        self.slack = self.behind;
+    }
+}
@@ -0,0 +1,64 @@
+use crate::gb::GbModel;
+use crate::{ReadMemory, WriteMemory};
+
+/// M-cycles per frame in normal speed
+pub const FRAME_CYCLES: u32 = 17556;
+/// M-cycles the CPU stalls while switching speeds
+pub const SWITCH_STALL_CYCLES: u16 = 2050;
+
+/// The CGB speed mode, a switch is prepared through KEY1 (0xFF4D) and performed by STOP
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Speed {
+    pub double_speed: bool,
+    pub switch_armed: bool,
+    model: GbModel,
+}
+
+impl Speed {
+    pub fn new(model: GbModel) -> Self {
+        Self {
+            model,
+            ..Default::default()
+        }
+    }
+
+    /// M-cycles per frame, they double in double speed mode since the PPU keeps its pace
+    pub fn frame_cycles(&self) -> u32 {
+        if self.double_speed {
+            FRAME_CYCLES * 2
+        } else {
+            FRAME_CYCLES
+        }
+    }
+
+    /// Performs a prepared speed switch, called when STOP is executed
+    pub fn switch(&mut self) {
+        if self.switch_armed {
+            self.switch_armed = false;
+            self.double_speed = !self.double_speed;
+        }
+    }
+
+    pub fn soft_reset(&mut self) {
+        *self = Self::new(self.model);
+    }
+}
+
+impl ReadMemory for Speed {
+    fn read_naive(&self, addr: u16) -> u8 {
+        if addr != 0xFF4D || !self.model.is_cgb() {
+            return 0xFF;
+        }
+
+        0x7E | ((self.double_speed as u8) << 7) | self.switch_armed as u8
+    }
+}
+
+impl WriteMemory for Speed {
+    fn write_naive(&mut self, addr: u16, value: u8) {
+        if addr == 0xFF4D && self.model.is_cgb() {
+            self.switch_armed = value & 0x01 != 0;
+        }
+    }
+}
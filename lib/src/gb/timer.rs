@@ -1,7 +1,7 @@
 use crate::gb::ic::ICInterface;
 use crate::{ReadMemory, WriteMemory};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Timer {
     pub div: u16,
@@ -46,6 +46,30 @@ impl Timer {
         self.check_falling_edge();
     }
 
+    /// M-cycles that can go by before the next one that changes TIMA or reloads it, during which
+    /// only DIV counts up
+    pub(crate) fn quiet_cycles(&self) -> u32 {
+        if self.overflow_pending || self.is_reloading {
+            return 0;
+        }
+        if !self.timer_enabled() {
+            return u32::MAX;
+        }
+        self.cycles_until_falling_edge(self.tima_bit().trailing_zeros()) - 1
+    }
+
+    /// Counts off quiet M-cycles at once, see [`Timer::quiet_cycles`]
+    pub(crate) fn skip(&mut self, cycles: u32) {
+        self.div = self.div.wrapping_add((cycles as u16).wrapping_mul(4));
+        self.prev_and = self.current_and();
+    }
+
+    /// M-cycles until DIV's `bit` falls, counting the one it falls in
+    pub(crate) fn cycles_until_falling_edge(&self, bit: u32) -> u32 {
+        let period = 2 << bit;
+        (period - (self.div as u32 & (period - 1))).div_ceil(4)
+    }
+
     fn tima_bit(&self) -> u16 {
         match self.tac & 0b11 {
             0b00 => 1 << 9,
@@ -103,11 +127,9 @@ impl WriteMemory for Timer {
                 self.div = 0;
                 self.check_falling_edge();
             }
-            0xFF05 => {
-                if !self.is_reloading {
-                    self.tima = value;
-                    self.overflow_pending = false;
-                }
+            0xFF05 if !self.is_reloading => {
+                self.tima = value;
+                self.overflow_pending = false;
             }
             0xFF06 => {
                 self.tma = value;
@@ -0,0 +1,260 @@
+//! GBS (Game Boy Sound System) files: music ripped from games, played back on the emulated APU.
+//!
+//! A GBS file is a header followed by the sound driver code and data of a game. [`Gbs::rom`] maps
+//! that code at its load address into a minimal synthetic MBC1 cartridge with a tiny driver: it
+//! calls the init routine with the selected track and then the play routine from the VBlank or
+//! timer interrupt, whichever the header asks for. [`GbsPlayer`] runs the result headlessly.
+//!
+//! See https://ocremix.org/info/GBS_Format_Specification
+
+use crate::error::{GbError, GbResult};
+use crate::gb::{GameBoy, GbModel};
+use crate::rom::Rom;
+use std::path::Path;
+
+pub const GBS_HEADER_SIZE: usize = 0x70;
+/// The driver and cartridge header live below this address, the GBS code has to be loaded above
+pub const MIN_LOAD_ADDRESS: u16 = 0x0400;
+
+const ROM_BANK_SIZE: usize = 0x4000;
+const DRIVER_ADDRESS: u16 = 0x0150;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GbsHeader {
+    pub version: u8,
+    pub track_count: u8,
+    /// 1-based like in the file
+    pub first_track: u8,
+    pub load_address: u16,
+    pub init_address: u16,
+    pub play_address: u16,
+    pub stack_pointer: u16,
+    pub timer_modulo: u8,
+    pub timer_control: u8,
+    pub title: String,
+    pub author: String,
+    pub copyright: String,
+}
+
+/// What calls the play routine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayTrigger {
+    /// Once per frame, ~59.7 Hz
+    VBlank,
+    /// On every timer interrupt, with TMA and TAC as given in the header
+    Timer { modulo: u8, control: u8 },
+}
+
+impl GbsHeader {
+    pub fn new(data: &[u8]) -> GbResult<Self> {
+        if data.len() < GBS_HEADER_SIZE {
+            return Err(GbError::RomTooSmall);
+        }
+        if &data[0x00..0x03] != b"GBS" {
+            return Err(GbError::InvalidGbsHeader);
+        }
+
+        let word = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]);
+        let text = |i: usize| {
+            let field = &data[i..i + 0x20];
+            let len = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+            String::from_utf8_lossy(&field[..len]).trim().to_string()
+        };
+
+        let header = Self {
+            version: data[0x03],
+            track_count: data[0x04],
+            first_track: data[0x05],
+            load_address: word(0x06),
+            init_address: word(0x08),
+            play_address: word(0x0A),
+            stack_pointer: word(0x0C),
+            timer_modulo: data[0x0E],
+            timer_control: data[0x0F],
+            title: text(0x10),
+            author: text(0x30),
+            copyright: text(0x50),
+        };
+
+        if header.load_address < MIN_LOAD_ADDRESS || header.load_address >= 0x8000 {
+            return Err(GbError::UnsupportedGbsLoadAddress(header.load_address));
+        }
+
+        Ok(header)
+    }
+
+    pub fn play_trigger(&self) -> PlayTrigger {
+        if self.timer_control & 0x04 != 0 {
+            PlayTrigger::Timer {
+                modulo: self.timer_modulo,
+                control: self.timer_control & 0x07,
+            }
+        } else {
+            PlayTrigger::VBlank
+        }
+    }
+
+    /// Bit 7 of TAC asks for CGB double speed
+    pub fn wants_double_speed(&self) -> bool {
+        self.timer_control & 0x80 != 0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Gbs {
+    pub header: GbsHeader,
+    /// Code and data, mapped starting at the load address
+    pub data: Vec<u8>,
+}
+
+impl Gbs {
+    pub fn new(data: &[u8]) -> GbResult<Self> {
+        let header = GbsHeader::new(data)?;
+        Ok(Self {
+            header,
+            data: data[GBS_HEADER_SIZE..].to_vec(),
+        })
+    }
+
+    pub fn from_file(path: &Path) -> GbResult<Self> {
+        let data = std::fs::read(path)?;
+        Self::new(&data)
+    }
+
+    /// The synthetic cartridge that plays the given 0-based track
+    pub fn rom(&self, track: u8, model: GbModel) -> Rom {
+        let header = &self.header;
+        let end = header.load_address as usize + self.data.len();
+        let banks = end.div_ceil(ROM_BANK_SIZE).max(2).next_power_of_two();
+        let mut image = vec![0xFF; banks * ROM_BANK_SIZE];
+        image[header.load_address as usize..end].copy_from_slice(&self.data);
+
+        // RST vectors are relocated to the load address
+        for vector in (0x00..0x40).step_by(8) {
+            let [lo, hi] = (header.load_address + vector).to_le_bytes();
+            image[vector as usize..vector as usize + 3].copy_from_slice(&[0xC3, lo, hi]);
+        }
+
+        // Interrupt vectors, CALL play then RETI for the trigger, a plain RETI for the others
+        let [play_lo, play_hi] = header.play_address.to_le_bytes();
+        for vector in [0x40, 0x48, 0x50, 0x58, 0x60] {
+            image[vector] = 0xD9;
+        }
+        let play_vector = match header.play_trigger() {
+            PlayTrigger::VBlank => 0x40,
+            PlayTrigger::Timer { .. } => 0x50,
+        };
+        image[play_vector..play_vector + 4].copy_from_slice(&[0xCD, play_lo, play_hi, 0xD9]);
+
+        // Entry point and cartridge header: MBC1 + 8 KiB of RAM
+        let [driver_lo, driver_hi] = DRIVER_ADDRESS.to_le_bytes();
+        image[0x100..0x104].copy_from_slice(&[0x00, 0xC3, driver_lo, driver_hi]);
+        image[0x104..0x150].fill(0x00);
+        let title = header.title.as_bytes();
+        let title_len = title.len().min(15);
+        image[0x134..0x134 + title_len].copy_from_slice(&title[..title_len]);
+        if model.is_cgb() {
+            // A DMG header would lock KEY1 in compatibility mode and the driver's speed switch with it
+            image[0x143] = 0x80;
+        }
+        image[0x147] = 0x02;
+        image[0x148] = banks.trailing_zeros() as u8 - 1;
+        image[0x149] = 0x02;
+        image[0x14D] = image[0x134..0x14D]
+            .iter()
+            .fold(0u8, |sum, &b| sum.wrapping_sub(b).wrapping_sub(1));
+
+        let driver = self.driver(track, model);
+        let driver_start = DRIVER_ADDRESS as usize;
+        image[driver_start..driver_start + driver.len()].copy_from_slice(&driver);
+
+        Rom::new(&image)
+    }
+
+    fn driver(&self, track: u8, model: GbModel) -> Vec<u8> {
+        let header = &self.header;
+        let [sp_lo, sp_hi] = header.stack_pointer.to_le_bytes();
+        let [init_lo, init_hi] = header.init_address.to_le_bytes();
+        let (tma, tac, ie) = match header.play_trigger() {
+            PlayTrigger::VBlank => (0x00, 0x00, 0x01),
+            PlayTrigger::Timer { modulo, control } => (modulo, control, 0x04),
+        };
+
+        let mut code = vec![
+            0x31, sp_lo, sp_hi, // LD SP, stack pointer
+            0x3E, 0x0A, 0xEA, 0x00, 0x00, // enable cartridge RAM
+            0x3E, 0x80, 0xE0, 0x26, // NR52, sound on
+            0x3E, 0xF3, 0xE0, 0x25, // NR51, post-boot panning
+            0x3E, 0x77, 0xE0, 0x24, // NR50, full volume
+        ];
+        if header.wants_double_speed() && model.is_cgb() {
+            code.extend([0x3E, 0x01, 0xE0, 0x4D, 0x10, 0x00]); // KEY1 = 1, STOP
+        }
+        code.extend([
+            0x3E, tma, 0xE0, 0x06, // TMA
+            0x3E, tac, 0xE0, 0x07, // TAC
+            0x3E, ie, 0xE0, 0xFF, // IE
+            0xAF, 0xE0, 0x0F, // IF = 0
+            0x3E, track, // A = track
+            0xCD, init_lo, init_hi, // CALL init
+            0xFB, 0x76, 0x18, 0xFD, // EI, HALT, JR back to the HALT
+        ]);
+        code
+    }
+}
+
+/// Plays a GBS file without a frontend, audio ends up in the interleaved stereo sample buffer of
+/// the APU like for a regular game.
+pub struct GbsPlayer {
+    pub gbs: Gbs,
+    pub gb: GameBoy,
+    track: u8,
+}
+
+impl GbsPlayer {
+    /// Starts playing the first track named in the header
+    pub fn new(gbs: Gbs, model: GbModel) -> GbResult<Self> {
+        let first_track = gbs.header.first_track.saturating_sub(1);
+        let mut player = Self {
+            gbs,
+            gb: GameBoy::new_empty(model),
+            track: 0,
+        };
+        player.select_track(first_track)?;
+        Ok(player)
+    }
+
+    pub fn track_count(&self) -> u8 {
+        self.gbs.header.track_count
+    }
+
+    /// The 0-based track currently playing
+    pub fn track(&self) -> u8 {
+        self.track
+    }
+
+    /// Restarts the machine and plays the given 0-based track
+    pub fn select_track(&mut self, track: u8) -> GbResult<()> {
+        if track >= self.track_count() {
+            return Err(GbError::GbsTrackOutOfRange(track));
+        }
+
+        self.gb.load_rom(&self.gbs.rom(track, self.gb.model))?;
+        self.track = track;
+        Ok(())
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.gb.apu.set_sample_rate(sample_rate);
+    }
+
+    /// Runs one frame worth of cycles, the samples pile up until [`GbsPlayer::take_samples`]
+    pub fn run_frame(&mut self) {
+        self.gb.run_frame();
+    }
+
+    /// Interleaved stereo samples produced since the last call
+    pub fn take_samples(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.gb.apu.audio_buffer)
+    }
+}
@@ -1,8 +1,11 @@
+pub mod audio_capture;
 #[cfg(feature = "debug")]
 mod debug;
 pub mod disassembly;
+pub mod env;
 pub mod error;
 pub mod gb;
+pub mod gbs;
 pub mod instructions;
 #[cfg(feature = "persistence")]
 pub mod persistence;
@@ -12,6 +15,9 @@ pub mod rom;
 #[cfg(test)]
 mod tests;
 pub mod utils;
+pub mod vgm;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
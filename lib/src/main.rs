@@ -0,0 +1,469 @@
+//! `citrine-gb`: runs a ROM headlessly until a frame or cycle limit or a stop condition, then
+//! writes the screenshot, audio, serial output and state dumps that were asked for.
+//!
+//! Exits with 0 when a stop condition was met (or none was given), 2 when the limit ran out first
+//! and 1 on errors, so CI jobs can smoke-test builds with it.
+
+use citrine_gb::audio_capture::AudioCapture;
+use citrine_gb::gb::boot_rom::BootRom;
+use citrine_gb::gb::{GameBoy, GbModel};
+use citrine_gb::persistence::bess::BESS_MAGIC;
+use citrine_gb::recording::{InputEvent, Recording};
+use citrine_gb::rom::Rom;
+use clap::{Parser, ValueEnum};
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const LD_B_B: u8 = 0x40;
+const DEFAULT_FRAMES: u64 = 600;
+
+#[derive(Parser, Debug)]
+#[command(name = "citrine-gb", version, about = "Run a Game Boy ROM headlessly")]
+struct Args {
+    /// Path to the ROM to run.
+    rom: PathBuf,
+
+    /// Model to emulate. A recording or save state brings its own.
+    #[arg(long, value_enum, default_value_t = ModelArg::Dmg)]
+    model: ModelArg,
+
+    /// Path to a boot ROM image. Defaults to Citrine's bundled boot ROM.
+    #[arg(long, conflicts_with = "skip_boot")]
+    boot_rom: Option<PathBuf>,
+
+    /// Start in the post-boot state instead of running a boot ROM.
+    #[arg(long, default_value_t = false)]
+    skip_boot: bool,
+
+    /// Path to an input recording JSON to replay. Its cycles count from the start of the run.
+    #[arg(long)]
+    recording: Option<PathBuf>,
+
+    /// Path to a Citrine snapshot or BESS save state to start from instead of power-on.
+    #[arg(long, conflicts_with_all = ["boot_rom", "skip_boot"])]
+    state: Option<PathBuf>,
+
+    /// Stop after this many frames. Defaults to 600 unless --cycles is given.
+    #[arg(long)]
+    frames: Option<u64>,
+
+    /// Stop after this many T-cycles, counted like recordings count them.
+    #[arg(long)]
+    cycles: Option<u64>,
+
+    /// Stop as soon as the serial output contains this string.
+    #[arg(long)]
+    until_serial: Option<String>,
+
+    /// Stop when the CPU reaches LD B,B, the result marker of the mooneye test suite.
+    #[arg(long, default_value_t = false)]
+    until_ld_b_b: bool,
+
+    /// Stop when the CPU reaches the instruction at this address, in hex.
+    #[arg(long, value_parser = parse_address)]
+    until_pc: Option<u16>,
+
+    /// Write the last frame to this path as PNG.
+    #[arg(long)]
+    screenshot: Option<PathBuf>,
+
+    /// Write the audio of the whole run to this path as WAV.
+    #[arg(long)]
+    audio: Option<PathBuf>,
+
+    /// Sample rate of the --audio output.
+    #[arg(long, default_value_t = 48_000)]
+    sample_rate: u32,
+
+    /// Write the serial output to this path, `-` for stdout.
+    #[arg(long)]
+    serial: Option<PathBuf>,
+
+    /// Write a Citrine snapshot of the final state to this path.
+    #[arg(long)]
+    dump_state: Option<PathBuf>,
+
+    /// Write a BESS save state of the final state to this path.
+    #[arg(long)]
+    dump_bess: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ModelArg {
+    Dmg,
+    Mgb,
+    Sgb2,
+    Cgb,
+    CgbE,
+    Agb,
+}
+
+impl From<ModelArg> for GbModel {
+    fn from(value: ModelArg) -> Self {
+        match value {
+            ModelArg::Dmg => GbModel::Dmg,
+            ModelArg::Mgb => GbModel::Mgb,
+            ModelArg::Sgb2 => GbModel::Sgb2,
+            ModelArg::Cgb => GbModel::Cgb,
+            ModelArg::CgbE => GbModel::CgbE,
+            ModelArg::Agb => GbModel::Agb,
+        }
+    }
+}
+
+/// Why the run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stop {
+    Serial,
+    LdBB,
+    Pc(u16),
+    FrameLimit,
+    CycleLimit,
+}
+
+impl Stop {
+    fn is_limit(self) -> bool {
+        matches!(self, Stop::FrameLimit | Stop::CycleLimit)
+    }
+}
+
+impl Display for Stop {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stop::Serial => write!(f, "serial output matched"),
+            Stop::LdBB => write!(f, "reached LD B,B"),
+            Stop::Pc(pc) => write!(f, "reached PC {pc:04X}"),
+            Stop::FrameLimit => write!(f, "frame limit"),
+            Stop::CycleLimit => write!(f, "cycle limit"),
+        }
+    }
+}
+
+struct Run {
+    gb: GameBoy,
+    events: std::iter::Peekable<std::vec::IntoIter<InputEvent>>,
+    audio: Option<AudioCapture>,
+    frames: u64,
+    t_cycles: u64,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let result = run(&args);
+    if let Err(err) = &result {
+        eprintln!("error: {err}");
+    }
+    ExitCode::from(exit_code(&args, &result))
+}
+
+/// 0 when a stop condition was met or none was given, 2 when the limit ran out first, 1 on errors
+fn exit_code(args: &Args, result: &Result<Stop, String>) -> u8 {
+    match result {
+        Ok(stop) if stop.is_limit() && has_condition(args) => 2,
+        Ok(_) => 0,
+        Err(_) => 1,
+    }
+}
+
+fn run(args: &Args) -> Result<Stop, String> {
+    let rom = Rom::new(&read(&args.rom)?);
+    let rom_sha256 = rom.header().map_err(|e| e.to_string())?.sha256_hex_string();
+
+    let recording = match &args.recording {
+        Some(path) => {
+            let json = std::fs::read_to_string(path)
+                .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+            let mut recording = Recording::from_json(&json)
+                .map_err(|e| format!("failed to parse recording {}: {e}", path.display()))?;
+            if !recording.rom_sha256.is_empty() && recording.rom_sha256 != rom_sha256 {
+                eprintln!("warning: the recording was made with another ROM");
+            }
+            recording.sort();
+            Some(recording)
+        }
+        None => None,
+    };
+
+    let mut gb = match &args.state {
+        Some(path) => {
+            let state = read(path)?;
+            let gb = if state.ends_with(&BESS_MAGIC) {
+                GameBoy::from_bess(&state, &rom)
+            } else {
+                GameBoy::from_dump(&state, &rom)
+            }
+            .map_err(|e| format!("failed to load state {}: {e}", path.display()))?;
+            if let Some(recording) = &recording
+                && recording.model != gb.model
+            {
+                return Err(format!(
+                    "the state was taken on {}, the recording on {}",
+                    gb.model, recording.model
+                ));
+            }
+            gb
+        }
+        None => {
+            let model = recording
+                .as_ref()
+                .map_or(args.model.into(), |recording| recording.model);
+            let mut gb = GameBoy::new_empty(model);
+            gb.set_boot_rom(match &args.boot_rom {
+                Some(path) => BootRom::Custom(read(path)?),
+                None if args.skip_boot => BootRom::Skip,
+                None => BootRom::Bundled,
+            });
+            gb.load_rom(&rom).map_err(|e| e.to_string())?;
+            gb
+        }
+    };
+    gb.start_serial_logging();
+
+    let audio = args.audio.as_ref().map(|_| {
+        let capture = AudioCapture::new(args.sample_rate, false);
+        capture.attach(&mut gb);
+        capture
+    });
+    gb.apu.suppress_output = audio.is_none();
+
+    let mut run = Run {
+        gb,
+        events: recording
+            .map(|recording| recording.events)
+            .unwrap_or_default()
+            .into_iter()
+            .peekable(),
+        audio,
+        frames: 0,
+        t_cycles: 0,
+    };
+    let stop = run_until_stop(&mut run, args);
+
+    eprintln!(
+        "stopped after {} frames ({} T-cycles): {stop}",
+        run.frames, run.t_cycles
+    );
+    if stop == Stop::LdBB {
+        let cpu = &run.gb.cpu;
+        eprintln!(
+            "B/C/D/E/H/L = {:02X?}",
+            [cpu.b, cpu.c, cpu.d, cpu.e, cpu.h, cpu.l]
+        );
+    }
+
+    write_outputs(&mut run, args)?;
+    Ok(stop)
+}
+
+fn run_until_stop(run: &mut Run, args: &Args) -> Stop {
+    let frame_limit = args
+        .frames
+        .or(args.cycles.is_none().then_some(DEFAULT_FRAMES));
+    let start = run.gb.debugger.total_cycles;
+    let mut serial_checked = 0;
+
+    loop {
+        run.t_cycles = (run.gb.debugger.total_cycles - start) as u64 * 4;
+        while let Some(event) = run.events.next_if(|event| event.cycle <= run.t_cycles) {
+            let button = event.button.to_joypad_state();
+            if event.pressed {
+                run.gb.press_button(button);
+            } else {
+                run.gb.release_button(button);
+            }
+        }
+        if args.cycles.is_some_and(|limit| run.t_cycles >= limit) {
+            return Stop::CycleLimit;
+        }
+
+        if run.gb.step_frame() {
+            collect_audio(run);
+            run.frames += 1;
+            if frame_limit.is_some_and(|limit| run.frames >= limit) {
+                return Stop::FrameLimit;
+            }
+        }
+
+        if args.until_ld_b_b && run.gb.cpu.ir == LD_B_B {
+            return Stop::LdBB;
+        }
+        if let Some(pc) = args.until_pc
+            && run.gb.cpu.pc.wrapping_sub(1) == pc
+        {
+            return Stop::Pc(pc);
+        }
+        if let Some(needle) = &args.until_serial {
+            let log = run.gb.serial_log.as_deref().unwrap_or_default();
+            if log.len() != serial_checked {
+                serial_checked = log.len();
+                if contains(log, needle.as_bytes()) {
+                    return Stop::Serial;
+                }
+            }
+        }
+    }
+}
+
+fn collect_audio(run: &mut Run) {
+    if let Some(audio) = &mut run.audio {
+        audio.collect(&mut run.gb);
+    }
+}
+
+fn write_outputs(run: &mut Run, args: &Args) -> Result<(), String> {
+    if let Some(path) = &args.screenshot {
+        write(path, &run.gb.frame().render_png())?;
+    }
+
+    run.gb.apu.flush_audio();
+    collect_audio(run);
+    if let (Some(path), Some(audio)) = (&args.audio, &run.audio) {
+        write(path, &audio.mix_wav())?;
+    }
+
+    let serial = run.gb.serial_log.as_deref().unwrap_or_default();
+    match &args.serial {
+        Some(path) if path.as_os_str() == "-" => std::io::stdout()
+            .write_all(serial)
+            .map_err(|e| format!("failed to write the serial output: {e}"))?,
+        Some(path) => write(path, serial)?,
+        None => {}
+    }
+
+    if let Some(path) = &args.dump_state {
+        let state = run.gb.dump_full().map_err(|e| e.to_string())?;
+        write(path, &state)?;
+    }
+    if let Some(path) = &args.dump_bess {
+        write(path, &run.gb.dump_bess())?;
+    }
+    Ok(())
+}
+
+fn has_condition(args: &Args) -> bool {
+    args.until_serial.is_some() || args.until_ld_b_b || args.until_pc.is_some()
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    needle.is_empty()
+        || haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+}
+
+fn parse_address(s: &str) -> Result<u16, String> {
+    let hex = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix('$'))
+        .unwrap_or(s);
+    u16::from_str_radix(hex, 16).map_err(|e| format!("'{s}' is not a hex address: {e}"))
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("failed to read {}: {e}", path.display()))
+}
+
+fn write(path: &Path, data: &[u8]) -> Result<(), String> {
+    std::fs::write(path, data).map_err(|e| format!("failed to write {}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sends "Passed" over serial on the internal clock, then spins
+    fn passing_rom() -> Vec<u8> {
+        let mut code: Vec<u8> = b"Passed"
+            .iter()
+            .flat_map(|&c| [0x3E, c, 0xE0, 0x01, 0x3E, 0x81, 0xE0, 0x02])
+            .collect();
+        code.extend([0x18, 0xFE]);
+
+        let mut data = vec![0x00; 0x8000];
+        data[0x100..0x100 + code.len()].copy_from_slice(&code);
+        data
+    }
+
+    fn args(rom: &Path, options: &[&str]) -> Args {
+        let rom = rom.to_str().unwrap();
+        Args::try_parse_from(["citrine-gb", rom].iter().chain(options)).unwrap()
+    }
+
+    #[test]
+    fn addresses_are_hex_with_or_without_a_prefix() {
+        assert_eq!(parse_address("0x0150"), Ok(0x0150));
+        assert_eq!(parse_address("$C000"), Ok(0xC000));
+        assert_eq!(parse_address("ff80"), Ok(0xFF80));
+        assert!(parse_address("0x10000").is_err());
+        assert!(parse_address("C0G0").is_err());
+        assert!(parse_address("").is_err());
+    }
+
+    #[test]
+    fn serial_matches_anywhere_in_the_log() {
+        assert!(contains(b"cpu_instrs\n\nPassed all tests\n", b"Passed"));
+        assert!(contains(b"Passed", b"Passed"));
+        assert!(contains(b"anything", b""));
+        assert!(!contains(b"Pass", b"Passed"));
+        assert!(!contains(b"Failed #3", b"Passed"));
+    }
+
+    #[test]
+    fn exit_codes_tell_conditions_limits_and_errors_apart() {
+        let rom = Path::new("game.gb");
+        let conditional = args(rom, &["--until-ld-b-b"]);
+        let unconditional = args(rom, &[]);
+
+        assert_eq!(exit_code(&conditional, &Ok(Stop::LdBB)), 0);
+        assert_eq!(exit_code(&conditional, &Ok(Stop::FrameLimit)), 2);
+        assert_eq!(exit_code(&conditional, &Ok(Stop::CycleLimit)), 2);
+        assert_eq!(
+            exit_code(&unconditional, &Ok(Stop::FrameLimit)),
+            0,
+            "a limit is the goal without a condition"
+        );
+        assert_eq!(exit_code(&conditional, &Err("failed".to_string())), 1);
+        assert_eq!(exit_code(&unconditional, &Err("failed".to_string())), 1);
+    }
+
+    #[test]
+    fn runs_until_the_serial_output_matches() {
+        let dir = std::env::temp_dir().join(format!("citrine-cli-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let rom = dir.join("passing.gb");
+        let serial = dir.join("serial.txt");
+        std::fs::write(&rom, passing_rom()).unwrap();
+        let serial_path = serial.to_str().unwrap();
+
+        let passing = args(
+            &rom,
+            &[
+                "--skip-boot",
+                "--until-serial",
+                "Passed",
+                "--serial",
+                serial_path,
+            ],
+        );
+        let result = run(&passing);
+        assert_eq!(result, Ok(Stop::Serial));
+        assert_eq!(exit_code(&passing, &result), 0);
+        assert_eq!(std::fs::read(&serial).unwrap(), b"Passed");
+
+        let failing = args(
+            &rom,
+            &["--skip-boot", "--until-serial", "Failed", "--frames", "5"],
+        );
+        let result = run(&failing);
+        assert_eq!(result, Ok(Stop::FrameLimit));
+        assert_eq!(exit_code(&failing, &result), 2);
+
+        let missing = args(&dir.join("missing.gb"), &["--until-serial", "Passed"]);
+        let result = run(&missing);
+        assert!(result.is_err());
+        assert_eq!(exit_code(&missing, &result), 1);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -1,2 +1,7 @@
+pub mod bess;
 pub mod full_dump;
+mod migration;
+pub mod rewind;
+pub mod sav;
+pub mod snapshot;
 pub mod sram_dump;
@@ -0,0 +1,457 @@
+//! [BESS](https://github.com/LIJI32/SameBoy/blob/master/BESS.md), the Best Effort Save State
+//! format other emulators like SameBoy read and write. Where [`crate::persistence::snapshot`] keeps
+//! every detail of the machine, BESS only keeps what any emulator has: the CPU, the IO registers,
+//! the memories and the MBC registers. Whatever sits in between, like the position within the
+//! current scanline or the sweep of a sound channel, starts over on import.
+//!
+//! The memories come first, followed by a chain of blocks (`NAME`, `INFO`, `CORE`, `MBC `,
+//! `END `) and an 8 byte footer holding the offset of the first block and the magic `BESS`.
+
+use crate::error::{GbError, GbResult};
+use crate::gb::memory::WRAM_BANK_SIZE;
+use crate::gb::timer::Timer;
+use crate::gb::{GameBoy, GbModel};
+use crate::persistence::snapshot::Incompatibility;
+use crate::persistence::sram_dump::SramDump;
+use crate::{ReadMemory, WriteMemory};
+
+pub const BESS_MAGIC: [u8; 4] = *b"BESS";
+
+const CORE_MAJOR_VERSION: u16 = 1;
+const CORE_MINOR_VERSION: u16 = 1;
+const CORE_SIZE: usize = 0xD0;
+const INFO_SIZE: usize = 0x12;
+
+// Offsets into the CORE block
+const CORE_MODEL: usize = 0x04;
+const CORE_PC: usize = 0x08;
+const CORE_IME: usize = 0x14;
+const CORE_IE: usize = 0x15;
+const CORE_EXECUTION_STATE: usize = 0x16;
+const CORE_IO: usize = 0x18;
+const CORE_RAM: usize = 0x98;
+const CORE_VRAM: usize = 0xA0;
+const CORE_MBC_RAM: usize = 0xA8;
+const CORE_OAM: usize = 0xB0;
+const CORE_HRAM: usize = 0xB8;
+const CORE_BG_PALETTES: usize = 0xC0;
+const CORE_OBJ_PALETTES: usize = 0xC8;
+
+const RUNNING: u8 = 0;
+const HALTED: u8 = 1;
+const STOPPED: u8 = 2;
+
+/// Writes the state of `gb` as BESS
+pub fn export(gb: &GameBoy) -> Vec<u8> {
+    let cgb = gb.model.is_cgb();
+    let mut data = Vec::new();
+    let mut core = vec![0u8; CORE_SIZE];
+
+    core[0x00..0x02].copy_from_slice(&CORE_MAJOR_VERSION.to_le_bytes());
+    core[0x02..0x04].copy_from_slice(&CORE_MINOR_VERSION.to_le_bytes());
+    core[CORE_MODEL..CORE_MODEL + 4].copy_from_slice(model_code(gb.model));
+
+    // The CPU has already fetched the opcode at PC - 1
+    let cpu = &gb.cpu;
+    let pc = if cpu.halt_bug {
+        cpu.pc
+    } else {
+        cpu.pc.wrapping_sub(1)
+    };
+    let registers = [
+        pc,
+        u16::from_be_bytes([cpu.a, cpu.f.into()]),
+        u16::from_be_bytes([cpu.b, cpu.c]),
+        u16::from_be_bytes([cpu.d, cpu.e]),
+        u16::from_be_bytes([cpu.h, cpu.l]),
+        cpu.sp,
+    ];
+    for (index, register) in registers.iter().enumerate() {
+        let at = CORE_PC + index * 2;
+        core[at..at + 2].copy_from_slice(&register.to_le_bytes());
+    }
+    core[CORE_IME] = cpu.ime as u8;
+    core[CORE_IE] = gb.ic.enable;
+    core[CORE_EXECUTION_STATE] = if cpu.stopped {
+        STOPPED
+    } else if cpu.halted {
+        HALTED
+    } else {
+        RUNNING
+    };
+    for (offset, byte) in core[CORE_IO..CORE_IO + 0x80].iter_mut().enumerate() {
+        *byte = io_register(gb, 0xFF00 + offset as u16);
+    }
+
+    // CGB states carry all 8 WRAM banks, the ones Citrine doesn't have stay empty
+    let mut wram = gb.memory.wram.concat();
+    wram.resize(if cgb { 8 } else { 2 } * WRAM_BANK_SIZE, 0);
+    let vram_banks = if cgb { 2 } else { 1 };
+    let mut cartridge_ram = gb.cartridge.ram_dump().as_slice().to_vec();
+    cartridge_ram.truncate(gb.cartridge.ram_size());
+
+    let buffers: [(usize, &[u8]); 7] = [
+        (CORE_RAM, &wram),
+        (CORE_VRAM, gb.ppu.vram[..vram_banks].as_flattened()),
+        (CORE_MBC_RAM, &cartridge_ram),
+        (CORE_OAM, &gb.ppu.oam),
+        (CORE_HRAM, &gb.memory.hram),
+        (
+            CORE_BG_PALETTES,
+            if cgb { &gb.ppu.bg_palette_ram[..] } else { &[] },
+        ),
+        (
+            CORE_OBJ_PALETTES,
+            if cgb {
+                &gb.ppu.obj_palette_ram[..]
+            } else {
+                &[]
+            },
+        ),
+    ];
+    for (at, buffer) in buffers {
+        core[at..at + 4].copy_from_slice(&(buffer.len() as u32).to_le_bytes());
+        core[at + 4..at + 8].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        data.extend_from_slice(buffer);
+    }
+
+    let first_block = data.len() as u32;
+
+    write_block(
+        &mut data,
+        b"NAME",
+        format!("Citrine v{}", crate::VERSION).as_bytes(),
+    );
+
+    let bank0 = gb.cartridge.rom_bank0();
+    let mut info = [0u8; INFO_SIZE];
+    info[..0x10].copy_from_slice(&bank0[0x134..0x144]);
+    info[0x10..].copy_from_slice(&bank0[0x14E..0x150]);
+    write_block(&mut data, b"INFO", &info);
+
+    write_block(&mut data, b"CORE", &core);
+
+    let mbc: Vec<u8> = gb
+        .cartridge
+        .mbc_register_writes()
+        .into_iter()
+        .flat_map(|(addr, value)| {
+            let [low, high] = addr.to_le_bytes();
+            [low, high, value]
+        })
+        .collect();
+    if !mbc.is_empty() {
+        write_block(&mut data, b"MBC ", &mbc);
+    }
+
+    write_block(&mut data, b"END ", &[]);
+
+    data.extend_from_slice(&first_block.to_le_bytes());
+    data.extend_from_slice(&BESS_MAGIC);
+    data
+}
+
+/// The model a BESS state was taken with
+pub fn model(data: &[u8]) -> GbResult<GbModel> {
+    let core = find_block(&blocks(data)?, b"CORE").ok_or(GbError::InvalidBess("no CORE block"))?;
+    read_core_model(core)
+}
+
+/// Puts a BESS state into `gb`, which has to have the state's ROM loaded and be of its model
+pub fn import(gb: &mut GameBoy, data: &[u8]) -> GbResult<()> {
+    let blocks = blocks(data)?;
+    let core = find_block(&blocks, b"CORE").ok_or(GbError::InvalidBess("no CORE block"))?;
+    if core.len() < CORE_SIZE {
+        return Err(GbError::InvalidBess("CORE block too short"));
+    }
+    if u16::from_le_bytes([core[0x00], core[0x01]]) != CORE_MAJOR_VERSION {
+        return Err(GbError::InvalidBess("unsupported CORE version"));
+    }
+    if read_core_model(core)? != gb.model {
+        return Err(GbError::InvalidBess("taken with a different model"));
+    }
+
+    if let Some(info) = find_block(&blocks, b"INFO") {
+        let bank0 = gb.cartridge.rom_bank0();
+        if info.len() < INFO_SIZE || info[0x10..INFO_SIZE] != bank0[0x14E..0x150] {
+            return Err(GbError::IncompatibleSnapshot(Incompatibility::RomMismatch));
+        }
+    }
+
+    let io = &core[CORE_IO..CORE_IO + 0x80];
+    let buffer = |at: usize| -> GbResult<&[u8]> {
+        let size = u32::from_le_bytes(core[at..at + 4].try_into().unwrap()) as usize;
+        let offset = u32::from_le_bytes(core[at + 4..at + 8].try_into().unwrap()) as usize;
+        data.get(offset..offset + size)
+            .ok_or(GbError::InvalidBess("buffer out of bounds"))
+    };
+
+    import_memories(gb, io, buffer(CORE_RAM)?, buffer(CORE_HRAM)?);
+    import_ppu(
+        gb,
+        io,
+        buffer(CORE_VRAM)?,
+        buffer(CORE_OAM)?,
+        buffer(CORE_BG_PALETTES)?,
+        buffer(CORE_OBJ_PALETTES)?,
+    );
+    import_apu(gb, io);
+
+    let mut cartridge_ram = buffer(CORE_MBC_RAM)?.to_vec();
+    cartridge_ram.resize(gb.cartridge.ram_size(), 0xFF);
+    if !cartridge_ram.is_empty() {
+        gb.cartridge
+            .put_ram_dump(&SramDump::from_slice(&cartridge_ram));
+    }
+    if let Some(mbc) = find_block(&blocks, b"MBC ") {
+        for write in mbc.chunks_exact(3) {
+            let addr = u16::from_le_bytes([write[0], write[1]]);
+            if addr < 0x8000 {
+                gb.cartridge.write_naive(addr, write[2]);
+            }
+        }
+    }
+
+    gb.joypad.write_naive(0xFF00, io[0x00]);
+    gb.memory.write_naive(0xFF01, io[0x01]);
+    gb.memory.write_naive(0xFF02, io[0x02]);
+
+    // A fresh timer has no falling edge pending that setting TAC could trip
+    gb.timer = Timer::new();
+    gb.timer.div = (io[0x04] as u16) << 8;
+    gb.timer.tima = io[0x05];
+    gb.timer.tma = io[0x06];
+    gb.timer.write_naive(0xFF07, io[0x07] & 0x07);
+
+    gb.ic.flag = io[0x0F].into();
+    gb.ic.enable = core[CORE_IE];
+    gb.dma.active = false;
+    gb.dma.source = io[0x46];
+    gb.boot_rom.mounted = gb.boot_rom.is_present() && io[0x50] & 0x01 == 0;
+    if gb.model.is_cgb() {
+        gb.speed.double_speed = io[0x4D] & 0x80 != 0;
+        gb.speed.switch_armed = io[0x4D] & 0x01 != 0;
+    }
+
+    let register = |at: usize| u16::from_le_bytes([core[at], core[at + 1]]);
+    let pc = register(CORE_PC);
+    let [a, f] = register(CORE_PC + 2).to_be_bytes();
+    let [b, c] = register(CORE_PC + 4).to_be_bytes();
+    let [d, e] = register(CORE_PC + 6).to_be_bytes();
+    let [h, l] = register(CORE_PC + 8).to_be_bytes();
+    let cpu = &mut gb.cpu;
+    (cpu.a, cpu.f, cpu.b, cpu.c) = (a, f.into(), b, c);
+    (cpu.d, cpu.e, cpu.h, cpu.l) = (d, e, h, l);
+    cpu.sp = register(CORE_PC + 10);
+    cpu.ime = core[CORE_IME] != 0;
+    cpu.ime_next = cpu.ime;
+    cpu.halted = core[CORE_EXECUTION_STATE] == HALTED;
+    cpu.stopped = core[CORE_EXECUTION_STATE] == STOPPED;
+    cpu.halt_bug = false;
+    cpu.speed_switch_stall = 0;
+    cpu.invalid_opcode = false;
+    // Fetch the opcode ahead like the CPU does at the end of every instruction
+    gb.cpu.ir = code_byte(gb, pc);
+    gb.cpu.pc = pc.wrapping_add(1);
+
+    Ok(())
+}
+
+fn import_memories(gb: &mut GameBoy, io: &[u8], wram: &[u8], hram: &[u8]) {
+    // Only bank 1 of the switchable CGB banks has a place here, take the selected one
+    let selected = if gb.model.is_cgb() {
+        (io[0x70] & 0x07).max(1) as usize
+    } else {
+        1
+    };
+    for (bank, source) in [(0, 0), (1, selected)] {
+        let start = source * WRAM_BANK_SIZE;
+        if let Some(source) = wram.get(start..start + WRAM_BANK_SIZE) {
+            gb.memory.wram[bank].copy_from_slice(source);
+        }
+    }
+
+    let len = hram.len().min(gb.memory.hram.len());
+    gb.memory.hram[..len].copy_from_slice(&hram[..len]);
+}
+
+fn import_ppu(
+    gb: &mut GameBoy,
+    io: &[u8],
+    vram: &[u8],
+    oam: &[u8],
+    bg_palettes: &[u8],
+    obj_palettes: &[u8],
+) {
+    let ppu = &mut gb.ppu;
+    ppu.dmg_compat = gb.model.is_cgb() && io[0x4C] & 0x04 != 0;
+    for (bank, source) in ppu.vram.iter_mut().zip(vram.chunks_exact(0x2000)) {
+        bank.copy_from_slice(source);
+    }
+    let len = oam.len().min(ppu.oam.len());
+    ppu.oam[..len].copy_from_slice(&oam[..len]);
+    if bg_palettes.len() == 0x40 {
+        ppu.bg_palette_ram.copy_from_slice(bg_palettes);
+    }
+    if obj_palettes.len() == 0x40 {
+        ppu.obj_palette_ram.copy_from_slice(obj_palettes);
+    }
+
+    // Writing LCDC would turn the LCD on or off, and the palette data ports would auto-increment
+    ppu.lcdc = io[0x40].into();
+    ppu.ly = io[0x44];
+    for addr in (0xFF41..=0xFF4B).chain([0xFF4F, 0xFF51, 0xFF52, 0xFF53, 0xFF54, 0xFF55]) {
+        if addr != 0xFF44 && addr != 0xFF46 {
+            ppu.write_naive(addr, io[(addr - 0xFF00) as usize]);
+        }
+    }
+    if gb.model.is_cgb() {
+        for addr in [0xFF68, 0xFF6A, 0xFF6C] {
+            ppu.write_naive(addr, io[(addr - 0xFF00) as usize]);
+        }
+    }
+    ppu.restart_line();
+}
+
+fn import_apu(gb: &mut GameBoy, io: &[u8]) {
+    let apu = &mut gb.apu;
+    apu.write_naive(0xFF26, 0x00);
+    apu.write_naive(0xFF26, io[0x26] & 0x80);
+    if io[0x26] & 0x80 == 0 {
+        apu.ch3.wave_ram.copy_from_slice(&io[0x30..0x40]);
+        return;
+    }
+
+    for addr in (0xFF10..=0xFF25).filter(|addr| !matches!(addr, 0xFF15 | 0xFF1F)) {
+        let value = io[(addr - 0xFF00) as usize];
+        let value = match addr {
+            0xFF14 | 0xFF19 | 0xFF1E | 0xFF23 => value & 0x7F,
+            _ => value,
+        };
+        apu.write_naive(addr, value);
+    }
+
+    // Channels that were playing start over
+    let triggers = [
+        (0x01, 0xFF14),
+        (0x02, 0xFF19),
+        (0x04, 0xFF1E),
+        (0x08, 0xFF23),
+    ];
+    for (active, addr) in triggers {
+        if io[0x26] & active != 0 {
+            apu.write_naive(addr, io[(addr - 0xFF00) as usize] | 0x80);
+        }
+    }
+    apu.ch3.wave_ram.copy_from_slice(&io[0x30..0x40]);
+}
+
+/// An IO register the way BESS stores it: as read, except for write-only bits and the registers of
+/// the DMA and boot ROM
+fn io_register(gb: &GameBoy, addr: u16) -> u8 {
+    match addr {
+        0xFF00 => gb.joypad.read_naive(addr),
+        0xFF01 | 0xFF02 => gb.memory.read_naive(addr),
+        0xFF04..=0xFF07 => gb.timer.read_naive(addr),
+        0xFF0F => gb.ic.flag.into(),
+        0xFF10..=0xFF3F => gb.apu.written_register(addr),
+        0xFF46 => gb.dma.source,
+        // KEY0 reads 0xFF, SameBoy keeps the value written to it
+        0xFF4C if gb.model.is_cgb() => {
+            if gb.ppu.dmg_compat {
+                0x04
+            } else {
+                0x80
+            }
+        }
+        0xFF4D if !gb.ppu.dmg_compat => gb.speed.read_naive(addr),
+        0xFF40..=0xFF45 | 0xFF47..=0xFF4B | 0xFF4F | 0xFF51..=0xFF55 | 0xFF68..=0xFF6C => {
+            gb.ppu.read_naive(addr)
+        }
+        0xFF50 if gb.boot_rom.mounted => 0xFE,
+        // WRAM bank 1 is the only switchable bank
+        0xFF70 if gb.model.is_cgb() => 0xF9,
+        0xFF76 | 0xFF77 => gb.apu.read_naive(addr),
+        _ => 0xFF,
+    }
+}
+
+/// The byte the CPU fetches at `addr`
+fn code_byte(gb: &GameBoy, addr: u16) -> u8 {
+    if gb.boot_rom.maps(addr) {
+        return gb.boot_rom.rom[addr as usize];
+    }
+    match addr {
+        0x0000..=0x7FFF | 0xA000..=0xBFFF => gb.cartridge.read_naive(addr),
+        0x8000..=0x9FFF | 0xFE00..=0xFE9F => gb.ppu.read_naive(addr),
+        _ => gb.memory.read_naive(addr),
+    }
+}
+
+fn model_code(model: GbModel) -> &'static [u8; 4] {
+    match model {
+        GbModel::Dmg => b"GDB ",
+        GbModel::Mgb => b"GM  ",
+        GbModel::Sgb2 => b"S2  ",
+        GbModel::Cgb => b"CCD ",
+        GbModel::CgbE => b"CCE ",
+        GbModel::Agb => b"CA  ",
+    }
+}
+
+fn read_core_model(core: &[u8]) -> GbResult<GbModel> {
+    let code = core
+        .get(CORE_MODEL..CORE_MODEL + 4)
+        .ok_or(GbError::InvalidBess("CORE block too short"))?;
+    match code {
+        [b'G', b'M', ..] => Ok(GbModel::Mgb),
+        [b'G', ..] => Ok(GbModel::Dmg),
+        [b'S', ..] => Ok(GbModel::Sgb2),
+        [b'C', b'A', ..] => Ok(GbModel::Agb),
+        [b'C', _, b'E', ..] => Ok(GbModel::CgbE),
+        [b'C', ..] => Ok(GbModel::Cgb),
+        _ => Err(GbError::InvalidBess("unknown model")),
+    }
+}
+
+fn write_block(data: &mut Vec<u8>, id: &[u8; 4], content: &[u8]) {
+    data.extend_from_slice(id);
+    data.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    data.extend_from_slice(content);
+}
+
+/// The blocks up to `END `, by their ids
+fn blocks(data: &[u8]) -> GbResult<Vec<([u8; 4], &[u8])>> {
+    if data.len() < 8 || !data.ends_with(&BESS_MAGIC) {
+        return Err(GbError::InvalidBess("no BESS footer"));
+    }
+    let footer = data.len() - 8;
+    let mut position = u32::from_le_bytes(data[footer..footer + 4].try_into().unwrap()) as usize;
+
+    let mut blocks = Vec::new();
+    loop {
+        let header = data
+            .get(position..position + 8)
+            .filter(|_| position + 8 <= footer)
+            .ok_or(GbError::InvalidBess("truncated block"))?;
+        let id: [u8; 4] = header[..4].try_into().unwrap();
+        let len = u32::from_le_bytes(header[4..].try_into().unwrap()) as usize;
+        let content = data
+            .get(position + 8..position + 8 + len)
+            .filter(|_| position + 8 + len <= footer)
+            .ok_or(GbError::InvalidBess("truncated block"))?;
+
+        if &id == b"END " {
+            return Ok(blocks);
+        }
+        blocks.push((id, content));
+        position += 8 + len;
+    }
+}
+
+fn find_block<'a>(blocks: &[([u8; 4], &'a [u8])], id: &[u8; 4]) -> Option<&'a [u8]> {
+    blocks
+        .iter()
+        .find(|(block_id, _)| block_id == id)
+        .map(|(_, content)| *content)
+}
@@ -0,0 +1,231 @@
+//! Upgrades the machine state of older snapshots. The state is decoded into a [`Value`] tree, every
+//! migration from the snapshot's format version up edits it in place, and the result is encoded
+//! again for the current [`crate::persistence::full_dump::FullDump`] to read.
+//!
+//! A change to a serialized struct that old snapshots can't be read with bumps
+//! [`SNAPSHOT_FORMAT_VERSION`] and appends a migration to [`MIGRATIONS`].
+
+use crate::error::{GbError, GbResult};
+use crate::persistence::snapshot::{Incompatibility, SNAPSHOT_FORMAT_VERSION};
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::Formatter;
+
+type Migration = fn(&mut Value) -> Result<(), Incompatibility>;
+
+/// `MIGRATIONS[n]` upgrades format version `n` to `n + 1`
+const MIGRATIONS: [Migration; SNAPSHOT_FORMAT_VERSION as usize] = [v0_to_v1, v1_to_v2, v2_to_v3];
+
+/// Upgrades the MessagePack encoded state of format version `from` to the current one
+pub(crate) fn migrate(state: &[u8], from: u16) -> GbResult<Vec<u8>> {
+    let mut value: Value = rmp_serde::from_slice(state)?;
+    for migration in &MIGRATIONS[from as usize..] {
+        migration(&mut value).map_err(GbError::IncompatibleSnapshot)?;
+    }
+    Ok(rmp_serde::to_vec_named(&value)?)
+}
+
+/// 0.6.0, before snapshots had a header. The APU and the speed switch learned about the model
+fn v0_to_v1(state: &mut Value) -> Result<(), Incompatibility> {
+    let gb = state.field("gb")?;
+    let model = gb.field("model")?.clone();
+
+    let apu = gb.field("apu")?;
+    apu.insert("model", model.clone());
+    apu.field("ch3")?
+        .insert("ticks_since_fetch", Value::UInt(0));
+
+    gb.insert(
+        "speed",
+        Value::Map(vec![
+            (Value::from("double_speed"), Value::Bool(false)),
+            (Value::from("switch_armed"), Value::Bool(false)),
+            (Value::from("model"), model),
+        ]),
+    );
+    Ok(())
+}
+
+/// Before a CGB learned DMG compatibility mode, it ran every cartridge as CGB software
+fn v1_to_v2(state: &mut Value) -> Result<(), Incompatibility> {
+    state
+        .field("gb")?
+        .field("ppu")?
+        .insert("dmg_compat", Value::Bool(false));
+    Ok(())
+}
+
+/// Before the CGB pixel fetcher read BG attributes, every tile used palette 0 from VRAM bank 0
+fn v2_to_v3(state: &mut Value) -> Result<(), Incompatibility> {
+    let flag = |name: &str| (Value::from(name), Value::Bool(false));
+    state.field("gb")?.field("ppu")?.field("fetcher")?.insert(
+        "attributes",
+        Value::Map(vec![
+            flag("priority"),
+            flag("y_flip"),
+            flag("x_flip"),
+            flag("bank"),
+            (Value::from("palette"), Value::UInt(0)),
+        ]),
+    );
+    Ok(())
+}
+
+/// Any self-describing serde value, MessagePack's data model
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    F32(f32),
+    F64(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    Array(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+}
+
+impl Value {
+    /// The value of a struct field
+    fn field(&mut self, name: &str) -> Result<&mut Value, Incompatibility> {
+        self.get_mut(name)
+            .ok_or_else(|| Incompatibility::MissingField(name.to_string()))
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut Value> {
+        let Value::Map(entries) = self else {
+            return None;
+        };
+        entries
+            .iter_mut()
+            .find(|(key, _)| matches!(key, Value::String(key) if key == name))
+            .map(|(_, value)| value)
+    }
+
+    /// Adds a struct field unless it's already there
+    fn insert(&mut self, name: &str, value: Value) {
+        if self.get_mut(name).is_some() {
+            return;
+        }
+        if let Value::Map(entries) = self {
+            entries.push((Value::from(name), value));
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Nil => serializer.serialize_unit(),
+            Value::Bool(v) => serializer.serialize_bool(*v),
+            Value::Int(v) => serializer.serialize_i64(*v),
+            Value::UInt(v) => serializer.serialize_u64(*v),
+            Value::F32(v) => serializer.serialize_f32(*v),
+            Value::F64(v) => serializer.serialize_f64(*v),
+            Value::String(v) => serializer.serialize_str(v),
+            Value::Bytes(v) => serializer.serialize_bytes(v),
+            Value::Array(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Value::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("any MessagePack value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Int(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::UInt(v))
+    }
+
+    fn visit_f32<E>(self, v: f32) -> Result<Value, E> {
+        Ok(Value::F32(v))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::F64(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Value, E> {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Value, E> {
+        Ok(Value::Bytes(v))
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Nil)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Nil)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(Value::Map(entries))
+    }
+}
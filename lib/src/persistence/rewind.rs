@@ -0,0 +1,267 @@
+//! In-memory rewind: a ring of recent states, cheap enough to capture every few frames.
+//!
+//! States are serialized with bincode, its fixed-width integers and lengths make two of them line
+//! up byte for byte as long as no collection changed its length. Every
+//! [`RewindConfig::keyframe_interval`]th state is a keyframe, the ones in between are stored as the
+//! XOR against their keyframe. Both are zero-run encoded: a keyframe is mostly empty RAM, a delta
+//! mostly unchanged bytes. When the ring outgrows its memory budget the oldest keyframe is dropped
+//! together with its deltas.
+
+use crate::error::{GbError, GbResult};
+use crate::gb::GameBoy;
+use crate::persistence::sram_dump::SramDump;
+use std::collections::VecDeque;
+
+/// Zero bytes in a row it takes to end a literal run, shorter gaps are cheaper as literals
+const MIN_ZERO_RUN: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RewindConfig {
+    /// Frames between two captured states
+    pub interval: u32,
+    /// Captured states per keyframe, including the keyframe
+    pub keyframe_interval: u32,
+    /// Upper bound of the encoded states in bytes, the newest keyframe is kept regardless
+    pub memory_budget: usize,
+}
+
+impl Default for RewindConfig {
+    fn default() -> Self {
+        Self {
+            interval: 2,
+            keyframe_interval: 30,
+            memory_budget: 32 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct RewindStateRef<'a> {
+    gb: &'a GameBoy,
+    ram: SramDump,
+}
+
+#[derive(serde::Deserialize)]
+struct RewindState {
+    gb: GameBoy,
+    ram: SramDump,
+}
+
+/// A keyframe and the states captured after it
+struct Segment {
+    keyframe: Vec<u8>,
+    deltas: Vec<Vec<u8>>,
+}
+
+impl Segment {
+    fn size(&self) -> usize {
+        self.keyframe.len() + self.deltas.iter().map(Vec::len).sum::<usize>()
+    }
+}
+
+pub struct RewindBuffer {
+    pub config: RewindConfig,
+    segments: VecDeque<Segment>,
+    /// The decoded keyframe of the newest segment
+    keyframe: Vec<u8>,
+    scratch: Vec<u8>,
+    frames_since_capture: u32,
+    memory_used: usize,
+}
+
+impl RewindBuffer {
+    pub fn new(config: RewindConfig) -> Self {
+        Self {
+            config,
+            segments: VecDeque::new(),
+            keyframe: vec![],
+            scratch: vec![],
+            frames_since_capture: 0,
+            memory_used: 0,
+        }
+    }
+
+    /// Call after every emulated frame, captures a state every [`RewindConfig::interval`] frames
+    pub fn frame_finished(&mut self, gb: &GameBoy) -> GbResult<()> {
+        self.frames_since_capture += 1;
+        if self.frames_since_capture >= self.config.interval {
+            self.capture(gb)?;
+        }
+        Ok(())
+    }
+
+    pub fn capture(&mut self, gb: &GameBoy) -> GbResult<()> {
+        self.frames_since_capture = 0;
+        let state = RewindStateRef {
+            gb,
+            ram: gb.cartridge.ram_dump(),
+        };
+        self.scratch.clear();
+        bincode::serialize_into(&mut self.scratch, &state)?;
+
+        let needs_keyframe = match self.segments.back() {
+            None => true,
+            Some(segment) => {
+                segment.deltas.len() + 1 >= self.config.keyframe_interval as usize
+                    || self.scratch.len() != self.keyframe.len()
+            }
+        };
+
+        if needs_keyframe {
+            let mut keyframe = vec![];
+            encode_delta(&[], &self.scratch, &mut keyframe);
+            self.memory_used += keyframe.len();
+            self.segments.push_back(Segment {
+                keyframe,
+                deltas: vec![],
+            });
+            std::mem::swap(&mut self.keyframe, &mut self.scratch);
+        } else if let Some(segment) = self.segments.back_mut() {
+            let mut delta = vec![];
+            encode_delta(&self.keyframe, &self.scratch, &mut delta);
+            self.memory_used += delta.len();
+            segment.deltas.push(delta);
+        }
+
+        while self.memory_used > self.config.memory_budget && self.segments.len() > 1 {
+            if let Some(segment) = self.segments.pop_front() {
+                self.memory_used -= segment.size();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Restores the newest state and drops it from the buffer, `false` if there is none left
+    pub fn rewind(&mut self, gb: &mut GameBoy) -> GbResult<bool> {
+        let Some(segment) = self.segments.back_mut() else {
+            return Ok(false);
+        };
+
+        if let Some(delta) = segment.deltas.pop() {
+            self.memory_used -= delta.len();
+            decode_delta(&self.keyframe, &delta, &mut self.scratch)?;
+        } else if let Some(segment) = self.segments.pop_back() {
+            self.memory_used -= segment.keyframe.len();
+            std::mem::swap(&mut self.keyframe, &mut self.scratch);
+            match self.segments.back() {
+                Some(previous) => decode_delta(&[], &previous.keyframe, &mut self.keyframe)?,
+                None => self.keyframe.clear(),
+            }
+        }
+
+        let state: RewindState = bincode::deserialize(&self.scratch)?;
+        gb.restore_state(state.gb);
+        gb.cartridge.put_ram_dump(&state.ram);
+        self.frames_since_capture = 0;
+        Ok(true)
+    }
+
+    pub fn clear(&mut self) {
+        self.segments.clear();
+        self.keyframe.clear();
+        self.frames_since_capture = 0;
+        self.memory_used = 0;
+    }
+
+    /// Number of states held
+    pub fn len(&self) -> usize {
+        self.segments
+            .iter()
+            .map(|segment| segment.deltas.len() + 1)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// How far back the buffer reaches, in frames
+    pub fn frames(&self) -> usize {
+        self.len() * self.config.interval as usize
+    }
+
+    /// Bytes taken by the encoded states
+    pub fn memory_used(&self) -> usize {
+        self.memory_used
+    }
+}
+
+impl Default for RewindBuffer {
+    fn default() -> Self {
+        Self::new(RewindConfig::default())
+    }
+}
+
+/// Encodes the XOR of `state` against `base`, which counts as zero past its end: the length of
+/// `state`, then alternating runs of a zero count and a literal count followed by the literal
+/// bytes, all counts in LEB128
+fn encode_delta(base: &[u8], state: &[u8], out: &mut Vec<u8>) {
+    let xor = |i: usize| state[i] ^ base.get(i).copied().unwrap_or(0);
+    let is_zero_run = |i: usize| (i..(i + MIN_ZERO_RUN).min(state.len())).all(|j| xor(j) == 0);
+
+    write_leb128(out, state.len());
+    let mut i = 0;
+    while i < state.len() {
+        let zeros_start = i;
+        while i < state.len() && xor(i) == 0 {
+            i += 1;
+        }
+
+        let literal_start = i;
+        while i < state.len() && !is_zero_run(i) {
+            i += 1;
+        }
+
+        write_leb128(out, literal_start - zeros_start);
+        write_leb128(out, i - literal_start);
+        out.extend((literal_start..i).map(xor));
+    }
+}
+
+fn decode_delta(base: &[u8], delta: &[u8], out: &mut Vec<u8>) -> GbResult<()> {
+    let base_at = |i: usize| base.get(i).copied().unwrap_or(0);
+    let mut input = delta.iter().copied();
+    let len = read_leb128(&mut input)?;
+
+    out.clear();
+    out.reserve(len);
+    while out.len() < len {
+        let zeros = read_leb128(&mut input)?;
+        let literals = read_leb128(&mut input)?;
+        if out.len() + zeros + literals > len {
+            return Err(GbError::RewindStateCorrupted);
+        }
+
+        for _ in 0..zeros {
+            out.push(base_at(out.len()));
+        }
+        for _ in 0..literals {
+            let byte = input.next().ok_or(GbError::RewindStateCorrupted)?;
+            out.push(base_at(out.len()) ^ byte);
+        }
+    }
+
+    Ok(())
+}
+
+fn write_leb128(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_leb128(input: &mut impl Iterator<Item = u8>) -> GbResult<usize> {
+    let mut value = 0;
+    for shift in (0..usize::BITS).step_by(7) {
+        let byte = input.next().ok_or(GbError::RewindStateCorrupted)?;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(GbError::RewindStateCorrupted)
+}
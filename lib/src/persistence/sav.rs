@@ -0,0 +1,152 @@
+//! Battery saves in the `.sav` layouts other emulators and flash carts read and write, told apart
+//! by their size:
+//!
+//! - The raw cartridge RAM. Flash carts often pad it to a larger size, the rest is dropped
+//! - The RAM followed by the 48 byte RTC footer of VBA-M and BGB, or its older 44 byte variant
+//!   with a 32-bit timestamp, for MBC3 cartridges with a clock
+//! - MBC2's 512 half-bytes, one per byte with the upper nibble left undefined
+//!
+//! Citrine doesn't emulate the RTC yet, the footer is kept as it was so the clock carries over when
+//! the save goes back to an emulator that does.
+
+use crate::error::{GbError, GbResult};
+use crate::gb::cartridge::Cartridge;
+use crate::persistence::sram_dump::SramDump;
+
+pub const RTC_FOOTER_SIZE: usize = 48;
+/// The footer of older VBA versions, whose timestamp is only 32 bits wide
+pub const RTC_FOOTER_SIZE_32BIT: usize = 44;
+
+const MBC2_RAM_SIZE: usize = 512;
+
+/// What a cartridge's `.sav` holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SavLayout {
+    /// Bytes of cartridge RAM, 512 for MBC2
+    pub ram_size: usize,
+    pub mbc2: bool,
+    pub rtc: bool,
+}
+
+impl SavLayout {
+    pub fn of(cartridge: &Cartridge) -> Self {
+        let cartridge_type = cartridge.header.cartridge_type;
+        let mbc2 = cartridge_type.is_some_and(|ct| ct.is_mbc2());
+        Self {
+            ram_size: if mbc2 {
+                MBC2_RAM_SIZE
+            } else {
+                cartridge.header.ram_size_bytes()
+            },
+            mbc2,
+            rtc: cartridge_type.is_some_and(|ct| ct.has_rtc()),
+        }
+    }
+}
+
+/// The MBC3 clock registers the way VBA-M and BGB save them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RtcFooter {
+    /// Seconds, minutes, hours, lower 8 bits of the day counter, and the day counter's upper bit
+    /// together with the halt and carry flags
+    pub registers: [u8; 5],
+    /// The same registers as last latched
+    pub latched: [u8; 5],
+    /// Unix time in seconds when the save was written, emulators advance the clock from there
+    pub timestamp: u64,
+}
+
+impl RtcFooter {
+    /// A clock standing at zero, as written at `timestamp`
+    pub fn new(timestamp: u64) -> Self {
+        Self {
+            timestamp,
+            ..Self::default()
+        }
+    }
+
+    /// Reads a footer of [`RTC_FOOTER_SIZE`] or [`RTC_FOOTER_SIZE_32BIT`] bytes
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        if data.len() != RTC_FOOTER_SIZE && data.len() != RTC_FOOTER_SIZE_32BIT {
+            return None;
+        }
+
+        let field = |index: usize| data[index * 4];
+        let mut footer = Self::default();
+        for index in 0..5 {
+            footer.registers[index] = field(index);
+            footer.latched[index] = field(index + 5);
+        }
+
+        let mut timestamp = [0; 8];
+        let timestamp_size = data.len() - 40;
+        timestamp[..timestamp_size].copy_from_slice(&data[40..]);
+        footer.timestamp = u64::from_le_bytes(timestamp);
+        Some(footer)
+    }
+
+    pub fn to_bytes(&self) -> [u8; RTC_FOOTER_SIZE] {
+        let mut data = [0; RTC_FOOTER_SIZE];
+        for (index, value) in self.registers.iter().chain(&self.latched).enumerate() {
+            data[index * 4] = *value;
+        }
+        data[40..].copy_from_slice(&self.timestamp.to_le_bytes());
+        data
+    }
+}
+
+/// A battery save as other emulators exchange it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavFile {
+    pub ram: Vec<u8>,
+    pub rtc: Option<RtcFooter>,
+}
+
+impl SavFile {
+    /// The RAM of `dump` cut to the size of `layout`, the footer is only kept for cartridges with
+    /// a clock
+    pub fn new(dump: &SramDump, layout: SavLayout, rtc: Option<RtcFooter>) -> Self {
+        let mut ram = dump.as_slice().to_vec();
+        ram.resize(layout.ram_size, 0xFF);
+        Self {
+            ram,
+            rtc: rtc.filter(|_| layout.rtc),
+        }
+    }
+
+    /// Reads a `.sav` of any of the known layouts for a cartridge with the given `layout`
+    pub fn parse(data: &[u8], layout: SavLayout) -> GbResult<Self> {
+        if data.is_empty() && layout.ram_size > 0 {
+            return Err(GbError::EmptySaveFile);
+        }
+
+        let (ram, rtc) = match data.len().checked_sub(layout.ram_size) {
+            Some(RTC_FOOTER_SIZE | RTC_FOOTER_SIZE_32BIT) => {
+                let (ram, footer) = data.split_at(layout.ram_size);
+                (ram, RtcFooter::from_bytes(footer))
+            }
+            Some(_) => (&data[..layout.ram_size], None),
+            // Some emulators leave out what the game never touches
+            None => (data, None),
+        };
+
+        let mut ram = ram.to_vec();
+        ram.resize(layout.ram_size, 0xFF);
+        if layout.mbc2 {
+            ram.iter_mut().for_each(|byte| *byte |= 0xF0);
+        }
+        Ok(Self { ram, rtc })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = self.ram.clone();
+        if let Some(rtc) = &self.rtc {
+            data.extend_from_slice(&rtc.to_bytes());
+        }
+        data
+    }
+
+    pub fn sram_dump(&self) -> SramDump {
+        SramDump::from_slice(&self.ram)
+    }
+}
@@ -0,0 +1,223 @@
+//! The save state file: a header that identifies the snapshot, followed by the machine state as a
+//! (possibly compressed) MessagePack [`FullDump`].
+//!
+//! | Offset | Size | Content                                          |
+//! |--------|------|--------------------------------------------------|
+//! | 0x00   | 8    | [`SNAPSHOT_MAGIC`]                               |
+//! | 0x08   | 2    | Format version, little endian                    |
+//! | 0x0A   | 1    | [`SnapshotCompression`]                          |
+//! | 0x0B   | 1    | [`GbModel`]                                      |
+//! | 0x0C   | 32   | SHA-256 of the ROM                               |
+//! | 0x2C   | 1    | Length of the emulator version                   |
+//! | 0x2D   | n    | Emulator version that wrote the snapshot, UTF-8  |
+//!
+//! Snapshots without the magic were written by 0.6.0 and count as format version 0.
+
+use crate::error::{GbError, GbResult};
+use crate::gb::{GameBoy, GbModel};
+use crate::persistence::full_dump::{FullDump, FullDumpRef};
+use crate::persistence::migration;
+use crate::rom::Rom;
+
+pub const SNAPSHOT_MAGIC: [u8; 8] = *b"CITRINE\x1A";
+/// Bumped whenever a change to the machine state needs a migration, see
+/// [`crate::persistence::migration`]
+pub const SNAPSHOT_FORMAT_VERSION: u16 = 3;
+
+const FIXED_HEADER_SIZE: usize = 0x2D;
+
+/// Why a snapshot can't be loaded
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Incompatibility {
+    #[error("written by a newer version ({emulator_version}, format {format_version})")]
+    NewerFormat {
+        format_version: u16,
+        emulator_version: String,
+    },
+    #[error("taken with a different ROM")]
+    RomMismatch,
+    #[error("compression {0} is not supported by this build")]
+    UnsupportedCompression(u8),
+    #[error("unknown model {0}")]
+    UnknownModel(u8),
+    #[error("truncated header")]
+    TruncatedHeader,
+    #[error("the state has no field `{0}` to migrate")]
+    MissingField(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SnapshotCompression {
+    None = 0,
+    Brotli = 1,
+}
+
+impl SnapshotCompression {
+    /// The best this build can write
+    pub fn preferred() -> Self {
+        if cfg!(feature = "brotli") {
+            Self::Brotli
+        } else {
+            Self::None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotHeader {
+    pub format_version: u16,
+    pub compression: SnapshotCompression,
+    pub model: GbModel,
+    pub rom_sha256: [u8; 32],
+    pub emulator_version: String,
+}
+
+impl SnapshotHeader {
+    pub fn new(gb: &GameBoy) -> Self {
+        Self {
+            format_version: SNAPSHOT_FORMAT_VERSION,
+            compression: SnapshotCompression::preferred(),
+            model: gb.model,
+            rom_sha256: gb.cartridge.header.sha256,
+            emulator_version: crate::VERSION.to_string(),
+        }
+    }
+
+    /// The header and the state that follows it, `None` for a snapshot without header
+    pub fn read(data: &[u8]) -> GbResult<Option<(Self, &[u8])>> {
+        if !data.starts_with(&SNAPSHOT_MAGIC) {
+            return Ok(None);
+        }
+        if data.len() < FIXED_HEADER_SIZE {
+            return Err(GbError::IncompatibleSnapshot(
+                Incompatibility::TruncatedHeader,
+            ));
+        }
+
+        let format_version = u16::from_le_bytes([data[0x08], data[0x09]]);
+        let version_end = FIXED_HEADER_SIZE + data[0x2C] as usize;
+        let Some(emulator_version) = data.get(FIXED_HEADER_SIZE..version_end) else {
+            return Err(GbError::IncompatibleSnapshot(
+                Incompatibility::TruncatedHeader,
+            ));
+        };
+        let emulator_version = String::from_utf8_lossy(emulator_version).to_string();
+
+        // Anything past the version may mean something else in a newer format
+        if format_version > SNAPSHOT_FORMAT_VERSION {
+            return Err(GbError::IncompatibleSnapshot(
+                Incompatibility::NewerFormat {
+                    format_version,
+                    emulator_version,
+                },
+            ));
+        }
+
+        let compression = match data[0x0A] {
+            0 => SnapshotCompression::None,
+            1 => SnapshotCompression::Brotli,
+            other => {
+                return Err(GbError::IncompatibleSnapshot(
+                    Incompatibility::UnsupportedCompression(other),
+                ));
+            }
+        };
+        let model = GbModel::ALL
+            .iter()
+            .copied()
+            .find(|model| *model as u8 == data[0x0B])
+            .ok_or(GbError::IncompatibleSnapshot(
+                Incompatibility::UnknownModel(data[0x0B]),
+            ))?;
+
+        let mut rom_sha256 = [0; 32];
+        rom_sha256.copy_from_slice(&data[0x0C..0x2C]);
+
+        let header = Self {
+            format_version,
+            compression,
+            model,
+            rom_sha256,
+            emulator_version,
+        };
+        Ok(Some((header, &data[version_end..])))
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        let version = self.emulator_version.as_bytes();
+        let version = &version[..version.len().min(u8::MAX as usize)];
+
+        out.extend_from_slice(&SNAPSHOT_MAGIC);
+        out.extend_from_slice(&self.format_version.to_le_bytes());
+        out.push(self.compression as u8);
+        out.push(self.model as u8);
+        out.extend_from_slice(&self.rom_sha256);
+        out.push(version.len() as u8);
+        out.extend_from_slice(version);
+    }
+}
+
+pub fn encode(dump: &FullDumpRef) -> GbResult<Vec<u8>> {
+    let header = SnapshotHeader::new(dump.gb);
+    let mut data = Vec::new();
+    header.write(&mut data);
+    data.extend(compress(&dump.to_rmp()?, header.compression)?);
+    Ok(data)
+}
+
+/// Reads a snapshot of any format version taken with `rom`
+pub fn decode(data: &[u8], rom: &Rom) -> GbResult<FullDump> {
+    let Some((header, state)) = SnapshotHeader::read(data)? else {
+        let state = decompress(data, SnapshotCompression::preferred())?;
+        return FullDump::from_rmp(&migration::migrate(&state, 0)?);
+    };
+
+    if header.rom_sha256 != rom.header()?.sha256 {
+        return Err(GbError::IncompatibleSnapshot(Incompatibility::RomMismatch));
+    }
+
+    let state = decompress(state, header.compression)?;
+    if header.format_version < SNAPSHOT_FORMAT_VERSION {
+        FullDump::from_rmp(&migration::migrate(&state, header.format_version)?)
+    } else {
+        FullDump::from_rmp(&state)
+    }
+}
+
+fn compress(state: &[u8], compression: SnapshotCompression) -> GbResult<Vec<u8>> {
+    match compression {
+        SnapshotCompression::None => Ok(state.to_vec()),
+        #[cfg(feature = "brotli")]
+        SnapshotCompression::Brotli => {
+            use std::io::Write;
+            let mut compressed = Vec::new();
+            {
+                let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, 11, 22);
+                writer.write_all(state)?;
+            }
+            Ok(compressed)
+        }
+        #[cfg(not(feature = "brotli"))]
+        SnapshotCompression::Brotli => Err(GbError::IncompatibleSnapshot(
+            Incompatibility::UnsupportedCompression(compression as u8),
+        )),
+    }
+}
+
+fn decompress(data: &[u8], compression: SnapshotCompression) -> GbResult<Vec<u8>> {
+    match compression {
+        SnapshotCompression::None => Ok(data.to_vec()),
+        #[cfg(feature = "brotli")]
+        SnapshotCompression::Brotli => {
+            let mut decompressed = Vec::new();
+            let mut decompressor = brotli::Decompressor::new(data, 4096);
+            std::io::Read::read_to_end(&mut decompressor, &mut decompressed)?;
+            Ok(decompressed)
+        }
+        #[cfg(not(feature = "brotli"))]
+        SnapshotCompression::Brotli => Err(GbError::IncompatibleSnapshot(
+            Incompatibility::UnsupportedCompression(compression as u8),
+        )),
+    }
+}
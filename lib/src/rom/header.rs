@@ -9,7 +9,7 @@ pub const NINTENDO_LOGO: [u8; 48] = [
     0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
 ];
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RomHeader {
     pub title: String,
@@ -453,7 +453,7 @@ impl RomHeader {
         crc32fast::hash(data)
     }
 
-    #[cfg(feature = "sha2")]
+    #[cfg(feature = "crc32fast")]
     pub fn crc32_hex_string(&self) -> String {
         format!("{:08X}", self.crc32)
     }
@@ -464,7 +464,7 @@ impl RomHeader {
         sha1::Sha1::digest(data).into()
     }
 
-    #[cfg(feature = "sha2")]
+    #[cfg(feature = "sha1")]
     pub fn sha1_hex_string(&self) -> String {
         self.sha1.iter().map(|b| format!("{:02X}", b)).collect()
     }
@@ -837,12 +837,22 @@ impl RomCartridgeType {
             self,
             Self::Mbc1RamBattery
                 | Self::Mbc2Battery
+                | Self::Mbc3TimerRamBattery
                 | Self::Mbc3RamBattery
                 | Self::Mbc5RamBattery
                 | Self::Mbc7SensorRumbleRamBattery
                 | Self::HuC1RamBattery
         )
     }
+
+    /// Whether the cartridge has a real-time clock
+    pub fn has_rtc(&self) -> bool {
+        matches!(self, Self::Mbc3TimerBattery | Self::Mbc3TimerRamBattery)
+    }
+
+    pub fn is_mbc2(&self) -> bool {
+        matches!(self, Self::Mbc2 | Self::Mbc2Battery)
+    }
 }
 
 impl std::fmt::Display for RomCartridgeType {
@@ -1,12 +1,63 @@
 use crate::gb::bus::CpuBusInterface;
 use crate::gb::ic::{ICInterface, Interrupt};
+use crate::gb::{GameBoy, GbModel};
+use crate::rom::Rom;
 use std::collections::HashMap;
 
+mod apu;
+mod audio_capture;
+#[cfg(feature = "persistence")]
+mod bess;
+mod boot_rom;
 mod cpu;
 mod e2e;
+mod env;
+mod gbs;
 mod halt;
+mod machine_state;
+mod models;
+mod native_frame;
+mod oam_bug;
+mod ppu_access;
+#[cfg(feature = "persistence")]
+mod rewind;
+#[cfg(feature = "persistence")]
+mod sav;
+mod scheduler;
+mod serial;
 #[cfg(feature = "persistence")]
 mod snapshot;
+mod stop;
+mod vgm;
+
+/// Instructions a test may step through before it gives up waiting
+pub const MAX_STEPS: usize = 100_000;
+
+/// A 32 KiB cartridge without MBC that runs `code` from the entry point, `cgb_flag` goes to 0x143
+pub fn rom_with(code: &[u8], cgb_flag: u8) -> Vec<u8> {
+    let mut data = vec![0x00; 0x8000];
+    data[0x100..0x100 + code.len()].copy_from_slice(code);
+    data[0x143] = cgb_flag;
+    data
+}
+
+/// A machine without boot ROM that starts `rom` in the post-boot state
+pub fn boot(model: GbModel, rom: &[u8]) -> GameBoy {
+    let mut gb = GameBoy::new_empty(model);
+    gb.load_rom(&Rom::new(rom)).unwrap();
+    gb
+}
+
+/// Steps one instruction at a time until `condition` holds, at most [`MAX_STEPS`] times
+pub fn step_until(gb: &mut GameBoy, condition: impl Fn(&GameBoy) -> bool) {
+    for _ in 0..MAX_STEPS {
+        if condition(gb) {
+            return;
+        }
+        gb.step();
+    }
+    panic!("condition never met");
+}
 
 #[derive(Debug, Default, Eq, PartialEq)]
 pub struct TestBus {
@@ -0,0 +1,210 @@
+use crate::gb::GbModel;
+use crate::gb::apu::{Apu, charge_factor};
+use crate::tests::{boot, rom_with};
+use crate::{ReadMemory, WriteMemory};
+
+/// Starts a square, the noise and the wave channel, then keeps flipping the panning of every
+/// channel, about 17 times per frame
+const TONES: [u8; 67] = [
+    // NR52, NR51, NR50, NR11, NR12, NR13, NR14
+    0x3E, 0x80, 0xE0, 0x26, 0x3E, 0xFF, 0xE0, 0x25, 0x3E, 0x77, 0xE0, 0x24, 0x3E, 0x80, 0xE0, 0x11,
+    0x3E, 0xF3, 0xE0, 0x12, 0x3E, 0x00, 0xE0, 0x13, 0x3E, 0x87, 0xE0, 0x14,
+    // NR42, NR43, NR44, NR30, NR32, NR34
+    0x3E, 0xF1, 0xE0, 0x21, 0x3E, 0x55, 0xE0, 0x22, 0x3E, 0x80, 0xE0, 0x23, 0x3E, 0x80, 0xE0, 0x1A,
+    0x3E, 0x20, 0xE0, 0x1C, 0x3E, 0x86, 0xE0, 0x1E,
+    // LD C,0xFF; LD B,0; DEC B; JR NZ,-3; LD A,C; XOR 0x0F; LD C,A; LDH [NR51],A; JR -13
+    0x0E, 0xFF, 0x06, 0x00, 0x05, 0x20, 0xFD, 0x79, 0xEE, 0x0F, 0x4F, 0xE0, 0x25, 0x18, 0xF3,
+];
+
+fn powered_on(model: GbModel) -> Apu {
+    let mut apu = Apu::new(model);
+    apu.write_naive(0xFF26, 0x80);
+    apu
+}
+
+/// Channel 3 playing wave RAM 0x00, 0x11, .., 0xFF at full volume
+fn playing_wave(model: GbModel) -> Apu {
+    let mut apu = powered_on(model);
+    for (i, addr) in (0xFF30..=0xFF3F).enumerate() {
+        apu.write_naive(addr, i as u8 * 0x11);
+    }
+    apu.write_naive(0xFF1A, 0x80);
+    apu.write_naive(0xFF1C, 0x20);
+    apu.write_naive(0xFF1E, 0x80);
+    apu
+}
+
+#[test]
+fn pcm_registers_show_the_digital_outputs_on_cgb() {
+    let apu = playing_wave(GbModel::Dmg);
+    assert_eq!(apu.read_naive(0xFF76), 0xFF);
+    assert_eq!(apu.read_naive(0xFF77), 0xFF);
+
+    let mut apu = playing_wave(GbModel::Cgb);
+    assert_eq!(apu.read_naive(0xFF76), 0x00);
+    assert_eq!(apu.read_naive(0xFF77), apu.ch3.sample());
+
+    apu.ch3.wave_step = 5;
+    assert_eq!(apu.read_naive(0xFF77), 0x02);
+    apu.write_naive(0xFF1C, 0x40);
+    assert_eq!(apu.read_naive(0xFF77), 0x01);
+}
+
+#[test]
+fn nr52_reports_the_live_channel_status() {
+    let mut apu = playing_wave(GbModel::Dmg);
+    assert_eq!(apu.read_naive(0xFF26), 0xF4);
+
+    apu.write_naive(0xFF26, 0x8F);
+    assert_eq!(apu.read_naive(0xFF26), 0xF4);
+
+    apu.write_naive(0xFF1A, 0x00);
+    assert_eq!(apu.read_naive(0xFF26), 0xF0);
+}
+
+#[test]
+fn power_off_clears_registers_and_blocks_writes() {
+    for model in [GbModel::Dmg, GbModel::Cgb] {
+        let mut apu = playing_wave(model);
+        apu.write_naive(0xFF11, 0xBF);
+        apu.write_naive(0xFF24, 0x77);
+        apu.write_naive(0xFF26, 0x00);
+
+        assert_eq!(apu.read_naive(0xFF26), 0x70);
+        assert_eq!(apu.read_naive(0xFF11), 0x3F);
+        assert_eq!(apu.read_naive(0xFF24), 0x00);
+        assert_eq!(apu.read_naive(0xFF30), 0x00, "wave RAM survives");
+        assert_eq!(apu.read_naive(0xFF3F), 0xFF, "wave RAM survives");
+
+        apu.write_naive(0xFF24, 0x77);
+        apu.write_naive(0xFF12, 0xF0);
+        assert_eq!(apu.read_naive(0xFF24), 0x00);
+        assert_eq!(apu.read_naive(0xFF12), 0x00);
+    }
+}
+
+#[test]
+fn dmg_keeps_length_timers_while_powered_off() {
+    let mut dmg = powered_on(GbModel::Dmg);
+    let mut cgb = powered_on(GbModel::Cgb);
+    for apu in [&mut dmg, &mut cgb] {
+        apu.write_naive(0xFF11, 0x30);
+        apu.write_naive(0xFF26, 0x00);
+        // Only the length timer of the write goes through, on DMG
+        apu.write_naive(0xFF1B, 0xF0);
+        apu.write_naive(0xFF16, 0xC8);
+    }
+
+    assert_eq!(dmg.ch1.length_counter.counter, 16);
+    assert_eq!(dmg.ch3.length_counter.counter, 16);
+    assert_eq!(dmg.ch2.length_counter.counter, 56);
+    assert_eq!(dmg.read_naive(0xFF16), 0x3F, "duty stays cleared");
+
+    assert_eq!(cgb.ch1.length_counter.counter, 0);
+    assert_eq!(cgb.ch3.length_counter.counter, 0);
+    assert_eq!(cgb.ch2.length_counter.counter, 0);
+}
+
+#[test]
+fn wave_ram_access_while_playing() {
+    let mut cgb = playing_wave(GbModel::Cgb);
+    cgb.ch3.wave_step = 9;
+    assert_eq!(cgb.read_naive(0xFF30), 0x44);
+    cgb.write_naive(0xFF30, 0xAB);
+    assert_eq!(cgb.ch3.wave_ram[4], 0xAB);
+    assert_eq!(cgb.ch3.wave_ram[0], 0x00);
+
+    let mut dmg = playing_wave(GbModel::Dmg);
+    dmg.ch3.wave_step = 9;
+    dmg.ch3.ticks_since_fetch = 10;
+    assert_eq!(dmg.read_naive(0xFF30), 0xFF);
+    dmg.write_naive(0xFF30, 0xAB);
+    assert_eq!(dmg.ch3.wave_ram, playing_wave(GbModel::Dmg).ch3.wave_ram);
+
+    dmg.ch3.ticks_since_fetch = 0;
+    assert_eq!(dmg.read_naive(0xFF30), 0x44);
+    dmg.write_naive(0xFF30, 0xAB);
+    assert_eq!(dmg.ch3.wave_ram[4], 0xAB);
+}
+
+#[test]
+fn dmg_retrigger_corrupts_wave_ram() {
+    let retrigger = |model: GbModel, wave_step: u8| {
+        let mut apu = playing_wave(model);
+        apu.ch3.wave_step = wave_step;
+        apu.ch3.frequency_timer = 2;
+        apu.write_naive(0xFF1E, 0x80);
+        apu.ch3.wave_ram
+    };
+    let untouched = playing_wave(GbModel::Dmg).ch3.wave_ram;
+
+    // Next sample in byte 1: only the first byte is overwritten
+    let corrupted = retrigger(GbModel::Dmg, 2);
+    assert_eq!(corrupted[0], 0x11);
+    assert_eq!(corrupted[1..], untouched[1..]);
+
+    // Next sample in byte 10: the block of bytes 8-11 is copied to the start
+    let corrupted = retrigger(GbModel::Dmg, 20);
+    assert_eq!(corrupted[0..4], [0x88, 0x99, 0xAA, 0xBB]);
+    assert_eq!(corrupted[4..], untouched[4..]);
+
+    assert_eq!(retrigger(GbModel::Cgb, 20), untouched);
+}
+
+#[test]
+fn cgb_high_pass_filter_discharges_faster() {
+    assert!(charge_factor(48_000, GbModel::Cgb) < charge_factor(48_000, GbModel::Dmg));
+    assert_eq!(
+        Apu::new(GbModel::Agb).charge_factor,
+        charge_factor(44_100, GbModel::Agb)
+    );
+}
+
+/// Checksum of the audio [`TONES`] makes in 30 frames, taken before the APU caught up lazily
+const TONES_CHECKSUM: u64 = 0x7318_B5D7_2038_D9EB;
+
+fn checksum(samples: &[f32]) -> u64 {
+    samples.iter().fold(0, |hash, sample| {
+        hash.rotate_left(5) ^ sample.to_bits() as u64
+    })
+}
+
+#[test]
+fn catching_up_sounds_like_ticking_every_cycle() {
+    let rom = rom_with(&TONES, 0x00);
+    let mut lazy = boot(GbModel::Dmg, &rom);
+    let mut stepped = boot(GbModel::Dmg, &rom);
+    let mut every_tick = boot(GbModel::Dmg, &rom);
+    every_tick.apu.mix_every_tick = true;
+
+    for _ in 0..30 {
+        lazy.run_frame();
+        every_tick.run_frame();
+    }
+
+    // Frames as in `GameBoy::run_frame`, but the APU catches up after every instruction
+    for _ in 0..30 {
+        stepped.ppu.frame_ready = false;
+        let frame_cycles = stepped.frame_cycles();
+        while !stepped.ppu.frame_ready
+            && (stepped.ppu.lcdc.lcd_enabled || stepped.cycle_counter < frame_cycles)
+        {
+            stepped.step();
+        }
+        if stepped.cycle_counter >= frame_cycles {
+            stepped.cycle_counter -= frame_cycles;
+        }
+        stepped.apu.flush_audio();
+    }
+
+    assert!(
+        every_tick
+            .apu
+            .audio_buffer
+            .iter()
+            .any(|&sample| sample != 0.0)
+    );
+    assert_eq!(checksum(&every_tick.apu.audio_buffer), TONES_CHECKSUM);
+    assert_eq!(lazy.apu.audio_buffer, every_tick.apu.audio_buffer);
+    assert_eq!(stepped.apu.audio_buffer, every_tick.apu.audio_buffer);
+}
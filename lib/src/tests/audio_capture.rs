@@ -0,0 +1,131 @@
+use crate::audio_capture::{AudioCapture, encode_wav};
+use crate::gb::GbModel;
+use crate::tests::{boot, rom_with};
+
+// Channel 1 panned left and channel 2 panned right, both holding a note
+const TWO_TONES: [u8; 46] = [
+    0x3E, 0x80, 0xE0, 0x26, // NR52
+    0x3E, 0x77, 0xE0, 0x24, // NR50
+    0x3E, 0x12, 0xE0, 0x25, // NR51
+    0x3E, 0xF0, 0xE0, 0x12, // NR12
+    0x3E, 0x80, 0xE0, 0x11, // NR11
+    0x3E, 0x00, 0xE0, 0x13, // NR13
+    0x3E, 0x87, 0xE0, 0x14, // NR14
+    0x3E, 0xF0, 0xE0, 0x17, // NR22
+    0x3E, 0x40, 0xE0, 0x16, // NR21
+    0x3E, 0x00, 0xE0, 0x18, // NR23
+    0x3E, 0x86, 0xE0, 0x19, // NR24
+    0x18, 0xFE,
+];
+
+fn rom() -> Vec<u8> {
+    rom_with(&TWO_TONES, 0x00)
+}
+
+fn channel(samples: &[f32], side: usize) -> impl Iterator<Item = f32> + '_ {
+    samples.iter().skip(side).step_by(2).copied()
+}
+
+#[test]
+fn wav_header_and_samples() {
+    let wav = encode_wav(&[0.0, 1.0, -1.0, 2.0], 48_000);
+
+    assert_eq!(wav.len(), 44 + 8);
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(u32::from_le_bytes(wav[4..8].try_into().unwrap()), 44);
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    assert_eq!(u16::from_le_bytes([wav[22], wav[23]]), 2, "stereo");
+    assert_eq!(u32::from_le_bytes(wav[24..28].try_into().unwrap()), 48_000);
+    assert_eq!(
+        u16::from_le_bytes([wav[34], wav[35]]),
+        16,
+        "bits per sample"
+    );
+    assert_eq!(&wav[36..40], b"data");
+    assert_eq!(u32::from_le_bytes(wav[40..44].try_into().unwrap()), 8);
+
+    let samples: Vec<i16> = wav[44..]
+        .chunks(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect();
+    assert_eq!(samples, [0, i16::MAX, -i16::MAX, i16::MAX]);
+}
+
+#[test]
+fn stems_add_up_to_the_mix() {
+    let mut gb = boot(GbModel::Dmg, &rom());
+    let mut capture = AudioCapture::new(32_000, true);
+    capture.attach(&mut gb);
+
+    for _ in 0..10 {
+        gb.run_frame();
+        capture.collect(&mut gb);
+    }
+
+    let stems = capture.stems.as_ref().unwrap();
+    assert!(capture.mix.len() > 10_000);
+    for stem in stems {
+        assert_eq!(stem.len(), capture.mix.len());
+    }
+
+    for (i, mixed) in capture.mix.iter().enumerate() {
+        let sum: f32 = stems.iter().map(|stem| stem[i]).sum();
+        assert!((sum - mixed).abs() < 0.01, "sample {i}: {sum} != {mixed}");
+    }
+
+    assert!(channel(&stems[0], 0).any(|s| s.abs() > 0.05));
+    assert!(
+        channel(&stems[0], 1).all(|s| s.abs() < 0.01),
+        "ch1 is panned left"
+    );
+    assert!(channel(&stems[1], 1).any(|s| s.abs() > 0.05));
+    assert!(
+        channel(&stems[1], 0).all(|s| s.abs() < 0.01),
+        "ch2 is panned right"
+    );
+    assert!(stems[2].iter().chain(&stems[3]).all(|s| s.abs() < 0.01));
+
+    assert!(capture.stem_wav(3).is_some());
+    assert!(capture.stem_wav(4).is_none());
+}
+
+#[test]
+fn stems_are_optional() {
+    let mut gb = boot(GbModel::Dmg, &rom());
+    let mut capture = AudioCapture::new(44_100, false);
+    capture.attach(&mut gb);
+
+    gb.run_frame();
+    capture.collect(&mut gb);
+
+    assert!(gb.apu.stems.is_none());
+    assert!(!capture.mix.is_empty());
+    assert!(capture.stem_wav(0).is_none());
+}
+
+#[cfg(feature = "recording")]
+#[test]
+fn recordings_are_captured_headlessly() {
+    use crate::audio_capture::capture_recording;
+    use crate::gb::boot_rom::BootRom;
+    use crate::recording::{Button, Recording};
+    use crate::rom::Rom;
+
+    let mut recording = Recording::new("", GbModel::Dmg);
+    recording.push(10_000, Button::A, true);
+    recording.push(20_000, Button::A, false);
+
+    // Half a second
+    let capture = capture_recording(
+        &Rom::new(&rom()),
+        BootRom::Skip,
+        &recording,
+        2_097_152,
+        AudioCapture::new(44_100, true),
+    )
+    .unwrap();
+
+    assert!((capture.duration_secs() - 0.5).abs() < 0.01);
+    assert!(capture.mix.iter().any(|s| s.abs() > 0.05));
+    assert_eq!(capture.stems.unwrap()[0].len(), capture.mix.len());
+}
@@ -0,0 +1,111 @@
+use crate::error::GbError;
+use crate::gb::{GameBoy, GbModel};
+use crate::persistence::bess::BESS_MAGIC;
+use crate::persistence::snapshot::Incompatibility;
+use crate::rom::Rom;
+use crate::tests::{boot, rom_with};
+use crate::{ReadMemory, WriteMemory};
+
+/// MBC1 with RAM, enables the RAM, stores 0x5A at 0xA000 and then keeps incrementing BGP
+fn test_rom(global_checksum: u8) -> Vec<u8> {
+    let mut data = rom_with(
+        &[
+            0x3E, 0x0A, 0xEA, 0x00, 0x00, 0x21, 0x00, 0xA0, 0x36, 0x5A, 0x21, 0x47, 0xFF, 0x34,
+            0x00, 0x00, 0x00, 0x18, 0xFA,
+        ],
+        0x00,
+    );
+    data[0x0147] = 0x03;
+    data[0x0149] = 0x02;
+    data[0x014F] = global_checksum;
+    data
+}
+
+fn running(model: GbModel) -> GameBoy {
+    let mut gb = boot(model, &test_rom(0));
+    for _ in 0..3 {
+        gb.run_frame();
+    }
+    gb
+}
+
+#[test]
+fn bess_ends_with_its_footer() {
+    let data = running(GbModel::Dmg).dump_bess();
+
+    assert!(data.ends_with(&BESS_MAGIC));
+    let first_block = u32::from_le_bytes(data[data.len() - 8..data.len() - 4].try_into().unwrap());
+    assert_eq!(
+        &data[first_block as usize..first_block as usize + 4],
+        b"NAME"
+    );
+}
+
+#[test]
+fn bess_round_trip_keeps_the_machine() {
+    for model in [GbModel::Dmg, GbModel::Cgb] {
+        let mut gb = running(model);
+        gb.ppu.write_naive(0x8010, 0x3C);
+        gb.memory.write_naive(0xD123, 0x77);
+        gb.memory.write_naive(0xFF90, 0x42);
+
+        let data = gb.dump_bess();
+        let mut restored = GameBoy::from_bess(&data, &Rom::new(&test_rom(0))).expect("import");
+
+        assert_eq!(restored.model, model);
+        assert_eq!(
+            (restored.cpu.pc, restored.cpu.ir, restored.cpu.sp),
+            (gb.cpu.pc, gb.cpu.ir, gb.cpu.sp)
+        );
+        assert_eq!(
+            (restored.cpu.a, restored.cpu.h, restored.cpu.l),
+            (gb.cpu.a, gb.cpu.h, gb.cpu.l)
+        );
+        assert_eq!(restored.ppu.read_naive(0x8010), 0x3C);
+        assert_eq!(restored.memory.read_naive(0xD123), 0x77);
+        assert_eq!(restored.memory.read_naive(0xFF90), 0x42);
+        assert_eq!(restored.ppu.read_naive(0xFF47), gb.ppu.read_naive(0xFF47));
+        assert_eq!(restored.ppu.ly, gb.ppu.ly);
+        assert_eq!(restored.ppu.dmg_compat, model.is_cgb());
+        assert_eq!(restored.cartridge.read_naive(0xA000), 0x5A);
+
+        // Both keep incrementing BGP from the same value
+        restored.run_frame();
+        assert_ne!(restored.ppu.read_naive(0xFF47), gb.ppu.read_naive(0xFF47));
+    }
+}
+
+#[test]
+fn bess_of_another_model_switches_the_model() {
+    let data = running(GbModel::Cgb).dump_bess();
+
+    let mut gb = running(GbModel::Dmg);
+    gb.load_bess(&data).expect("import");
+
+    assert_eq!(gb.model, GbModel::Cgb);
+    assert!(gb.cartridge.has_rom_loaded);
+    assert_eq!(gb.cartridge.read_naive(0xA000), 0x5A);
+}
+
+#[test]
+fn bess_of_another_rom_is_incompatible() {
+    let data = running(GbModel::Dmg).dump_bess();
+
+    let result = GameBoy::from_bess(&data, &Rom::new(&test_rom(0x01)));
+
+    assert!(matches!(
+        result,
+        Err(GbError::IncompatibleSnapshot(Incompatibility::RomMismatch))
+    ));
+}
+
+#[test]
+fn data_without_footer_is_not_bess() {
+    let mut gb = running(GbModel::Dmg);
+    let snapshot = gb.dump_full().expect("dump");
+
+    assert!(matches!(
+        gb.load_bess(&snapshot),
+        Err(GbError::InvalidBess(_))
+    ));
+}
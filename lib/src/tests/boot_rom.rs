@@ -0,0 +1,213 @@
+use crate::ReadMemory;
+use crate::gb::boot_rom::{self, BootRom};
+use crate::gb::cpu::Cpu;
+use crate::gb::ppu::{COMPAT_BG_PALETTE, COMPAT_OBJ_PALETTE};
+use crate::gb::{GameBoy, GbModel};
+use crate::rom::Rom;
+use crate::rom::header::RomHeader;
+use crate::tests::rom_with;
+
+const HANDOVER_STEPS: u32 = 4_000_000;
+
+/// A cartridge that spins at its entry point, with a made-up logo and the given header checksum
+fn spinning_rom(header_checksum: u8) -> Rom {
+    spinning_rom_with_cgb_flag(header_checksum, 0x00)
+}
+
+fn spinning_rom_with_cgb_flag(header_checksum: u8, cgb_flag: u8) -> Rom {
+    let mut data = rom_with(&[0x18, 0xFE], cgb_flag); // JR -2
+    for (i, byte) in data[0x104..0x134].iter_mut().enumerate() {
+        *byte = (i as u8).wrapping_mul(0x1D);
+    }
+    data[0x14D] = header_checksum;
+    Rom::new(&data)
+}
+
+fn run_to_handover(gb: &mut GameBoy) {
+    let mut steps = 0;
+    while gb.boot_rom.is_present() && gb.boot_rom.mounted {
+        // Ends frames like `GameBoy::run_frame`, the resamplers overflow without their flush
+        gb.step_frame();
+        gb.apu.audio_buffer.clear();
+        steps += 1;
+        assert!(steps < HANDOVER_STEPS, "boot ROM never handed over");
+    }
+}
+
+fn assert_hands_over(model: GbModel, header_checksum: u8) {
+    let mut gb = GameBoy::new_empty(model);
+    gb.set_boot_rom(BootRom::Bundled);
+    gb.load_rom(&spinning_rom(header_checksum)).unwrap();
+    assert_eq!(gb.cpu.pc, 0x0000);
+
+    run_to_handover(&mut gb);
+
+    let expected = Cpu::new_post_boot(model, header_checksum);
+    // The entry point opcode is already fetched when the boot ROM unmaps itself
+    assert_eq!((gb.cpu.pc, gb.cpu.ir), (0x0101, 0x18), "{model}");
+    assert_eq!(gb.cpu.sp, 0xFFFE, "{model}");
+    assert_eq!(
+        [gb.cpu.a, gb.cpu.f.into(), gb.cpu.b, gb.cpu.c],
+        [expected.a, expected.f.into(), expected.b, expected.c],
+        "{model} AF/BC with header checksum {header_checksum:02X}"
+    );
+    assert_eq!(
+        [gb.cpu.d, gb.cpu.e, gb.cpu.h, gb.cpu.l],
+        [expected.d, expected.e, expected.h, expected.l],
+        "{model} DE/HL with header checksum {header_checksum:02X}"
+    );
+}
+
+#[test]
+fn bundled_boot_rom_hands_over_with_post_boot_registers() {
+    // One run per bundled image, CGB-E shares the CGB one
+    for model in [
+        GbModel::Dmg,
+        GbModel::Mgb,
+        GbModel::Sgb2,
+        GbModel::Cgb,
+        GbModel::Agb,
+    ] {
+        assert_hands_over(model, 0x4D);
+    }
+    assert_hands_over(GbModel::Dmg, 0x00);
+}
+
+#[test]
+fn bundled_boot_rom_draws_the_cartridge_logo() {
+    let mut gb = GameBoy::new_empty(GbModel::Dmg);
+    gb.set_boot_rom(BootRom::Bundled);
+    gb.load_rom(&spinning_rom(0x00)).unwrap();
+    run_to_handover(&mut gb);
+
+    // The first logo byte 0x00, the second 0x1D expands to 0x03 0xF3 in tile 1 rows 2-3
+    assert_eq!(gb.ppu.read_naive(0x8010), 0x00);
+    assert_eq!(gb.ppu.read_naive(0x8018), 0x03);
+    assert_eq!(gb.ppu.read_naive(0x801A), 0x03);
+    assert_eq!(gb.ppu.read_naive(0x801C), 0xF3);
+    assert_eq!(gb.ppu.read_naive(0x9904), 0x01);
+    assert_eq!(gb.ppu.read_naive(0x9910), 0x19);
+    assert_eq!(gb.ppu.read_naive(0x992F), 0x18);
+    assert_eq!(gb.ppu.scy, 0x00);
+}
+
+#[test]
+fn boot_rom_choice_survives_loading_a_rom_and_resets() {
+    let mut gb = GameBoy::new_empty(GbModel::Dmg);
+    gb.set_boot_rom(BootRom::Bundled);
+    gb.load_rom(&spinning_rom(0x00)).unwrap();
+    assert_eq!(gb.boot_rom.source, BootRom::Bundled);
+
+    run_to_handover(&mut gb);
+    gb.soft_reset();
+    assert!(gb.boot_rom.mounted);
+    assert_eq!(gb.cpu.pc, 0x0000);
+
+    gb.set_boot_rom(BootRom::Skip);
+    gb.load_rom(&spinning_rom(0x00)).unwrap();
+    assert_eq!(gb.cpu.pc, 0x0100);
+}
+
+#[test]
+fn cgb_boot_roms_leave_the_cartridge_header_visible() {
+    let mut custom = vec![0x00; 0x900];
+    custom[0x150] = 0xAA;
+    custom[0x250] = 0xBB;
+
+    let mut gb = GameBoy::new_empty(GbModel::Cgb);
+    gb.set_boot_rom(BootRom::Custom(custom));
+    gb.load_rom(&spinning_rom(0x42)).unwrap();
+
+    assert!(gb.boot_rom.maps(0x0050));
+    assert!(!gb.boot_rom.maps(0x0150));
+    assert!(gb.boot_rom.maps(0x0250));
+    assert!(!gb.boot_rom.maps(0x0900));
+}
+
+#[test]
+fn bundled_boot_roms_match_their_checksums() {
+    // `make boot-roms` checks a fresh build against the same list
+    let images = [
+        ("dmg_boot.bin", boot_rom::DMG_BOOT_ROM),
+        ("mgb_boot.bin", boot_rom::MGB_BOOT_ROM),
+        ("sgb2_boot.bin", boot_rom::SGB2_BOOT_ROM),
+        ("cgb_boot.bin", boot_rom::CGB_BOOT_ROM),
+        ("agb_boot.bin", boot_rom::AGB_BOOT_ROM),
+    ];
+    let sums = include_str!("../../boot/SHA256SUMS");
+    assert_eq!(sums.lines().count(), images.len());
+
+    for (name, image) in images {
+        let sum: String = RomHeader::calculate_sha256(image)
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        assert!(
+            sums.lines().any(|line| line == format!("{sum}  {name}")),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn cgb_runs_dmg_cartridges_in_dmg_compatibility_mode() {
+    for (model, boot_rom) in [
+        (GbModel::Cgb, BootRom::Bundled),
+        (GbModel::Agb, BootRom::Bundled),
+        (GbModel::Cgb, BootRom::Skip),
+    ] {
+        let mut gb = GameBoy::new_empty(model);
+        gb.set_boot_rom(boot_rom.clone());
+        gb.load_rom(&spinning_rom(0x4D)).unwrap();
+        run_to_handover(&mut gb);
+        gb.run_frame();
+
+        assert!(gb.ppu.dmg_compat, "{model} {boot_rom:?}");
+        assert_eq!(gb.ppu.bg_palette_ram[..8], COMPAT_BG_PALETTE);
+        assert_eq!(gb.ppu.obj_palette_ram[..8], COMPAT_OBJ_PALETTE);
+        assert_eq!(gb.ppu.obj_palette_ram[8..16], COMPAT_OBJ_PALETTE);
+        // BGP maps color 0 to shade 0, the first color of the BG palette
+        assert_eq!(
+            gb.ppu.native_frame().as_slice()[0],
+            0x7FFF,
+            "{model} {boot_rom:?}"
+        );
+    }
+}
+
+#[test]
+fn cgb_runs_cgb_cartridges_in_cgb_mode() {
+    for boot_rom in [BootRom::Bundled, BootRom::Skip] {
+        let mut gb = GameBoy::new_empty(GbModel::Cgb);
+        gb.set_boot_rom(boot_rom.clone());
+        gb.load_rom(&spinning_rom_with_cgb_flag(0x4D, 0x80))
+            .unwrap();
+        run_to_handover(&mut gb);
+
+        assert!(!gb.ppu.dmg_compat, "{boot_rom:?}");
+        assert!(gb.ppu.cgb_mode());
+    }
+}
+
+#[test]
+fn dmg_compatibility_mode_locks_the_cgb_registers() {
+    let mut gb = GameBoy::new_empty(GbModel::Cgb);
+    gb.set_boot_rom(BootRom::Skip);
+    gb.load_rom(&spinning_rom(0x4D)).unwrap();
+
+    // KEY0 only listens to the boot ROM
+    gb.write_memory(0xFF4C, 0x00);
+    assert!(gb.ppu.dmg_compat);
+
+    gb.write_memory(0xFF4F, 0x01);
+    gb.write_memory(0xFF68, 0x80);
+    gb.write_memory(0xFF69, 0x12);
+    for addr in [0xFF4D, 0xFF4F, 0xFF68, 0xFF69] {
+        assert_eq!(gb.read_memory(addr), 0xFF, "{addr:04X}");
+    }
+    assert_eq!(gb.ppu.bg_palette_ram[..8], COMPAT_BG_PALETTE);
+
+    // A soft reset without boot ROM keeps the mode the cartridge was started in
+    gb.soft_reset();
+    assert!(gb.ppu.dmg_compat);
+}
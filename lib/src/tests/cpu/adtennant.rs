@@ -130,6 +130,8 @@ impl From<&TestState> for Cpu {
             ime_next: false,
             halted: false,
             halt_bug: false,
+            stopped: false,
+            speed_switch_stall: 0,
             model: GbModel::Dmg,
             invalid_opcode: false,
         }
@@ -0,0 +1,203 @@
+use crate::env::{
+    Env, EnvConfig, Observation, ObservationKind, RamDelta, RamNumber, SCREEN_TILES_X,
+};
+use crate::gb::joypad::JoypadState;
+use crate::rom::Rom;
+use crate::tests::rom_with;
+
+// Counts frames in 0xC000 from the VBlank interrupt and halts in between
+const COUNT_FRAMES: [u8; 14] = [
+    0x3E, 0x01, 0xE0, 0xFF, 0xFB, 0x76, 0x18, 0xFD, // LD A,1; LDH [IE],A; EI; HALT; JR -3
+    0x21, 0x00, 0xC0, 0x34, 0xD9, 0x00, // LD HL,0xC000; INC [HL]; RETI
+];
+
+fn env(observation: ObservationKind) -> Env {
+    let mut data = rom_with(&COUNT_FRAMES[..8], 0x00);
+    data[0x40..0x46].copy_from_slice(&COUNT_FRAMES[8..]);
+    let config = EnvConfig {
+        observation,
+        ..Default::default()
+    };
+    Env::new(&Rom::new(&data), config).unwrap()
+}
+
+fn wram(env: &mut Env) -> Vec<u8> {
+    (0xC100..0xC200)
+        .map(|addr| env.gb.read_memory(addr))
+        .collect()
+}
+
+#[test]
+fn seeds_replay_episodes() {
+    let mut env = env(ObservationKind::Greyscale);
+    env.reset(1);
+    let first = wram(&mut env);
+    env.reset(2);
+    assert_ne!(wram(&mut env), first);
+    env.reset(1);
+    assert_eq!(wram(&mut env), first);
+}
+
+#[test]
+fn steps_skip_frames() {
+    let mut env = env(ObservationKind::Tiles);
+    env.config.max_frames = 10;
+    env.reset(0);
+
+    let (_, info) = env.step(JoypadState::A, 4);
+    assert_eq!(info.frame, 4);
+    assert!(!info.truncated);
+    let counted = env.gb.read_memory(0xC000);
+
+    let (_, info) = env.step(JoypadState::empty(), 0);
+    assert_eq!(info.frame, 5);
+    assert_eq!(env.gb.read_memory(0xC000), counted.wrapping_add(1));
+
+    let (_, info) = env.step(JoypadState::empty(), 5);
+    assert!(info.truncated);
+}
+
+#[test]
+fn noops_depend_on_the_seed() {
+    let mut env = env(ObservationKind::Tiles);
+    env.config.noop_max = 30;
+    let frames: Vec<u64> = (0..8)
+        .map(|seed| {
+            env.reset(seed);
+            env.frame()
+        })
+        .collect();
+    assert!(frames.iter().all(|&frames| frames <= 30));
+    assert!(frames.iter().any(|&count| count != frames[0]));
+}
+
+#[test]
+fn ram_numbers() {
+    let mut env = env(ObservationKind::Tiles);
+    env.reset(0);
+    for (offset, value) in [0x56, 0x34, 0x12].into_iter().enumerate() {
+        env.gb.write_memory(0xD000 + offset as u16, value);
+    }
+    assert_eq!(RamNumber::U8.read(&mut env.gb, 0xD000), 0x56);
+    assert_eq!(RamNumber::U16Le.read(&mut env.gb, 0xD000), 0x3456);
+    assert_eq!(RamNumber::U16Be.read(&mut env.gb, 0xD000), 0x5634);
+    assert_eq!(RamNumber::BcdLe(3).read(&mut env.gb, 0xD000), 123456);
+    assert_eq!(RamNumber::BcdBe(3).read(&mut env.gb, 0xD000), 563412);
+}
+
+#[test]
+fn long_bcd_numbers_keep_their_least_significant_bytes() {
+    let mut env = env(ObservationKind::Tiles);
+    env.reset(0);
+    // 12 bytes, 0x01 to 0x12 from the first to the last
+    for offset in 0..12u16 {
+        let value = offset as u8 + 1;
+        env.gb
+            .write_memory(0xD000 + offset, ((value / 10) << 4) | (value % 10));
+    }
+    assert_eq!(
+        RamNumber::BcdLe(12).read(&mut env.gb, 0xD000),
+        90_807_060_504_030_201
+    );
+    assert_eq!(
+        RamNumber::BcdBe(12).read(&mut env.gb, 0xD000),
+        40_506_070_809_101_112
+    );
+
+    for offset in 0..12u16 {
+        env.gb.write_memory(0xD000 + offset, 0x99);
+    }
+    assert_eq!(
+        RamNumber::BcdLe(12).read(&mut env.gb, 0xD000),
+        999_999_999_999_999_999
+    );
+}
+
+#[test]
+fn rewards_and_done_hooks_read_ram() {
+    let mut env = env(ObservationKind::Tiles);
+    env.add_reward(RamDelta::new(0xD000, RamNumber::BcdLe(2)).scaled(0.5));
+    env.add_reward(|gb: &mut crate::gb::GameBoy| gb.read_memory(0xD010) as f64);
+    env.set_done(|gb| gb.read_memory(0xD020) == 0xFF);
+    env.reset(0);
+    // Power-on RAM is random
+    for addr in [0xD000, 0xD001, 0xD010, 0xD020] {
+        env.gb.write_memory(addr, 0x00);
+    }
+    env.step(JoypadState::empty(), 1);
+
+    let (_, info) = env.step(JoypadState::empty(), 1);
+    assert_eq!(info.reward, 0.0);
+
+    env.gb.write_memory(0xD001, 0x01);
+    env.gb.write_memory(0xD010, 3);
+    let (_, info) = env.step(JoypadState::empty(), 1);
+    assert_eq!(info.reward, 50.0 + 3.0);
+    assert!(!info.terminated);
+
+    env.gb.write_memory(0xD020, 0xFF);
+    let (_, info) = env.step(JoypadState::empty(), 1);
+    assert!(info.terminated);
+}
+
+#[test]
+fn start_states_are_cloned() {
+    let mut env = env(ObservationKind::Tiles);
+    env.reset(0);
+    env.step(JoypadState::empty(), 10);
+    env.gb.write_memory(0xD000, 0x42);
+    env.set_start(Some(env.gb.clone()));
+
+    env.step(JoypadState::empty(), 10);
+    env.gb.write_memory(0xD000, 0x00);
+    env.reset(5);
+    assert_eq!(env.gb.read_memory(0xD000), 0x42);
+}
+
+#[test]
+fn observations() {
+    let mut env = env(ObservationKind::Rgba);
+    let Observation::Rgba(pixels) = env.reset(0) else {
+        panic!("expected RGBA");
+    };
+    assert_eq!(pixels.len(), 160 * 144 * 4);
+
+    env.config.observation = ObservationKind::Greyscale;
+    let Observation::Greyscale(pixels) = env.observe() else {
+        panic!("expected greyscale");
+    };
+    assert_eq!(pixels.len(), 160 * 144);
+
+    env.config.observation = ObservationKind::Downsampled { factor: 4 };
+    let Observation::Downsampled {
+        width,
+        height,
+        pixels,
+    } = env.observe()
+    else {
+        panic!("expected downsampled");
+    };
+    assert_eq!((width, height, pixels.len()), (40, 36, 40 * 36));
+}
+
+#[test]
+fn tile_observations_follow_the_scroll() {
+    let mut env = env(ObservationKind::Tiles);
+    env.reset(0);
+    // BG map at 0x9800, scrolled by one tile in both directions
+    env.gb.write_memory(0xFF40, 0x91);
+    env.gb.write_memory(0x9800 + 32 + 1, 0x07);
+    env.gb.write_memory(0x9800 + 32 * 2 + 3, 0x09);
+    env.gb.write_memory(0xFF42, 8);
+    env.gb.write_memory(0xFF43, 8);
+    env.gb.write_memory(0xFE00, 0x50);
+
+    let Observation::Tiles(tiles) = env.observe() else {
+        panic!("expected tiles");
+    };
+    assert_eq!(tiles.background.len(), 20 * 18);
+    assert_eq!(tiles.background[0], 0x07);
+    assert_eq!(tiles.background[SCREEN_TILES_X + 2], 0x09);
+    assert_eq!(tiles.oam.len(), 160);
+    assert_eq!(tiles.oam[0], 0x50);
+}
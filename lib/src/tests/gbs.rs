@@ -0,0 +1,166 @@
+use crate::ReadMemory;
+use crate::error::GbError;
+use crate::gb::GbModel;
+use crate::gbs::{Gbs, GbsHeader, GbsPlayer, PlayTrigger};
+
+const INIT: u16 = 0x0400;
+const PLAY: u16 = 0x0420;
+
+/// Init stores the track at 0xC000 and starts a tone on channel 2, play counts its calls at 0xC001
+fn gbs_file(tma: u8, tac: u8) -> Vec<u8> {
+    let mut data = vec![0x00; 0x70];
+    data[0x00..0x03].copy_from_slice(b"GBS");
+    data[0x03] = 1;
+    data[0x04] = 3;
+    data[0x05] = 2;
+    data[0x06..0x08].copy_from_slice(&INIT.to_le_bytes());
+    data[0x08..0x0A].copy_from_slice(&INIT.to_le_bytes());
+    data[0x0A..0x0C].copy_from_slice(&PLAY.to_le_bytes());
+    data[0x0C..0x0E].copy_from_slice(&0xFFFEu16.to_le_bytes());
+    data[0x0E] = tma;
+    data[0x0F] = tac;
+    data[0x10..0x15].copy_from_slice(b"Title");
+    data[0x30..0x36].copy_from_slice(b"Author");
+
+    let mut code = vec![0x00; 0x30];
+    code[0x00..0x14].copy_from_slice(&[
+        0xEA, 0x00, 0xC0, // LD [$C000], A
+        0xAF, 0xEA, 0x01, 0xC0, // LD [$C001], 0
+        0x3E, 0xF0, 0xE0, 0x17, // NR22
+        0x3E, 0x80, 0xE0, 0x16, // NR21
+        0x3E, 0x87, 0xE0, 0x19, // NR24, trigger
+        0xC9, // RET
+    ]);
+    code[0x20..0x25].copy_from_slice(&[0x21, 0x01, 0xC0, 0x34, 0xC9]);
+    data.extend(code);
+    data
+}
+
+fn play_count(player: &GbsPlayer) -> u8 {
+    player.gb.memory.read_naive(0xC001)
+}
+
+#[test]
+fn header_is_parsed() {
+    let gbs = Gbs::new(&gbs_file(0x00, 0x00)).unwrap();
+
+    assert_eq!(gbs.header.track_count, 3);
+    assert_eq!(gbs.header.first_track, 2);
+    assert_eq!(gbs.header.play_address, PLAY);
+    assert_eq!(gbs.header.title, "Title");
+    assert_eq!(gbs.header.author, "Author");
+    assert_eq!(gbs.header.copyright, "");
+    assert_eq!(gbs.header.play_trigger(), PlayTrigger::VBlank);
+    assert_eq!(gbs.data.len(), 0x30);
+}
+
+#[test]
+fn invalid_headers_are_rejected() {
+    let mut data = gbs_file(0x00, 0x00);
+    data[0x02] = b'X';
+    assert!(matches!(
+        GbsHeader::new(&data),
+        Err(GbError::InvalidGbsHeader)
+    ));
+
+    let mut data = gbs_file(0x00, 0x00);
+    data[0x06..0x08].copy_from_slice(&0x0200u16.to_le_bytes());
+    assert!(matches!(
+        GbsHeader::new(&data),
+        Err(GbError::UnsupportedGbsLoadAddress(0x0200))
+    ));
+
+    assert!(matches!(
+        GbsHeader::new(&data[..0x40]),
+        Err(GbError::RomTooSmall)
+    ));
+}
+
+#[test]
+fn vblank_drives_the_play_routine() {
+    let gbs = Gbs::new(&gbs_file(0x00, 0x00)).unwrap();
+    let mut player = GbsPlayer::new(gbs, GbModel::Dmg).unwrap();
+    assert_eq!(player.track(), 1, "the header's first track is 1-based");
+
+    for _ in 0..10 {
+        player.run_frame();
+    }
+    assert_eq!(player.gb.memory.read_naive(0xC000), 1);
+    assert!((9..=11).contains(&play_count(&player)));
+}
+
+#[test]
+fn timer_drives_the_play_routine() {
+    // 65536 Hz / 256 = 256 calls per second, ~43 in 10 frames
+    let gbs = Gbs::new(&gbs_file(0x00, 0x06)).unwrap();
+    assert_eq!(
+        gbs.header.play_trigger(),
+        PlayTrigger::Timer {
+            modulo: 0x00,
+            control: 0x06
+        }
+    );
+    let mut player = GbsPlayer::new(gbs, GbModel::Dmg).unwrap();
+
+    for _ in 0..10 {
+        player.run_frame();
+    }
+    assert!((41..=45).contains(&play_count(&player)));
+}
+
+#[test]
+fn double_speed_is_entered_on_cgb() {
+    // Bit 7 of TAC switches to double speed, which doubles the 256 Hz timer to ~85 calls
+    let gbs = Gbs::new(&gbs_file(0x00, 0x86)).unwrap();
+    assert!(gbs.header.wants_double_speed());
+    let mut player = GbsPlayer::new(gbs, GbModel::Cgb).unwrap();
+
+    for _ in 0..10 {
+        player.run_frame();
+    }
+    assert!(player.gb.speed.double_speed);
+    assert!(!player.gb.cpu.stopped);
+    assert_eq!(player.gb.memory.read_naive(0xC000), 1);
+    assert!(
+        (82..=88).contains(&play_count(&player)),
+        "{}",
+        play_count(&player)
+    );
+}
+
+#[test]
+fn tracks_can_be_selected() {
+    let gbs = Gbs::new(&gbs_file(0x00, 0x00)).unwrap();
+    let mut player = GbsPlayer::new(gbs, GbModel::Cgb).unwrap();
+    for _ in 0..5 {
+        player.run_frame();
+    }
+
+    let played = play_count(&player);
+    player.select_track(2).unwrap();
+    assert_eq!(player.track(), 2);
+    player.run_frame();
+    assert_eq!(player.gb.memory.read_naive(0xC000), 2);
+    assert!(
+        play_count(&player) < played,
+        "selecting a track restarts the machine"
+    );
+
+    assert!(matches!(
+        player.select_track(3),
+        Err(GbError::GbsTrackOutOfRange(3))
+    ));
+}
+
+#[test]
+fn samples_come_out_of_the_apu() {
+    let gbs = Gbs::new(&gbs_file(0x00, 0x00)).unwrap();
+    let mut player = GbsPlayer::new(gbs, GbModel::Dmg).unwrap();
+    player.set_sample_rate(48_000);
+
+    player.run_frame();
+    let samples = player.take_samples();
+    assert!((1500..1700).contains(&samples.len()), "{}", samples.len());
+    assert!(samples.iter().any(|&s| s.abs() > 0.01));
+    assert!(player.take_samples().is_empty());
+}
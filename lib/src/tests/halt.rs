@@ -70,3 +70,46 @@ fn no_halt_bug_without_pending_interrupt() {
     let cpu = run(false);
     assert!(cpu.halted, "HALT without a pending interrupt should halt");
 }
+
+#[test]
+fn ei_before_halt_returns_to_the_halt() {
+    let mut bus = HaltBus::new(true);
+    bus.mem[0x0200] = 0xFB; // EI
+    bus.mem[0x0201] = 0x76; // HALT
+    bus.mem[0x0202] = 0x3C; // INC A
+
+    let mut cpu = Cpu::new_dmg(0x01);
+    cpu.ime = false;
+    cpu.pc = 0x0201;
+    cpu.ir = 0xFB;
+
+    cpu.step(&mut bus);
+    cpu.step(&mut bus);
+    assert!(!cpu.halted);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x0002, "the handler at 0x0000 should be running");
+    assert_eq!(
+        [bus.mem[0xFFFC], bus.mem[0xFFFD]],
+        [0x01, 0x02],
+        "the handler should return to the HALT"
+    );
+}
+
+#[test]
+fn halt_bug_makes_rst_return_to_itself() {
+    let mut bus = HaltBus::new(true);
+    bus.mem[0x0200] = 0x76; // HALT
+    bus.mem[0x0201] = 0xCF; // RST 08h
+
+    let mut cpu = Cpu::new_dmg(0x01);
+    cpu.ime = false;
+    cpu.pc = 0x0201;
+    cpu.ir = 0x76;
+
+    cpu.step(&mut bus);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x0009);
+    assert_eq!([bus.mem[0xFFFC], bus.mem[0xFFFD]], [0x01, 0x02]);
+}
@@ -0,0 +1,145 @@
+use crate::ReadMemory;
+use crate::gb::{GameBoy, GbModel};
+use crate::tests::{boot, rom_with};
+
+/// Increments BGP every 9 M-cycles, which doesn't divide a frame, so consecutive frames differ
+fn loaded() -> GameBoy {
+    // LD HL, 0xFF47; INC (HL); NOP; NOP; NOP; JR -6
+    let code = [0x21, 0x47, 0xFF, 0x34, 0x00, 0x00, 0x00, 0x18, 0xFA];
+    boot(GbModel::Dmg, &rom_with(&code, 0x00))
+}
+
+fn fingerprint(gb: &GameBoy) -> (u16, u8, u8, u32) {
+    (
+        gb.cpu.pc,
+        gb.ppu.ly,
+        gb.ppu.read_naive(0xFF47),
+        gb.cycle_counter,
+    )
+}
+
+#[test]
+fn loaded_state_runs_like_the_original() {
+    let mut gb = loaded();
+    gb.run_frame();
+    let state = gb.save_state();
+
+    gb.run_frame();
+    gb.run_frame();
+    let expected = (fingerprint(&gb), gb.frame().as_slice().to_vec());
+
+    gb.load_state(&state);
+    gb.run_frame();
+    gb.run_frame();
+    assert_eq!(fingerprint(&gb), expected.0);
+    assert_eq!(gb.frame().as_slice(), expected.1.as_slice());
+}
+
+#[test]
+fn stepping_ends_frames_where_running_them_does() {
+    let mut stepped = loaded();
+    let mut run = loaded();
+
+    for _ in 0..3 {
+        let mut steps = 0;
+        while !stepped.step_frame() {
+            steps += 1;
+        }
+        run.run_frame();
+        assert!(steps > 1000);
+        assert_eq!(fingerprint(&stepped), fingerprint(&run));
+        assert_eq!(stepped.frame().as_slice(), run.frame().as_slice());
+    }
+}
+
+#[test]
+fn run_ahead_shows_the_future_but_stays_in_the_present() {
+    let mut ahead = loaded();
+    let mut present = loaded();
+    let mut future = loaded();
+    future.run_frame();
+    future.run_frame();
+
+    for _ in 0..2 {
+        ahead.run_frame_ahead(2);
+        present.run_frame();
+        future.run_frame();
+    }
+
+    assert_eq!(fingerprint(&ahead), fingerprint(&present));
+    assert_eq!(ahead.frame().as_slice(), future.frame().as_slice());
+    assert_ne!(ahead.frame().as_slice(), present.frame().as_slice());
+    assert_eq!(ahead.apu.audio_buffer.len(), present.apu.audio_buffer.len());
+}
+
+#[test]
+fn skipped_frames_keep_their_timing() {
+    let mut skipped = loaded();
+    let mut shown = loaded();
+    let blank = skipped.frame().as_slice().to_vec();
+    skipped.ppu.skip_rendering = true;
+    skipped.apu.suppress_output = true;
+
+    for _ in 0..3 {
+        skipped.run_frame();
+        shown.run_frame();
+    }
+    assert_eq!(fingerprint(&skipped), fingerprint(&shown));
+    assert_eq!(skipped.frame().as_slice(), blank.as_slice());
+    assert!(skipped.apu.audio_buffer.is_empty());
+    assert!(!shown.apu.audio_buffer.is_empty());
+
+    skipped.ppu.skip_rendering = false;
+    skipped.run_frame();
+    shown.run_frame();
+    assert_eq!(skipped.frame().as_slice(), shown.frame().as_slice());
+}
+
+#[test]
+fn run_ahead_leaves_the_skip_modes_as_they_were() {
+    let mut gb = loaded();
+    gb.ppu.skip_rendering = true;
+    gb.apu.suppress_output = true;
+    let blank = gb.frame().as_slice().to_vec();
+
+    gb.run_frame_ahead(2);
+    assert!(gb.ppu.skip_rendering && gb.apu.suppress_output);
+    assert_eq!(gb.frame().as_slice(), blank.as_slice());
+    assert!(gb.apu.audio_buffer.is_empty());
+}
+
+#[test]
+fn clone_runs_independently_of_the_original() {
+    let mut gb = loaded();
+    gb.run_frame();
+    let mut fork = gb.clone();
+
+    fork.run_frame();
+    assert_ne!(fingerprint(&fork), fingerprint(&gb));
+
+    gb.run_frame();
+    assert_eq!(fingerprint(&fork), fingerprint(&gb));
+    assert_eq!(fork.frame().as_slice(), gb.frame().as_slice());
+}
+
+#[test]
+fn clones_run_on_worker_threads() {
+    let mut gb = loaded();
+    gb.run_frame();
+
+    let workers: Vec<_> = (0..2)
+        .map(|_| {
+            let mut fork = gb.clone();
+            std::thread::spawn(move || {
+                fork.run_frame();
+                fork
+            })
+        })
+        .collect();
+
+    gb.run_frame();
+    for worker in workers {
+        let fork = worker.join().expect("worker");
+        assert_eq!(fingerprint(&fork), fingerprint(&gb));
+    }
+}
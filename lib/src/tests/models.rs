@@ -0,0 +1,69 @@
+use crate::gb::cpu::Cpu;
+use crate::gb::{GameBoy, GbModel};
+
+fn regs(cpu: &Cpu) -> [u8; 8] {
+    [
+        cpu.a,
+        cpu.f.into(),
+        cpu.b,
+        cpu.c,
+        cpu.d,
+        cpu.e,
+        cpu.h,
+        cpu.l,
+    ]
+}
+
+#[test]
+fn post_boot_registers_match_each_model() {
+    let expected = [
+        (
+            GbModel::Dmg,
+            [0x01, 0xB0, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D],
+        ),
+        (
+            GbModel::Mgb,
+            [0xFF, 0xB0, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D],
+        ),
+        (
+            GbModel::Sgb2,
+            [0xFF, 0x00, 0x00, 0x14, 0x00, 0x00, 0xC0, 0x60],
+        ),
+        (
+            GbModel::Cgb,
+            [0x11, 0x80, 0x00, 0x00, 0xFF, 0x56, 0x00, 0x0D],
+        ),
+        (
+            GbModel::CgbE,
+            [0x11, 0x80, 0x00, 0x00, 0xFF, 0x56, 0x00, 0x0D],
+        ),
+        (
+            GbModel::Agb,
+            [0x11, 0x00, 0x01, 0x00, 0xFF, 0x56, 0x00, 0x0D],
+        ),
+    ];
+
+    for (model, regs_expected) in expected {
+        let cpu = Cpu::new_post_boot(model, 0x4D);
+        assert_eq!(regs(&cpu), regs_expected, "{model} post-boot registers");
+        assert_eq!(cpu.model, model);
+    }
+}
+
+#[test]
+fn soft_reset_keeps_the_model() {
+    for &model in GbModel::ALL {
+        let mut gb = GameBoy::new_empty(model);
+        gb.cpu.a = 0x42;
+        gb.soft_reset();
+        assert_eq!(gb.cpu.model, model);
+        assert_eq!(gb.cpu.a, Cpu::new_post_boot(model, 0x00).a);
+    }
+}
+
+#[test]
+fn model_families() {
+    assert!(GbModel::ALL.iter().all(|m| m.is_dmg() != m.is_cgb()));
+    assert!(GbModel::Sgb2.is_dmg() && GbModel::Sgb2.is_sgb());
+    assert!(GbModel::Agb.is_cgb() && GbModel::Agb.is_agb());
+}
@@ -0,0 +1,104 @@
+use crate::gb::GbModel;
+use crate::gb::ppu::SCREEN_WIDTH;
+use crate::gb::ppu::types::color::RGBA;
+use crate::gb::ppu::types::native_frame::NativeFormat;
+use crate::gb::ppu::types::theme::DmgTheme;
+use crate::tests::{boot, rom_with};
+
+#[test]
+fn dmg_frames_hold_the_shades_after_bgp() {
+    // LD A,0xE7; LDH [BGP],A; JR -2: color 0 is the darkest shade
+    let mut gb = boot(
+        GbModel::Dmg,
+        &rom_with(&[0x3E, 0xE7, 0xE0, 0x47, 0x18, 0xFE], 0x00),
+    );
+    gb.ppu.dmg_theme = DmgTheme::Pocket;
+    gb.run_frame();
+    gb.run_frame();
+
+    let native = gb.native_frame();
+    assert_eq!(native.format(), NativeFormat::Shade);
+    assert!(native.as_slice().iter().all(|&shade| shade == 3));
+
+    let mut rgba = Vec::new();
+    native.rgba_into(gb.ppu.dmg_theme, &mut rgba);
+    assert_eq!(rgba, gb.frame().as_slice());
+}
+
+#[test]
+fn cgb_frames_hold_the_colors_from_palette_ram() {
+    // JR -2
+    let mut gb = boot(GbModel::Cgb, &rom_with(&[0x18, 0xFE], 0x00));
+    // Pure red as BG color 0 of palette 0
+    gb.ppu.bg_palette_ram[..2].copy_from_slice(&[0x1F, 0x00]);
+    gb.run_frame();
+    gb.run_frame();
+
+    let native = gb.native_frame();
+    assert_eq!(native.format(), NativeFormat::Bgr555);
+    assert!(native.as_slice().iter().all(|&color| color == 0x001F));
+    assert_eq!(native.color(0, DmgTheme::default()), RGBA::rgb(0xFF, 0, 0));
+
+    let mut rgb565 = Vec::new();
+    native.rgb565_into(DmgTheme::default(), &mut rgb565);
+    assert!(rgb565.iter().all(|&color| color == 0xF800));
+
+    let mut xrgb8888 = Vec::new();
+    native.xrgb8888_into(DmgTheme::default(), &mut xrgb8888);
+    assert!(xrgb8888.iter().all(|&color| color == 0x00FF_0000));
+}
+
+#[test]
+fn cgb_tiles_follow_their_attributes() {
+    // LD A,0x93; LDH [LCDC],A; JR -2: objects on
+    let mut gb = boot(
+        GbModel::Cgb,
+        &rom_with(&[0x3E, 0x93, 0xE0, 0x40, 0x18, 0xFE], 0x80),
+    );
+
+    let color = |palette: usize, index: usize| 0x0421 * (palette * 4 + index + 1) as u16;
+    for palette in 0..8 {
+        for index in 0..4 {
+            let at = palette * 8 + index * 2;
+            let bgr555 = color(palette, index).to_le_bytes();
+            gb.ppu.bg_palette_ram[at..at + 2].copy_from_slice(&bgr555);
+            gb.ppu.obj_palette_ram[at..at + 2]
+                .copy_from_slice(&(!color(palette, index) & 0x7FFF).to_le_bytes());
+        }
+    }
+
+    let vram = &mut gb.ppu.vram;
+    // Bank 1, tile 0: the leftmost pixel of the top row in color 1
+    vram[1][0x0000] = 0x80;
+    // Bank 1, tile 1: the top row in color 3
+    vram[1][0x0010..0x0012].copy_from_slice(&[0xFF, 0xFF]);
+    // Bank 1, tile 2: the top row in color 1
+    vram[1][0x0020] = 0xFF;
+    // Tile (0, 0): palette 2 from bank 1, flipped horizontally
+    vram[1][0x1800] = 0b0010_1010;
+    // Tile (1, 0): palette 3 from bank 1, flipped vertically
+    vram[1][0x1801] = 0b0100_1011;
+    // Tile (2, 2): tile 2 from bank 1 above objects
+    vram[0][0x1842] = 0x02;
+    vram[1][0x1842] = 0b1000_1000;
+
+    // Tile 1 from bank 1 with OBJ palette 1, at (0, 16) and over tile (2, 2)
+    gb.ppu.oam[..8].copy_from_slice(&[32, 8, 0x01, 0b0000_1001, 32, 24, 0x01, 0b0000_1001]);
+
+    gb.run_frame();
+    gb.run_frame();
+
+    let native = gb.native_frame();
+    let at = |x: usize, y: usize| native.as_slice()[y * SCREEN_WIDTH + x];
+    assert_eq!(at(7, 0), color(2, 1));
+    assert_eq!(at(0, 0), color(2, 0));
+    assert_eq!(at(8, 7), color(3, 1));
+    assert_eq!(at(8, 0), color(3, 0));
+    assert_eq!(at(40, 40), color(0, 0));
+    assert_eq!(at(0, 16), !color(1, 3) & 0x7FFF);
+    assert_eq!(at(16, 16), color(0, 1));
+
+    let mut rgba = Vec::new();
+    native.rgba_into(gb.ppu.dmg_theme, &mut rgba);
+    assert_eq!(rgba, gb.frame().as_slice());
+}
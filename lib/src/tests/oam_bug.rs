@@ -0,0 +1,233 @@
+use crate::gb::bus::CpuBusInterface;
+use crate::gb::cpu::Cpu;
+use crate::gb::ic::{ICInterface, Interrupt};
+use crate::gb::ppu::OamCorruption;
+use crate::gb::ppu::types::mode::PpuMode;
+use crate::gb::{GameBoy, GbModel};
+use crate::tests::{rom_with, step_until};
+use crate::{ReadMemory, WriteMemory};
+
+fn boot(model: GbModel, code: &[u8]) -> GameBoy {
+    let mut data = rom_with(code, 0x00);
+    // NOP sled back to the entry point, every step is a single M-cycle
+    data[0x7FFD..].copy_from_slice(&[0xC3, 0x00, 0x01]);
+    let mut gb = crate::tests::boot(model, &data);
+
+    for i in 0..0xA0 {
+        gb.ppu
+            .write_naive(0xFE00 + i, (i as u8).wrapping_mul(0x35) ^ 0x5A);
+    }
+    gb
+}
+
+fn oam(gb: &GameBoy) -> Vec<u8> {
+    (0xFE00..0xFEA0)
+        .map(|addr| gb.ppu.read_naive(addr))
+        .collect()
+}
+
+fn word(oam: &[u8], row: usize, word: usize) -> u16 {
+    let i = row * 8 + word * 2;
+    u16::from_le_bytes([oam[i], oam[i + 1]])
+}
+
+fn step_until_row(gb: &mut GameBoy, row: usize) {
+    step_until(gb, |gb| {
+        gb.ppu.stat.ppu_mode == PpuMode::OamScan && gb.ppu.scanner.accessed_row() == row
+    });
+}
+
+#[test]
+fn write_corrupts_the_accessed_row() {
+    let mut gb = boot(GbModel::Dmg, &[]);
+    step_until_row(&mut gb, 5);
+    let before = oam(&gb);
+
+    gb.ppu.corrupt_oam(0xFE10, OamCorruption::Write);
+    let after = oam(&gb);
+
+    let (a, b, c) = (
+        word(&before, 5, 0),
+        word(&before, 4, 0),
+        word(&before, 4, 2),
+    );
+    assert_eq!(word(&after, 5, 0), ((a ^ c) & (b ^ c)) ^ c);
+    assert_eq!(after[42..48], before[34..40]);
+    assert_eq!(after[..40], before[..40]);
+    assert_eq!(after[48..], before[48..]);
+}
+
+#[test]
+fn read_corrupts_the_accessed_row() {
+    let mut gb = boot(GbModel::Dmg, &[]);
+    step_until_row(&mut gb, 9);
+    let before = oam(&gb);
+
+    gb.ppu.corrupt_oam(0xFEFF, OamCorruption::Read);
+    let after = oam(&gb);
+
+    let (a, b, c) = (
+        word(&before, 9, 0),
+        word(&before, 8, 0),
+        word(&before, 8, 2),
+    );
+    assert_eq!(word(&after, 9, 0), b | (a & c));
+    assert_eq!(after[74..80], before[66..72]);
+}
+
+#[test]
+fn read_during_increase_copies_the_preceding_row() {
+    let mut gb = boot(GbModel::Dmg, &[]);
+    step_until_row(&mut gb, 6);
+    let before = oam(&gb);
+
+    gb.ppu.corrupt_oam(0xFE00, OamCorruption::ReadIncrease);
+    let after = oam(&gb);
+
+    let (a, b, c, d) = (
+        word(&before, 4, 0),
+        word(&before, 5, 0),
+        word(&before, 6, 0),
+        word(&before, 4, 2),
+    );
+    let glitched = (b & (a | c | d)) | (a & c & d);
+    assert_eq!(word(&after, 5, 0), glitched);
+    assert_eq!(
+        after[32..40],
+        after[40..48],
+        "row 4 receives the corrupted row 5"
+    );
+    assert_eq!(after[42..48], after[50..56]);
+    let c5 = word(&after, 5, 2);
+    assert_eq!(word(&after, 6, 0), glitched | (glitched & c5));
+    assert_eq!(after[..32], before[..32]);
+}
+
+#[test]
+fn first_row_and_other_modes_are_spared() {
+    let mut gb = boot(GbModel::Dmg, &[]);
+    step_until_row(&mut gb, 0);
+    let before = oam(&gb);
+    gb.ppu.corrupt_oam(0xFE00, OamCorruption::Write);
+    assert_eq!(oam(&gb), before);
+
+    while gb.ppu.stat.ppu_mode != PpuMode::HBlank {
+        gb.step();
+    }
+    gb.ppu.corrupt_oam(0xFE00, OamCorruption::Write);
+    assert_eq!(oam(&gb), before);
+
+    step_until_row(&mut gb, 3);
+    gb.ppu.corrupt_oam(0xFF00, OamCorruption::Write);
+    gb.ppu.corrupt_oam(0xC000, OamCorruption::Read);
+    assert_eq!(oam(&gb), before, "only 0xFE00-0xFEFF triggers the bug");
+}
+
+// LD HL,$FE00, then INC HL / DEC HL in a loop for a few frames
+const IDU_LOOP: [u8; 7] = [0x21, 0x00, 0xFE, 0x23, 0x2B, 0x18, 0xFC];
+
+#[test]
+fn inc_dec_rr_corrupts_oam_on_dmg_models() {
+    for model in [GbModel::Dmg, GbModel::Mgb, GbModel::Sgb2] {
+        let mut gb = boot(model, &IDU_LOOP);
+        let before = oam(&gb);
+        gb.run_frame();
+        assert_ne!(oam(&gb), before, "{model} should corrupt OAM");
+    }
+}
+
+#[test]
+fn cgb_models_are_not_affected() {
+    for model in [GbModel::Cgb, GbModel::CgbE, GbModel::Agb] {
+        let mut gb = boot(model, &IDU_LOOP);
+        let before = oam(&gb);
+        gb.run_frame();
+        assert_eq!(oam(&gb), before, "{model} should leave OAM alone");
+    }
+}
+
+/// Records the addresses the CPU's increment/decrement unit puts on the bus in internal cycles
+struct IduBus {
+    mem: Vec<u8>,
+    idu: Vec<u16>,
+    interrupt: bool,
+}
+
+impl CpuBusInterface for IduBus {
+    fn cycle(&mut self) {}
+
+    fn read(&mut self, addr: u16) -> u8 {
+        self.mem[addr as usize]
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        self.mem[addr as usize] = value;
+    }
+
+    fn idu_cycle(&mut self, addr: u16) {
+        self.idu.push(addr);
+    }
+}
+
+impl ICInterface for IduBus {
+    fn request_interrupt(&mut self, _interrupt: Interrupt) {}
+
+    fn take_interrupt(&mut self) -> Option<Interrupt> {
+        std::mem::take(&mut self.interrupt).then_some(Interrupt::VBlank)
+    }
+
+    fn has_pending_interrupt(&self) -> bool {
+        self.interrupt
+    }
+}
+
+#[cfg(feature = "debug")]
+impl crate::debug::DebuggerInterface for IduBus {}
+
+/// Runs one instruction with SP in OAM and returns the addresses of its IDU cycles
+fn idu_cycles_of(code: &[u8], interrupt: bool) -> Vec<u16> {
+    let mut bus = IduBus {
+        mem: vec![0; 0x10000],
+        idu: vec![],
+        interrupt,
+    };
+    bus.mem[0x0200..0x0200 + code.len()].copy_from_slice(code);
+
+    let mut cpu = Cpu::new_dmg(0x00);
+    cpu.pc = 0x0201;
+    cpu.ir = code[0];
+    cpu.sp = 0xFE10;
+    cpu.ime = interrupt;
+    cpu.f.carry = true;
+    cpu.step(&mut bus);
+    bus.idu
+}
+
+#[test]
+fn push_decrements_sp_through_the_idu() {
+    assert_eq!(idu_cycles_of(&[0xC5], false), [0xFE10]); // PUSH BC
+}
+
+#[test]
+fn call_decrements_sp_through_the_idu() {
+    assert_eq!(idu_cycles_of(&[0xCD, 0x00, 0x40], false), [0xFE10]); // CALL $4000
+}
+
+#[test]
+fn taken_conditional_call_decrements_sp_through_the_idu() {
+    assert_eq!(idu_cycles_of(&[0xDC, 0x00, 0x40], false), [0xFE10]); // CALL C,$4000
+    assert!(
+        idu_cycles_of(&[0xD4, 0x00, 0x40], false).is_empty(),
+        "CALL NC,$4000 is not taken and pushes nothing"
+    );
+}
+
+#[test]
+fn rst_decrements_sp_through_the_idu() {
+    assert_eq!(idu_cycles_of(&[0xFF], false), [0xFE10]); // RST $38
+}
+
+#[test]
+fn interrupt_dispatch_decrements_sp_through_the_idu() {
+    assert_eq!(idu_cycles_of(&[0x00], true), [0xFE10]);
+}
@@ -0,0 +1,171 @@
+use crate::gb::boot_rom::BootRom;
+use crate::gb::bus::CpuBusInterface;
+use crate::gb::ppu::SCREEN_WIDTH;
+use crate::gb::ppu::types::mode::PpuMode;
+use crate::gb::{GameBoy, GbModel};
+use crate::tests::{MAX_STEPS, boot, rom_with, step_until};
+use crate::{ReadMemory, WriteMemory};
+
+/// Runs `code` in CGB mode on CGB
+fn boot_with(model: GbModel, code: &[u8]) -> GameBoy {
+    boot(model, &rom_with(code, 0x80))
+}
+
+/// Spins at the entry point
+fn boot_spinning(model: GbModel) -> GameBoy {
+    boot_with(model, &[0x18, 0xFE])
+}
+
+fn step_until_mode(gb: &mut GameBoy, mode: PpuMode) {
+    step_until(gb, |gb| gb.ppu.stat.ppu_mode == mode);
+}
+
+#[test]
+fn vram_and_oam_are_locked_by_the_ppu_mode() {
+    let mut gb = boot_spinning(GbModel::Dmg);
+
+    step_until_mode(&mut gb, PpuMode::OamScan);
+    assert!(gb.ppu.cpu_conflicts(0xFE00));
+    assert!(gb.ppu.cpu_conflicts(0xFE9F));
+    assert!(!gb.ppu.cpu_conflicts(0x8000));
+
+    step_until_mode(&mut gb, PpuMode::Drawing);
+    assert!(gb.ppu.cpu_conflicts(0x8000));
+    assert!(gb.ppu.cpu_conflicts(0x9FFF));
+    assert!(gb.ppu.cpu_conflicts(0xFE00));
+    assert!(!gb.ppu.cpu_conflicts(0xC000));
+    assert!(!gb.ppu.cpu_conflicts(0xFEA0));
+    assert!(
+        !gb.ppu.cpu_conflicts(0xFF69),
+        "DMG has no palette RAM to lock"
+    );
+
+    step_until_mode(&mut gb, PpuMode::HBlank);
+    assert!(!gb.ppu.cpu_conflicts(0x8000));
+    assert!(!gb.ppu.cpu_conflicts(0xFE00));
+
+    step_until_mode(&mut gb, PpuMode::VBlank);
+    assert!(!gb.ppu.cpu_conflicts(0x8000));
+    assert!(!gb.ppu.cpu_conflicts(0xFE00));
+}
+
+#[test]
+fn cgb_palettes_are_locked_while_drawing() {
+    let mut gb = boot_spinning(GbModel::Cgb);
+
+    step_until_mode(&mut gb, PpuMode::OamScan);
+    assert!(!gb.ppu.cpu_conflicts(0xFF69));
+    assert!(!gb.ppu.cpu_conflicts(0xFF6B));
+
+    step_until_mode(&mut gb, PpuMode::Drawing);
+    assert!(gb.ppu.cpu_conflicts(0xFF69));
+    assert!(gb.ppu.cpu_conflicts(0xFF6B));
+    assert!(
+        !gb.ppu.cpu_conflicts(0xFF68),
+        "the index registers stay accessible"
+    );
+}
+
+#[test]
+fn nothing_is_locked_with_the_lcd_off() {
+    let mut gb = boot_spinning(GbModel::Dmg);
+    step_until_mode(&mut gb, PpuMode::Drawing);
+    gb.ppu.write_naive(0xFF40, 0x11);
+
+    assert!(!gb.ppu.cpu_conflicts(0x8000));
+    assert!(!gb.ppu.cpu_conflicts(0xFE00));
+}
+
+#[test]
+fn boot_rom_starts_with_the_lcd_off() {
+    let gb = GameBoy::new(GbModel::Dmg, BootRom::Bundled, 0x00);
+
+    assert!(!gb.ppu.lcdc.lcd_enabled);
+    assert!(
+        !gb.ppu.cpu_conflicts(0x9FFF),
+        "the boot ROM clears VRAM first"
+    );
+}
+
+/// Reads `addr` every M-cycle from the start of mode 3 and returns the read in its last M-cycle,
+/// the one in the first M-cycle of mode 0 and the dot mode 0 began at
+fn reads_around_the_end_of_mode_3(gb: &mut GameBoy, addr: u16) -> (u8, u8, u16) {
+    step_until_mode(gb, PpuMode::Drawing);
+    let (_, mut bus) = gb.split_bus();
+    let mut last_blocked = None;
+    for _ in 0..MAX_STEPS {
+        let read = bus.read(addr);
+        if bus.ppu.stat.ppu_mode == PpuMode::HBlank {
+            let last_blocked = last_blocked.expect("mode 3 lasts longer than one M-cycle");
+            return (last_blocked, read, bus.ppu.line_dot_counter);
+        }
+        last_blocked = Some(read);
+    }
+    panic!("mode 3 never ended");
+}
+
+#[test]
+fn access_reopens_in_the_first_m_cycle_of_mode_0() {
+    let mut gb = boot_spinning(GbModel::Cgb);
+    gb.ppu.write_naive(0x8000, 0x5A);
+    gb.ppu.write_naive(0xFE00, 0xA5);
+    gb.run_frame();
+
+    // 80 dots of OAM scan, 6 for the first tile fetch and 160 pixels without sprites or scrolling
+    assert_eq!(
+        reads_around_the_end_of_mode_3(&mut gb, 0x8000),
+        (0xFF, 0x5A, 246)
+    );
+    assert_eq!(
+        reads_around_the_end_of_mode_3(&mut gb, 0xFE00),
+        (0xFF, 0xA5, 246)
+    );
+}
+
+#[test]
+fn cpu_reads_vram_as_0xff_during_mode_3() {
+    // LD A,[$8000], LD [HL+],A, JR -6: one read every 9 M-cycles into WRAM from 0xC000
+    let mut gb = boot_with(
+        GbModel::Dmg,
+        &[0x21, 0x00, 0xC0, 0xFA, 0x00, 0x80, 0x22, 0x18, 0xFA],
+    );
+    gb.ppu.write_naive(0x8000, 0x5A);
+    gb.run_frame();
+    gb.run_frame();
+
+    let reads: Vec<u8> = (0xC000..0xC000 + 1_900)
+        .map(|addr| gb.memory.read_naive(addr))
+        .collect();
+    assert!(reads.iter().all(|&read| read == 0x5A || read == 0xFF));
+    // Mode 3 takes 43 of the 114 M-cycles of each of the 144 visible lines without sprites
+    let blocked = reads.iter().filter(|&&read| read == 0xFF).count() as f64 / reads.len() as f64;
+    let expected = (144.0 * 43.0) / 17556.0;
+    assert!(
+        (blocked - expected).abs() < 0.02,
+        "{blocked:.3} of the reads were blocked, expected {expected:.3}"
+    );
+}
+
+#[test]
+fn vram_written_during_vblank_shows_up() {
+    // Waits for LY 144 and fills tile 1 with color 3 for the top left tile of the map, like
+    // blargg's test ROMs print their text
+    let mut gb = boot_with(
+        GbModel::Dmg,
+        &[
+            0xF0, 0x44, 0xFE, 0x90, 0x20, 0xFA, // wait for VBlank
+            0x3E, 0xFF, 0x21, 0x10, 0x80, 0x06, 0x10, // A = 0xFF, HL = 0x8010, B = 16
+            0x22, 0x05, 0x20, 0xFC, // fill tile 1
+            0x3E, 0x01, 0xEA, 0x00, 0x98, // tile 1 at the top left
+            0x18, 0xFE,
+        ],
+    );
+    for _ in 0..3 {
+        gb.run_frame();
+    }
+
+    let shades = gb.native_frame().as_slice();
+    assert_eq!(shades[0], 3);
+    assert_eq!(shades[7 * SCREEN_WIDTH + 7], 3);
+    assert_eq!(shades[8], 0);
+}
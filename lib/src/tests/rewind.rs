@@ -0,0 +1,105 @@
+use crate::gb::ram_init::RamInit;
+use crate::gb::{GameBoy, GbModel};
+use crate::persistence::rewind::{RewindBuffer, RewindConfig};
+use crate::rom::Rom;
+use crate::tests::{boot, rom_with};
+use crate::{ReadMemory, WriteMemory};
+
+/// MBC1 with RAM, the program increments WRAM bytes in a loop
+fn test_rom() -> Vec<u8> {
+    // LD HL, 0xC000; INC (HL); INC L; JR -4
+    let mut data = rom_with(&[0x21, 0x00, 0xC0, 0x34, 0x2C, 0x18, 0xFC], 0x00);
+    data[0x0147] = 0x02;
+    data[0x0149] = 0x02;
+    data
+}
+
+fn loaded() -> GameBoy {
+    boot(GbModel::Dmg, &test_rom())
+}
+
+fn fingerprint(gb: &GameBoy) -> (u16, u8, u8, u8) {
+    (
+        gb.cpu.pc,
+        gb.cpu.l,
+        gb.memory.read_naive(0xC000),
+        gb.cartridge.read_naive(0xA000),
+    )
+}
+
+#[test]
+fn rewind_walks_back_through_keyframes_and_deltas() {
+    let mut gb = loaded();
+    gb.cartridge.write_naive(0x0000, 0x0A);
+    let mut rewind = RewindBuffer::new(RewindConfig {
+        interval: 1,
+        keyframe_interval: 4,
+        ..Default::default()
+    });
+
+    let mut expected = vec![];
+    for frame in 0..10 {
+        gb.cartridge.write_naive(0xA000, frame);
+        gb.run_frame();
+        rewind.frame_finished(&gb).expect("capture");
+        expected.push(fingerprint(&gb));
+    }
+    assert_eq!(rewind.len(), 10);
+
+    gb.run_frame();
+    while let Some(state) = expected.pop() {
+        assert!(rewind.rewind(&mut gb).expect("rewind"));
+        assert_eq!(fingerprint(&gb), state);
+    }
+    assert!(!rewind.rewind(&mut gb).expect("rewind"));
+    assert_eq!(rewind.memory_used(), 0);
+
+    assert_eq!(gb.cartridge.read_naive(0x0100), 0x21, "ROM survives");
+}
+
+#[test]
+fn deltas_are_smaller_than_keyframes() {
+    let mut gb = GameBoy::new_empty(GbModel::Dmg);
+    gb.ram_init = RamInit::random();
+    gb.load_rom(&Rom::new(&test_rom())).expect("load");
+    let mut rewind = RewindBuffer::new(RewindConfig {
+        interval: 3,
+        keyframe_interval: 8,
+        ..Default::default()
+    });
+
+    gb.run_frame();
+    rewind.capture(&gb).expect("capture");
+    let keyframe = rewind.memory_used();
+    for _ in 0..3 {
+        gb.run_frame();
+        rewind.frame_finished(&gb).expect("capture");
+    }
+
+    assert_eq!(rewind.len(), 2);
+    assert_eq!(rewind.frames(), 6);
+    assert!(rewind.memory_used() - keyframe < keyframe / 16);
+}
+
+#[test]
+fn memory_budget_drops_the_oldest_segments() {
+    let mut gb = loaded();
+    let mut rewind = RewindBuffer::new(RewindConfig {
+        interval: 1,
+        keyframe_interval: 2,
+        memory_budget: 0,
+    });
+
+    for _ in 0..6 {
+        gb.run_frame();
+        rewind.frame_finished(&gb).expect("capture");
+    }
+    assert!(rewind.len() <= 2);
+
+    rewind.config.memory_budget = usize::MAX;
+    for _ in 0..6 {
+        gb.run_frame();
+        rewind.frame_finished(&gb).expect("capture");
+    }
+    assert!(rewind.len() >= 6);
+}
@@ -0,0 +1,113 @@
+use crate::error::GbError;
+use crate::gb::GbModel;
+use crate::persistence::sav::{RTC_FOOTER_SIZE, RtcFooter, SavFile, SavLayout};
+use crate::persistence::sram_dump::SramDump;
+use crate::tests::{boot, rom_with};
+
+const RAM: SavLayout = SavLayout {
+    ram_size: 0x2000,
+    mbc2: false,
+    rtc: false,
+};
+const RTC: SavLayout = SavLayout {
+    ram_size: 0x8000,
+    mbc2: false,
+    rtc: true,
+};
+const MBC2: SavLayout = SavLayout {
+    ram_size: 512,
+    mbc2: true,
+    rtc: false,
+};
+
+fn footer(timestamp_size: usize) -> Vec<u8> {
+    let mut footer = Vec::new();
+    for value in [1u32, 2, 3, 4, 0xC1, 5, 6, 7, 8, 0x01] {
+        footer.extend_from_slice(&value.to_le_bytes());
+    }
+    footer.extend_from_slice(&0x6500_0000u64.to_le_bytes()[..timestamp_size]);
+    footer
+}
+
+#[test]
+fn raw_sav_is_the_ram() {
+    let data: Vec<u8> = (0..0x2000).map(|i| i as u8).collect();
+
+    let sav = SavFile::parse(&data, RAM).expect("parse");
+
+    assert_eq!(sav.ram, data);
+    assert_eq!(sav.rtc, None);
+    assert_eq!(sav.to_bytes(), data);
+}
+
+#[test]
+fn rtc_footers_are_split_off() {
+    for timestamp_size in [8, 4] {
+        let mut data = vec![0x42; 0x8000];
+        data.extend(footer(timestamp_size));
+
+        let sav = SavFile::parse(&data, RTC).expect("parse");
+
+        assert_eq!(sav.ram, vec![0x42; 0x8000]);
+        assert_eq!(
+            sav.rtc,
+            Some(RtcFooter {
+                registers: [1, 2, 3, 4, 0xC1],
+                latched: [5, 6, 7, 8, 0x01],
+                timestamp: 0x6500_0000,
+            })
+        );
+    }
+}
+
+#[test]
+fn rtc_footer_is_written_in_the_48_byte_layout() {
+    let mut data = vec![0x42; 0x8000];
+    data.extend(footer(4));
+
+    let bytes = SavFile::parse(&data, RTC).expect("parse").to_bytes();
+
+    assert_eq!(bytes.len(), 0x8000 + RTC_FOOTER_SIZE);
+    assert_eq!(bytes[0x8000..], footer(8));
+}
+
+#[test]
+fn mbc2_half_bytes_get_their_upper_nibble_set() {
+    let data: Vec<u8> = (0..512).map(|i| (i % 16) as u8).collect();
+
+    let sav = SavFile::parse(&data, MBC2).expect("parse");
+
+    assert!(sav.ram.iter().all(|byte| byte & 0xF0 == 0xF0));
+    assert_eq!(sav.ram[5], 0xF5);
+}
+
+#[test]
+fn padded_and_short_saves_fit_the_ram() {
+    let padded = SavFile::parse(&[0x11; 0x8000], RAM).expect("padded");
+    assert_eq!(padded.ram, vec![0x11; 0x2000]);
+
+    let short = SavFile::parse(&[0x22; 0x800], RAM).expect("short");
+    assert_eq!(short.ram.len(), 0x2000);
+    assert_eq!(short.ram[0x7FF], 0x22);
+    assert_eq!(short.ram[0x800], 0xFF);
+
+    assert!(matches!(
+        SavFile::parse(&[], RAM),
+        Err(GbError::EmptySaveFile)
+    ));
+}
+
+#[test]
+fn layout_follows_the_cartridge() {
+    let mut data = rom_with(&[], 0x00);
+    data[0x0147] = 0x10; // MBC3 + Timer + RAM + Battery
+    data[0x0149] = 0x03;
+    let gb = boot(GbModel::Dmg, &data);
+
+    assert_eq!(SavLayout::of(&gb.cartridge), RTC);
+    assert!(gb.cartridge.supports_sram_saves());
+
+    let dump = SramDump::from_slice(&[0x33; 0x8000]);
+    let sav = SavFile::new(&dump, RAM, Some(RtcFooter::new(1)));
+    assert_eq!(sav.rtc, None, "only cartridges with a clock get a footer");
+}
@@ -0,0 +1,120 @@
+use crate::gb::joypad::JoypadState;
+use crate::gb::{GameBoy, GbModel};
+use crate::tests::{boot, rom_with};
+use std::path::Path;
+
+/// Switches a CGB to double speed, then keeps halting with the timer at every rate, each with its
+/// own TMA, and a square wave retriggered with its length counter on. Every 32 rounds DIV is reset,
+/// every 64 the LCD goes off or back on. DIV and TIMA are folded into C and D
+const TIMER_WORKOUT: [u8; 83] = [
+    // LD SP,0xFFFE; CP 0x11 (CGB); JR NZ,+6; LD A,1; LDH [KEY1],A; STOP
+    0x31, 0xFE, 0xFF, 0xFE, 0x11, 0x20, 0x06, 0x3E, 0x01, 0xE0, 0x4D, 0x10, 0x00,
+    // NR52, NR50, NR51, NR11, NR12, IE (VBlank and timer); EI
+    0x3E, 0x80, 0xE0, 0x26, 0x3E, 0x77, 0xE0, 0x24, 0x3E, 0xFF, 0xE0, 0x25, 0x3E, 0x3F, 0xE0, 0x11,
+    0x3E, 0xF1, 0xE0, 0x12, 0x3E, 0x05, 0xE0, 0xFF, 0xFB,
+    // LD A,B; AND 3; OR 4; LDH [TAC],A; LD A,B; LDH [TMA],A; LD A,0xC7; LDH [NR14],A; HALT; NOP
+    0x78, 0xE6, 0x03, 0xF6, 0x04, 0xE0, 0x07, 0x78, 0xE0, 0x06, 0x3E, 0xC7, 0xE0, 0x14, 0x76, 0x00,
+    // INC B; LD A,B; AND 0x1F; JR NZ,+2; LDH [DIV],A
+    0x04, 0x78, 0xE6, 0x1F, 0x20, 0x02, 0xE0, 0x04,
+    // LD A,B; AND 0x3F; JR NZ,+6; LDH A,[LCDC]; XOR 0x80; LDH [LCDC],A
+    0x78, 0xE6, 0x3F, 0x20, 0x06, 0xF0, 0x40, 0xEE, 0x80, 0xE0, 0x40,
+    // LDH A,[DIV]; ADD A,C; LD C,A; LDH A,[TIMA]; XOR D; LD D,A; JR -45
+    0xF0, 0x04, 0x81, 0x4F, 0xF0, 0x05, 0xAA, 0x57, 0x18, 0xD3,
+];
+
+fn timer_workout(model: GbModel) -> GameBoy {
+    // NOP; JP 0x0150
+    let mut rom = rom_with(&[0x00, 0xC3, 0x50, 0x01], 0x80);
+    rom[0x150..0x150 + TIMER_WORKOUT.len()].copy_from_slice(&TIMER_WORKOUT);
+    // RETI from the VBlank and timer handlers
+    rom[0x40] = 0xD9;
+    rom[0x50] = 0xD9;
+    boot(model, &rom)
+}
+
+fn homebrew(name: &str) -> GameBoy {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("../app/homebrew/{name}.gb"));
+    boot(GbModel::Dmg, &std::fs::read(path).expect("homebrew ROM"))
+}
+
+/// Runs `gb` scheduled and a copy of it in lock-step for `frames`, pressing Start and A now and
+/// then, and compares them after every frame
+fn assert_lock_step_agrees(gb: GameBoy, frames: u32) {
+    let mut scheduled = gb;
+    let mut lock_step = scheduled.clone();
+    lock_step.set_lock_step(true);
+
+    for frame in 0..frames {
+        for gb in [&mut scheduled, &mut lock_step] {
+            match frame % 60 {
+                20 => gb.press_button(JoypadState::START),
+                25 => gb.release_button(JoypadState::START),
+                40 => gb.press_button(JoypadState::A),
+                45 => gb.release_button(JoypadState::A),
+                _ => {}
+            }
+            gb.run_frame();
+        }
+
+        let context = format!("after frame {frame}");
+        assert_eq!(scheduled.cpu, lock_step.cpu, "{context}");
+        assert_eq!(
+            scheduled.cycle_counter, lock_step.cycle_counter,
+            "{context}"
+        );
+        assert_eq!(
+            format!("{:?}", scheduled.timer),
+            format!("{:?}", lock_step.timer),
+            "{context}"
+        );
+        assert_eq!(scheduled.ic.flag, lock_step.ic.flag, "{context}");
+        assert_eq!(scheduled.ppu.ly, lock_step.ppu.ly, "{context}");
+        assert_eq!(scheduled.ppu.stat, lock_step.ppu.stat, "{context}");
+        assert_eq!(
+            scheduled.ppu.blank_timeout, lock_step.ppu.blank_timeout,
+            "{context}"
+        );
+        assert_eq!(
+            scheduled.ppu.line_dot_counter, lock_step.ppu.line_dot_counter,
+            "{context}"
+        );
+        assert!(
+            scheduled.frame().as_slice() == lock_step.frame().as_slice(),
+            "{context}: the pictures differ"
+        );
+        assert!(
+            scheduled.apu.audio_buffer == lock_step.apu.audio_buffer,
+            "{context}: the audio differs"
+        );
+        scheduled.apu.audio_buffer.clear();
+        lock_step.apu.audio_buffer.clear();
+    }
+}
+
+#[test]
+fn timer_workout_runs_the_same_in_lock_step() {
+    for model in [GbModel::Dmg, GbModel::Cgb] {
+        let gb = timer_workout(model);
+        assert_lock_step_agrees(gb, 120);
+    }
+}
+
+#[test]
+fn timer_workout_switches_a_cgb_to_double_speed() {
+    let mut gb = timer_workout(GbModel::Cgb);
+    gb.run_frame();
+    assert!(gb.speed.double_speed);
+}
+
+#[test]
+fn homebrew_runs_the_same_in_lock_step() {
+    for name in [
+        "adjustris",
+        "porklike",
+        "shock_lobster",
+        "sushi_nights",
+        "tobu",
+    ] {
+        assert_lock_step_agrees(homebrew(name), 240);
+    }
+}
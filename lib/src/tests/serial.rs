@@ -0,0 +1,29 @@
+use crate::gb::{GameBoy, GbModel};
+use crate::tests::rom_with;
+
+// Sends "OK" on the internal clock, then 'X' on the external one, and spins
+const SEND_OK: [u8; 26] = [
+    0x3E, 0x4F, 0xE0, 0x01, 0x3E, 0x81, 0xE0, 0x02, 0x3E, 0x4B, 0xE0, 0x01, 0x3E, 0x81, 0xE0, 0x02,
+    0x3E, 0x58, 0xE0, 0x01, 0x3E, 0x80, 0xE0, 0x02, 0x18, 0xFE,
+];
+
+fn boot() -> GameBoy {
+    crate::tests::boot(GbModel::Dmg, &rom_with(&SEND_OK, 0x00))
+}
+
+#[test]
+fn serial_log_collects_internal_clock_transfers() {
+    let mut gb = boot();
+    gb.start_serial_logging();
+    gb.run_frame();
+
+    assert_eq!(gb.serial_log.as_deref(), Some(&b"OK"[..]));
+}
+
+#[test]
+fn serial_log_is_off_by_default() {
+    let mut gb = boot();
+    gb.run_frame();
+
+    assert_eq!(gb.serial_log, None);
+}
@@ -1,5 +1,8 @@
+use crate::error::GbError;
 use crate::gb::{GameBoy, GbModel};
+use crate::persistence::snapshot::{Incompatibility, SNAPSHOT_FORMAT_VERSION, SnapshotHeader};
 use crate::rom::Rom;
+use crate::rom::header::RomHeader;
 use crate::{ReadMemory, WriteMemory};
 
 fn test_rom() -> Vec<u8> {
@@ -88,3 +91,77 @@ fn a_restored_machine_keeps_running() {
         restored.step();
     }
 }
+
+#[test]
+fn snapshot_starts_with_a_header() {
+    let mut gb = loaded();
+    let dump = gb.dump_full().expect("dump");
+
+    let (header, _) = SnapshotHeader::read(&dump).expect("header").expect("magic");
+    assert_eq!(header.format_version, SNAPSHOT_FORMAT_VERSION);
+    assert_eq!(header.model, GbModel::Dmg);
+    assert_eq!(header.rom_sha256, RomHeader::calculate_sha256(&test_rom()));
+    assert_eq!(header.emulator_version, crate::VERSION);
+}
+
+#[test]
+fn snapshot_of_another_rom_is_incompatible() {
+    let mut gb = loaded();
+    let dump = gb.dump_full().expect("dump");
+
+    let mut other_rom = test_rom();
+    other_rom[0x0100] = 0x00;
+    let result = GameBoy::from_dump(&dump, &Rom::new(&other_rom));
+    assert!(matches!(
+        result,
+        Err(GbError::IncompatibleSnapshot(Incompatibility::RomMismatch))
+    ));
+}
+
+#[test]
+fn snapshot_of_a_newer_format_is_incompatible() {
+    let mut gb = loaded();
+    let mut dump = gb.dump_full().expect("dump");
+    dump[0x08..0x0A].copy_from_slice(&(SNAPSHOT_FORMAT_VERSION + 1).to_le_bytes());
+
+    let result = GameBoy::from_dump(&dump, &Rom::new(&test_rom()));
+    assert!(matches!(
+        result,
+        Err(GbError::IncompatibleSnapshot(
+            Incompatibility::NewerFormat { .. }
+        ))
+    ));
+}
+
+/// The ROM `tests/snapshots/0.6.0.snapshot` was taken with after 3 frames: enables cartridge RAM,
+/// writes 0x5A to 0xA000 and increments BGP in a loop
+#[cfg(feature = "brotli")]
+fn rom_of_0_6_0_snapshot() -> Vec<u8> {
+    let mut data = vec![0u8; 0x8000];
+    data[0x0147] = 0x03;
+    data[0x0149] = 0x02;
+    data[0x0100..0x0113].copy_from_slice(&[
+        0x3E, 0x0A, 0xEA, 0x00, 0x00, 0x21, 0x00, 0xA0, 0x36, 0x5A, 0x21, 0x47, 0xFF, 0x34, 0x00,
+        0x00, 0x00, 0x18, 0xFA,
+    ]);
+    data
+}
+
+#[cfg(feature = "brotli")]
+#[test]
+fn snapshot_of_0_6_0_is_migrated() {
+    let dump = include_bytes!("../../tests/snapshots/0.6.0.snapshot");
+    let mut restored =
+        GameBoy::from_dump(dump, &Rom::new(&rom_of_0_6_0_snapshot())).expect("restore");
+
+    assert_eq!(restored.cpu.pc, 0x0111);
+    assert_eq!(restored.cpu.a, 0x0A);
+    assert_eq!(restored.ppu.ly, 144);
+    assert_eq!(restored.ppu.read_naive(0xFF47), 0xCA);
+    assert_eq!(restored.cartridge.read_naive(0xA000), 0x5A);
+    assert_eq!(restored.apu.model, GbModel::Dmg);
+
+    let bgp = restored.ppu.read_naive(0xFF47);
+    restored.run_frame();
+    assert_ne!(restored.ppu.read_naive(0xFF47), bgp);
+}
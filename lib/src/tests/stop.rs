@@ -0,0 +1,94 @@
+use crate::ReadMemory;
+use crate::gb::joypad::JoypadState;
+use crate::gb::speed::SWITCH_STALL_CYCLES;
+use crate::gb::{GameBoy, GbModel};
+use crate::tests::{rom_with, step_until};
+
+/// Runs `code` in CGB mode on CGB, with all interrupts disabled
+fn boot(model: GbModel, code: &[u8]) -> GameBoy {
+    let mut gb = crate::tests::boot(model, &rom_with(code, 0x80));
+    gb.ic.enable = 0x00;
+    gb
+}
+
+// STOP, then INC A twice (the first one is the skipped operand byte) and spin
+const STOP_THEN_INC: [u8; 6] = [0x10, 0x3C, 0x3C, 0x00, 0x18, 0xFE];
+
+#[test]
+fn stop_halts_the_clock_until_a_button_is_pressed() {
+    let mut gb = boot(GbModel::Dmg, &STOP_THEN_INC);
+    step_until(&mut gb, |gb| gb.cpu.stopped);
+
+    let div = gb.timer.div;
+    assert!(div < 0x10, "STOP should reset DIV");
+    let blank = gb.ppu.dmg_theme.color_from_shade(0);
+    assert_eq!(
+        gb.frame().as_slice()[..4],
+        [blank.r(), blank.g(), blank.b(), blank.a()]
+    );
+
+    gb.run_frame();
+    assert!(gb.cpu.stopped, "only the joypad ends STOP mode");
+    assert_eq!(gb.timer.div, div, "the timer should not run while stopped");
+
+    gb.press_button(JoypadState::START);
+    step_until(&mut gb, |gb| gb.cpu.ir == 0x18);
+    assert!(!gb.cpu.stopped);
+    assert_eq!(gb.cpu.a, 0x02, "STOP should skip its operand byte");
+}
+
+#[test]
+fn stop_with_a_pending_interrupt_is_one_byte() {
+    let mut gb = boot(GbModel::Dmg, &STOP_THEN_INC);
+    gb.ic.enable = 0x01;
+    gb.ic.flag = 0x01u8.into();
+    step_until(&mut gb, |gb| gb.cpu.stopped);
+
+    gb.press_button(JoypadState::START);
+    step_until(&mut gb, |gb| gb.cpu.ir == 0x18);
+    assert_eq!(gb.cpu.a, 0x03);
+}
+
+#[test]
+fn stop_with_a_held_button_halts_instead() {
+    let mut gb = boot(GbModel::Dmg, &STOP_THEN_INC);
+    gb.press_button(JoypadState::A);
+    let div = gb.timer.div;
+    step_until(&mut gb, |gb| gb.cpu.halted);
+
+    assert!(!gb.cpu.stopped);
+    assert!(gb.timer.div > div, "DIV should keep running");
+}
+
+#[test]
+fn stop_performs_a_prepared_speed_switch() {
+    // LD A, 1; LDH [KEY1], A; STOP; spin
+    let mut gb = boot(
+        GbModel::Cgb,
+        &[0x3E, 0x01, 0xE0, 0x4D, 0x10, 0x00, 0x18, 0xFE],
+    );
+    assert_eq!(gb.speed.read_naive(0xFF4D), 0x7E);
+    assert_eq!(gb.frame_cycles(), 17556);
+
+    step_until(&mut gb, |gb| gb.speed.double_speed);
+    assert_eq!(gb.speed.read_naive(0xFF4D), 0xFE);
+    assert_eq!(gb.frame_cycles(), 35112);
+    assert!(!gb.cpu.stopped);
+
+    assert_eq!(gb.cpu.speed_switch_stall, SWITCH_STALL_CYCLES);
+    for _ in 0..SWITCH_STALL_CYCLES {
+        gb.step();
+    }
+    assert_eq!(gb.cpu.speed_switch_stall, 0);
+}
+
+#[test]
+fn key1_is_unmapped_on_dmg() {
+    let mut gb = boot(
+        GbModel::Dmg,
+        &[0x3E, 0x01, 0xE0, 0x4D, 0x10, 0x00, 0x18, 0xFE],
+    );
+    assert_eq!(gb.speed.read_naive(0xFF4D), 0xFF);
+    step_until(&mut gb, |gb| gb.cpu.stopped);
+    assert!(!gb.speed.double_speed);
+}
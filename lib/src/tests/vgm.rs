@@ -0,0 +1,127 @@
+use crate::gb::GbModel;
+use crate::gb::apu::{APU_CLOCK_RATE, Apu};
+use crate::tests::{boot, rom_with};
+use crate::vgm::{VGM_VERSION, VgmLogger};
+
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Write(u8, u8),
+    Wait(u32),
+}
+
+fn u32_at(file: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(file[offset..offset + 4].try_into().unwrap())
+}
+
+fn parse(file: &[u8]) -> Vec<Command> {
+    assert_eq!(&file[0x00..0x04], b"Vgm ");
+    assert_eq!(u32_at(file, 0x04) as usize, file.len() - 4);
+    assert_eq!(u32_at(file, 0x08), VGM_VERSION);
+    assert_eq!(u32_at(file, 0x80), APU_CLOCK_RATE);
+
+    let mut i = 0x34 + u32_at(file, 0x34) as usize;
+    let mut commands = vec![];
+    loop {
+        match file[i] {
+            0xB3 => {
+                commands.push(Command::Write(file[i + 1], file[i + 2]));
+                i += 3;
+            }
+            0x61 => {
+                commands.push(Command::Wait(
+                    u16::from_le_bytes([file[i + 1], file[i + 2]]) as u32,
+                ));
+                i += 3;
+            }
+            0x62 => {
+                commands.push(Command::Wait(735));
+                i += 1;
+            }
+            0x63 => {
+                commands.push(Command::Wait(882));
+                i += 1;
+            }
+            n @ 0x70..=0x7F => {
+                commands.push(Command::Wait((n - 0x6F) as u32));
+                i += 1;
+            }
+            0x66 => break,
+            other => panic!("unexpected command {other:02X}"),
+        }
+    }
+    assert_eq!(i, file.len() - 1);
+
+    let waited: u32 = commands
+        .iter()
+        .map(|c| if let Command::Wait(n) = c { *n } else { 0 })
+        .sum();
+    assert_eq!(waited, u32_at(file, 0x18), "total samples");
+    commands
+}
+
+#[test]
+fn waits_follow_the_apu_clock() {
+    let mut logger = VgmLogger::new(&Apu::new(GbModel::Dmg));
+    logger.tick(APU_CLOCK_RATE as u64 * 2);
+    logger.log_write(0xFF12, 0xF0);
+    logger.tick(400);
+    logger.log_write(0xFF3F, 0x12);
+    logger.log_write(0xFF40, 0x91);
+    logger.tick(APU_CLOCK_RATE as u64 / 60);
+
+    assert_eq!(
+        parse(&logger.finish()),
+        [
+            Command::Write(0x16, 0x00),
+            Command::Wait(0xFFFF),
+            Command::Wait(88200 - 0xFFFF),
+            Command::Write(0x02, 0xF0),
+            Command::Wait(4),
+            Command::Write(0x2F, 0x12),
+            Command::Wait(735),
+        ]
+    );
+}
+
+#[test]
+fn a_log_starts_with_the_current_apu_state() {
+    let mut apu = Apu::new(GbModel::Dmg);
+    crate::WriteMemory::write_naive(&mut apu, 0xFF26, 0x80);
+    crate::WriteMemory::write_naive(&mut apu, 0xFF25, 0x5A);
+    crate::WriteMemory::write_naive(&mut apu, 0xFF30, 0x9C);
+
+    let commands = parse(&VgmLogger::new(&apu).finish());
+    assert_eq!(commands[0], Command::Write(0x16, 0x80));
+    assert!(commands.contains(&Command::Write(0x15, 0x5A)));
+    assert!(commands.contains(&Command::Write(0x20, 0x9C)));
+}
+
+#[test]
+fn cpu_writes_are_logged() {
+    // Sound on, NR12 = $F0, first wave RAM byte = $AB, then spin
+    let code = [
+        0x3E, 0x80, 0xE0, 0x26, 0x3E, 0xF0, 0xE0, 0x12, 0x3E, 0xAB, 0xE0, 0x30, 0x18, 0xFE,
+    ];
+    let mut gb = boot(GbModel::Dmg, &rom_with(&code, 0x00));
+
+    gb.start_vgm_logging();
+    gb.run_frame();
+    let file = gb.stop_vgm_logging().unwrap();
+    assert!(gb.stop_vgm_logging().is_none());
+
+    let writes: Vec<_> = parse(&file)
+        .into_iter()
+        .filter(|c| matches!(c, Command::Write(..)))
+        .collect();
+    assert_eq!(
+        writes,
+        [
+            Command::Write(0x16, 0x00),
+            Command::Write(0x16, 0x80),
+            Command::Write(0x02, 0xF0),
+            Command::Write(0x20, 0xAB),
+        ]
+    );
+    // At most one frame of 70224 T-cycles
+    assert!((1..=70224 * 44100 / APU_CLOCK_RATE).contains(&u32_at(&file, 0x18)));
+}
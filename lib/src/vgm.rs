@@ -0,0 +1,117 @@
+//! VGM logging: every CPU write to the sound registers (0xFF10-0xFF3F, wave RAM included) is
+//! recorded with its timing and written out as a VGM 1.71 file for the Game Boy DMG chip.
+//!
+//! Timing is taken from the APU clock, so waits stay accurate in CGB double speed mode too.
+//! See https://vgmrips.net/wiki/VGM_Specification
+
+use crate::ReadMemory;
+use crate::gb::apu::{APU_CLOCK_RATE, Apu};
+
+pub const VGM_VERSION: u32 = 0x171;
+pub const VGM_SAMPLE_RATE: u64 = 44_100;
+const HEADER_SIZE: usize = 0x100;
+
+/// Registers that are replayed at the start of a log to recreate the current APU state.
+/// Frequency and trigger registers are left out, the game writes them again for its next note.
+const STATE_REGISTERS: [u16; 11] = [
+    0xFF24, 0xFF25, 0xFF10, 0xFF11, 0xFF12, 0xFF16, 0xFF17, 0xFF1A, 0xFF1C, 0xFF21, 0xFF22,
+];
+
+#[derive(Debug, Clone)]
+pub struct VgmLogger {
+    commands: Vec<u8>,
+    /// APU clock ticks since logging started
+    ticks: u64,
+    /// Samples already covered by wait commands
+    samples: u64,
+}
+
+impl VgmLogger {
+    /// Starts a log with the current APU state, so a log started mid-game replays correctly
+    pub fn new(apu: &Apu) -> Self {
+        let mut logger = Self {
+            commands: vec![],
+            ticks: 0,
+            samples: 0,
+        };
+
+        let nr52 = apu.read_naive(0xFF26);
+        logger.write_register(0xFF26, nr52 & 0x80);
+        if nr52 & 0x80 != 0 {
+            for addr in STATE_REGISTERS {
+                logger.write_register(addr, apu.read_naive(addr));
+            }
+            // Straight from the channel, the CPU can't see wave RAM while the channel plays
+            for (addr, value) in (0xFF30..=0xFF3F).zip(apu.ch3.wave_ram) {
+                logger.write_register(addr, value);
+            }
+        }
+
+        logger
+    }
+
+    /// Advances the log by the given number of APU clock ticks
+    pub fn tick(&mut self, ticks: u64) {
+        self.ticks += ticks;
+    }
+
+    /// Records a CPU write, addresses outside of 0xFF10-0xFF3F are ignored
+    pub fn log_write(&mut self, addr: u16, value: u8) {
+        if !(0xFF10..=0xFF3F).contains(&addr) {
+            return;
+        }
+
+        self.catch_up();
+        self.write_register(addr, value);
+    }
+
+    /// Length of the log so far in 44.1 kHz samples
+    pub fn sample_count(&self) -> u64 {
+        self.ticks * VGM_SAMPLE_RATE / APU_CLOCK_RATE as u64
+    }
+
+    /// The complete VGM file
+    pub fn finish(mut self) -> Vec<u8> {
+        self.catch_up();
+        self.commands.push(0x66);
+
+        let mut file = vec![0u8; HEADER_SIZE];
+        let mut put = |offset: usize, value: u32| {
+            file[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        };
+        put(0x00, u32::from_le_bytes(*b"Vgm "));
+        put(0x04, (HEADER_SIZE + self.commands.len() - 0x04) as u32);
+        put(0x08, VGM_VERSION);
+        put(0x18, self.samples as u32);
+        put(0x34, (HEADER_SIZE - 0x34) as u32);
+        put(0x80, APU_CLOCK_RATE);
+
+        file.extend(self.commands);
+        file
+    }
+
+    fn write_register(&mut self, addr: u16, value: u8) {
+        self.commands.extend([0xB3, (addr - 0xFF10) as u8, value]);
+    }
+
+    /// Emits the wait commands up to the current time
+    fn catch_up(&mut self) {
+        let target = self.sample_count();
+        let mut wait = target - self.samples;
+        self.samples = target;
+
+        while wait > 0 {
+            let step = wait.min(0xFFFF);
+            match step {
+                735 => self.commands.push(0x62),
+                882 => self.commands.push(0x63),
+                1..=16 => self.commands.push(0x70 + (step - 1) as u8),
+                _ => {
+                    let [lo, hi] = (step as u16).to_le_bytes();
+                    self.commands.extend([0x61, lo, hi]);
+                }
+            }
+            wait -= step;
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! JavaScript bindings through wasm-bindgen, for putting the emulator into a web page without the
+//! egui app.
+//!
+//! ```js
+//! import init, { GameBoy, Button } from "./citrine_gb.js";
+//!
+//! await init();
+//! const gb = new GameBoy("DMG");
+//! gb.loadRom(new Uint8Array(await (await fetch("game.gb")).arrayBuffer()));
+//!
+//! const context = canvas.getContext("2d");
+//! function frame() {
+//!     gb.setButtons(Button.Start | Button.A);
+//!     gb.runFrame();
+//!     context.putImageData(new ImageData(gb.framebuffer(), 160, 144), 0, 0);
+//!     requestAnimationFrame(frame);
+//! }
+//! ```
+
+use crate::gb::joypad::JoypadState;
+use crate::gb::{GameBoy, GbModel};
+use crate::rom::Rom;
+use wasm_bindgen::Clamped;
+use wasm_bindgen::prelude::*;
+
+/// The buttons of [`WasmGameBoy::set_buttons`], combine them with `|`
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    A = 0x01,
+    B = 0x02,
+    Select = 0x04,
+    Start = 0x08,
+    Right = 0x10,
+    Left = 0x20,
+    Up = 0x40,
+    Down = 0x80,
+}
+
+/// A Game Boy with the ROM it runs, `GameBoy` in JavaScript
+#[wasm_bindgen(js_name = GameBoy)]
+pub struct WasmGameBoy {
+    gb: GameBoy,
+    rom: Option<Rom>,
+}
+
+#[wasm_bindgen(js_class = GameBoy)]
+impl WasmGameBoy {
+    /// A Game Boy of the given model (`"DMG"`, `"MGB"`, `"SGB2"`, `"CGB"`, `"CGB-E"` or `"AGB"`),
+    /// DMG if left out
+    #[wasm_bindgen(constructor)]
+    pub fn new(model: Option<String>) -> Result<WasmGameBoy, JsError> {
+        let model = match model {
+            Some(name) => *GbModel::ALL
+                .iter()
+                .find(|model| model.to_string().eq_ignore_ascii_case(&name))
+                .ok_or_else(|| JsError::new(&format!("Unknown model {name}")))?,
+            None => GbModel::Dmg,
+        };
+        Ok(Self {
+            gb: GameBoy::new_empty(model),
+            rom: None,
+        })
+    }
+
+    /// Inserts a ROM and powers the Game Boy on
+    #[wasm_bindgen(js_name = loadRom)]
+    pub fn load_rom(&mut self, data: &[u8]) -> Result<(), JsError> {
+        let rom = Rom::new(data);
+        self.rom = None;
+        self.gb.load_rom(&rom)?;
+        self.rom = Some(rom);
+        Ok(())
+    }
+
+    /// Powers the Game Boy off and on again, keeping the ROM and its battery RAM
+    pub fn reset(&mut self) {
+        self.gb.soft_reset();
+    }
+
+    /// Runs until the next frame is complete
+    #[wasm_bindgen(js_name = runFrame)]
+    pub fn run_frame(&mut self) -> Result<(), JsError> {
+        if self.rom.is_none() {
+            return Err(JsError::new("No ROM loaded"));
+        }
+        self.gb.run_frame();
+        Ok(())
+    }
+
+    /// A copy of the last complete frame, 160x144 RGBA pixels ready for `new ImageData(...)`
+    pub fn framebuffer(&self) -> Clamped<Vec<u8>> {
+        Clamped(self.gb.frame().as_slice().to_vec())
+    }
+
+    /// Sets the rate of [`WasmGameBoy::take_audio`], 44100 Hz by default. Match it to the
+    /// `AudioContext`'s `sampleRate`
+    #[wasm_bindgen(js_name = setSampleRate)]
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.gb.apu.set_sample_rate(sample_rate.max(1));
+    }
+
+    /// The audio since the last call as interleaved left/right samples between -1.0 and 1.0
+    #[wasm_bindgen(js_name = takeAudio)]
+    pub fn take_audio(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.gb.apu.audio_buffer)
+    }
+
+    /// Sets the buttons held from now on, a combination of [`Button`]s
+    #[wasm_bindgen(js_name = setButtons)]
+    pub fn set_buttons(&mut self, buttons: u8) {
+        let buttons = JoypadState::from_bits_truncate(buttons);
+        self.gb.press_button(buttons);
+        self.gb.release_button(buttons.complement());
+    }
+
+    /// A save state of the machine, only loadable with the same ROM
+    #[wasm_bindgen(js_name = saveState)]
+    pub fn save_state(&mut self) -> Result<Vec<u8>, JsError> {
+        if self.rom.is_none() {
+            return Err(JsError::new("No ROM loaded"));
+        }
+        Ok(self.gb.dump_full()?)
+    }
+
+    /// Restores a state from [`WasmGameBoy::save_state`], states of older versions are upgraded
+    #[wasm_bindgen(js_name = loadState)]
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), JsError> {
+        let Some(rom) = &self.rom else {
+            return Err(JsError::new("No ROM loaded"));
+        };
+        let mut gb = GameBoy::from_dump(data, rom)?;
+        gb.apu.set_sample_rate(self.gb.apu.output_sample_rate);
+        self.gb = gb;
+        Ok(())
+    }
+
+    /// Reads `addr` as the CPU sees it, without taking time
+    #[wasm_bindgen(js_name = readMemory)]
+    pub fn read_memory(&mut self, addr: u16) -> u8 {
+        self.gb.read_memory(addr)
+    }
+
+    /// Writes `addr` as the CPU would, without taking time
+    #[wasm_bindgen(js_name = writeMemory)]
+    pub fn write_memory(&mut self, addr: u16, value: u8) {
+        self.gb.write_memory(addr, value);
+    }
+}
@@ -0,0 +1,102 @@
+use citrine_gb::ReadMemory;
+use citrine_gb::gb::{GameBoy, GbModel};
+use citrine_gb::rom::Rom;
+use std::path::{Path, PathBuf};
+
+/// About a minute of emulated time, the sound suites take the longest
+const MAX_FRAMES: u32 = 3_600;
+/// Cartridge RAM starts with this once a test reports through memory, the status byte precedes it
+const SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+const RUNNING: u8 = 0x80;
+
+/// Suites that run on every model of a group, matched anywhere below the ROM directory
+macro_rules! suite {
+    ($names:literal) => {
+        concat!(r"(?:^|/)(?:", $names, r")\.gb$")
+    };
+}
+
+const DMG_SUITE: &str = suite!("halt_bug|oam_bug|dmg_sound");
+const CGB_SUITE: &str = suite!("halt_bug|cgb_sound");
+
+/// Blargg's ROMs from https://github.com/retrio/gb-test-roms, where the e2e tests look for theirs
+fn rom_root() -> String {
+    let roms = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../roms/test");
+    if roms.is_dir() {
+        return roms.to_string_lossy().into_owned();
+    }
+
+    let placeholder = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../target/blargg-missing");
+    std::fs::create_dir_all(&placeholder).ok();
+    placeholder.to_string_lossy().into_owned()
+}
+
+/// The result once the test is done: through cartridge RAM if it reports there, otherwise from
+/// the text it prints over the link port
+fn result(gb: &GameBoy) -> Option<Result<(), String>> {
+    let ram = |addr: u16| gb.cartridge.read_naive(addr);
+    if [ram(0xA001), ram(0xA002), ram(0xA003)] == SIGNATURE {
+        let status = ram(0xA000);
+        if status == RUNNING {
+            return None;
+        }
+        let text: Vec<u8> = (0xA004..0xC000)
+            .map(ram)
+            .take_while(|&byte| byte != 0)
+            .collect();
+        let text = String::from_utf8_lossy(&text).trim().to_string();
+        return Some(if status == 0 {
+            Ok(())
+        } else {
+            Err(format!("status {status}: {text}"))
+        });
+    }
+
+    let serial = String::from_utf8_lossy(gb.serial_log.as_deref().unwrap_or_default());
+    if serial.contains("Passed") {
+        Some(Ok(()))
+    } else if serial.contains("Failed") {
+        Some(Err(serial.trim().to_string()))
+    } else {
+        None
+    }
+}
+
+fn run_rom(model: GbModel, path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
+    let rom = Rom::new(&data);
+    let mut gb = GameBoy::new_empty(model);
+    gb.load_rom(&rom)
+        .map_err(|e| format!("failed to load {}: {e:?}", path.display()))?;
+    gb.apu.suppress_output = true;
+    gb.start_serial_logging();
+
+    for _ in 0..MAX_FRAMES {
+        gb.run_frame();
+        match result(&gb) {
+            Some(Ok(())) => return Ok(()),
+            Some(Err(report)) => {
+                return Err(format!("{} failed on {model}: {report}", path.display()).into());
+            }
+            None => {}
+        }
+    }
+
+    Err(format!(
+        "{} reported no result within {MAX_FRAMES} frames on {model}",
+        path.display()
+    )
+    .into())
+}
+
+fn dmg(path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
+    run_rom(GbModel::Dmg, path, data)
+}
+
+fn cgb(path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
+    run_rom(GbModel::Cgb, path, data)
+}
+
+datatest_stable::harness! {
+    { test = dmg, root = rom_root(), pattern = DMG_SUITE },
+    { test = cgb, root = rom_root(), pattern = CGB_SUITE },
+}
@@ -3,12 +3,36 @@ use citrine_gb::rom::Rom;
 use std::path::{Path, PathBuf};
 
 const LD_B_B: u8 = 0x40;
-const MAX_CYCLES: u32 = 30_000_000;
+/// About 30 seconds of emulated time
+const MAX_FRAMES: u32 = 1_800;
 const PASS_REGS: [u8; 6] = [3, 5, 8, 13, 21, 34];
 const FAIL_REGS: [u8; 6] = [0x42; 6];
 
-const DMG_SUITE: &str =
-    r"^(?:acceptance|emulator-only)/(?:.*/)?(?:[^/-]+|[^/]*-[^/-]*(?:G|dmg)[^/-]*)\.gb$";
+/// Selects the tests without a model suffix plus the ones whose suffix names the model.
+///
+/// `misc/` only holds CGB and AGB tests, all of them suffixed, so it only feeds those suites.
+///
+/// Suffixes come in two shapes: group letters (`-GS`, `-C`: G = DMG/MGB, S = SGB/SGB2,
+/// C = CGB/AGB, A = AGB) or model names with optional revisions (`-dmgABCmgb`, `-cgb0`, `-sgb2`).
+/// Revision letters overlap with the group letters, so the two shapes are matched separately.
+macro_rules! suite {
+    ($groups:literal, $models:literal) => {
+        concat!(
+            r"^(?:acceptance|emulator-only|misc)/(?:.*/)?[^/-]+(?:-(?:[GSCA]*[",
+            $groups,
+            r"][GSCA]*|(?:[a-z]+[0-9A-E]*)*(?:",
+            $models,
+            r")(?:[a-z]+[0-9A-E]*)*))?\.gb$"
+        )
+    };
+}
+
+const DMG_SUITE: &str = suite!("G", r"dmg[A-E]*[ABC][A-E]*");
+const MGB_SUITE: &str = suite!("G", r"mgb");
+const SGB2_SUITE: &str = suite!("S", r"sgb2");
+const CGB_SUITE: &str = suite!("C", r"cgb(?:[A-E]*[CD][A-E]*)?");
+const CGB_E_SUITE: &str = suite!("C", r"cgb(?:[A-E]*E)?");
+const AGB_SUITE: &str = suite!("CA", r"ag[bs][0-9A-E]*");
 
 fn build_root() -> String {
     let build = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../tests/mooneye/build");
@@ -21,17 +45,21 @@ fn build_root() -> String {
     placeholder.to_string_lossy().into_owned()
 }
 
-fn run_rom(path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
+fn run_rom(model: GbModel, path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
     let rom = Rom::new(&data);
-    let mut gb = GameBoy::new_empty(GbModel::Dmg);
+    let mut gb = GameBoy::new_empty(model);
     gb.load_rom(&rom)
         .map_err(|e| format!("failed to load {}: {e:?}", path.display()))?;
+    gb.apu.suppress_output = true;
 
+    let mut frames = 0;
     while gb.cpu.ir != LD_B_B {
-        gb.step();
-        if gb.cycle_counter >= MAX_CYCLES {
+        if gb.step_frame() {
+            frames += 1;
+        }
+        if frames >= MAX_FRAMES {
             return Err(format!(
-                "{} never reached the result marker within {MAX_CYCLES} cycles",
+                "{} never reached the result marker within {MAX_FRAMES} frames on {model}",
                 path.display()
             )
             .into());
@@ -41,17 +69,48 @@ fn run_rom(path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
     let regs = [gb.cpu.b, gb.cpu.c, gb.cpu.d, gb.cpu.e, gb.cpu.h, gb.cpu.l];
     match regs {
         PASS_REGS => Ok(()),
-        FAIL_REGS => {
-            Err(format!("{} reported failure (B/C/D/E/H/L = 0x42)", path.display()).into())
-        }
+        FAIL_REGS => Err(format!(
+            "{} reported failure on {model} (B/C/D/E/H/L = 0x42)",
+            path.display()
+        )
+        .into()),
         _ => Err(format!(
-            "{} stopped with unexpected registers {regs:02X?}",
+            "{} stopped with unexpected registers {regs:02X?} on {model}",
             path.display()
         )
         .into()),
     }
 }
 
+fn dmg(path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
+    run_rom(GbModel::Dmg, path, data)
+}
+
+fn mgb(path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
+    run_rom(GbModel::Mgb, path, data)
+}
+
+fn sgb2(path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
+    run_rom(GbModel::Sgb2, path, data)
+}
+
+fn cgb(path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
+    run_rom(GbModel::Cgb, path, data)
+}
+
+fn cgb_e(path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
+    run_rom(GbModel::CgbE, path, data)
+}
+
+fn agb(path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
+    run_rom(GbModel::Agb, path, data)
+}
+
 datatest_stable::harness! {
-    { test = run_rom, root = build_root(), pattern = DMG_SUITE },
+    { test = dmg, root = build_root(), pattern = DMG_SUITE },
+    { test = mgb, root = build_root(), pattern = MGB_SUITE },
+    { test = sgb2, root = build_root(), pattern = SGB2_SUITE },
+    { test = cgb, root = build_root(), pattern = CGB_SUITE },
+    { test = cgb_e, root = build_root(), pattern = CGB_E_SUITE },
+    { test = agb, root = build_root(), pattern = AGB_SUITE },
 }
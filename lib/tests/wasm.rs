@@ -0,0 +1,78 @@
+//! The JavaScript bindings under Node, run with `make test-wasm`
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use citrine_gb::gb::joypad::JoypadState;
+use citrine_gb::wasm::{Button, WasmGameBoy};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+/// MBC1 with 8 KiB of battery RAM: enables the RAM, stores 0x42 at 0xA000, copies 0xA001 to
+/// 0xA002 and spins
+fn battery_rom() -> Vec<u8> {
+    let mut data = vec![0u8; 0x8000];
+    data[0x0147] = 0x03;
+    data[0x0149] = 0x02;
+    data[0x0100..0x0114].copy_from_slice(&[
+        0x3E, 0x0A, 0xEA, 0x00, 0x00, 0x3E, 0x42, 0xEA, 0x00, 0xA0, 0xFA, 0x01, 0xA0, 0xEA, 0x02,
+        0xA0, 0x00, 0x00, 0x18, 0xFE,
+    ]);
+    data
+}
+
+fn with_rom() -> WasmGameBoy {
+    let mut gb = WasmGameBoy::new(None).unwrap();
+    gb.load_rom(&battery_rom()).unwrap();
+    gb
+}
+
+#[wasm_bindgen_test]
+fn models_are_picked_by_name() {
+    assert!(WasmGameBoy::new(Some("cgb-e".to_string())).is_ok());
+    assert!(WasmGameBoy::new(Some("NES".to_string())).is_err());
+}
+
+#[wasm_bindgen_test]
+fn needs_a_rom() {
+    let mut gb = WasmGameBoy::new(None).unwrap();
+    assert!(gb.run_frame().is_err());
+    assert!(gb.save_state().is_err());
+    assert!(gb.load_rom(&[0; 0x100]).is_err());
+}
+
+#[wasm_bindgen_test]
+fn runs_frames_with_video_and_audio() {
+    let mut gb = with_rom();
+    gb.set_sample_rate(48_000);
+    for _ in 0..60 {
+        gb.run_frame().unwrap();
+    }
+
+    let frame = gb.framebuffer();
+    assert_eq!(frame.len(), 160 * 144 * 4);
+    assert!(frame.chunks_exact(4).all(|pixel| pixel[3] == 0xFF));
+
+    // A second of stereo audio, give or take what the resampler holds back
+    let audio = gb.take_audio();
+    assert!(audio.len().abs_diff(2 * 48_000) < 2_000);
+    assert!(gb.take_audio().is_empty());
+}
+
+#[wasm_bindgen_test]
+fn button_values_match_the_joypad() {
+    assert_eq!(Button::A as u8, JoypadState::A.bits());
+    assert_eq!(Button::B as u8, JoypadState::B.bits());
+    assert_eq!(Button::Select as u8, JoypadState::SELECT.bits());
+    assert_eq!(Button::Start as u8, JoypadState::START.bits());
+    assert_eq!(Button::Right as u8, JoypadState::RIGHT.bits());
+    assert_eq!(Button::Left as u8, JoypadState::LEFT.bits());
+    assert_eq!(Button::Up as u8, JoypadState::UP.bits());
+    assert_eq!(Button::Down as u8, JoypadState::DOWN.bits());
+}
+
+#[wasm_bindgen_test]
+fn states_are_byte_arrays() {
+    let mut gb = with_rom();
+    gb.run_frame().unwrap();
+    let state = gb.save_state().unwrap();
+    assert!(gb.load_state(&state).is_ok());
+    assert!(gb.load_state(&state[..10]).is_err());
+}
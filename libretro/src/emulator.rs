@@ -0,0 +1,181 @@
+//! The emulator behind the `retro_*` functions, free of FFI so a frontend stub can drive it too.
+
+use crate::options::Options;
+use citrine_gb::error::GbResult;
+use citrine_gb::gb::apu::APU_CLOCK_RATE;
+use citrine_gb::gb::joypad::JoypadState;
+use citrine_gb::gb::{GameBoy, GbModel};
+use citrine_gb::persistence::sram_dump::SramDump;
+use citrine_gb::rom::Rom;
+use citrine_gb::rom::header::RomCgbMode;
+
+pub const SAMPLE_RATE: u32 = 48_000;
+/// Frames per second, 70224 T-cycles each
+pub const FRAME_RATE: f64 = APU_CLOCK_RATE as f64 / 70_224.0;
+/// Room for the snapshot to grow after its size was reported, the frontend expects a fixed size
+const SERIALIZE_SLACK: usize = 16 * 1024;
+/// Length prefix in front of the snapshot
+const SERIALIZE_HEADER: usize = 4;
+
+pub struct Emulator {
+    pub gb: GameBoy,
+    pub options: Options,
+    rom: Rom,
+    /// What the frontend reads and writes as save RAM, synced with the cartridge around frames
+    save_ram: Vec<u8>,
+    /// Whether the cartridge already holds what the frontend put into `save_ram`
+    save_ram_loaded: bool,
+    serialize_size: Option<usize>,
+}
+
+impl Emulator {
+    /// Runs games that support the Game Boy Color on one, everything else on a DMG
+    pub fn load(data: &[u8], options: Options) -> GbResult<Self> {
+        let rom = Rom::new(data);
+        let model = match rom.cgb_mode()? {
+            RomCgbMode::CgbOnly | RomCgbMode::CgbAndGb => GbModel::Cgb,
+            RomCgbMode::None => GbModel::Dmg,
+        };
+        let mut gb = GameBoy::new_empty_with_ram_init(model, options.ram_init);
+        gb.apu.set_sample_rate(SAMPLE_RATE);
+        gb.load_rom(&rom)?;
+        gb.ppu.dmg_theme = options.dmg_theme;
+
+        let save_ram = if gb.cartridge.has_battery() {
+            vec![0; gb.cartridge.ram_size()]
+        } else {
+            vec![]
+        };
+
+        Ok(Self {
+            gb,
+            options,
+            rom,
+            save_ram,
+            save_ram_loaded: false,
+            serialize_size: None,
+        })
+    }
+
+    /// Powers the Game Boy off and on again, the battery RAM survives
+    pub fn reset(&mut self) {
+        self.gb.ram_init = self.options.ram_init;
+        self.gb.soft_reset();
+        self.save_ram_loaded = false;
+    }
+
+    pub fn set_options(&mut self, options: Options) {
+        self.options = options;
+        self.gb.ppu.dmg_theme = options.dmg_theme;
+    }
+
+    /// Frames the frontend won't show or play skip drawing or mixing, the machine runs the same
+    pub fn set_outputs_enabled(&mut self, video: bool, audio: bool) {
+        self.gb.ppu.skip_rendering = !video;
+        self.gb.apu.suppress_output = !audio;
+    }
+
+    pub fn run_frame(&mut self, buttons: JoypadState) {
+        if !self.save_ram_loaded {
+            self.gb.put_sram_dump(SramDump::from_slice(&self.save_ram));
+            self.save_ram_loaded = true;
+        }
+
+        self.gb.press_button(buttons);
+        self.gb.release_button(buttons.complement());
+        self.gb.run_frame();
+
+        if let Some(dump) = self.gb.poll_sram_dump(false) {
+            self.copy_save_ram(&dump);
+        }
+    }
+
+    /// The framebuffer as XRGB8888, one `u32` per pixel
+    pub fn video_xrgb8888(&self, out: &mut Vec<u32>) {
+        out.clear();
+        out.extend(
+            self.gb
+                .frame()
+                .as_slice()
+                .chunks_exact(4)
+                .map(|rgba| u32::from_be_bytes([0, rgba[0], rgba[1], rgba[2]])),
+        );
+    }
+
+    /// Moves the samples of the last frame into `out` as interleaved stereo
+    pub fn take_audio(&mut self, out: &mut Vec<i16>) {
+        out.clear();
+        out.extend(
+            self.gb
+                .apu
+                .audio_buffer
+                .drain(..)
+                .map(|sample| (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16),
+        );
+    }
+
+    pub fn save_ram_mut(&mut self) -> &mut [u8] {
+        &mut self.save_ram
+    }
+
+    /// The size of every state of this game, fixed at the first call
+    pub fn serialize_size(&mut self) -> GbResult<usize> {
+        if let Some(size) = self.serialize_size {
+            return Ok(size);
+        }
+        let size = SERIALIZE_HEADER + self.snapshot()?.len() + SERIALIZE_SLACK;
+        self.serialize_size = Some(size);
+        Ok(size)
+    }
+
+    /// Writes a snapshot, prefixed with its length and padded with zeros to `out`'s size
+    pub fn serialize(&mut self, out: &mut [u8]) -> GbResult<bool> {
+        let snapshot = self.snapshot()?;
+        let end = SERIALIZE_HEADER + snapshot.len();
+        if out.len() < end {
+            return Ok(false);
+        }
+
+        out[..SERIALIZE_HEADER].copy_from_slice(&(snapshot.len() as u32).to_le_bytes());
+        out[SERIALIZE_HEADER..end].copy_from_slice(&snapshot);
+        out[end..].fill(0);
+        Ok(true)
+    }
+
+    /// Loads a state from [`Emulator::serialize`], `false` if its length prefix doesn't fit the data
+    pub fn unserialize(&mut self, data: &[u8]) -> GbResult<bool> {
+        let Some(length) = data.first_chunk::<SERIALIZE_HEADER>() else {
+            return Ok(false);
+        };
+        let length = u32::from_le_bytes(*length) as usize;
+        let Some(snapshot) = data.get(SERIALIZE_HEADER..SERIALIZE_HEADER + length) else {
+            return Ok(false);
+        };
+
+        let mut gb = GameBoy::from_dump(snapshot, &self.rom)?;
+        gb.apu.set_sample_rate(SAMPLE_RATE);
+        gb.ppu.dmg_theme = self.options.dmg_theme;
+        gb.ram_init = self.options.ram_init;
+        self.gb = gb;
+
+        // The state's RAM replaces what the frontend holds
+        let ram = self.gb.cartridge.ram_dump();
+        self.copy_save_ram(&ram);
+        self.save_ram_loaded = true;
+        Ok(true)
+    }
+
+    fn snapshot(&mut self) -> GbResult<Vec<u8>> {
+        // Dumping takes the RAM as saved, changes since the last frame would go unnoticed
+        if let Some(dump) = self.gb.poll_sram_dump(false) {
+            self.copy_save_ram(&dump);
+        }
+        self.gb.dump_full()
+    }
+
+    fn copy_save_ram(&mut self, dump: &SramDump) {
+        let data = dump.as_slice();
+        let length = self.save_ram.len().min(data.len());
+        self.save_ram[..length].copy_from_slice(&data[..length]);
+    }
+}
@@ -0,0 +1,468 @@
+//! A libretro core around [`citrine_gb::gb::GameBoy`], for RetroArch and other libretro frontends.
+//!
+//! The `retro_*` functions are the C ABI every frontend loads. Libretro runs a single core instance
+//! per process, so the loaded game and the frontend's callbacks live in one global state.
+//! [`emulator::Emulator`] holds the logic without any FFI.
+
+use crate::emulator::{Emulator, FRAME_RATE, SAMPLE_RATE};
+use crate::options::Options;
+use crate::sys::*;
+use citrine_gb::gb::joypad::JoypadState;
+use citrine_gb::gb::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use std::ffi::{CStr, CString, c_char, c_uint, c_void};
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+
+pub mod emulator;
+pub mod options;
+pub mod sys;
+
+const LIBRARY_NAME: &CStr = c"Citrine";
+const LIBRARY_VERSION: &CStr =
+    match CStr::from_bytes_with_nul(concat!(env!("CARGO_PKG_VERSION"), "\0").as_bytes()) {
+        Ok(version) => version,
+        Err(_) => panic!("version contains a nul byte"),
+    };
+const VALID_EXTENSIONS: &CStr = c"gb|gbc";
+
+const BUTTONS: [(c_uint, JoypadState, &CStr); 8] = [
+    (RETRO_DEVICE_ID_JOYPAD_A, JoypadState::A, c"A"),
+    (RETRO_DEVICE_ID_JOYPAD_B, JoypadState::B, c"B"),
+    (
+        RETRO_DEVICE_ID_JOYPAD_SELECT,
+        JoypadState::SELECT,
+        c"Select",
+    ),
+    (RETRO_DEVICE_ID_JOYPAD_START, JoypadState::START, c"Start"),
+    (RETRO_DEVICE_ID_JOYPAD_RIGHT, JoypadState::RIGHT, c"Right"),
+    (RETRO_DEVICE_ID_JOYPAD_LEFT, JoypadState::LEFT, c"Left"),
+    (RETRO_DEVICE_ID_JOYPAD_UP, JoypadState::UP, c"Up"),
+    (RETRO_DEVICE_ID_JOYPAD_DOWN, JoypadState::DOWN, c"Down"),
+];
+
+/// The frontend's callbacks. They are copied out of [`State`] before they run, a frontend may call
+/// back into the core from them
+#[derive(Clone, Copy)]
+struct Callbacks {
+    environment: Option<retro_environment_t>,
+    video_refresh: Option<retro_video_refresh_t>,
+    audio_sample: Option<retro_audio_sample_t>,
+    audio_sample_batch: Option<retro_audio_sample_batch_t>,
+    input_poll: Option<retro_input_poll_t>,
+    input_state: Option<retro_input_state_t>,
+}
+
+/// The frontend's callbacks and the running game
+struct State {
+    callbacks: Callbacks,
+    emulator: Option<Emulator>,
+    video: Vec<u32>,
+    audio: Vec<i16>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    callbacks: Callbacks {
+        environment: None,
+        video_refresh: None,
+        audio_sample: None,
+        audio_sample_batch: None,
+        input_poll: None,
+        input_state: None,
+    },
+    emulator: None,
+    video: Vec::new(),
+    audio: Vec::new(),
+});
+
+fn state() -> MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The callbacks as set right now, without holding on to the lock
+fn callbacks() -> Callbacks {
+    state().callbacks
+}
+
+impl Callbacks {
+    /// Sends a command to the frontend, `false` if it doesn't support it
+    fn environment<T>(&self, cmd: c_uint, data: &mut T) -> bool {
+        match self.environment {
+            Some(environment) => unsafe { environment(cmd, (data as *mut T).cast()) },
+            None => false,
+        }
+    }
+
+    /// The options as currently set in the frontend
+    fn options(&self) -> Options {
+        let mut options = Options::default();
+        for key in Options::KEYS {
+            let Ok(key_c) = CString::new(key) else {
+                continue;
+            };
+            let mut variable = retro_variable {
+                key: key_c.as_ptr(),
+                value: std::ptr::null(),
+            };
+            if self.environment(RETRO_ENVIRONMENT_GET_VARIABLE, &mut variable)
+                && !variable.value.is_null()
+            {
+                let value = unsafe { CStr::from_ptr(variable.value) };
+                options.set(key, &value.to_string_lossy());
+            }
+        }
+        options
+    }
+
+    fn buttons(&self) -> JoypadState {
+        let Some(input_state) = self.input_state else {
+            return JoypadState::empty();
+        };
+        BUTTONS
+            .iter()
+            .filter(|(id, _, _)| unsafe { input_state(0, RETRO_DEVICE_JOYPAD, 0, *id) } != 0)
+            .fold(JoypadState::empty(), |buttons, (_, button, _)| {
+                buttons | *button
+            })
+    }
+
+    fn send_audio(&self, audio: &[i16]) {
+        if let Some(batch) = self.audio_sample_batch {
+            let mut offset = 0;
+            while offset < audio.len() {
+                let frames = (audio.len() - offset) / 2;
+                let written = unsafe { batch(audio[offset..].as_ptr(), frames) };
+                if written == 0 {
+                    break;
+                }
+                offset += written * 2;
+            }
+        } else if let Some(sample) = self.audio_sample {
+            for frame in audio.chunks_exact(2) {
+                unsafe { sample(frame[0], frame[1]) };
+            }
+        }
+    }
+}
+
+/// The variables passed to `RETRO_ENVIRONMENT_SET_VARIABLES`, they have to outlive the call
+fn variables() -> &'static [(CString, CString)] {
+    static VARIABLES: OnceLock<Vec<(CString, CString)>> = OnceLock::new();
+    VARIABLES.get_or_init(|| {
+        Options::definitions()
+            .into_iter()
+            .filter_map(|(key, value)| Some((CString::new(key).ok()?, CString::new(value).ok()?)))
+            .collect()
+    })
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_environment(environment: Option<retro_environment_t>) {
+    state().callbacks.environment = environment;
+
+    let mut variables: Vec<retro_variable> = variables()
+        .iter()
+        .map(|(key, value)| retro_variable {
+            key: key.as_ptr(),
+            value: value.as_ptr(),
+        })
+        .collect();
+    variables.push(retro_variable {
+        key: std::ptr::null(),
+        value: std::ptr::null(),
+    });
+    callbacks().environment(RETRO_ENVIRONMENT_SET_VARIABLES, &mut variables[0]);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_video_refresh(video_refresh: Option<retro_video_refresh_t>) {
+    state().callbacks.video_refresh = video_refresh;
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_audio_sample(audio_sample: Option<retro_audio_sample_t>) {
+    state().callbacks.audio_sample = audio_sample;
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_audio_sample_batch(
+    audio_sample_batch: Option<retro_audio_sample_batch_t>,
+) {
+    state().callbacks.audio_sample_batch = audio_sample_batch;
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_input_poll(input_poll: Option<retro_input_poll_t>) {
+    state().callbacks.input_poll = input_poll;
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_input_state(input_state: Option<retro_input_state_t>) {
+    state().callbacks.input_state = input_state;
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_init() {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_deinit() {
+    let mut state = state();
+    state.emulator = None;
+    state.video = Vec::new();
+    state.audio = Vec::new();
+}
+
+/// # Safety
+///
+/// `info` must point to a writable `retro_system_info`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut retro_system_info) {
+    let Some(info) = (unsafe { info.as_mut() }) else {
+        return;
+    };
+    *info = retro_system_info {
+        library_name: LIBRARY_NAME.as_ptr(),
+        library_version: LIBRARY_VERSION.as_ptr(),
+        valid_extensions: VALID_EXTENSIONS.as_ptr(),
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+/// # Safety
+///
+/// `info` must point to a writable `retro_system_av_info`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut retro_system_av_info) {
+    let Some(info) = (unsafe { info.as_mut() }) else {
+        return;
+    };
+    *info = retro_system_av_info {
+        geometry: retro_game_geometry {
+            base_width: SCREEN_WIDTH as c_uint,
+            base_height: SCREEN_HEIGHT as c_uint,
+            max_width: SCREEN_WIDTH as c_uint,
+            max_height: SCREEN_HEIGHT as c_uint,
+            aspect_ratio: SCREEN_WIDTH as f32 / SCREEN_HEIGHT as f32,
+        },
+        timing: retro_system_timing {
+            fps: FRAME_RATE,
+            sample_rate: SAMPLE_RATE as f64,
+        },
+    };
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_reset() {
+    if let Some(emulator) = &mut state().emulator {
+        emulator.reset();
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_run() {
+    let callbacks = callbacks();
+    let mut updated = false;
+    let options = (callbacks.environment(RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE, &mut updated)
+        && updated)
+        .then(|| callbacks.options());
+
+    if let Some(input_poll) = callbacks.input_poll {
+        unsafe { input_poll() };
+    }
+    let buttons = callbacks.buttons();
+    let mut av_enable = 0;
+    if !callbacks.environment(RETRO_ENVIRONMENT_GET_AUDIO_VIDEO_ENABLE, &mut av_enable) {
+        av_enable = RETRO_AV_ENABLE_VIDEO | RETRO_AV_ENABLE_AUDIO;
+    }
+    let video_enabled = av_enable & RETRO_AV_ENABLE_VIDEO != 0;
+
+    // The buffers leave the state while the frontend reads them
+    let (video, audio) = {
+        let mut state = state();
+        let State {
+            emulator,
+            video,
+            audio,
+            ..
+        } = &mut *state;
+        let Some(emulator) = emulator else {
+            return;
+        };
+        if let Some(options) = options {
+            emulator.set_options(options);
+        }
+        emulator.set_outputs_enabled(video_enabled, av_enable & RETRO_AV_ENABLE_AUDIO != 0);
+        emulator.run_frame(buttons);
+        if video_enabled {
+            emulator.video_xrgb8888(video);
+        }
+        emulator.take_audio(audio);
+        (std::mem::take(video), std::mem::take(audio))
+    };
+
+    // A frame the frontend doesn't show is reported as a dupe without data. Until a frame was
+    // drawn there is nothing to hand over
+    let data = if !video_enabled {
+        Some(std::ptr::null())
+    } else {
+        (!video.is_empty()).then(|| video.as_ptr().cast())
+    };
+    if let Some(video_refresh) = callbacks.video_refresh
+        && let Some(data) = data
+    {
+        unsafe {
+            video_refresh(
+                data,
+                SCREEN_WIDTH as c_uint,
+                SCREEN_HEIGHT as c_uint,
+                SCREEN_WIDTH * size_of::<u32>(),
+            )
+        };
+    }
+    callbacks.send_audio(&audio);
+
+    let mut state = state();
+    state.video = video;
+    state.audio = audio;
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_serialize_size() -> usize {
+    match &mut state().emulator {
+        Some(emulator) => emulator.serialize_size().unwrap_or(0),
+        None => 0,
+    }
+}
+
+/// # Safety
+///
+/// `data` must point to `size` writable bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let Some(emulator) = &mut state().emulator else {
+        return false;
+    };
+    if data.is_null() {
+        return false;
+    }
+    let out = unsafe { std::slice::from_raw_parts_mut(data.cast::<u8>(), size) };
+    emulator.serialize(out).unwrap_or(false)
+}
+
+/// # Safety
+///
+/// `data` must point to `size` readable bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    let Some(emulator) = &mut state().emulator else {
+        return false;
+    };
+    if data.is_null() {
+        return false;
+    }
+    let data = unsafe { std::slice::from_raw_parts(data.cast::<u8>(), size) };
+    emulator.unserialize(data).unwrap_or(false)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+/// # Safety
+///
+/// `game` must be null or point to a `retro_game_info` whose `data` holds `size` readable bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_load_game(game: *const retro_game_info) -> bool {
+    let Some(game) = (unsafe { game.as_ref() }) else {
+        return false;
+    };
+    if game.data.is_null() {
+        return false;
+    }
+    let rom = unsafe { std::slice::from_raw_parts(game.data.cast::<u8>(), game.size) };
+
+    let callbacks = callbacks();
+    let mut pixel_format = RETRO_PIXEL_FORMAT_XRGB8888;
+    if !callbacks.environment(RETRO_ENVIRONMENT_SET_PIXEL_FORMAT, &mut pixel_format) {
+        return false;
+    }
+
+    let mut descriptors: Vec<retro_input_descriptor> = BUTTONS
+        .iter()
+        .map(|(id, _, description)| retro_input_descriptor {
+            port: 0,
+            device: RETRO_DEVICE_JOYPAD,
+            index: 0,
+            id: *id,
+            description: description.as_ptr(),
+        })
+        .collect();
+    descriptors.push(retro_input_descriptor {
+        port: 0,
+        device: 0,
+        index: 0,
+        id: 0,
+        description: std::ptr::null(),
+    });
+    callbacks.environment(RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS, &mut descriptors[0]);
+
+    let options = callbacks.options();
+    match Emulator::load(rom, options) {
+        Ok(emulator) => {
+            state().emulator = Some(emulator);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const retro_game_info,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_unload_game() {
+    state().emulator = None;
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+/// The battery RAM, the frontend loads the save file into it before the first frame and writes it
+/// out from there
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_memory_data(id: c_uint) -> *mut c_void {
+    let mut state = state();
+    let Some(emulator) = &mut state.emulator else {
+        return std::ptr::null_mut();
+    };
+    let save_ram = emulator.save_ram_mut();
+    if id != RETRO_MEMORY_SAVE_RAM || save_ram.is_empty() {
+        return std::ptr::null_mut();
+    }
+    save_ram.as_mut_ptr().cast()
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_memory_size(id: c_uint) -> usize {
+    match &mut state().emulator {
+        Some(emulator) if id == RETRO_MEMORY_SAVE_RAM => emulator.save_ram_mut().len(),
+        _ => 0,
+    }
+}
@@ -0,0 +1,55 @@
+//! Core options, shown in the frontend's quick menu. Values are the labels the frontend displays,
+//! the first one is the default.
+
+use citrine_gb::gb::ppu::types::theme::DmgTheme;
+use citrine_gb::gb::ram_init::RamInit;
+
+pub const DMG_THEME: &str = "citrine_dmg_theme";
+pub const RAM_INIT: &str = "citrine_ram_init";
+
+const ZEROED: &str = "Zeroed";
+const RANDOM: &str = "Random";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Options {
+    pub dmg_theme: DmgTheme,
+    /// Takes effect on the next power-on or reset
+    pub ram_init: RamInit,
+}
+
+impl Options {
+    pub const KEYS: [&'static str; 2] = [DMG_THEME, RAM_INIT];
+
+    /// The option keys with the `Description; first|second|...` strings of
+    /// `RETRO_ENVIRONMENT_SET_VARIABLES`
+    pub fn definitions() -> [(&'static str, String); 2] {
+        let themes: Vec<String> = DmgTheme::SELECTABLE
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        [
+            (DMG_THEME, format!("DMG palette; {}", themes.join("|"))),
+            (RAM_INIT, format!("Power-on RAM; {ZEROED}|{RANDOM}")),
+        ]
+    }
+
+    /// Applies the value the frontend reports for `key`, unknown keys and values are ignored
+    pub fn set(&mut self, key: &str, value: &str) {
+        match key {
+            DMG_THEME => {
+                if let Some(theme) = DmgTheme::SELECTABLE
+                    .iter()
+                    .find(|theme| theme.to_string() == value)
+                {
+                    self.dmg_theme = *theme;
+                }
+            }
+            RAM_INIT => match value {
+                ZEROED => self.ram_init = RamInit::Zeroed,
+                RANDOM => self.ram_init = RamInit::random(),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,295 @@
+//! A minimal libretro frontend: hands the core its callbacks, loads a game through the C ABI and
+//! checks what comes back the way RetroArch would receive it.
+
+use citrine_gb::gb::GbModel;
+use citrine_libretro::emulator::Emulator;
+use citrine_libretro::options::Options;
+use citrine_libretro::sys::*;
+use citrine_libretro::*;
+use std::ffi::{CStr, c_int, c_uint, c_void};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// MBC1 with 8 KiB of battery RAM: enables the RAM, stores 0x42 at 0xA000, copies 0xA001 to
+/// 0xA002 and spins. A frontend that loaded save RAM before the first frame sees its 0xA001 at
+/// 0xA002.
+fn battery_rom() -> Vec<u8> {
+    let mut data = vec![0u8; 0x8000];
+    data[0x0147] = 0x03;
+    data[0x0149] = 0x02;
+    data[0x0100..0x0114].copy_from_slice(&[
+        0x3E, 0x0A, 0xEA, 0x00, 0x00, 0x3E, 0x42, 0xEA, 0x00, 0xA0, 0xFA, 0x01, 0xA0, 0xEA, 0x02,
+        0xA0, 0x00, 0x00, 0x18, 0xFE,
+    ]);
+    data
+}
+
+/// The core is a process-wide singleton, tests take turns
+static FRONTEND: Mutex<()> = Mutex::new(());
+static RECEIVED: Mutex<Received> = Mutex::new(Received::new());
+
+struct Received {
+    pixel_format: Option<retro_pixel_format>,
+    option_keys: Vec<String>,
+    frame: Vec<u32>,
+    frame_size: (c_uint, c_uint, usize),
+    /// Frames the core reported as dupes, without data
+    dupes: usize,
+    audio_frames: usize,
+    /// What `RETRO_ENVIRONMENT_GET_AUDIO_VIDEO_ENABLE` answers, unsupported if `None`
+    av_enable: Option<c_int>,
+    /// The save RAM size the core reported from inside the video callback
+    save_ram_size_in_callback: Option<usize>,
+}
+
+impl Received {
+    const fn new() -> Self {
+        Self {
+            pixel_format: None,
+            option_keys: Vec::new(),
+            frame: Vec::new(),
+            frame_size: (0, 0, 0),
+            dupes: 0,
+            audio_frames: 0,
+            av_enable: None,
+            save_ram_size_in_callback: None,
+        }
+    }
+}
+
+fn received() -> MutexGuard<'static, Received> {
+    RECEIVED.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+unsafe extern "C" fn environment(cmd: c_uint, data: *mut c_void) -> bool {
+    match cmd {
+        RETRO_ENVIRONMENT_SET_PIXEL_FORMAT => {
+            received().pixel_format = Some(unsafe { *data.cast::<retro_pixel_format>() });
+            true
+        }
+        RETRO_ENVIRONMENT_SET_VARIABLES => {
+            let mut variable = data.cast::<retro_variable>();
+            let mut keys = Vec::new();
+            while !unsafe { (*variable).key }.is_null() {
+                keys.push(
+                    unsafe { CStr::from_ptr((*variable).key) }
+                        .to_string_lossy()
+                        .into_owned(),
+                );
+                variable = unsafe { variable.add(1) };
+            }
+            received().option_keys = keys;
+            true
+        }
+        RETRO_ENVIRONMENT_GET_VARIABLE => {
+            let variable = unsafe { &mut *data.cast::<retro_variable>() };
+            if unsafe { CStr::from_ptr(variable.key) } == c"citrine_dmg_theme" {
+                variable.value = c"Grey Scale".as_ptr();
+                return true;
+            }
+            false
+        }
+        RETRO_ENVIRONMENT_GET_AUDIO_VIDEO_ENABLE => match received().av_enable {
+            Some(av_enable) => {
+                unsafe { *data.cast::<c_int>() = av_enable };
+                true
+            }
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+unsafe extern "C" fn video_refresh(
+    data: *const c_void,
+    width: c_uint,
+    height: c_uint,
+    pitch: usize,
+) {
+    if data.is_null() {
+        received().dupes += 1;
+        return;
+    }
+    let pixels =
+        unsafe { std::slice::from_raw_parts(data.cast::<u32>(), pitch / 4 * height as usize) };
+    // Frontends query the core from their callbacks, e.g. to write the save file
+    let save_ram_size = retro_get_memory_size(RETRO_MEMORY_SAVE_RAM);
+    let mut received = received();
+    received.save_ram_size_in_callback = Some(save_ram_size);
+    received.frame = pixels.to_vec();
+    received.frame_size = (width, height, pitch);
+}
+
+unsafe extern "C" fn audio_sample_batch(_data: *const i16, frames: usize) -> usize {
+    received().audio_frames += frames;
+    frames
+}
+
+unsafe extern "C" fn input_poll() {}
+
+/// Nothing is held
+unsafe extern "C" fn input_state(
+    _port: c_uint,
+    _device: c_uint,
+    _index: c_uint,
+    _id: c_uint,
+) -> i16 {
+    0
+}
+
+/// Starts the core with `rom` loaded, the returned guard keeps other tests out
+fn start(rom: &[u8]) -> MutexGuard<'static, ()> {
+    let guard = FRONTEND.lock().unwrap_or_else(PoisonError::into_inner);
+    *received() = Received::new();
+
+    retro_set_environment(Some(environment));
+    retro_set_video_refresh(Some(video_refresh));
+    retro_set_audio_sample_batch(Some(audio_sample_batch));
+    retro_set_input_poll(Some(input_poll));
+    retro_set_input_state(Some(input_state));
+    retro_init();
+
+    let game = retro_game_info {
+        path: std::ptr::null(),
+        data: rom.as_ptr().cast(),
+        size: rom.len(),
+        meta: std::ptr::null(),
+    };
+    assert!(unsafe { retro_load_game(&game) });
+    guard
+}
+
+fn stop() {
+    retro_unload_game();
+    retro_deinit();
+}
+
+fn run_frames(frames: usize) {
+    for _ in 0..frames {
+        retro_run();
+    }
+}
+
+#[test]
+fn reports_the_game_boy_screen_and_timing() {
+    assert_eq!(retro_api_version(), RETRO_API_VERSION);
+
+    let mut info = std::mem::MaybeUninit::<retro_system_av_info>::uninit();
+    let info = unsafe {
+        retro_get_system_av_info(info.as_mut_ptr());
+        info.assume_init()
+    };
+    assert_eq!(
+        (info.geometry.base_width, info.geometry.base_height),
+        (160, 144)
+    );
+    assert!((info.timing.fps - 59.7275).abs() < 0.001);
+
+    let mut system = std::mem::MaybeUninit::<retro_system_info>::uninit();
+    let system = unsafe {
+        retro_get_system_info(system.as_mut_ptr());
+        system.assume_init()
+    };
+    assert_eq!(unsafe { CStr::from_ptr(system.library_name) }, c"Citrine");
+    assert!(!system.need_fullpath);
+}
+
+#[test]
+fn runs_frames_with_video_and_audio() {
+    let _guard = start(&battery_rom());
+    run_frames(30);
+
+    let received = received();
+    assert_eq!(received.pixel_format, Some(RETRO_PIXEL_FORMAT_XRGB8888));
+    assert_eq!(
+        received.option_keys,
+        ["citrine_dmg_theme", "citrine_ram_init"]
+    );
+    assert_eq!(received.frame_size, (160, 144, 640));
+    // The grey scale palette the options asked for
+    let greys = [0x00FF_FFFF, 0x00AA_AAAA, 0x0055_5555, 0x0000_0000];
+    assert!(received.frame.iter().all(|pixel| greys.contains(pixel)));
+    // 30 frames at 48 kHz, give or take what the resampler holds back
+    let expected = 30.0 * 48_000.0 / 59.7275;
+    assert!((received.audio_frames as f64 - expected).abs() < 200.0);
+    drop(received);
+
+    stop();
+}
+
+#[test]
+fn frames_the_frontend_skips_are_neither_drawn_nor_mixed() {
+    let _guard = start(&battery_rom());
+    run_frames(5);
+    let shown = received().frame.clone();
+    let audio_frames = received().audio_frames;
+
+    received().av_enable = Some(0);
+    run_frames(5);
+    assert_eq!(received().frame, shown);
+    assert_eq!(received().dupes, 5);
+    assert_eq!(received().audio_frames, audio_frames);
+
+    received().av_enable = Some(RETRO_AV_ENABLE_VIDEO | RETRO_AV_ENABLE_AUDIO);
+    run_frames(5);
+    assert!(received().audio_frames > audio_frames);
+    stop();
+}
+
+#[test]
+fn save_ram_is_shared_with_the_frontend() {
+    let _guard = start(&battery_rom());
+
+    assert_eq!(retro_get_memory_size(RETRO_MEMORY_SAVE_RAM), 0x2000);
+    let save_ram = retro_get_memory_data(RETRO_MEMORY_SAVE_RAM).cast::<u8>();
+    assert!(!save_ram.is_null());
+    // What the frontend loads from the save file before the first frame
+    unsafe { save_ram.add(1).write(0x99) };
+
+    run_frames(300);
+
+    let save_ram = unsafe { std::slice::from_raw_parts(save_ram, 3) };
+    assert_eq!(save_ram, [0x42, 0x99, 0x99]);
+    stop();
+}
+
+#[test]
+fn unserializing_replays_the_same_frames() {
+    let _guard = start(&battery_rom());
+    run_frames(10);
+
+    let size = retro_serialize_size();
+    assert!(size > 0);
+    let mut state = vec![0; size];
+    assert!(unsafe { retro_serialize(state.as_mut_ptr().cast(), size) });
+    assert_eq!(retro_serialize_size(), size);
+
+    run_frames(5);
+    let expected = received().frame.clone();
+
+    run_frames(20);
+    assert!(unsafe { retro_unserialize(state.as_ptr().cast(), size) });
+    run_frames(5);
+    assert_eq!(received().frame, expected);
+
+    assert!(!unsafe { retro_unserialize(state.as_ptr().cast(), 2) });
+    stop();
+}
+
+#[test]
+fn callbacks_can_call_into_the_core() {
+    let _guard = start(&battery_rom());
+    run_frames(2);
+    assert_eq!(received().save_ram_size_in_callback, Some(0x2000));
+    stop();
+}
+
+#[test]
+fn the_cartridge_header_picks_the_model() {
+    let mut rom = battery_rom();
+    let model = |rom: &[u8]| Emulator::load(rom, Options::default()).unwrap().gb.model;
+    assert_eq!(model(&rom), GbModel::Dmg);
+    rom[0x0143] = 0x80;
+    assert_eq!(model(&rom), GbModel::Cgb);
+    rom[0x0143] = 0xC0;
+    assert_eq!(model(&rom), GbModel::Cgb);
+}